@@ -95,6 +95,7 @@ struct GFX {
     dimension: i16,
     window: glutin::Window,
     device:gfx_device_gl::Device,
+    factory:gfx_device_gl::Factory,
     encoder: gfx::Encoder<R,CB>,
     data: pipe::Data<R>,
     pso: gfx::PipelineState<R, pipe::Meta>,
@@ -120,7 +121,8 @@ impl GFX {
 
         let (window, device, mut factory, main_color, _) =
             gfx_window_glutin::init::<ColorFormat, DepthStencil>(builder);
-        let encoder: gfx::Encoder<_,_> = factory.create_command_buffer().into();
+        let mut encoder: gfx::Encoder<_,_> = factory.create_command_buffer().into();
+        encoder.set_profiler(gfx::profiler::Profiler::new(&mut factory, 16).ok());
 
         let pso = factory.create_pipeline_simple(
             VERTEX_SRC, FRAGMENT_SRC,
@@ -139,6 +141,7 @@ impl GFX {
             window: window,
             dimension: dimension,
             device: device,
+            factory: factory,
             encoder: encoder,
             data: data,
             pso: pso,
@@ -150,15 +153,20 @@ impl GFX {
 impl Renderer for GFX {
     fn render(&mut self, proj_view: &Matrix4<f32>) {
         let start = Instant::now();
-        self.encoder.clear(&self.data.out_color, [CLEAR_COLOR.0,
-                                                  CLEAR_COLOR.1,
-                                                  CLEAR_COLOR.2,
-                                                  CLEAR_COLOR.3]);
-
-        for x in (-self.dimension) ..self.dimension {
-            for y in (-self.dimension) ..self.dimension {
-                self.data.transform = transform(x, y, proj_view).into();
-                self.encoder.draw(&self.slice, &self.pso, &self.data);
+        {
+            let mut encoder = self.encoder.profile_scope("clear");
+            encoder.clear(&self.data.out_color, [CLEAR_COLOR.0,
+                                                 CLEAR_COLOR.1,
+                                                 CLEAR_COLOR.2,
+                                                 CLEAR_COLOR.3]);
+        }
+        {
+            let mut encoder = self.encoder.profile_scope("draw");
+            for x in (-self.dimension) ..self.dimension {
+                for y in (-self.dimension) ..self.dimension {
+                    self.data.transform = transform(x, y, proj_view).into();
+                    encoder.draw(&self.slice, &self.pso, &self.data);
+                }
             }
         }
 
@@ -173,6 +181,20 @@ impl Renderer for GFX {
         println!("\tcreate list:\t{0:4.2}ms", duration_to_ms(pre_submit));
         println!("\tsubmit:\t\t{0:4.2}ms", duration_to_ms(post_submit - pre_submit));
         println!("\tgpu wait:\t{0:4.2}ms", duration_to_ms(swap - post_submit));
+
+        let stats = self.encoder.get_state_stats();
+        println!("\tstate:\t\t{} emitted, {} skipped", stats.emitted, stats.skipped);
+        self.encoder.reset_state_stats();
+
+        if let Some(profiler) = self.encoder.profiler_mut() {
+            profiler.end_frame();
+            profiler.collect(&mut self.factory);
+            for event in profiler.events() {
+                println!("\tgpu {}:\t{:4.2}ms (frame {})", event.name,
+                         event.duration() as f64 / 1e6, event.frame);
+            }
+            profiler.clear_events();
+        }
     }
     fn window(&mut self) -> &glutin::Window { &self.window }
 }
@@ -0,0 +1,8 @@
+#!/bin/bash
+# Render the examples headlessly and compare them with the references
+# checked into `tests/reference`. A missing reference fails the check.
+
+set -ev
+
+cargo build --examples --features png
+cargo test --test examples -- --ignored
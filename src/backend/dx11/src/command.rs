@@ -19,12 +19,12 @@ use winapi::{FLOAT, INT, UINT, UINT8, DXGI_FORMAT,
              DXGI_FORMAT_R16_UINT, DXGI_FORMAT_R32_UINT,
              D3D11_CLEAR_FLAG, D3D11_PRIMITIVE_TOPOLOGY, D3D11_VIEWPORT, D3D11_RECT,
              ID3D11RasterizerState, ID3D11DepthStencilState, ID3D11BlendState};
-use core::{command, pso, shade, state, target, texture as tex};
+use core::{command, format, pso, shade, state, target, texture as tex};
 use core::{IndexType, VertexCount};
 use core::{MAX_VERTEX_ATTRIBUTES, MAX_CONSTANT_BUFFERS,
            MAX_RESOURCE_VIEWS, MAX_UNORDERED_VIEWS,
            MAX_SAMPLERS, MAX_COLOR_TARGETS};
-use {native, Resources, InputLayout, Buffer, Texture, Pipeline, Program};
+use {data, native, Resources, InputLayout, Buffer, Texture, Pipeline, Program};
 
 /// The place of some data in the data buffer.
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -76,6 +76,9 @@ pub enum Command {
     SetDepthStencil(*const ID3D11DepthStencilState, UINT),
     SetBlend(*const ID3D11BlendState, [FLOAT; 4], UINT),
     CopyBuffer(Buffer, Buffer, UINT, UINT, UINT),
+    CopyTexture(Texture, tex::Kind, Option<tex::CubeFace>, tex::RawImageInfo,
+                Texture, Option<tex::CubeFace>, tex::RawImageInfo),
+    Resolve(Texture, Texture, DXGI_FORMAT),
     // resource updates
     UpdateBuffer(Buffer, DataPointer, usize),
     UpdateTexture(Texture, tex::Kind, Option<tex::CubeFace>, DataPointer, tex::RawImageInfo),
@@ -87,6 +90,8 @@ pub enum Command {
     DrawInstanced(UINT, UINT, UINT, UINT),
     DrawIndexed(UINT, UINT, INT),
     DrawIndexedInstanced(UINT, UINT, UINT, INT, UINT),
+    DrawInstancedIndirect(Buffer, UINT),
+    DrawIndexedInstancedIndirect(Buffer, UINT),
 }
 
 unsafe impl Send for Command {}
@@ -198,6 +203,9 @@ impl<P: Parser> command::Buffer<Resources> for CommandBuffer<P> {
             let mut count = 0;
             for cbuf in cbs.iter() {
                 if cbuf.1.contains(mask) {
+                    if cbuf.3.is_some() {
+                        error!("Constant buffer ranges are not supported, binding the whole buffer");
+                    }
                     buffers[cbuf.2 as usize] = (cbuf.0).0;
                     count += 1;
                 }
@@ -341,6 +349,34 @@ impl<P: Parser> command::Buffer<Resources> for CommandBuffer<P> {
         unimplemented!()
     }
 
+    fn copy_texture_to_texture(&mut self,
+                               src: Texture, src_kind: tex::Kind,
+                               src_face: Option<tex::CubeFace>, src_img: tex::RawImageInfo,
+                               dst: Texture, _: tex::Kind,
+                               dst_face: Option<tex::CubeFace>, dst_img: tex::RawImageInfo) {
+        self.parser.parse(Command::CopyTexture(src, src_kind, src_face, src_img,
+                                               dst, dst_face, dst_img));
+    }
+
+    fn blit_texture(&mut self,
+                    _: Texture, _: tex::Kind,
+                    _: Option<tex::CubeFace>, _: tex::RawImageInfo,
+                    _: Texture, _: tex::Kind,
+                    _: Option<tex::CubeFace>, _: tex::RawImageInfo,
+                    _: target::Mirror, _: command::BlitFilter) {
+        error!("Texture blits are not supported");
+    }
+
+    fn resolve_texture(&mut self,
+                       src: Texture, _: tex::Kind,
+                       dst: Texture, _: tex::Kind,
+                       format: format::Format) {
+        match data::map_format(format, true) {
+            Some(dxgi) => self.parser.parse(Command::Resolve(dst, src, dxgi)),
+            None => error!("Unable to resolve texture of format {:?}", format),
+        }
+    }
+
     fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset: usize) {
         self.parser.update_buffer(buf, data, offset);
     }
@@ -395,4 +431,44 @@ impl<P: Parser> command::Buffer<Resources> for CommandBuffer<P> {
         });
     }
 
+    fn call_draw_indirect(&mut self, buf: Buffer, offset: usize, count: usize) {
+        use std::mem::size_of;
+        self.flush();
+        let stride = size_of::<command::DrawIndirectArgs>();
+        for i in 0 .. count {
+            self.parser.parse(Command::DrawInstancedIndirect(buf, (offset + i * stride) as UINT));
+        }
+    }
+
+    fn call_draw_indexed_indirect(&mut self, buf: Buffer, offset: usize, count: usize) {
+        use std::mem::size_of;
+        self.flush();
+        let stride = size_of::<command::DrawIndexedIndirectArgs>();
+        for i in 0 .. count {
+            self.parser.parse(Command::DrawIndexedInstancedIndirect(buf, (offset + i * stride) as UINT));
+        }
+    }
+
+    fn call_dispatch(&mut self, _: [u32; 3]) {
+        error!("Compute dispatch is not supported");
+    }
+
+    fn begin_query(&mut self, _: ()) {
+        error!("Queries are not supported");
+    }
+
+    fn end_query(&mut self, _: ()) {
+        error!("Queries are not supported");
+    }
+
+    fn write_timestamp(&mut self, _: ()) {
+        error!("Queries are not supported");
+    }
+
+    fn begin_conditional(&mut self, _: ()) {
+        error!("Conditional rendering is not supported");
+    }
+
+    fn end_conditional(&mut self) {}
+
 }
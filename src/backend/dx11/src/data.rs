@@ -142,6 +142,40 @@ pub fn map_format(format: Format, is_target: bool) -> Option<DXGI_FORMAT> {
             (false, Float) => DXGI_FORMAT_R32_FLOAT,
             _ => return None,
         },
+        BC1_R8_G8_B8_A8 => match format.1 {
+            Unorm => DXGI_FORMAT_BC1_UNORM,
+            Srgb  => DXGI_FORMAT_BC1_UNORM_SRGB,
+            _ => return None,
+        },
+        BC2_R8_G8_B8_A8 => match format.1 {
+            Unorm => DXGI_FORMAT_BC2_UNORM,
+            Srgb  => DXGI_FORMAT_BC2_UNORM_SRGB,
+            _ => return None,
+        },
+        BC3_R8_G8_B8_A8 => match format.1 {
+            Unorm => DXGI_FORMAT_BC3_UNORM,
+            Srgb  => DXGI_FORMAT_BC3_UNORM_SRGB,
+            _ => return None,
+        },
+        BC4_R8 => match format.1 {
+            Inorm => DXGI_FORMAT_BC4_SNORM,
+            Unorm => DXGI_FORMAT_BC4_UNORM,
+            _ => return None,
+        },
+        BC5_R8_G8 => match format.1 {
+            Inorm => DXGI_FORMAT_BC5_SNORM,
+            Unorm => DXGI_FORMAT_BC5_UNORM,
+            _ => return None,
+        },
+        BC6H_R16_G16_B16_UF => DXGI_FORMAT_BC6H_UF16,
+        BC6H_R16_G16_B16_SF => DXGI_FORMAT_BC6H_SF16,
+        BC7_R8_G8_B8_A8 => match format.1 {
+            Unorm => DXGI_FORMAT_BC7_UNORM,
+            Srgb  => DXGI_FORMAT_BC7_UNORM_SRGB,
+            _ => return None,
+        },
+        // ETC2, EAC and ASTC are not supported by Direct3D
+        _ => return None,
     })
 }
 
@@ -166,6 +200,14 @@ pub fn map_surface(surface: SurfaceType) -> Option<DXGI_FORMAT> {
         D16             => DXGI_FORMAT_R16_TYPELESS,
         D24 | D24_S8    => DXGI_FORMAT_R24G8_TYPELESS,
         D32             => DXGI_FORMAT_R32_TYPELESS,
+        BC1_R8_G8_B8_A8 => DXGI_FORMAT_BC1_TYPELESS,
+        BC2_R8_G8_B8_A8 => DXGI_FORMAT_BC2_TYPELESS,
+        BC3_R8_G8_B8_A8 => DXGI_FORMAT_BC3_TYPELESS,
+        BC4_R8          => DXGI_FORMAT_BC4_TYPELESS,
+        BC5_R8_G8       => DXGI_FORMAT_BC5_TYPELESS,
+        BC6H_R16_G16_B16_UF | BC6H_R16_G16_B16_SF => DXGI_FORMAT_BC6H_TYPELESS,
+        BC7_R8_G8_B8_A8 => DXGI_FORMAT_BC7_TYPELESS,
+        _ => return None,
     })
 }
 
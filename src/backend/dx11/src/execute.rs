@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{mem, ptr};
+use std::{cmp, mem, ptr};
 use winapi::{self, UINT};
 use core::{self, texture as tex};
 use command;
@@ -38,6 +38,37 @@ fn copy_buffer(context: *mut winapi::ID3D11DeviceContext,
     };
 }
 
+fn copy_texture(context: *mut winapi::ID3D11DeviceContext,
+                src: &Texture, kind: tex::Kind,
+                src_face: Option<tex::CubeFace>, src_image: &tex::RawImageInfo,
+                dst: &Texture,
+                dst_face: Option<tex::CubeFace>, dst_image: &tex::RawImageInfo) {
+    // only the volume textures are addressed by depth
+    let (front, back) = match kind {
+        tex::Kind::D3(..) => (src_image.zoffset, src_image.zoffset + src_image.depth),
+        _ => (0, 1),
+    };
+    let dst_z = match kind {
+        tex::Kind::D3(..) => dst_image.zoffset,
+        _ => 0,
+    };
+    let src_box = winapi::D3D11_BOX {
+        left: src_image.xoffset as UINT,
+        right: (src_image.xoffset + src_image.width) as UINT,
+        top: src_image.yoffset as UINT,
+        bottom: (src_image.yoffset + cmp::max(1, src_image.height)) as UINT,
+        front: front as UINT,
+        back: cmp::max(front + 1, back) as UINT,
+    };
+    unsafe {
+        (*context).CopySubresourceRegion(dst.as_resource(), texture_subres(dst_face, dst_image),
+                                         dst_image.xoffset as UINT, dst_image.yoffset as UINT,
+                                         dst_z as UINT,
+                                         src.as_resource(), texture_subres(src_face, src_image),
+                                         &src_box)
+    };
+}
+
 pub fn update_buffer(context: *mut winapi::ID3D11DeviceContext, buffer: &Buffer,
                      data: &[u8], offset_bytes: usize) {
     let dst_resource = (buffer.0).0 as *mut winapi::ID3D11Resource;
@@ -62,13 +93,16 @@ pub fn update_texture(context: *mut winapi::ID3D11DeviceContext, texture: &Textu
     let subres = texture_subres(face, image);
     let dst_resource = texture.as_resource();
     let (width, height, _, _) = kind.get_level_dimensions(image.mipmap);
-    let stride = image.format.0.get_total_bits() as usize;
-    let row_pitch = width as usize * stride;
-    let depth_pitch = height as usize * row_pitch;
+    // compressed formats are addressed by blocks
+    let (bw, bh) = image.format.0.get_block_dimensions();
+    let (bw, bh) = (bw as usize, bh as usize);
+    let stride = image.format.0.get_total_bits() as usize / 8;
+    let row_pitch = (width as usize + bw - 1) / bw * stride;
+    let depth_pitch = (height as usize + bh - 1) / bh * row_pitch;
 
     // DYNAMIC only
-    let offset_bytes = image.xoffset as usize +
-                       image.yoffset as usize * row_pitch +
+    let offset_bytes = image.xoffset as usize / bw * stride +
+                       image.yoffset as usize / bh * row_pitch +
                        image.zoffset as usize * depth_pitch;
     let map_type = winapi::D3D11_MAP_WRITE_DISCARD;
     let hr = unsafe {
@@ -143,6 +177,9 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
             Stage::Pixel => unsafe {
                 (*ctx).PSSetConstantBuffers(0, max_cb, &buffers[0].0);
             },
+            Stage::Compute => unsafe {
+                (*ctx).CSSetConstantBuffers(0, max_cb, &buffers[0].0);
+            },
         },
         BindShaderResources(stage, ref views) => match stage {
             Stage::Vertex => unsafe {
@@ -160,6 +197,9 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
             Stage::Pixel => unsafe {
                 (*ctx).PSSetShaderResources(0, max_srv, &views[0].0);
             },
+            Stage::Compute => unsafe {
+                (*ctx).CSSetShaderResources(0, max_srv, &views[0].0);
+            },
         },
         BindSamplers(stage, ref samplers) => match stage {
             Stage::Vertex => unsafe {
@@ -177,6 +217,9 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
             Stage::Pixel => unsafe {
                 (*ctx).PSSetSamplers(0, max_sm, &samplers[0].0);
             },
+            Stage::Compute => unsafe {
+                (*ctx).CSSetSamplers(0, max_sm, &samplers[0].0);
+            },
         },
         BindPixelTargets(ref colors, ds) => unsafe {
             (*ctx).OMSetRenderTargets(core::MAX_COLOR_TARGETS as UINT,
@@ -203,6 +246,12 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
         CopyBuffer(ref src, ref dst, src_offset, dst_offset, size) => {
             copy_buffer(ctx, src, dst, src_offset, dst_offset, size);
         },
+        CopyTexture(ref src, kind, src_face, ref src_image, ref dst, dst_face, ref dst_image) => {
+            copy_texture(ctx, src, kind, src_face, src_image, dst, dst_face, dst_image);
+        },
+        Resolve(ref dst, ref src, format) => unsafe {
+            (*ctx).ResolveSubresource(dst.as_resource(), 0, src.as_resource(), 0, format);
+        },
         UpdateBuffer(ref buffer, pointer, offset) => {
             let data = data_buf.get(pointer);
             update_buffer(ctx, buffer, data, offset);
@@ -232,5 +281,11 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
         DrawIndexedInstanced(nind, ninst, sind, base, sinst) => unsafe {
             (*ctx).DrawIndexedInstanced(nind, ninst, sind, base, sinst);
         },
+        DrawInstancedIndirect(buffer, offset) => unsafe {
+            (*ctx).DrawInstancedIndirect((buffer.0).0, offset);
+        },
+        DrawIndexedInstancedIndirect(buffer, offset) => unsafe {
+            (*ctx).DrawIndexedInstancedIndirect((buffer.0).0, offset);
+        },
     }
 }
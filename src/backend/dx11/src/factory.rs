@@ -52,7 +52,7 @@ impl core::mapping::Gate<R> for MappingGate {
 struct TextureParam {
     levels: winapi::UINT,
     format: winapi::DXGI_FORMAT,
-    bytes_per_texel: winapi::UINT,
+    surface: core::format::SurfaceType,
     bind: winapi::D3D11_BIND_FLAG,
     usage: winapi::D3D11_USAGE,
     cpu_access: winapi::D3D11_CPU_ACCESS_FLAG,
@@ -138,7 +138,7 @@ impl Factory {
             },
             buffer::Role::Constant  => // 16 bit alignment
                 (D3D11_BIND_CONSTANT_BUFFER, (info.size + 0xF) & !0xF),
-            buffer::Role::Staging =>
+            buffer::Role::Staging | buffer::Role::Indirect =>
                 (D3D11_BIND_FLAG(0), info.size)
         };
 
@@ -153,7 +153,11 @@ impl Factory {
             Usage: usage,
             BindFlags: bind.0,
             CPUAccessFlags: cpu.0,
-            MiscFlags: 0,
+            MiscFlags: if info.role == buffer::Role::Indirect {
+                D3D11_RESOURCE_MISC_DRAWINDIRECT_ARGS.0
+            } else {
+                0
+            },
             StructureByteStride: 0, //TODO
         };
         let mut sub = D3D11_SUBRESOURCE_DATA {
@@ -190,12 +194,15 @@ impl Factory {
         }
     }
 
-    fn update_sub_data(&mut self, w: texture::Size, h: texture::Size, bpt: winapi::UINT)
+    fn update_sub_data(&mut self, w: texture::Size, h: texture::Size, surface: core::format::SurfaceType)
                        -> *const winapi::D3D11_SUBRESOURCE_DATA {
         use winapi::UINT;
+        // the pitches of the compressed formats are given in rows of blocks
+        let (bw, bh) = surface.get_block_dimensions();
+        let block_bytes = (surface.get_total_bits() >> 3) as UINT;
         for sub in self.sub_data_array.iter_mut() {
-            sub.SysMemPitch = w as UINT * bpt;
-            sub.SysMemSlicePitch = (h as UINT) * sub.SysMemPitch;
+            sub.SysMemPitch = (w as UINT + bw as UINT - 1) / bw as UINT * block_bytes;
+            sub.SysMemSlicePitch = (h as UINT + bh as UINT - 1) / bh as UINT * sub.SysMemPitch;
         }
         self.sub_data_array.as_ptr()
     }
@@ -222,7 +229,7 @@ impl Factory {
                     array, tp.levels, self.sub_data_array.len());
                 return Err(winapi::S_OK)
             }
-            self.update_sub_data(size, 0, tp.bytes_per_texel)
+            self.update_sub_data(size, 0, tp.surface)
         }else {
             ptr::null()
         };
@@ -267,7 +274,7 @@ impl Factory {
                     array, tp.levels, self.sub_data_array.len());
                 return Err(winapi::S_OK)
             }
-            self.update_sub_data(size[0], size[1], tp.bytes_per_texel)
+            self.update_sub_data(size[0], size[1], tp.surface)
         }else {
             ptr::null()
         };
@@ -308,7 +315,7 @@ impl Factory {
                     tp.levels, self.sub_data_array.len());
                 return Err(winapi::S_OK)
             }
-            self.update_sub_data(size[0], size[1], tp.bytes_per_texel)
+            self.update_sub_data(size[0], size[1], tp.surface)
         }else {
             ptr::null()
         };
@@ -397,7 +404,7 @@ impl core::Factory<R> for Factory {
                 };
                 (hr, ret as *mut ID3D11DeviceChild)
             },
-            //_ => return Err(CreateShaderError::StageNotSupported(stage))
+            Stage::Compute => return Err(CreateShaderError::StageNotSupported(stage)),
         };
 
         if winapi::SUCCEEDED(hr) {
@@ -487,7 +494,10 @@ impl core::Factory<R> for Factory {
                     ps: ps.object as *mut ID3D11PixelShader,
                     vs_hash: vs.code_hash,
                 }
-            }
+            },
+            &core::ShaderSet::Compute(..) => {
+                return Err("Compute programs are not supported".into());
+            },
         };
         Ok(self.share.handles.borrow_mut().make_program(prog, info))
     }
@@ -618,7 +628,7 @@ impl core::Factory<R> for Factory {
                     None => return Err(CreationError::Format(desc.format, None))
                 },
             },
-            bytes_per_texel: (desc.format.get_total_bits() >> 3) as winapi::UINT,
+            surface: desc.format,
             bind: map_bind(desc.bind),
             usage: usage,
             cpu_access: cpu_access,
@@ -867,6 +877,15 @@ impl core::Factory<R> for Factory {
         Ok(self.share.handles.borrow_mut().make_dsv(native::Dsv(raw_view), htex, dim))
     }
 
+    fn create_query(&mut self, ty: core::query::QueryType)
+                    -> Result<h::Query<R>, core::query::CreationError> {
+        Err(core::query::CreationError::UnsupportedType(ty))
+    }
+
+    fn get_query_result(&mut self, _: &h::Query<R>) -> Option<u64> {
+        None
+    }
+
     fn create_sampler(&mut self, info: texture::SamplerInfo) -> h::Sampler<R> {
         use core::texture::FilterMethod;
         use data::{FilterOp, map_function, map_filter, map_wrap};
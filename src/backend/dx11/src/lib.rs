@@ -156,6 +156,7 @@ impl core::Resources for Resources {
     type UnorderedAccessView = ();
     type Sampler             = native::Sampler;
     type Fence               = Fence;
+    type Query               = ();
     type Mapping             = factory::MappingGate;
 }
 
@@ -191,15 +192,25 @@ pub fn create(driver_type: winapi::D3D_DRIVER_TYPE, desc: &winapi::DXGI_SWAP_CHA
             max_index_count: 0,
             max_texture_size: 0,
             max_patch_size: 32, //hard-coded in D3D11
+            constant_buffer_alignment: 256,
             instance_base_supported: false,
             instance_call_supported: false,
             instance_rate_supported: false,
             vertex_base_supported: false,
             srgb_color_supported: false,
             constant_buffer_supported: true,
+            constant_buffer_range_supported: false,
             unordered_access_view_supported: false,
             separate_blending_slots_supported: false,
             copy_buffer_supported: true,
+            copy_texture_supported: true,
+            blit_texture_supported: false,
+            resolve_texture_supported: true,
+            compute_supported: false,
+            draw_indirect_supported: true,
+            bc_compression_supported: true,
+            etc2_compression_supported: false,
+            astc_compression_supported: false,
         },
         handles: RefCell::new(h::Manager::new()),
     };
@@ -402,6 +413,7 @@ impl core::Device for Device {
             |_, v| unsafe { (*v.0).Release(); }, //DSV
             |_, v| unsafe { (*v.0).Release(); }, //sampler
             |_, _fence| {},
+            |_, _query| {},
         );
     }
 }
@@ -18,7 +18,7 @@ use gl;
 use core::{self as c, command, state as s};
 use core::target::{ColorValue, Depth, Mirror, Rect, Stencil};
 use {Buffer, BufferElement, Program, FrameBuffer, Texture,
-     NewTexture, Resources, PipelineState, ResourceView, TargetView};
+     NewTexture, Resources, PipelineState, ResourceView, TargetView, Query};
 
 
 fn primitive_to_gl(primitive: c::Primitive) -> gl::types::GLenum {
@@ -71,6 +71,14 @@ impl DataBuffer {
     }
 }
 
+/// A part of a texture, copied or blitted from/to.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureRegion {
+    pub texture: NewTexture,
+    pub kind: c::texture::Kind,
+    pub face: Option<c::texture::CubeFace>,
+    pub info: c::texture::RawImageInfo,
+}
 
 ///Serialized device command.
 #[derive(Clone, Copy, Debug)]
@@ -105,11 +113,13 @@ pub enum Command {
                         c::texture::Kind,
                         Option<c::texture::CubeFace>,
                         c::texture::RawImageInfo),
-    CopyTextureToBuffer(NewTexture,
+    CopyTextureToBuffer(FrameBuffer, NewTexture,
                         c::texture::Kind,
                         Option<c::texture::CubeFace>,
                         c::texture::RawImageInfo,
                         Buffer, gl::types::GLintptr),
+    CopyTexture(FrameBuffer, FrameBuffer, TextureRegion, TextureRegion),
+    Blit(FrameBuffer, FrameBuffer, TextureRegion, TextureRegion, Mirror, gl::types::GLenum),
     // resource updates
     UpdateBuffer(Buffer, DataPointer, usize),
     UpdateTexture(Texture, c::texture::Kind, Option<c::texture::CubeFace>,
@@ -120,7 +130,15 @@ pub enum Command {
     Draw(gl::types::GLenum, c::VertexCount, c::VertexCount, Option<command::InstanceParams>),
     DrawIndexed(gl::types::GLenum, gl::types::GLenum, RawOffset,
                 c::VertexCount, c::VertexCount, Option<command::InstanceParams>),
-    _Blit(Rect, Rect, Mirror, usize),
+    DrawIndirect(gl::types::GLenum, Buffer, RawOffset, usize),
+    DrawIndexedIndirect(gl::types::GLenum, gl::types::GLenum, Buffer, RawOffset, usize),
+    Dispatch([u32; 3]),
+    // queries
+    BeginQuery(Query),
+    EndQuery(Query),
+    WriteTimestamp(Query),
+    BeginConditional(Query),
+    EndConditional,
 }
 
 pub const COLOR_DEFAULT: s::Color = s::Color {
@@ -186,16 +204,18 @@ pub struct CommandBuffer {
     pub buf: Vec<Command>,
     pub data: DataBuffer,
     fbo: FrameBuffer,
+    read_fbo: FrameBuffer,
     cache: Cache,
     active_attribs: usize,
 }
 
 impl CommandBuffer {
-    pub fn new(fbo: FrameBuffer) -> CommandBuffer {
+    pub fn new(fbo: FrameBuffer, read_fbo: FrameBuffer) -> CommandBuffer {
         CommandBuffer {
             buf: Vec::new(),
             data: DataBuffer::new(),
             fbo: fbo,
+            read_fbo: read_fbo,
             cache: Cache::new(),
             active_attribs: 0,
         }
@@ -371,11 +391,79 @@ impl command::Buffer<Resources> for CommandBuffer {
                               img: c::texture::RawImageInfo,
                               dst: Buffer, dst_offset_bytes: usize) {
         self.buf.push(Command::CopyTextureToBuffer(
-            src, kind, face, img,
+            self.read_fbo, src, kind, face, img,
             dst, dst_offset_bytes as gl::types::GLintptr
         ));
     }
 
+    fn copy_texture_to_texture(&mut self,
+                               src: NewTexture,
+                               src_kind: c::texture::Kind,
+                               src_face: Option<c::texture::CubeFace>,
+                               src_img: c::texture::RawImageInfo,
+                               dst: NewTexture,
+                               dst_kind: c::texture::Kind,
+                               dst_face: Option<c::texture::CubeFace>,
+                               dst_img: c::texture::RawImageInfo) {
+        self.buf.push(Command::CopyTexture(
+            self.read_fbo, self.fbo,
+            TextureRegion { texture: src, kind: src_kind, face: src_face, info: src_img },
+            TextureRegion { texture: dst, kind: dst_kind, face: dst_face, info: dst_img }
+        ));
+    }
+
+    fn blit_texture(&mut self,
+                    src: NewTexture,
+                    src_kind: c::texture::Kind,
+                    src_face: Option<c::texture::CubeFace>,
+                    src_img: c::texture::RawImageInfo,
+                    dst: NewTexture,
+                    dst_kind: c::texture::Kind,
+                    dst_face: Option<c::texture::CubeFace>,
+                    dst_img: c::texture::RawImageInfo,
+                    mirror: Mirror,
+                    filter: command::BlitFilter) {
+        let filter = match filter {
+            command::BlitFilter::Nearest => gl::NEAREST,
+            command::BlitFilter::Linear => gl::LINEAR,
+        };
+        self.buf.push(Command::Blit(
+            self.read_fbo, self.fbo,
+            TextureRegion { texture: src, kind: src_kind, face: src_face, info: src_img },
+            TextureRegion { texture: dst, kind: dst_kind, face: dst_face, info: dst_img },
+            mirror, filter
+        ));
+    }
+
+    fn resolve_texture(&mut self,
+                       src: NewTexture, src_kind: c::texture::Kind,
+                       dst: NewTexture, dst_kind: c::texture::Kind,
+                       format: c::format::Format) {
+        let region = |texture, kind: c::texture::Kind| {
+            let (w, h, _, _) = kind.get_dimensions();
+            TextureRegion {
+                texture: texture,
+                kind: kind,
+                face: None,
+                info: c::texture::RawImageInfo {
+                    xoffset: 0,
+                    yoffset: 0,
+                    zoffset: 0,
+                    width: w,
+                    height: h,
+                    depth: 0,
+                    format: format,
+                    mipmap: 0,
+                },
+            }
+        };
+        self.buf.push(Command::Blit(
+            self.read_fbo, self.fbo,
+            region(src, src_kind), region(dst, dst_kind),
+            Mirror::empty(), gl::NEAREST
+        ));
+    }
+
     fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset_bytes: usize) {
         let ptr = self.data.add(data);
         self.buf.push(Command::UpdateBuffer(buf, ptr, offset_bytes));
@@ -432,4 +520,62 @@ impl command::Buffer<Resources> for CommandBuffer {
         self.buf.push(Command::DrawIndexed(self.cache.primitive,
             gl_index, RawOffset(offset as *const gl::types::GLvoid), count, base, instances));
     }
+
+    fn call_draw_indirect(&mut self, buf: Buffer, offset: usize, count: usize) {
+        self.buf.push(Command::DrawIndirect(self.cache.primitive, buf,
+            RawOffset(offset as *const gl::types::GLvoid), count));
+    }
+
+    fn call_draw_indexed_indirect(&mut self, buf: Buffer, offset: usize, count: usize) {
+        let gl_index = match self.cache.index_type {
+            c::IndexType::U16 => gl::UNSIGNED_SHORT,
+            c::IndexType::U32 => gl::UNSIGNED_INT,
+        };
+        self.buf.push(Command::DrawIndexedIndirect(self.cache.primitive, gl_index, buf,
+            RawOffset(offset as *const gl::types::GLvoid), count));
+    }
+
+    fn call_dispatch(&mut self, groups: [u32; 3]) {
+        self.buf.push(Command::Dispatch(groups));
+    }
+
+    fn begin_query(&mut self, query: Query) {
+        self.buf.push(Command::BeginQuery(query));
+    }
+
+    fn end_query(&mut self, query: Query) {
+        self.buf.push(Command::EndQuery(query));
+    }
+
+    fn write_timestamp(&mut self, query: Query) {
+        self.buf.push(Command::WriteTimestamp(query));
+    }
+
+    fn begin_conditional(&mut self, query: Query) {
+        self.buf.push(Command::BeginConditional(query));
+    }
+
+    fn end_conditional(&mut self) {
+        self.buf.push(Command::EndConditional);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{command, IndexType};
+    use core::command::Buffer;
+    use super::{Command, CommandBuffer};
+    use gl;
+
+    #[test]
+    fn test_draw_indexed_indirect() {
+        let mut cb = CommandBuffer::new(0, 0);
+        cb.bind_index(5, IndexType::U32);
+        cb.call_draw_indexed_indirect(7, 2 * ::std::mem::size_of::<command::DrawIndexedIndirectArgs>(), 3);
+        match cb.buf.last() {
+            Some(&Command::DrawIndexedIndirect(_, gl::UNSIGNED_INT, 7, ref offset, 3)) =>
+                assert_eq!(offset.0 as usize, 40),
+            _ => panic!("Unexpected commands: {:?}", cb.buf),
+        }
+    }
 }
@@ -16,16 +16,16 @@ use std::rc::Rc;
 use std::{slice, ptr};
 
 use {gl, tex};
-use core::{self as d, factory as f, texture as t, buffer, mapping};
-use core::memory::{self, Bind, SHADER_RESOURCE, UNORDERED_ACCESS, Typed};
-use core::format::ChannelType;
+use core::{self as d, factory as f, texture as t, buffer, mapping, query};
+use core::memory::{self, Bind, SHADER_RESOURCE, UNORDERED_ACCESS, RENDER_TARGET, DEPTH_STENCIL, Typed};
+use core::format::{ChannelType, SurfaceType};
 use core::handle::{self, Producer};
 use core::target::{Layer, Level};
 
 use command::{CommandBuffer, COLOR_DEFAULT};
 use {Resources as R, Share, OutputMerger};
 use {Buffer, BufferElement, FatSampler, NewTexture,
-     PipelineState, ResourceView, TargetView, Fence};
+     PipelineState, ResourceView, TargetView, Fence, Query};
 
 
 pub fn role_to_target(role: buffer::Role) -> gl::types::GLenum {
@@ -34,6 +34,7 @@ pub fn role_to_target(role: buffer::Role) -> gl::types::GLenum {
         buffer::Role::Index    => gl::ELEMENT_ARRAY_BUFFER,
         buffer::Role::Constant => gl::UNIFORM_BUFFER,
         buffer::Role::Staging  => gl::ARRAY_BUFFER,
+        buffer::Role::Indirect => gl::DRAW_INDIRECT_BUFFER,
     }
 }
 
@@ -89,7 +90,9 @@ impl Factory {
     }
 
     pub fn create_command_buffer(&mut self) -> CommandBuffer {
-        CommandBuffer::new(self.create_fbo_internal())
+        let fbo = self.create_fbo_internal();
+        let read_fbo = self.create_fbo_internal();
+        CommandBuffer::new(fbo, read_fbo)
     }
 
     fn create_fbo_internal(&mut self) -> gl::types::GLuint {
@@ -228,6 +231,10 @@ impl Factory {
                 shaders[3] = *ps.reference(frame_handles);
                 &shaders[..4]
             },
+            &d::ShaderSet::Compute(ref cs) => {
+                shaders[0] = *cs.reference(frame_handles);
+                &shaders[..1]
+            },
         };
         let result = create_program(&self.share.context, &self.share.capabilities,
                                     &self.share.private_caps, shader_slice, usage);
@@ -237,6 +244,26 @@ impl Factory {
         result
     }
 
+    fn is_compression_supported(&self, surface: SurfaceType) -> bool {
+        use core::format::SurfaceType as S;
+        let caps = &self.share.private_caps;
+        match surface {
+            S::BC1_R8_G8_B8_A8 | S::BC2_R8_G8_B8_A8 | S::BC3_R8_G8_B8_A8 =>
+                caps.texture_compression_s3tc_supported,
+            S::BC4_R8 | S::BC5_R8_G8 => caps.texture_compression_rgtc_supported,
+            S::BC6H_R16_G16_B16_UF | S::BC6H_R16_G16_B16_SF | S::BC7_R8_G8_B8_A8 =>
+                caps.texture_compression_bptc_supported,
+            S::ETC2_R8_G8_B8 | S::ETC2_R8_G8_B8_A1 | S::ETC2_R8_G8_B8_A8 |
+            S::EAC_R11 | S::EAC_R11_G11 => self.share.capabilities.etc2_compression_supported,
+            S::ASTC_4x4 | S::ASTC_5x4 | S::ASTC_5x5 | S::ASTC_6x5 | S::ASTC_6x6 |
+            S::ASTC_8x5 | S::ASTC_8x6 | S::ASTC_8x8 | S::ASTC_10x5 | S::ASTC_10x6 |
+            S::ASTC_10x8 | S::ASTC_10x10 | S::ASTC_12x10 | S::ASTC_12x12 =>
+                self.share.capabilities.astc_compression_supported,
+            // not a compressed format
+            _ => false,
+        }
+    }
+
     fn view_texture_as_target(&mut self, htex: &handle::RawTexture<R>, level: Level, layer: Option<Layer>)
                               -> Result<TargetView, f::TargetViewError> {
         match (self.frame_handles.ref_texture(htex), layer) {
@@ -414,9 +441,17 @@ impl f::Factory<R> for Factory {
         if dim.1 as usize > max_size {
             return Err(CreationError::Size(dim.1));
         }
+        if desc.format.is_compressed() {
+            // compressed textures can only be sampled
+            if !self.is_compression_supported(desc.format) ||
+                desc.bind.intersects(RENDER_TARGET | DEPTH_STENCIL | UNORDERED_ACCESS) {
+                return Err(CreationError::Format(desc.format, hint))
+            }
+        }
         let cty = hint.unwrap_or(ChannelType::Uint); //careful here
         let gl = &self.share.context;
-        let object = if desc.bind.intersects(SHADER_RESOURCE | UNORDERED_ACCESS) || data_opt.is_some() {
+        let object = if desc.bind.intersects(SHADER_RESOURCE | UNORDERED_ACCESS) ||
+                        desc.format.is_compressed() || data_opt.is_some() {
             let name = if caps.immutable_storage_supported {
                 try!(tex::make_with_storage(gl, &desc, cty))
             } else {
@@ -510,6 +545,51 @@ impl f::Factory<R> for Factory {
         self.share.handles.borrow_mut().make_sampler(sam, info)
     }
 
+    fn create_query(&mut self, ty: query::QueryType) -> Result<handle::Query<R>, query::CreationError> {
+        let target = match ty {
+            query::QueryType::Occlusion if self.share.private_caps.occlusion_query_supported =>
+                gl::SAMPLES_PASSED,
+            query::QueryType::AnySamplesPassed if self.share.private_caps.occlusion_query2_supported =>
+                gl::ANY_SAMPLES_PASSED,
+            query::QueryType::Timestamp if self.share.private_caps.timer_query_supported =>
+                gl::TIMESTAMP,
+            _ => return Err(query::CreationError::UnsupportedType(ty)),
+        };
+        let mut name = 0;
+        unsafe { self.share.context.GenQueries(1, &mut name) };
+        info!("\tCreated query {} of type {:?}", name, ty);
+        if let Err(err) = self.share.check() {
+            panic!("Error {:?} creating query: {:?}", err, ty)
+        }
+        let query = Query {
+            object: name,
+            target: target,
+        };
+        Ok(self.share.handles.borrow_mut().make_query(query, ty))
+    }
+
+    fn get_query_result(&mut self, query: &handle::Query<R>) -> Option<u64> {
+        if !query.is_submitted() {
+            return None
+        }
+        let gl = &self.share.context;
+        let name = query.resource().object;
+        let mut available = 0;
+        unsafe { gl.GetQueryObjectuiv(name, gl::QUERY_RESULT_AVAILABLE, &mut available) };
+        if available == 0 {
+            return None
+        }
+        if self.share.private_caps.timer_query_supported {
+            let mut result = 0;
+            unsafe { gl.GetQueryObjectui64v(name, gl::QUERY_RESULT, &mut result) };
+            Some(result)
+        } else {
+            let mut result = 0;
+            unsafe { gl.GetQueryObjectuiv(name, gl::QUERY_RESULT, &mut result) };
+            Some(result as u64)
+        }
+    }
+
     fn read_mapping<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>)
                                -> Result<mapping::Reader<'b, R, T>,
                                          mapping::Error>
@@ -551,6 +631,32 @@ impl f::Factory<R> for Factory {
             })
         }
     }
+
+    fn write_mapping_region<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>,
+                                       region: usize, num_regions: usize)
+                                       -> Result<mapping::Writer<'b, R, T>,
+                                                 mapping::Error>
+        where T: Copy
+    {
+        // check the region before it is used to track the accesses
+        try!(mapping::get_region(buf.len(), region, num_regions));
+        let gl = &self.share.context;
+        let handles = &mut self.frame_handles;
+        let writer = unsafe {
+            mapping::write(buf.raw(), |mapping| match mapping.kind {
+                MappingKind::Persistent(ref mut status) =>
+                    status.cpu_write_region_access(region, num_regions,
+                        |fence| wait_fence(&handles.ref_fence(&fence), gl)),
+                MappingKind::Temporary =>
+                    temporary_ensure_mapped(&mut mapping.pointer,
+                                            role_to_target(buf.get_info().role),
+                                            *buf.raw().resource(),
+                                            memory::WRITE,
+                                            gl),
+            })
+        };
+        writer.and_then(|w| w.into_region(region, num_regions))
+    }
 }
 
 pub fn wait_fence(fence: &Fence, gl: &gl::Gl) {
@@ -564,3 +670,10 @@ pub fn wait_fence(fence: &Fence, gl: &gl::Gl) {
         gl.ClientWaitSync(fence.0, gl::SYNC_FLUSH_COMMANDS_BIT, timeout);
     }
 }
+
+pub fn is_fence_signaled(fence: &Fence, gl: &gl::Gl) -> bool {
+    let status = unsafe {
+        gl.ClientWaitSync(fence.0, gl::SYNC_FLUSH_COMMANDS_BIT, 0)
+    };
+    status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+}
@@ -170,6 +170,15 @@ pub struct PrivateCaps {
     pub program_interface_supported: bool,
     pub buffer_storage_supported: bool,
     pub clear_buffer_supported: bool,
+    pub multi_draw_indirect_supported: bool,
+    pub occlusion_query_supported: bool,
+    pub occlusion_query2_supported: bool,
+    pub conditional_render_supported: bool,
+    pub timer_query_supported: bool,
+    pub copy_image_supported: bool,
+    pub texture_compression_s3tc_supported: bool,
+    pub texture_compression_rgtc_supported: bool,
+    pub texture_compression_bptc_supported: bool,
 }
 
 /// OpenGL implementation information
@@ -230,33 +239,65 @@ impl Info {
 pub fn get(gl: &gl::Gl) -> (Info, Capabilities, PrivateCaps) {
     let info = Info::get(gl);
     let tessellation_supported =           info.is_version_or_extension_supported(4, 0, "GL_ARB_tessellation_shader");
+    let constant_buffer_supported =        info.is_version_or_extension_supported(3, 1, "GL_ARB_uniform_buffer_object");
+    let frame_buffer_supported =           info.is_version_or_extension_supported(3, 0, "GL_ARB_framebuffer_object") |
+                                           info.is_embedded_version_supported(2, 0);
+    let copy_image_supported =             info.is_version_or_extension_supported(4, 3, "GL_ARB_copy_image") |
+                                           info.is_embedded_version_supported(3, 2);
+    let s3tc_supported =                   info.is_extension_supported("GL_EXT_texture_compression_s3tc");
+    let rgtc_supported =                   info.is_version_or_extension_supported(3, 0, "GL_ARB_texture_compression_rgtc");
+    let bptc_supported =                   info.is_version_or_extension_supported(4, 2, "GL_ARB_texture_compression_bptc");
     let caps = Capabilities {
         max_vertex_count: get_usize(gl, gl::MAX_ELEMENTS_VERTICES),
         max_index_count:  get_usize(gl, gl::MAX_ELEMENTS_INDICES),
         max_texture_size: get_usize(gl, gl::MAX_TEXTURE_SIZE),
         max_patch_size: if tessellation_supported { get_usize(gl, gl::MAX_PATCH_VERTICES) } else {0},
+        constant_buffer_alignment: if constant_buffer_supported { get_usize(gl, gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT) } else {0},
 
         instance_base_supported:           info.is_version_or_extension_supported(4, 2, "GL_ARB_base_instance"),
         instance_call_supported:           info.is_version_or_extension_supported(3, 1, "GL_ARB_draw_instanced"),
         instance_rate_supported:           info.is_version_or_extension_supported(3, 3, "GL_ARB_instanced_arrays"),
         vertex_base_supported:             info.is_version_or_extension_supported(3, 2, "GL_ARB_draw_elements_base_vertex"),
         srgb_color_supported:              info.is_version_or_extension_supported(3, 2, "GL_ARB_framebuffer_sRGB"),
-        constant_buffer_supported:         info.is_version_or_extension_supported(3, 1, "GL_ARB_uniform_buffer_object"),
+        constant_buffer_supported:         constant_buffer_supported,
+        constant_buffer_range_supported:   constant_buffer_supported,
         unordered_access_view_supported:   info.is_version_supported(4, 0), //TODO: extension
         separate_blending_slots_supported: info.is_version_or_extension_supported(4, 0, "GL_ARB_draw_buffers_blend"),
         copy_buffer_supported:             info.is_version_or_extension_supported(3, 1, "GL_ARB_copy_buffer") |
                                            info.is_embedded_version_supported(3, 0) |
                                           (info.is_embedded_version_supported(2, 0) & info.is_extension_supported("GL_NV_copy_buffer")),
+        copy_texture_supported:            copy_image_supported | frame_buffer_supported,
+        blit_texture_supported:            frame_buffer_supported,
+        resolve_texture_supported:         frame_buffer_supported,
+        // the unordered views, needed to get anything out of a compute shader, are not bound yet
+        compute_supported:                 false,
+        draw_indirect_supported:           info.is_version_or_extension_supported(4, 0, "GL_ARB_draw_indirect") |
+                                           info.is_embedded_version_supported(3, 1),
+        bc_compression_supported:          s3tc_supported & rgtc_supported & bptc_supported,
+        etc2_compression_supported:        info.is_version_or_extension_supported(4, 3, "GL_ARB_ES3_compatibility") |
+                                           info.is_embedded_version_supported(3, 0),
+        astc_compression_supported:        info.is_extension_supported("GL_KHR_texture_compression_astc_ldr") |
+                                           info.is_embedded_version_supported(3, 2),
     };
     let private = PrivateCaps {
         array_buffer_supported:            info.is_version_or_extension_supported(3, 0, "GL_ARB_vertex_array_object"),
-        frame_buffer_supported:            info.is_version_or_extension_supported(3, 0, "GL_ARB_framebuffer_object") |
-                                           info.is_embedded_version_supported(2, 0),
+        frame_buffer_supported:            frame_buffer_supported,
         immutable_storage_supported:       info.is_version_or_extension_supported(4, 2, "GL_ARB_texture_storage"),
         sampler_objects_supported:         info.is_version_or_extension_supported(3, 3, "GL_ARB_sampler_objects"),
         program_interface_supported:       info.is_version_or_extension_supported(4, 3, "GL_ARB_program_interface_query"),
         buffer_storage_supported:          info.is_version_or_extension_supported(4, 4, "GL_ARB_buffer_storage"),
         clear_buffer_supported:            info.is_version_supported(3, 0) | info.is_embedded_version_supported(3, 0),
+        multi_draw_indirect_supported:     info.is_version_or_extension_supported(4, 3, "GL_ARB_multi_draw_indirect"),
+        // GLES only has the boolean occlusion queries
+        occlusion_query_supported:         info.is_version_or_extension_supported(1, 5, "GL_ARB_occlusion_query"),
+        occlusion_query2_supported:        info.is_version_or_extension_supported(3, 3, "GL_ARB_occlusion_query2") |
+                                           info.is_embedded_version_supported(3, 0),
+        conditional_render_supported:      info.is_version_or_extension_supported(3, 0, "GL_NV_conditional_render"),
+        timer_query_supported:             info.is_version_or_extension_supported(3, 3, "GL_ARB_timer_query"),
+        copy_image_supported:              copy_image_supported,
+        texture_compression_s3tc_supported: s3tc_supported,
+        texture_compression_rgtc_supported: rgtc_supported,
+        texture_compression_bptc_supported: bptc_supported,
     };
     (info, caps, private)
 }
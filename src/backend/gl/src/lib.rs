@@ -24,6 +24,7 @@ extern crate gfx_gl as gl;
 extern crate gfx_core as core;
 
 use std::cell::RefCell;
+use std::mem;
 use std::rc::Rc;
 use core::{self as c, handle, state as s, format, pso, texture, memory, command as com, buffer};
 use core::target::{Layer, Level};
@@ -56,6 +57,12 @@ pub struct Fence(gl::types::GLsync);
 unsafe impl Send for Fence {}
 unsafe impl Sync for Fence {}
 
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Query {
+    object: gl::types::GLuint,
+    target: gl::types::GLenum,
+}
+
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Resources {}
 
@@ -71,6 +78,7 @@ impl c::Resources for Resources {
     type UnorderedAccessView = ();
     type Sampler             = FatSampler;
     type Fence               = Fence;
+    type Query               = Query;
     type Mapping             = factory::MappingGate;
 }
 
@@ -188,7 +196,7 @@ pub fn create_main_targets_raw(dim: texture::Dimensions, color_format: format::S
             levels: 1,
             kind: texture::Kind::D2(dim.0, dim.1, dim.3),
             format: color_format,
-            bind: memory::RENDER_TARGET | memory::TRANSFER_SRC,
+            bind: memory::RENDER_TARGET | memory::TRANSFER_SRC | memory::TRANSFER_DST,
             usage: memory::Usage::Data,
         },
     );
@@ -207,6 +215,16 @@ pub fn create_main_targets_raw(dim: texture::Dimensions, color_format: format::S
     (m_color, m_ds)
 }
 
+/// Get the buffer mask and the framebuffer attachment of a surface, for blits and reads.
+fn get_blit_attachment(surface: format::SurfaceType) -> (gl::types::GLbitfield, gl::types::GLenum) {
+    use core::format::SurfaceType as S;
+    match surface {
+        S::D24_S8 => (gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT, gl::DEPTH_STENCIL_ATTACHMENT),
+        S::D16 | S::D24 | S::D32 => (gl::DEPTH_BUFFER_BIT, gl::DEPTH_ATTACHMENT),
+        _ => (gl::COLOR_BUFFER_BIT, gl::COLOR_ATTACHMENT0),
+    }
+}
+
 /// Internal struct of shared data between the device and its factories.
 #[doc(hidden)]
 pub struct Share {
@@ -394,6 +412,74 @@ impl Device {
         unsafe { gl.FramebufferTexture(point, attachment, 0, 0) };
     }
 
+    fn bind_blit_target(&mut self, point: gl::types::GLenum, fbo: FrameBuffer,
+                        attachment: gl::types::GLenum, region: &command::TextureRegion) {
+        let view = match region.texture {
+            NewTexture::Surface(0) => {
+                unsafe { self.share.context.BindFramebuffer(point, 0) };
+                return
+            },
+            NewTexture::Surface(s) => TargetView::Surface(s),
+            NewTexture::Texture(t) => {
+                match tex::get_layer(region.kind, region.face, region.info.zoffset) {
+                    Some(layer) => TargetView::TextureLayer(t, region.info.mipmap, layer),
+                    None => TargetView::Texture(t, region.info.mipmap),
+                }
+            },
+        };
+        unsafe { self.share.context.BindFramebuffer(point, fbo) };
+        self.bind_target(point, attachment, &view);
+    }
+
+    fn blit(&mut self, read_fbo: FrameBuffer, draw_fbo: FrameBuffer,
+            src: &command::TextureRegion, dst: &command::TextureRegion,
+            mirror: c::target::Mirror, filter: gl::types::GLenum) {
+        use std::cmp::max;
+        type GLint = gl::types::GLint;
+
+        if !self.share.private_caps.frame_buffer_supported {
+            error!("Blits are not supported without FBO support");
+            return
+        }
+        let (mask, attachment) = get_blit_attachment(src.info.format.0);
+        self.bind_blit_target(gl::READ_FRAMEBUFFER, read_fbo, attachment, src);
+        self.bind_blit_target(gl::DRAW_FRAMEBUFFER, draw_fbo, attachment, dst);
+        let draw_default = dst.texture == NewTexture::Surface(0);
+        if mask == gl::COLOR_BUFFER_BIT && !draw_default {
+            state::bind_draw_color_buffers(&self.share.context, 1);
+        }
+        // mirror
+        let (mut s_x0, mut s_x1) = (src.info.xoffset, src.info.xoffset + src.info.width);
+        let (mut s_y0, mut s_y1) = (src.info.yoffset, src.info.yoffset + max(1, src.info.height));
+        if mirror.intersects(c::target::MIRROR_X) {
+            mem::swap(&mut s_x0, &mut s_x1);
+        }
+        if mirror.intersects(c::target::MIRROR_Y) {
+            mem::swap(&mut s_y0, &mut s_y1);
+        }
+        unsafe {
+            self.share.context.BlitFramebuffer(
+                s_x0 as GLint,
+                s_y0 as GLint,
+                s_x1 as GLint,
+                s_y1 as GLint,
+                dst.info.xoffset as GLint,
+                dst.info.yoffset as GLint,
+                (dst.info.xoffset + dst.info.width) as GLint,
+                (dst.info.yoffset + max(1, dst.info.height)) as GLint,
+                mask,
+                filter
+            )
+        };
+        // detach the images, so that the frame buffers don't keep them alive
+        if src.texture != NewTexture::Surface(0) {
+            self.unbind_target(gl::READ_FRAMEBUFFER, attachment);
+        }
+        if !draw_default {
+            self.unbind_target(gl::DRAW_FRAMEBUFFER, attachment);
+        }
+    }
+
     fn reset_state(&mut self) {
         let data = DataBuffer::new();
         for com in command::RESET.iter() {
@@ -471,14 +557,19 @@ impl Device {
             Command::BindProgram(program) => unsafe {
                 self.share.context.UseProgram(program);
             },
-            Command::BindConstantBuffer(pso::ConstantBufferParam(buffer, _, slot)) => unsafe {
-                self.share.context.BindBufferBase(gl::UNIFORM_BUFFER, slot as gl::types::GLuint, buffer);
+            Command::BindConstantBuffer(pso::ConstantBufferParam(buffer, _, slot, range)) => unsafe {
+                match range {
+                    Some((offset, size)) => self.share.context.BindBufferRange(gl::UNIFORM_BUFFER,
+                        slot as gl::types::GLuint, buffer,
+                        offset as gl::types::GLintptr, size as gl::types::GLsizeiptr),
+                    None => self.share.context.BindBufferBase(gl::UNIFORM_BUFFER, slot as gl::types::GLuint, buffer),
+                }
             },
             Command::BindResourceView(pso::ResourceViewParam(view, _, slot)) => unsafe {
                 self.share.context.ActiveTexture(gl::TEXTURE0 + slot as gl::types::GLenum);
                 self.share.context.BindTexture(view.bind, view.object);
             },
-            Command::BindUnorderedView(_uav) => unimplemented!(),
+            Command::BindUnorderedView(_) => error!("Unordered views are not supported"),
             Command::BindSampler(pso::SamplerParam(sampler, _, slot), bind_opt) => {
                 let gl = &self.share.context;
                 if self.share.private_caps.sampler_objects_supported {
@@ -594,7 +685,18 @@ impl Device {
                     Err(e) => error!("GL: {:?} failed: {:?}", cmd, e)
                 }
             },
-            Command::CopyTextureToBuffer(src, kind, face, img, dst, dst_offset) => {
+            Command::CopyTextureToBuffer(read_fbo, src, kind, face, img, dst, dst_offset) => {
+                if let NewTexture::Surface(_) = src {
+                    // renderbuffers are only readable through a framebuffer
+                    let region = command::TextureRegion {
+                        texture: src,
+                        kind: kind,
+                        face: face,
+                        info: img,
+                    };
+                    let (_, attachment) = get_blit_attachment(img.format.0);
+                    self.bind_blit_target(gl::READ_FRAMEBUFFER, read_fbo, attachment, &region);
+                }
                 match tex::copy_to_buffer(&self.share.context, src, kind, face, &img, dst, dst_offset) {
                     Ok(_) => (),
                     Err(e) => error!("GL: {:?} failed: {:?}", cmd, e)
@@ -711,52 +813,95 @@ impl Device {
                     },
                 }
             },
-            Command::_Blit(mut s_rect, d_rect, mirror, _) => {
-                type GLint = gl::types::GLint;
-                // mirror
-                let mut s_end_x = s_rect.x + s_rect.w;
-                let mut s_end_y = s_rect.y + s_rect.h;
-                if mirror.intersects(c::target::MIRROR_X) {
-                    s_end_x = s_rect.x;
-                    s_rect.x += s_rect.w;
+            Command::DrawIndirect(primitive, buffer, offset, count) => {
+                let gl = &self.share.context;
+                if !self.share.capabilities.draw_indirect_supported {
+                    error!("Indirect draw calls are not supported");
+                } else if self.share.private_caps.multi_draw_indirect_supported {
+                    unsafe {
+                        gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, buffer);
+                        gl.MultiDrawArraysIndirect(primitive, offset.0,
+                            count as gl::types::GLsizei, 0);
+                    }
+                } else {
+                    let stride = mem::size_of::<com::DrawIndirectArgs>();
+                    unsafe {
+                        gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, buffer);
+                        for i in 0 .. count {
+                            gl.DrawArraysIndirect(primitive,
+                                (offset.0 as usize + i * stride) as *const gl::types::GLvoid);
+                        }
+                    }
+                }
+            },
+            Command::DrawIndexedIndirect(primitive, index_type, buffer, offset, count) => {
+                let gl = &self.share.context;
+                if !self.share.capabilities.draw_indirect_supported {
+                    error!("Indirect draw calls are not supported");
+                } else if self.share.private_caps.multi_draw_indirect_supported {
+                    unsafe {
+                        gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, buffer);
+                        gl.MultiDrawElementsIndirect(primitive, index_type, offset.0,
+                            count as gl::types::GLsizei, 0);
+                    }
+                } else {
+                    let stride = mem::size_of::<com::DrawIndexedIndirectArgs>();
+                    unsafe {
+                        gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, buffer);
+                        for i in 0 .. count {
+                            gl.DrawElementsIndirect(primitive, index_type,
+                                (offset.0 as usize + i * stride) as *const gl::types::GLvoid);
+                        }
+                    }
                 }
-                if mirror.intersects(c::target::MIRROR_Y) {
-                    s_end_y = s_rect.y;
-                    s_rect.y += s_rect.h;
+            },
+            Command::BeginQuery(query) => unsafe {
+                self.share.context.BeginQuery(query.target, query.object);
+            },
+            Command::EndQuery(query) => unsafe {
+                self.share.context.EndQuery(query.target);
+            },
+            Command::WriteTimestamp(query) => unsafe {
+                self.share.context.QueryCounter(query.object, query.target);
+            },
+            Command::BeginConditional(query) => {
+                if self.share.private_caps.conditional_render_supported {
+                    unsafe {
+                        self.share.context.BeginConditionalRender(query.object, gl::QUERY_WAIT);
+                    }
+                } else {
+                    error!("Conditional rendering is not supported");
                 }
-                // build mask
-                let flags = 0;
-                error!("Blit mask setup is not implemented");
-                /*TODO
-                if mask.intersects(d::target::COLOR) {
-                    flags |= gl::COLOR_BUFFER_BIT;
+            },
+            Command::EndConditional => {
+                if self.share.private_caps.conditional_render_supported {
+                    unsafe { self.share.context.EndConditionalRender(); }
                 }
-                if mask.intersects(d::target::DEPTH) {
-                    flags |= gl::DEPTH_BUFFER_BIT;
+            },
+            Command::Dispatch(groups) => {
+                if self.share.capabilities.compute_supported {
+                    let gl = &self.share.context;
+                    unsafe {
+                        gl.DispatchCompute(groups[0], groups[1], groups[2]);
+                        gl.MemoryBarrier(gl::ALL_BARRIER_BITS);
+                    }
+                } else {
+                    error!("Compute dispatch is not supported");
+                }
+            },
+            Command::CopyTexture(read_fbo, draw_fbo, ref src, ref dst) => {
+                let is_default = |r: &command::TextureRegion| r.texture == NewTexture::Surface(0);
+                if self.share.private_caps.copy_image_supported && !is_default(src) && !is_default(dst) {
+                    tex::copy_texture(&self.share.context, src, dst);
+                } else {
+                    let mut region = *dst;
+                    region.info.width = src.info.width;
+                    region.info.height = src.info.height;
+                    self.blit(read_fbo, draw_fbo, src, &region, c::target::Mirror::empty(), gl::NEAREST);
                 }
-                if mask.intersects(d::target::STENCIL) {
-                    flags |= gl::STENCIL_BUFFER_BIT;
-                }*/
-                // build filter
-                let filter = if s_rect.w == d_rect.w && s_rect.h == d_rect.h {
-                    gl::NEAREST
-                }else {
-                    gl::LINEAR
-                };
-                // blit
-                let gl = &self.share.context;
-                unsafe { gl.BlitFramebuffer(
-                    s_rect.x as GLint,
-                    s_rect.y as GLint,
-                    s_end_x as GLint,
-                    s_end_y as GLint,
-                    d_rect.x as GLint,
-                    d_rect.y as GLint,
-                    (d_rect.x + d_rect.w) as GLint,
-                    (d_rect.y + d_rect.h) as GLint,
-                    flags,
-                    filter
-                ) };
+            },
+            Command::Blit(read_fbo, draw_fbo, ref src, ref dst, mirror, filter) => {
+                self.blit(read_fbo, draw_fbo, src, dst, mirror, filter);
             },
         }
         if let Err(err) = self.share.check() {
@@ -919,6 +1064,11 @@ impl c::Device for Device {
                             &self.share.context);
     }
 
+    fn is_fence_signaled(&mut self, fence: &handle::Fence<Self::Resources>) -> bool {
+        factory::is_fence_signaled(self.frame_handles.ref_fence(&fence),
+                                   &self.share.context)
+    }
+
     fn cleanup(&mut self) {
         use core::handle::Producer;
         self.frame_handles.clear();
@@ -955,6 +1105,7 @@ impl c::Device for Device {
             |_, _| {}, //DSV
             |gl, v| unsafe { if v.object != 0 { gl.DeleteSamplers(1, &v.object) }},
             |gl, fence| unsafe { gl.DeleteSync(fence.0) },
+            |gl, query| unsafe { gl.DeleteQueries(1, &query.resource().object) },
         );
     }
 }
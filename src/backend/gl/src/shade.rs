@@ -61,6 +61,7 @@ pub fn create_shader(gl: &gl::Gl, stage: s::Stage, data: &[u8])
         s::Stage::Domain => gl::TESS_EVALUATION_SHADER,
         s::Stage::Geometry => gl::GEOMETRY_SHADER,
         s::Stage::Pixel => gl::FRAGMENT_SHADER,
+        s::Stage::Compute => gl::COMPUTE_SHADER,
     };
     let name = unsafe { gl.CreateShader(target) };
     unsafe {
@@ -252,6 +253,7 @@ fn query_blocks(gl: &gl::Gl, caps: &c::Capabilities, prog: super::Program,
                 (s::VERTEX,   gl::UNIFORM_BLOCK_REFERENCED_BY_VERTEX_SHADER),
                 (s::GEOMETRY, gl::UNIFORM_BLOCK_REFERENCED_BY_GEOMETRY_SHADER),
                 (s::PIXEL,    gl::UNIFORM_BLOCK_REFERENCED_BY_FRAGMENT_SHADER),
+                (s::COMPUTE,  gl::UNIFORM_BLOCK_REFERENCED_BY_COMPUTE_SHADER),
             ];
             let mut usage = s::Usage::empty();
             for &(stage, eval) in usage_list.iter() {
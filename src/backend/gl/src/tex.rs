@@ -15,6 +15,7 @@
 use {gl, Surface, Texture, NewTexture, Buffer, Sampler};
 use gl::types::{GLenum, GLuint, GLint, GLfloat, GLsizei, GLvoid};
 use state;
+use command::TextureRegion;
 use core::memory::SHADER_RESOURCE;
 use core::format::{Format as NewFormat, ChannelType};
 use core::texture as t;
@@ -45,6 +46,29 @@ pub fn kind_to_gl(kind: t::Kind) -> GLenum {
     }
 }
 
+fn cube_face_to_layer(face: t::CubeFace) -> t::Layer {
+    match face {
+        t::CubeFace::PosX => 0,
+        t::CubeFace::NegX => 1,
+        t::CubeFace::PosY => 2,
+        t::CubeFace::NegY => 3,
+        t::CubeFace::PosZ => 4,
+        t::CubeFace::NegZ => 5,
+    }
+}
+
+/// Get the layer selected by a cube face and a depth offset,
+/// or `None` if the texture kind is not layered.
+pub fn get_layer(kind: t::Kind, face: Option<t::CubeFace>, zoffset: t::Size) -> Option<t::Layer> {
+    match kind {
+        t::Kind::D1(_) | t::Kind::D2(_, _, _) => None,
+        t::Kind::Cube(_) | t::Kind::CubeArray(_, _) =>
+            Some(zoffset + face.map_or(0, cube_face_to_layer)),
+        t::Kind::D1Array(_, _) | t::Kind::D2Array(_, _, _, _) | t::Kind::D3(_, _, _) =>
+            Some(zoffset),
+    }
+}
+
 fn kind_face_to_gl(kind: t::Kind, face: Option<t::CubeFace>) -> GLenum {
     match face {
         Some(f) => cube_face_to_gl(f),
@@ -68,6 +92,8 @@ fn format_to_glpixel(format: NewFormat) -> GLenum {
         S::D24_S8 => gl::DEPTH_STENCIL,
         S::D16 | S::D24 | S::D32 => gl::DEPTH,
         S::B8_G8_R8_A8 => unimplemented!(), // TODO
+        // compressed formats, only used when allocating the storage
+        _ => rgba,
     }
 }
 
@@ -97,9 +123,103 @@ fn format_to_gltype(format: NewFormat) -> Result<GLenum, ()> {
         S::D24 => gl::UNSIGNED_INT,
         S::D24_S8 => gl::UNSIGNED_INT_24_8,
         S::D32 => gl::FLOAT,
+        // compressed formats, only used when allocating the storage
+        _ => gl::UNSIGNED_BYTE,
     })
 }
 
+// compressed formats, coming from extensions
+const COMPRESSED_RGBA_S3TC_DXT1_EXT: GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3_EXT: GLenum = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: GLenum = 0x83F3;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT: GLenum = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT: GLenum = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT: GLenum = 0x8C4F;
+const COMPRESSED_RED_RGTC1: GLenum = 0x8DBB;
+const COMPRESSED_SIGNED_RED_RGTC1: GLenum = 0x8DBC;
+const COMPRESSED_RG_RGTC2: GLenum = 0x8DBD;
+const COMPRESSED_SIGNED_RG_RGTC2: GLenum = 0x8DBE;
+const COMPRESSED_RGBA_BPTC_UNORM: GLenum = 0x8E8C;
+const COMPRESSED_SRGB_ALPHA_BPTC_UNORM: GLenum = 0x8E8D;
+const COMPRESSED_RGB_BPTC_SIGNED_FLOAT: GLenum = 0x8E8E;
+const COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT: GLenum = 0x8E8F;
+const COMPRESSED_R11_EAC: GLenum = 0x9270;
+const COMPRESSED_SIGNED_R11_EAC: GLenum = 0x9271;
+const COMPRESSED_RG11_EAC: GLenum = 0x9272;
+const COMPRESSED_SIGNED_RG11_EAC: GLenum = 0x9273;
+const COMPRESSED_RGB8_ETC2: GLenum = 0x9274;
+const COMPRESSED_SRGB8_ETC2: GLenum = 0x9275;
+const COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2: GLenum = 0x9276;
+const COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2: GLenum = 0x9277;
+const COMPRESSED_RGBA8_ETC2_EAC: GLenum = 0x9278;
+const COMPRESSED_SRGB8_ALPHA8_ETC2_EAC: GLenum = 0x9279;
+/// `COMPRESSED_RGBA_ASTC_4x4_KHR`, followed by the other block sizes
+const COMPRESSED_RGBA_ASTC_KHR: GLenum = 0x93B0;
+/// `COMPRESSED_SRGB8_ALPHA8_ASTC_4x4_KHR`, followed by the other block sizes
+const COMPRESSED_SRGB8_ALPHA8_ASTC_KHR: GLenum = 0x93D0;
+
+fn compressed_to_gl(format: NewFormat) -> Result<GLenum, ()> {
+    use core::format::SurfaceType as S;
+    use core::format::ChannelType as C;
+    let (unorm, srgb) = match format.0 {
+        S::BC1_R8_G8_B8_A8 => (COMPRESSED_RGBA_S3TC_DXT1_EXT, COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT),
+        S::BC2_R8_G8_B8_A8 => (COMPRESSED_RGBA_S3TC_DXT3_EXT, COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT),
+        S::BC3_R8_G8_B8_A8 => (COMPRESSED_RGBA_S3TC_DXT5_EXT, COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT),
+        S::BC7_R8_G8_B8_A8 => (COMPRESSED_RGBA_BPTC_UNORM, COMPRESSED_SRGB_ALPHA_BPTC_UNORM),
+        S::ETC2_R8_G8_B8 => (COMPRESSED_RGB8_ETC2, COMPRESSED_SRGB8_ETC2),
+        S::ETC2_R8_G8_B8_A1 => (COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+                                COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2),
+        S::ETC2_R8_G8_B8_A8 => (COMPRESSED_RGBA8_ETC2_EAC, COMPRESSED_SRGB8_ALPHA8_ETC2_EAC),
+        S::BC4_R8 => return match format.1 {
+            C::Unorm => Ok(COMPRESSED_RED_RGTC1),
+            C::Inorm => Ok(COMPRESSED_SIGNED_RED_RGTC1),
+            _ => Err(()),
+        },
+        S::BC5_R8_G8 => return match format.1 {
+            C::Unorm => Ok(COMPRESSED_RG_RGTC2),
+            C::Inorm => Ok(COMPRESSED_SIGNED_RG_RGTC2),
+            _ => Err(()),
+        },
+        S::EAC_R11 => return match format.1 {
+            C::Unorm => Ok(COMPRESSED_R11_EAC),
+            C::Inorm => Ok(COMPRESSED_SIGNED_R11_EAC),
+            _ => Err(()),
+        },
+        S::EAC_R11_G11 => return match format.1 {
+            C::Unorm => Ok(COMPRESSED_RG11_EAC),
+            C::Inorm => Ok(COMPRESSED_SIGNED_RG11_EAC),
+            _ => Err(()),
+        },
+        S::BC6H_R16_G16_B16_UF => return Ok(COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT),
+        S::BC6H_R16_G16_B16_SF => return Ok(COMPRESSED_RGB_BPTC_SIGNED_FLOAT),
+        astc => {
+            let index = match astc {
+                S::ASTC_4x4 => 0,
+                S::ASTC_5x4 => 1,
+                S::ASTC_5x5 => 2,
+                S::ASTC_6x5 => 3,
+                S::ASTC_6x6 => 4,
+                S::ASTC_8x5 => 5,
+                S::ASTC_8x6 => 6,
+                S::ASTC_8x8 => 7,
+                S::ASTC_10x5 => 8,
+                S::ASTC_10x6 => 9,
+                S::ASTC_10x8 => 10,
+                S::ASTC_10x10 => 11,
+                S::ASTC_12x10 => 12,
+                S::ASTC_12x12 => 13,
+                _ => return Err(()),
+            };
+            (COMPRESSED_RGBA_ASTC_KHR + index, COMPRESSED_SRGB8_ALPHA8_ASTC_KHR + index)
+        },
+    };
+    match format.1 {
+        C::Unorm => Ok(unorm),
+        C::Srgb => Ok(srgb),
+        _ => Err(()),
+    }
+}
+
 fn format_to_glfull(format: NewFormat) -> Result<GLenum, ()> {
     use core::format::SurfaceType as S;
     use core::format::ChannelType as C;
@@ -214,6 +334,7 @@ fn format_to_glfull(format: NewFormat) -> Result<GLenum, ()> {
         S::D24 => gl::DEPTH_COMPONENT24,
         S::D24_S8 => gl::DEPTH24_STENCIL8,
         S::D32 => gl::DEPTH_COMPONENT32F,
+        _ => return compressed_to_gl(format),
     })
 }
 
@@ -635,6 +756,50 @@ fn tex_sub_image<F>(gl: &gl::Gl, kind: t::Kind, target: GLenum, pix: GLenum,
     })
 }
 
+fn compressed_sub_image(gl: &gl::Gl, kind: t::Kind, target: GLenum,
+                        img: &t::RawImageInfo, data: *const GLvoid)
+                        -> Result<(), t::CreationError> {
+    let format = match compressed_to_gl(img.format) {
+        Ok(f) => f,
+        Err(_) => return Err(t::CreationError::Format(img.format.0, Some(img.format.1))),
+    };
+    let size = img.get_byte_count() as GLsizei;
+    Ok(match kind {
+        t::Kind::D2(_, _, t::AaMode::Single) | t::Kind::Cube(_) => unsafe {
+            gl.CompressedTexSubImage2D(
+                target,
+                img.mipmap as GLint,
+                img.xoffset as GLint,
+                img.yoffset as GLint,
+                img.width as GLint,
+                img.height as GLint,
+                format,
+                size,
+                data
+            );
+        },
+        t::Kind::D2Array(_, _, _, t::AaMode::Single) | t::Kind::D3(_, _, _) => unsafe {
+            gl.CompressedTexSubImage3D(
+                target,
+                img.mipmap as GLint,
+                img.xoffset as GLint,
+                img.yoffset as GLint,
+                img.zoffset as GLint,
+                img.width as GLint,
+                img.height as GLint,
+                img.depth as GLint,
+                format,
+                size,
+                data
+            );
+        },
+        t::Kind::D2(_, _, aa) => return Err(t::CreationError::Samples(aa)),
+        t::Kind::D2Array(_, _, _, aa) => return Err(t::CreationError::Samples(aa)),
+        // one-dimensional and cube array textures can not be compressed
+        _ => return Err(t::CreationError::Kind),
+    })
+}
+
 pub fn copy_from_buffer(gl: &gl::Gl,
                         dst: Texture,
                         kind: t::Kind,
@@ -647,6 +812,11 @@ pub fn copy_from_buffer(gl: &gl::Gl,
     let data = src_offset as *const GLvoid;
     unsafe { gl.BindBuffer(gl::PIXEL_UNPACK_BUFFER, src); }
 
+    if img.format.0.is_compressed() {
+        unsafe { gl.BindTexture(kind_to_gl(kind), dst); }
+        return compressed_sub_image(gl, kind, kind_face_to_gl(kind, face), img, data)
+    }
+
     let pixel_format = format_to_glpixel(img.format);
     let data_type = match format_to_gltype(img.format) {
         Ok(t) => t,
@@ -692,16 +862,20 @@ pub fn copy_to_buffer(gl: &gl::Gl,
                           img.height == h &&
                           img.depth == d);
             unsafe {
-                gl.GetTexImage(target,
-                               img.mipmap as GLint,
-                               pixel_format,
-                               data_type,
-                               data);
+                if img.format.0.is_compressed() {
+                    gl.GetCompressedTexImage(target, img.mipmap as GLint, data);
+                } else {
+                    gl.GetTexImage(target,
+                                   img.mipmap as GLint,
+                                   pixel_format,
+                                   data_type,
+                                   data);
+                }
             }
         }
-        NewTexture::Surface(s) => {
+        NewTexture::Surface(_) => {
+            // the surface is attached to the bound read framebuffer
             unsafe {
-                gl.BindFramebuffer(gl::READ_FRAMEBUFFER, s);
                 gl.ReadPixels(img.xoffset as GLint,
                               img.yoffset as GLint,
                               img.width as GLint,
@@ -716,12 +890,48 @@ pub fn copy_to_buffer(gl: &gl::Gl,
     Ok(())
 }
 
+fn copy_image_params(region: &TextureRegion) -> (GLuint, GLenum, GLint) {
+    match region.texture {
+        NewTexture::Surface(s) => (s, gl::RENDERBUFFER, 0),
+        NewTexture::Texture(t) => {
+            let layer = get_layer(region.kind, region.face, region.info.zoffset);
+            (t, kind_to_gl(region.kind), layer.unwrap_or(0) as GLint)
+        },
+    }
+}
+
+pub fn copy_texture(gl: &gl::Gl, src: &TextureRegion, dst: &TextureRegion) {
+    use std::cmp::max;
+    let (src_name, src_target, src_z) = copy_image_params(src);
+    let (dst_name, dst_target, dst_z) = copy_image_params(dst);
+    unsafe {
+        gl.CopyImageSubData(src_name, src_target,
+                            src.info.mipmap as GLint,
+                            src.info.xoffset as GLint,
+                            src.info.yoffset as GLint,
+                            src_z,
+                            dst_name, dst_target,
+                            dst.info.mipmap as GLint,
+                            dst.info.xoffset as GLint,
+                            dst.info.yoffset as GLint,
+                            dst_z,
+                            max(1, src.info.width) as GLsizei,
+                            max(1, src.info.height) as GLsizei,
+                            max(1, src.info.depth) as GLsizei);
+    }
+}
+
 pub fn update_texture(gl: &gl::Gl, name: Texture,
                       kind: t::Kind, face: Option<t::CubeFace>,
                       img: &t::RawImageInfo, slice: &[u8])
                           -> Result<(), t::CreationError> {
     //TODO: check size
     let data = slice.as_ptr() as *const GLvoid;
+    if img.format.0.is_compressed() {
+        unsafe { gl.BindTexture(kind_to_gl(kind), name) };
+        return compressed_sub_image(gl, kind, kind_face_to_gl(kind, face), img, data)
+    }
+
     let pixel_format = format_to_glpixel(img.format);
     let data_type = match format_to_gltype(img.format) {
         Ok(t) => t,
@@ -16,7 +16,7 @@
 
 //use cocoa::foundation::NSRange;
 
-use core::{pso, shade, state, target, texture, command};
+use core::{pso, shade, state, target, format, texture, command};
 use core::{IndexType, VertexCount};
 use core::{MAX_VERTEX_ATTRIBUTES, MAX_CONSTANT_BUFFERS, MAX_RESOURCE_VIEWS,
            MAX_SAMPLERS, MAX_COLOR_TARGETS};
@@ -131,12 +131,13 @@ impl command::Buffer<Resources> for CommandBuffer {
             let mask = stage.into();
             for cb in cbs.iter() {
                 if cb.1.contains(mask) {
+                    let offset = cb.3.map_or(0, |range| range.0) as u64;
                     match stage {
                         Stage::Vertex => {
-                            self.encoder.set_vertex_buffer(cb.2 as u64, 0, unsafe { *((cb.0).0).0 });
+                            self.encoder.set_vertex_buffer(cb.2 as u64, offset, unsafe { *((cb.0).0).0 });
                         },
                         Stage::Pixel => {
-                            self.encoder.set_fragment_buffer(cb.2 as u64, 0, unsafe { *((cb.0).0).0 });
+                            self.encoder.set_fragment_buffer(cb.2 as u64, offset, unsafe { *((cb.0).0).0 });
                         },
                         _ => { unimplemented!() }
                     }
@@ -343,6 +344,28 @@ impl command::Buffer<Resources> for CommandBuffer {
         unimplemented!()
     }
 
+    fn copy_texture_to_texture(&mut self,
+                               _: Texture, _: texture::Kind,
+                               _: Option<texture::CubeFace>, _: texture::RawImageInfo,
+                               _: Texture, _: texture::Kind,
+                               _: Option<texture::CubeFace>, _: texture::RawImageInfo) {
+        error!("Texture copies are not supported");
+    }
+
+    fn blit_texture(&mut self,
+                    _: Texture, _: texture::Kind,
+                    _: Option<texture::CubeFace>, _: texture::RawImageInfo,
+                    _: Texture, _: texture::Kind,
+                    _: Option<texture::CubeFace>, _: texture::RawImageInfo,
+                    _: target::Mirror, _: command::BlitFilter) {
+        error!("Texture blits are not supported");
+    }
+
+    fn resolve_texture(&mut self, _: Texture, _: texture::Kind,
+                       _: Texture, _: texture::Kind, _: format::Format) {
+        error!("Multisampled texture resolves are not supported");
+    }
+
     fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset: usize) {
         use map::{map_buffer_usage};
 
@@ -465,4 +488,34 @@ impl command::Buffer<Resources> for CommandBuffer {
             }
         }
     }
+
+    fn call_draw_indirect(&mut self, _: Buffer, _: usize, _: usize) {
+        error!("Indirect draws are not supported");
+    }
+
+    fn call_draw_indexed_indirect(&mut self, _: Buffer, _: usize, _: usize) {
+        error!("Indirect draws are not supported");
+    }
+
+    fn call_dispatch(&mut self, _: [u32; 3]) {
+        error!("Compute dispatches are not supported");
+    }
+
+    fn begin_query(&mut self, _: ()) {
+        error!("Queries are not supported");
+    }
+
+    fn end_query(&mut self, _: ()) {
+        error!("Queries are not supported");
+    }
+
+    fn write_timestamp(&mut self, _: ()) {
+        error!("Queries are not supported");
+    }
+
+    fn begin_conditional(&mut self, _: ()) {
+        error!("Conditional rendering is not supported");
+    }
+
+    fn end_conditional(&mut self) {}
 }
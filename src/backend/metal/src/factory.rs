@@ -778,6 +778,15 @@ impl core::Factory<Resources> for Factory {
         Ok(self.share.handles.borrow_mut().make_dsv(native::Dsv(raw_tex.0, desc.layer), htex, size))
     }
 
+    fn create_query(&mut self, ty: core::query::QueryType)
+                    -> Result<handle::Query<Resources>, core::query::CreationError> {
+        Err(core::query::CreationError::UnsupportedType(ty))
+    }
+
+    fn get_query_result(&mut self, _: &handle::Query<Resources>) -> Option<u64> {
+        None
+    }
+
     fn create_sampler(&mut self, info: core::texture::SamplerInfo) -> handle::Sampler<Resources> {
         use core::texture::FilterMethod;
         use map::{map_function, map_filter, map_wrap};
@@ -183,6 +183,7 @@ impl core::Resources for Resources {
     type UnorderedAccessView = ();
     type Sampler = native::Sampler;
     type Fence = Fence;
+    type Query = ();
     type Mapping = factory::RawMapping;
 }
 
@@ -283,6 +284,9 @@ impl core::Device for Device {
                                                    }, // sampler
                                                    |_, _| {
                                                        // fence
+                                                   },
+                                                   |_, _| {
+                                                       // query
                                                    });
     }
 }
@@ -309,15 +313,25 @@ pub fn create(format: core::format::Format,
             max_index_count: 0,
             max_texture_size: 0,
             max_patch_size: 0,
+            constant_buffer_alignment: 256,
             instance_base_supported: false,
             instance_call_supported: false,
             instance_rate_supported: false,
             vertex_base_supported: false,
             srgb_color_supported: false,
             constant_buffer_supported: true,
+            constant_buffer_range_supported: true,
             unordered_access_view_supported: false,
             separate_blending_slots_supported: false,
             copy_buffer_supported: true,
+            copy_texture_supported: false,
+            blit_texture_supported: false,
+            resolve_texture_supported: false,
+            compute_supported: false,
+            draw_indirect_supported: false,
+            bc_compression_supported: false,
+            etc2_compression_supported: false,
+            astc_compression_supported: false,
         },
         handles: RefCell::new(handle::Manager::new()),
     };
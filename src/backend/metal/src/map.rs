@@ -335,6 +335,139 @@ pub fn map_format(format: Format, is_target: bool) -> Option<MTLPixelFormat> {
                 _ => return None,
             }
         }
+        BC1_R8_G8_B8_A8 => match format.1 {
+            Unorm => BC1_RGBA,
+            Srgb  => BC1_RGBA_sRGB,
+            _ => return None,
+        },
+        BC2_R8_G8_B8_A8 => match format.1 {
+            Unorm => BC2_RGBA,
+            Srgb  => BC2_RGBA_sRGB,
+            _ => return None,
+        },
+        BC3_R8_G8_B8_A8 => match format.1 {
+            Unorm => BC3_RGBA,
+            Srgb  => BC3_RGBA_sRGB,
+            _ => return None,
+        },
+        BC4_R8 => match format.1 {
+            Inorm => BC4_RSnorm,
+            Unorm => BC4_RUnorm,
+            _ => return None,
+        },
+        BC5_R8_G8 => match format.1 {
+            Inorm => BC5_RGSnorm,
+            Unorm => BC5_RGUnorm,
+            _ => return None,
+        },
+        BC6H_R16_G16_B16_UF => match format.1 {
+            Float => BC6H_RGBUfloat,
+            _ => return None,
+        },
+        BC6H_R16_G16_B16_SF => match format.1 {
+            Float => BC6H_RGBFloat,
+            _ => return None,
+        },
+        BC7_R8_G8_B8_A8 => match format.1 {
+            Unorm => BC7_RGBAUnorm,
+            Srgb  => BC7_RGBAUnorm_sRGB,
+            _ => return None,
+        },
+        ETC2_R8_G8_B8 => match format.1 {
+            Unorm => ETC2_RGB8,
+            Srgb  => ETC2_RGB8_sRGB,
+            _ => return None,
+        },
+        ETC2_R8_G8_B8_A1 => match format.1 {
+            Unorm => ETC2_RGB8A1,
+            Srgb  => ETC2_RGB8A1_sRGB,
+            _ => return None,
+        },
+        ETC2_R8_G8_B8_A8 => match format.1 {
+            Unorm => EAC_RGBA8,
+            Srgb  => EAC_RGBA8_sRGB,
+            _ => return None,
+        },
+        EAC_R11 => match format.1 {
+            Inorm => EAC_R11Snorm,
+            Unorm => EAC_R11Unorm,
+            _ => return None,
+        },
+        EAC_R11_G11 => match format.1 {
+            Inorm => EAC_RG11Snorm,
+            Unorm => EAC_RG11Unorm,
+            _ => return None,
+        },
+        ASTC_4x4 => match format.1 {
+            Unorm => ASTC_4x4_LDR,
+            Srgb  => ASTC_4x4_sRGB,
+            _ => return None,
+        },
+        ASTC_5x4 => match format.1 {
+            Unorm => ASTC_5x4_LDR,
+            Srgb  => ASTC_5x4_sRGB,
+            _ => return None,
+        },
+        ASTC_5x5 => match format.1 {
+            Unorm => ASTC_5x5_LDR,
+            Srgb  => ASTC_5x5_sRGB,
+            _ => return None,
+        },
+        ASTC_6x5 => match format.1 {
+            Unorm => ASTC_6x5_LDR,
+            Srgb  => ASTC_6x5_sRGB,
+            _ => return None,
+        },
+        ASTC_6x6 => match format.1 {
+            Unorm => ASTC_6x6_LDR,
+            Srgb  => ASTC_6x6_sRGB,
+            _ => return None,
+        },
+        ASTC_8x5 => match format.1 {
+            Unorm => ASTC_8x5_LDR,
+            Srgb  => ASTC_8x5_sRGB,
+            _ => return None,
+        },
+        ASTC_8x6 => match format.1 {
+            Unorm => ASTC_8x6_LDR,
+            Srgb  => ASTC_8x6_sRGB,
+            _ => return None,
+        },
+        ASTC_8x8 => match format.1 {
+            Unorm => ASTC_8x8_LDR,
+            Srgb  => ASTC_8x8_sRGB,
+            _ => return None,
+        },
+        ASTC_10x5 => match format.1 {
+            Unorm => ASTC_10x5_LDR,
+            Srgb  => ASTC_10x5_sRGB,
+            _ => return None,
+        },
+        ASTC_10x6 => match format.1 {
+            Unorm => ASTC_10x6_LDR,
+            Srgb  => ASTC_10x6_sRGB,
+            _ => return None,
+        },
+        ASTC_10x8 => match format.1 {
+            Unorm => ASTC_10x8_LDR,
+            Srgb  => ASTC_10x8_sRGB,
+            _ => return None,
+        },
+        ASTC_10x10 => match format.1 {
+            Unorm => ASTC_10x10_LDR,
+            Srgb  => ASTC_10x10_sRGB,
+            _ => return None,
+        },
+        ASTC_12x10 => match format.1 {
+            Unorm => ASTC_12x10_LDR,
+            Srgb  => ASTC_12x10_sRGB,
+            _ => return None,
+        },
+        ASTC_12x12 => match format.1 {
+            Unorm => ASTC_12x12_LDR,
+            Srgb  => ASTC_12x12_sRGB,
+            _ => return None,
+        },
     })
 }
 
@@ -353,6 +486,12 @@ pub fn map_channel_hint(hint: SurfaceType) -> Option<ChannelType> {
         D24 => Unorm,
         D24_S8 => Unorm,
         D32 => Float,
+        BC6H_R16_G16_B16_UF | BC6H_R16_G16_B16_SF => Float,
+        BC4_R8 | BC5_R8_G8 | EAC_R11 | EAC_R11_G11 => Unorm,
+        BC1_R8_G8_B8_A8 | BC2_R8_G8_B8_A8 | BC3_R8_G8_B8_A8 | BC7_R8_G8_B8_A8 |
+        ETC2_R8_G8_B8 | ETC2_R8_G8_B8_A1 | ETC2_R8_G8_B8_A8 |
+        ASTC_4x4 | ASTC_5x4 | ASTC_5x5 | ASTC_6x5 | ASTC_6x6 | ASTC_8x5 | ASTC_8x6 | ASTC_8x8 |
+        ASTC_10x5 | ASTC_10x6 | ASTC_10x8 | ASTC_10x10 | ASTC_12x10 | ASTC_12x12 => Unorm,
     })
 }
 
@@ -0,0 +1,274 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![allow(missing_docs)]
+
+use core::{self as c, command, pso, shade, state as s, texture as t};
+use core::{ConstantBufferSlot, ResourceViewSlot, SamplerSlot, IndexType, VertexCount};
+use core::target::{Depth, Mirror, Rect, Stencil};
+use {Buffer, Texture, Resources, PipelineState, ResourceView, TargetView, Query};
+
+
+/// A region of a texture, as given to the copy commands.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureRegion {
+    pub texture: Texture,
+    pub face: Option<t::CubeFace>,
+    pub info: t::RawImageInfo,
+}
+
+/// Parameters of a draw call.
+#[derive(Clone, Copy, Debug)]
+pub struct DrawCall {
+    pub start: VertexCount,
+    pub count: VertexCount,
+    pub base_vertex: i32,
+    pub instances: Option<command::InstanceParams>,
+    pub indexed: bool,
+}
+
+/// Serialized device command.
+#[derive(Clone, Debug)]
+pub enum Command {
+    // states
+    BindPipeline(PipelineState),
+    BindVertexBuffers(pso::VertexBufferSet<Resources>),
+    BindConstantBuffer(ConstantBufferSlot, Buffer, Option<pso::BufferRange>),
+    BindGlobal(shade::Location, shade::UniformValue),
+    BindResourceView(ResourceViewSlot, ResourceView),
+    BindSampler(SamplerSlot, t::SamplerInfo),
+    BindPixelTargets(pso::PixelTargetSet<Resources>),
+    BindIndex(Buffer, IndexType),
+    SetScissor(Rect),
+    SetRefValues(s::RefValues),
+    // resource updates
+    CopyBuffer(Buffer, Buffer, usize, usize, usize),
+    CopyBufferToTexture(Buffer, usize, TextureRegion),
+    CopyTextureToBuffer(TextureRegion, Buffer, usize),
+    CopyTexture(TextureRegion, TextureRegion),
+    Blit(TextureRegion, TextureRegion, Mirror, command::BlitFilter),
+    Resolve(Texture, Texture),
+    UpdateBuffer(Buffer, Vec<u8>, usize),
+    UpdateTexture(TextureRegion, Vec<u8>),
+    GenerateMipmap(ResourceView),
+    // drawing
+    ClearColor(TargetView, command::ClearColor),
+    ClearDepthStencil(TargetView, Option<Depth>, Option<Stencil>),
+    Draw(DrawCall),
+    DrawIndirect(Buffer, usize, usize, bool),
+    // queries
+    BeginQuery(Query),
+    EndQuery(Query),
+    WriteTimestamp(Query),
+    BeginConditional(Query),
+    EndConditional,
+}
+
+/// A command buffer, recording the commands for the `Device` to
+/// execute upon submission.
+pub struct CommandBuffer {
+    pub buf: Vec<Command>,
+}
+
+impl CommandBuffer {
+    pub fn new() -> CommandBuffer {
+        CommandBuffer {
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl command::Buffer<Resources> for CommandBuffer {
+    fn reset(&mut self) {
+        self.buf.clear();
+    }
+
+    fn bind_pipeline_state(&mut self, pso: PipelineState) {
+        self.buf.push(Command::BindPipeline(pso));
+    }
+
+    fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<Resources>) {
+        self.buf.push(Command::BindVertexBuffers(vbs));
+    }
+
+    fn bind_constant_buffers(&mut self, cbs: &[pso::ConstantBufferParam<Resources>]) {
+        for param in cbs.iter() {
+            self.buf.push(Command::BindConstantBuffer(param.2, param.0, param.3));
+        }
+    }
+
+    fn bind_global_constant(&mut self, loc: shade::Location, value: shade::UniformValue) {
+        self.buf.push(Command::BindGlobal(loc, value));
+    }
+
+    fn bind_resource_views(&mut self, srvs: &[pso::ResourceViewParam<Resources>]) {
+        for param in srvs.iter() {
+            self.buf.push(Command::BindResourceView(param.2, param.0));
+        }
+    }
+
+    fn bind_unordered_views(&mut self, uavs: &[pso::UnorderedViewParam<Resources>]) {
+        if !uavs.is_empty() {
+            error!("Unordered access views are not supported by the software backend");
+        }
+    }
+
+    fn bind_samplers(&mut self, ss: &[pso::SamplerParam<Resources>]) {
+        for param in ss.iter() {
+            self.buf.push(Command::BindSampler(param.2, param.0));
+        }
+    }
+
+    fn bind_pixel_targets(&mut self, pts: pso::PixelTargetSet<Resources>) {
+        self.buf.push(Command::BindPixelTargets(pts));
+    }
+
+    fn bind_index(&mut self, buf: Buffer, itype: IndexType) {
+        self.buf.push(Command::BindIndex(buf, itype));
+    }
+
+    fn set_scissor(&mut self, rect: Rect) {
+        self.buf.push(Command::SetScissor(rect));
+    }
+
+    fn set_ref_values(&mut self, rv: s::RefValues) {
+        self.buf.push(Command::SetRefValues(rv));
+    }
+
+    fn copy_buffer(&mut self, src: Buffer, dst: Buffer,
+                   src_offset_bytes: usize, dst_offset_bytes: usize,
+                   size_bytes: usize) {
+        self.buf.push(Command::CopyBuffer(src, dst, src_offset_bytes,
+                                          dst_offset_bytes, size_bytes));
+    }
+
+    fn copy_buffer_to_texture(&mut self,
+                              src: Buffer, src_offset_bytes: usize,
+                              dst: Texture, _: t::Kind,
+                              face: Option<t::CubeFace>, img: t::RawImageInfo) {
+        let region = TextureRegion { texture: dst, face: face, info: img };
+        self.buf.push(Command::CopyBufferToTexture(src, src_offset_bytes, region));
+    }
+
+    fn copy_texture_to_buffer(&mut self,
+                              src: Texture, _: t::Kind,
+                              face: Option<t::CubeFace>, img: t::RawImageInfo,
+                              dst: Buffer, dst_offset_bytes: usize) {
+        let region = TextureRegion { texture: src, face: face, info: img };
+        self.buf.push(Command::CopyTextureToBuffer(region, dst, dst_offset_bytes));
+    }
+
+    fn copy_texture_to_texture(&mut self,
+                               src: Texture, _: t::Kind,
+                               src_face: Option<t::CubeFace>, src_img: t::RawImageInfo,
+                               dst: Texture, _: t::Kind,
+                               dst_face: Option<t::CubeFace>, dst_img: t::RawImageInfo) {
+        let src_region = TextureRegion { texture: src, face: src_face, info: src_img };
+        let dst_region = TextureRegion { texture: dst, face: dst_face, info: dst_img };
+        self.buf.push(Command::CopyTexture(src_region, dst_region));
+    }
+
+    fn blit_texture(&mut self,
+                    src: Texture, _: t::Kind,
+                    src_face: Option<t::CubeFace>, src_img: t::RawImageInfo,
+                    dst: Texture, _: t::Kind,
+                    dst_face: Option<t::CubeFace>, dst_img: t::RawImageInfo,
+                    mirror: Mirror, filter: command::BlitFilter) {
+        let src_region = TextureRegion { texture: src, face: src_face, info: src_img };
+        let dst_region = TextureRegion { texture: dst, face: dst_face, info: dst_img };
+        self.buf.push(Command::Blit(src_region, dst_region, mirror, filter));
+    }
+
+    fn resolve_texture(&mut self, src: Texture, _: t::Kind,
+                       dst: Texture, _: t::Kind, _: c::format::Format) {
+        self.buf.push(Command::Resolve(src, dst));
+    }
+
+    fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset_bytes: usize) {
+        self.buf.push(Command::UpdateBuffer(buf, data.to_vec(), offset_bytes));
+    }
+
+    fn update_texture(&mut self, tex: Texture, _: t::Kind, face: Option<t::CubeFace>,
+                      data: &[u8], img: t::RawImageInfo) {
+        let region = TextureRegion { texture: tex, face: face, info: img };
+        self.buf.push(Command::UpdateTexture(region, data.to_vec()));
+    }
+
+    fn generate_mipmap(&mut self, srv: ResourceView) {
+        self.buf.push(Command::GenerateMipmap(srv));
+    }
+
+    fn clear_color(&mut self, target: TargetView, value: command::ClearColor) {
+        self.buf.push(Command::ClearColor(target, value));
+    }
+
+    fn clear_depth_stencil(&mut self, target: TargetView, depth: Option<Depth>,
+                           stencil: Option<Stencil>) {
+        self.buf.push(Command::ClearDepthStencil(target, depth, stencil));
+    }
+
+    fn call_draw(&mut self, start: VertexCount, count: VertexCount,
+                 instances: Option<command::InstanceParams>) {
+        self.buf.push(Command::Draw(DrawCall {
+            start: start,
+            count: count,
+            base_vertex: 0,
+            instances: instances,
+            indexed: false,
+        }));
+    }
+
+    fn call_draw_indexed(&mut self, start: VertexCount, count: VertexCount,
+                         base: VertexCount, instances: Option<command::InstanceParams>) {
+        self.buf.push(Command::Draw(DrawCall {
+            start: start,
+            count: count,
+            base_vertex: base as i32,
+            instances: instances,
+            indexed: true,
+        }));
+    }
+
+    fn call_draw_indirect(&mut self, buf: Buffer, offset_bytes: usize, count: usize) {
+        self.buf.push(Command::DrawIndirect(buf, offset_bytes, count, false));
+    }
+
+    fn call_draw_indexed_indirect(&mut self, buf: Buffer, offset_bytes: usize, count: usize) {
+        self.buf.push(Command::DrawIndirect(buf, offset_bytes, count, true));
+    }
+
+    fn call_dispatch(&mut self, _: [u32; 3]) {
+        error!("Compute shaders are not supported by the software backend");
+    }
+
+    fn begin_query(&mut self, query: Query) {
+        self.buf.push(Command::BeginQuery(query));
+    }
+
+    fn end_query(&mut self, query: Query) {
+        self.buf.push(Command::EndQuery(query));
+    }
+
+    fn write_timestamp(&mut self, query: Query) {
+        self.buf.push(Command::WriteTimestamp(query));
+    }
+
+    fn begin_conditional(&mut self, query: Query) {
+        self.buf.push(Command::BeginConditional(query));
+    }
+
+    fn end_conditional(&mut self) {
+        self.buf.push(Command::EndConditional);
+    }
+}
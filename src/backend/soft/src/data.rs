@@ -0,0 +1,70 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encoding and decoding of texels and vertex elements.
+//!
+//! The texels are handled by `core::format::convert`, whose packed formats
+//! follow the bit layout of the matching OpenGL packed pixel types, so that
+//! the read back data is identical to what the GL backend produces.
+
+use core::format::{ChannelType, Format, SurfaceType};
+pub use core::format::convert::{decode, encode};
+
+/// Return the size of a single texel of the given surface, in bytes.
+pub fn get_texel_size(surface: SurfaceType) -> usize {
+    surface.get_total_bits() as usize / 8
+}
+
+/// Check if the surface holds depth and/or stencil values.
+pub fn is_depth_stencil(surface: SurfaceType) -> bool {
+    use core::format::SurfaceType::*;
+    match surface {
+        D16 | D24 | D24_S8 | D32 => true,
+        _ => false,
+    }
+}
+
+/// Decode the depth value of a depth-stencil texel.
+pub fn decode_depth(surface: SurfaceType, texel: &[u8]) -> f32 {
+    let channel = match surface {
+        SurfaceType::D32 => ChannelType::Float,
+        _ => ChannelType::Unorm,
+    };
+    decode(Format(surface, channel), texel)[0]
+}
+
+/// Encode the depth value of a depth-stencil texel, keeping the stencil.
+pub fn encode_depth(surface: SurfaceType, value: f32, texel: &mut [u8]) {
+    let channel = match surface {
+        SurfaceType::D32 => ChannelType::Float,
+        _ => ChannelType::Unorm,
+    };
+    encode(Format(surface, channel), [value, 0.0, 0.0, 0.0], texel)
+}
+
+/// Decode the stencil value of a depth-stencil texel.
+pub fn decode_stencil(surface: SurfaceType, texel: &[u8]) -> u8 {
+    match surface {
+        SurfaceType::D24_S8 => texel[0],
+        _ => 0,
+    }
+}
+
+/// Encode the stencil value of a depth-stencil texel, keeping the depth.
+pub fn encode_stencil(surface: SurfaceType, value: u8, texel: &mut [u8]) {
+    match surface {
+        SurfaceType::D24_S8 => texel[0] = value,
+        _ => (),
+    }
+}
@@ -0,0 +1,370 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use std::sync::Arc;
+use std::slice;
+
+use core::{self as d, factory as f, texture as t, buffer, mapping, query};
+use core::memory::{self, Bind};
+use core::format::{ChannelType, Format};
+use core::handle::{self, Producer};
+use core::target::{Layer, Level};
+
+use command::CommandBuffer;
+use shade::{self, Interface};
+use storage::{Image, Memory, PipelineObject, ProgramObject, QueryObject, ShaderObject};
+use {data, Resources as R, Share};
+use {Buffer, PipelineState, Program, Query, ResourceView, Shader, TargetView, Texture};
+
+
+/// Software resource factory.
+pub struct Factory {
+    share: Rc<Share>,
+    frame_handles: handle::Manager<R>,
+}
+
+impl Clone for Factory {
+    fn clone(&self) -> Factory {
+        Factory::new(self.share.clone())
+    }
+}
+
+impl Factory {
+    /// Create a new `Factory`.
+    pub fn new(share: Rc<Share>) -> Factory {
+        Factory {
+            share: share,
+            frame_handles: handle::Manager::new(),
+        }
+    }
+
+    pub fn create_command_buffer(&mut self) -> CommandBuffer {
+        CommandBuffer::new()
+    }
+
+    /// Create a vertex shader from a Rust function and its interface.
+    pub fn create_vertex_shader<S>(&mut self, interface: Interface, shader: S)
+                                   -> d::VertexShader<R> where
+        S: shade::VertexShader + 'static
+    {
+        let object = ShaderObject::Vertex(Arc::new(shader), interface.with_usage(d::shade::VERTEX));
+        let index = self.share.storage.borrow_mut().shaders.insert(object);
+        d::VertexShader::new(self.share.handles.borrow_mut().make_shader(Shader(index)))
+    }
+
+    /// Create a pixel shader from a Rust function and its interface.
+    pub fn create_pixel_shader<S>(&mut self, interface: Interface, shader: S)
+                                  -> d::PixelShader<R> where
+        S: shade::PixelShader + 'static
+    {
+        let object = ShaderObject::Pixel(Arc::new(shader), interface.with_usage(d::shade::PIXEL));
+        let index = self.share.storage.borrow_mut().shaders.insert(object);
+        d::PixelShader::new(self.share.handles.borrow_mut().make_shader(Shader(index)))
+    }
+
+    fn create_buffer_internal(&mut self, info: buffer::Info, data_opt: Option<&[u8]>)
+                              -> handle::RawBuffer<R> {
+        let mut memory = Memory::new(info.size);
+        if let Some(data) = data_opt {
+            memory.as_mut_slice().copy_from_slice(data);
+        }
+        let mapping = match info.usage {
+            memory::Usage::Upload | memory::Usage::Download => Some(MappingGate {
+                pointer: memory.as_mut_ptr(),
+            }),
+            memory::Usage::Data | memory::Usage::Dynamic => None,
+        };
+        let index = self.share.storage.borrow_mut().buffers.insert(memory);
+        self.share.handles.borrow_mut().make_buffer(Buffer(index), info, mapping)
+    }
+
+    fn view_texture_as_target(&mut self, htex: &handle::RawTexture<R>, format: Format,
+                              level: Level, layer: Option<Layer>, flags: t::DepthStencilFlags)
+                              -> Result<TargetView, f::TargetViewError> {
+        let info = htex.get_info();
+        if level >= info.levels {
+            return Err(f::TargetViewError::Level(level))
+        }
+        match (layer, info.kind.get_num_slices()) {
+            (Some(l), None) if l != 0 =>
+                return Err(f::TargetViewError::Layer(t::LayerError::NotExpected(info.kind))),
+            (Some(l), Some(n)) if l >= n =>
+                return Err(f::TargetViewError::Layer(t::LayerError::OutOfBounds(l, n))),
+            _ => (),
+        }
+        Ok(TargetView {
+            texture: *self.frame_handles.ref_texture(htex),
+            format: format,
+            level: level,
+            layer: layer,
+            flags: flags,
+        })
+    }
+}
+
+#[derive(Debug, Eq, Hash, PartialEq)]
+#[allow(missing_copy_implementations)]
+pub struct MappingGate {
+    pointer: *mut u8,
+}
+
+unsafe impl Send for MappingGate {}
+unsafe impl Sync for MappingGate {}
+
+impl mapping::Gate<R> for MappingGate {
+    unsafe fn set<T>(&self, index: usize, val: T) {
+        *(self.pointer as *mut T).offset(index as isize) = val;
+    }
+
+    unsafe fn slice<'a, 'b, T>(&'a self, len: usize) -> &'b [T] {
+        slice::from_raw_parts(self.pointer as *const T, len)
+    }
+
+    unsafe fn mut_slice<'a, 'b, T>(&'a self, len: usize) -> &'b mut [T] {
+        slice::from_raw_parts_mut(self.pointer as *mut T, len)
+    }
+}
+
+impl f::Factory<R> for Factory {
+    fn get_capabilities(&self) -> &d::Capabilities {
+        &self.share.capabilities
+    }
+
+    fn create_buffer_raw(&mut self, info: buffer::Info) -> Result<handle::RawBuffer<R>, buffer::CreationError> {
+        if info.bind.contains(memory::UNORDERED_ACCESS) {
+            return Err(buffer::CreationError::UnsupportedBind(info.bind))
+        }
+        Ok(self.create_buffer_internal(info, None))
+    }
+
+    fn create_buffer_immutable_raw(&mut self, data: &[u8], stride: usize, role: buffer::Role, bind: Bind)
+                                   -> Result<handle::RawBuffer<R>, buffer::CreationError> {
+        if bind.contains(memory::UNORDERED_ACCESS) {
+            return Err(buffer::CreationError::UnsupportedBind(bind))
+        }
+        let info = buffer::Info {
+            role: role,
+            usage: memory::Usage::Data,
+            bind: bind,
+            size: data.len(),
+            stride: stride,
+        };
+        Ok(self.create_buffer_internal(info, Some(data)))
+    }
+
+    fn create_shader(&mut self, _stage: d::shade::Stage, _code: &[u8])
+                     -> Result<handle::Shader<R>, d::shade::CreateShaderError> {
+        Err(d::shade::CreateShaderError::CompilationFailed(
+            "The software backend can't compile shader code, use \
+             `create_vertex_shader` and `create_pixel_shader` instead".to_string()))
+    }
+
+    fn create_program(&mut self, shader_set: &d::ShaderSet<R>)
+                      -> Result<handle::Program<R>, d::shade::CreateProgramError> {
+        let (vs, ps) = match shader_set {
+            &d::ShaderSet::Simple(ref vs, ref ps) =>
+                (*vs.reference(&mut self.frame_handles), *ps.reference(&mut self.frame_handles)),
+            _ => return Err("Only the vertex and pixel shader stages are supported".into()),
+        };
+        let (object, info) = {
+            let storage = self.share.storage.borrow();
+            match (storage.shaders.get(vs.0), storage.shaders.get(ps.0)) {
+                (Some(&ShaderObject::Vertex(ref vs, ref vi)), Some(&ShaderObject::Pixel(ref ps, ref pi))) => {
+                    let info = try!(shade::link(vi, pi));
+                    let object = ProgramObject {
+                        vertex: vs.clone(),
+                        pixel: ps.clone(),
+                    };
+                    (object, info)
+                },
+                _ => return Err("Shader stages do not match".into()),
+            }
+        };
+        let index = self.share.storage.borrow_mut().programs.insert(object);
+        Ok(self.share.handles.borrow_mut().make_program(Program(index), info))
+    }
+
+    fn create_pipeline_state_raw(&mut self, program: &handle::Program<R>, desc: &d::pso::Descriptor)
+                                 -> Result<handle::RawPipelineState<R>, d::pso::CreationError> {
+        match desc.primitive {
+            d::Primitive::PointList | d::Primitive::LineList | d::Primitive::LineStrip |
+            d::Primitive::TriangleList | d::Primitive::TriangleStrip => (),
+            _ => return Err(d::pso::CreationError),
+        }
+        if desc.unordered_views.iter().any(|uav| uav.is_some()) {
+            return Err(d::pso::CreationError)
+        }
+        let index = *self.frame_handles.ref_program(program);
+        let mut storage = self.share.storage.borrow_mut();
+        let object = match storage.programs.get(index.0) {
+            Some(p) => PipelineObject {
+                program: p.clone(),
+                desc: *desc,
+            },
+            None => return Err(d::pso::CreationError),
+        };
+        let pso = PipelineState(storage.pipelines.insert(object));
+        Ok(self.share.handles.borrow_mut().make_pso(pso, program))
+    }
+
+    fn create_texture_raw(&mut self, desc: t::Info, hint: Option<ChannelType>, data_opt: Option<&[&[u8]]>)
+                          -> Result<handle::RawTexture<R>, t::CreationError> {
+        if desc.levels == 0 {
+            return Err(t::CreationError::Size(0))
+        }
+        let max_size = self.share.capabilities.max_texture_size;
+        let dim = desc.kind.get_dimensions();
+        for &size in [dim.0, dim.1, dim.2].iter() {
+            if size as usize > max_size {
+                return Err(t::CreationError::Size(size))
+            }
+        }
+        if desc.bind.contains(memory::UNORDERED_ACCESS) {
+            return Err(t::CreationError::Kind)
+        }
+        if desc.format.is_compressed() {
+            return Err(t::CreationError::Format(desc.format, hint))
+        }
+        let mut image = Image::new(desc, hint.unwrap_or(ChannelType::Unorm));
+        if let Some(data) = data_opt {
+            let num_layers = image.get_num_layers();
+            if data.len() != num_layers * desc.levels as usize {
+                error!("Texture {:?} needs data for {} layers with {} levels each, got {} slices",
+                       desc.kind, num_layers, desc.levels, data.len());
+                return Err(t::CreationError::Data(data.len()))
+            }
+            let texel_size = image.get_texel_size();
+            for layer in 0 .. num_layers {
+                for level in 0 .. desc.levels {
+                    let slice = data[layer * desc.levels as usize + level as usize];
+                    let (w, h, d) = image.get_level_size(level);
+                    let size = w * h * d * texel_size;
+                    if slice.len() != size {
+                        return Err(t::CreationError::Data(slice.len()))
+                    }
+                    let offset = image.get_texel_offset(level, layer, 0, 0, 0);
+                    image.memory.as_mut_slice()[offset .. offset + size].copy_from_slice(slice);
+                }
+            }
+        }
+        let index = self.share.storage.borrow_mut().textures.insert(image);
+        Ok(self.share.handles.borrow_mut().make_texture(Texture(index), desc))
+    }
+
+    fn view_buffer_as_shader_resource_raw(&mut self, _hbuf: &handle::RawBuffer<R>)
+                                      -> Result<handle::RawShaderResourceView<R>, f::ResourceViewError> {
+        Err(f::ResourceViewError::Unsupported)
+    }
+
+    fn view_buffer_as_unordered_access_raw(&mut self, _hbuf: &handle::RawBuffer<R>)
+                                       -> Result<handle::RawUnorderedAccessView<R>, f::ResourceViewError> {
+        Err(f::ResourceViewError::Unsupported)
+    }
+
+    fn view_texture_as_shader_resource_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::ResourceDesc)
+                                       -> Result<handle::RawShaderResourceView<R>, f::ResourceViewError> {
+        let info = htex.get_info().clone();
+        if !info.bind.contains(memory::SHADER_RESOURCE) {
+            return Err(f::ResourceViewError::NoBindFlag)
+        }
+        if desc.min >= info.levels || desc.max < desc.min {
+            return Err(f::ResourceViewError::Unsupported)
+        }
+        let view = ResourceView {
+            texture: *self.frame_handles.ref_texture(htex),
+            format: Format(info.format, desc.channel),
+            min: desc.min,
+            max: desc.max,
+            layer: desc.layer,
+            swizzle: desc.swizzle,
+        };
+        Ok(self.share.handles.borrow_mut().make_texture_srv(view, htex))
+    }
+
+    fn view_texture_as_unordered_access_raw(&mut self, _htex: &handle::RawTexture<R>)
+                                        -> Result<handle::RawUnorderedAccessView<R>, f::ResourceViewError> {
+        Err(f::ResourceViewError::Unsupported)
+    }
+
+    fn view_texture_as_render_target_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::RenderDesc)
+                                         -> Result<handle::RawRenderTargetView<R>, f::TargetViewError> {
+        let info = htex.get_info().clone();
+        if !info.bind.contains(memory::RENDER_TARGET) {
+            return Err(f::TargetViewError::NoBindFlag)
+        }
+        let format = Format(info.format, desc.channel);
+        self.view_texture_as_target(htex, format, desc.level, desc.layer, t::DepthStencilFlags::empty())
+            .map(|view| {
+                let dim = info.kind.get_level_dimensions(desc.level);
+                self.share.handles.borrow_mut().make_rtv(view, htex, dim)
+            })
+    }
+
+    fn view_texture_as_depth_stencil_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::DepthStencilDesc)
+                                         -> Result<handle::RawDepthStencilView<R>, f::TargetViewError> {
+        let info = htex.get_info().clone();
+        if !info.bind.contains(memory::DEPTH_STENCIL) {
+            return Err(f::TargetViewError::NoBindFlag)
+        }
+        if !data::is_depth_stencil(info.format) {
+            return Err(f::TargetViewError::Channel(ChannelType::Unorm))
+        }
+        let format = Format(info.format, ChannelType::Unorm);
+        self.view_texture_as_target(htex, format, desc.level, desc.layer, desc.flags)
+            .map(|view| {
+                let dim = info.kind.get_level_dimensions(desc.level);
+                self.share.handles.borrow_mut().make_dsv(view, htex, dim)
+            })
+    }
+
+    fn create_sampler(&mut self, info: t::SamplerInfo) -> handle::Sampler<R> {
+        self.share.handles.borrow_mut().make_sampler(info, info)
+    }
+
+    fn create_query(&mut self, ty: query::QueryType) -> Result<handle::Query<R>, query::CreationError> {
+        let object = QueryObject {
+            ty: ty,
+            value: 0,
+        };
+        let index = self.share.storage.borrow_mut().queries.insert(object);
+        Ok(self.share.handles.borrow_mut().make_query(Query(index), ty))
+    }
+
+    fn get_query_result(&mut self, query: &handle::Query<R>) -> Option<u64> {
+        // the commands are executed upon submission, so the results
+        // are always available once the query is submitted
+        if !query.is_submitted() {
+            return None
+        }
+        let storage = self.share.storage.borrow();
+        storage.queries.get(query.resource().0).map(|q| q.value)
+    }
+
+    fn read_mapping<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>)
+                               -> Result<mapping::Reader<'b, R, T>,
+                                         mapping::Error>
+        where T: Copy
+    {
+        unsafe { mapping::read(buf.raw(), |_| ()) }
+    }
+
+    fn write_mapping<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>)
+                                -> Result<mapping::Writer<'b, R, T>,
+                                          mapping::Error>
+        where T: Copy
+    {
+        unsafe { mapping::write(buf.raw(), |_| ()) }
+    }
+}
@@ -0,0 +1,429 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Software implementation of a device, rasterizing on the CPU. It needs
+//! no window nor GPU, which makes it suitable for the tests and headless
+//! rendering, but it is not meant to be fast.
+//!
+//! The commands are executed synchronously upon submission. The shaders
+//! are written in Rust, see the `shade` module.
+
+#![allow(missing_docs)]
+#![deny(missing_copy_implementations)]
+
+#[macro_use]
+extern crate log;
+extern crate gfx_core as core;
+
+use std::cell::RefCell;
+use std::{mem, ptr};
+use std::rc::Rc;
+use std::time::Instant;
+use core::{self as c, handle, texture, format, query, command as com};
+use core::target::{Layer, Level};
+use command::{Command, DrawCall};
+use storage::Storage;
+
+pub use self::command::CommandBuffer;
+pub use self::factory::Factory;
+
+mod command;
+mod data;
+mod factory;
+mod raster;
+mod sample;
+pub mod shade;
+mod storage;
+mod transfer;
+
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Buffer(usize);
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Texture(usize);
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Shader(usize);
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Program(usize);
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PipelineState(usize);
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Query(usize);
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Fence;
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ResourceView {
+    texture: Texture,
+    format: format::Format,
+    min: Level,
+    max: Level,
+    layer: Option<Layer>,
+    swizzle: format::Swizzle,
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TargetView {
+    texture: Texture,
+    format: format::Format,
+    level: Level,
+    layer: Option<Layer>,
+    flags: texture::DepthStencilFlags,
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Resources {}
+
+impl c::Resources for Resources {
+    type Buffer              = Buffer;
+    type Shader              = Shader;
+    type Program             = Program;
+    type PipelineStateObject = PipelineState;
+    type Texture             = Texture;
+    type RenderTargetView    = TargetView;
+    type DepthStencilView    = TargetView;
+    type ShaderResourceView  = ResourceView;
+    type UnorderedAccessView = ();
+    type Sampler             = texture::SamplerInfo;
+    type Fence               = Fence;
+    type Query               = Query;
+    type Mapping             = factory::MappingGate;
+}
+
+/// Create a new device with a factory.
+pub fn create() -> (Device, Factory) {
+    let device = Device::new();
+    let factory = Factory::new(device.share.clone());
+    (device, factory)
+}
+
+/// Internal struct of shared data between the device and its factories.
+#[doc(hidden)]
+pub struct Share {
+    capabilities: c::Capabilities,
+    handles: RefCell<handle::Manager<Resources>>,
+    storage: RefCell<Storage>,
+}
+
+/// A software device, executing the commands on the CPU.
+pub struct Device {
+    share: Rc<Share>,
+    frame_handles: handle::Manager<Resources>,
+    state: raster::State,
+    /// Occlusion queries in progress
+    active_queries: Vec<Query>,
+    /// Query deciding if the draw calls are executed
+    conditional: Option<Query>,
+    start_time: Instant,
+}
+
+impl Device {
+    fn new() -> Device {
+        let capabilities = c::Capabilities {
+            max_vertex_count: !0,
+            max_index_count: !0,
+            max_texture_size: 16384,
+            max_patch_size: 0,
+            constant_buffer_alignment: 16,
+
+            instance_base_supported: true,
+            instance_call_supported: true,
+            instance_rate_supported: true,
+            vertex_base_supported: true,
+            srgb_color_supported: true,
+            constant_buffer_supported: true,
+            constant_buffer_range_supported: true,
+            unordered_access_view_supported: false,
+            separate_blending_slots_supported: true,
+            copy_buffer_supported: true,
+            copy_texture_supported: true,
+            blit_texture_supported: true,
+            resolve_texture_supported: true,
+            compute_supported: false,
+            draw_indirect_supported: true,
+            bc_compression_supported: false,
+            etc2_compression_supported: false,
+            astc_compression_supported: false,
+        };
+        let share = Share {
+            capabilities: capabilities,
+            handles: RefCell::new(handle::Manager::new()),
+            storage: RefCell::new(Storage::new()),
+        };
+        Device {
+            share: Rc::new(share),
+            frame_handles: handle::Manager::new(),
+            state: raster::State::new(),
+            active_queries: Vec::new(),
+            conditional: None,
+            start_time: Instant::now(),
+        }
+    }
+
+    fn reset_state(&mut self) {
+        self.state = raster::State::new();
+        self.active_queries.clear();
+        self.conditional = None;
+    }
+
+    /// Check if the rendering is allowed by the active conditional query.
+    fn is_enabled(&self, storage: &Storage) -> bool {
+        match self.conditional {
+            Some(q) => storage.queries.get(q.0).map_or(true, |q| q.value != 0),
+            None => true,
+        }
+    }
+
+    fn draw(&mut self, storage: &mut Storage, call: &DrawCall) {
+        if !self.is_enabled(storage) {
+            return
+        }
+        let passed = raster::draw(storage, &self.state, call);
+        for q in self.active_queries.iter() {
+            if let Some(object) = storage.queries.get_mut(q.0) {
+                object.value += passed;
+            }
+        }
+    }
+
+    fn draw_indirect(&mut self, storage: &mut Storage, buffer: Buffer,
+                     offset: usize, count: usize, indexed: bool) {
+        let calls: Vec<DrawCall> = match storage.buffers.get(buffer.0) {
+            Some(memory) => (0 .. count).filter_map(|i| {
+                let data = memory.as_slice();
+                if indexed {
+                    let size = mem::size_of::<com::DrawIndexedIndirectArgs>();
+                    let start = offset + i * size;
+                    if start + size > data.len() {
+                        return None
+                    }
+                    let args: com::DrawIndexedIndirectArgs = unsafe {
+                        let mut args = mem::zeroed();
+                        ptr::copy_nonoverlapping(data[start ..].as_ptr(),
+                                                 &mut args as *mut _ as *mut u8, size);
+                        args
+                    };
+                    Some(DrawCall {
+                        start: args.first_index,
+                        count: args.index_count,
+                        base_vertex: args.base_vertex,
+                        instances: Some((args.instance_count, args.first_instance)),
+                        indexed: true,
+                    })
+                } else {
+                    let size = mem::size_of::<com::DrawIndirectArgs>();
+                    let start = offset + i * size;
+                    if start + size > data.len() {
+                        return None
+                    }
+                    let args: com::DrawIndirectArgs = unsafe {
+                        let mut args = mem::zeroed();
+                        ptr::copy_nonoverlapping(data[start ..].as_ptr(),
+                                                 &mut args as *mut _ as *mut u8, size);
+                        args
+                    };
+                    Some(DrawCall {
+                        start: args.first_vertex,
+                        count: args.vertex_count,
+                        base_vertex: 0,
+                        instances: Some((args.instance_count, args.first_instance)),
+                        indexed: false,
+                    })
+                }
+            }).collect(),
+            None => return,
+        };
+        if calls.len() != count {
+            error!("Indirect draw arguments {}+{} are out of bounds", offset, count);
+        }
+        for call in calls.iter() {
+            self.draw(storage, call);
+        }
+    }
+
+    fn process(&mut self, storage: &mut Storage, cmd: &Command) {
+        match *cmd {
+            Command::BindPipeline(pso) => {
+                self.state.pipeline = Some(pso);
+            },
+            Command::BindVertexBuffers(ref vbs) => {
+                self.state.vertex_buffers = *vbs;
+            },
+            Command::BindConstantBuffer(slot, buf, range) => {
+                self.state.constant_buffers[slot as usize] = Some((buf, range));
+            },
+            Command::BindGlobal(loc, value) => {
+                if self.state.globals.len() <= loc {
+                    self.state.globals.resize(loc + 1, None);
+                }
+                self.state.globals[loc] = Some(value);
+            },
+            Command::BindResourceView(slot, view) => {
+                self.state.resource_views[slot as usize] = Some(view);
+            },
+            Command::BindSampler(slot, info) => {
+                self.state.samplers[slot as usize] = Some(info);
+            },
+            Command::BindPixelTargets(ref pts) => {
+                self.state.targets = *pts;
+            },
+            Command::BindIndex(buf, ty) => {
+                self.state.index = Some((buf, ty));
+            },
+            Command::SetScissor(rect) => {
+                self.state.scissor = rect;
+            },
+            Command::SetRefValues(rv) => {
+                self.state.ref_values = rv;
+            },
+            Command::CopyBuffer(src, dst, src_offset, dst_offset, size) => {
+                transfer::copy_buffer(storage, src, dst, src_offset, dst_offset, size);
+            },
+            Command::CopyBufferToTexture(src, offset, ref dst) => {
+                transfer::copy_buffer_to_texture(storage, src, offset, dst);
+            },
+            Command::CopyTextureToBuffer(ref src, dst, offset) => {
+                transfer::copy_texture_to_buffer(storage, src, dst, offset);
+            },
+            Command::CopyTexture(ref src, ref dst) => {
+                transfer::copy_texture(storage, src, dst);
+            },
+            Command::Blit(ref src, ref dst, mirror, filter) => {
+                transfer::blit(storage, src, dst, mirror, filter);
+            },
+            Command::Resolve(src, dst) => {
+                transfer::resolve(storage, src, dst);
+            },
+            Command::UpdateBuffer(buf, ref data, offset) => {
+                transfer::update_buffer(storage, buf, data, offset);
+            },
+            Command::UpdateTexture(ref dst, ref data) => {
+                transfer::update_texture(storage, dst, data);
+            },
+            Command::GenerateMipmap(ref view) => {
+                transfer::generate_mipmap(storage, view);
+            },
+            Command::ClearColor(ref view, value) => {
+                if self.is_enabled(storage) {
+                    transfer::clear_color(storage, view, value);
+                }
+            },
+            Command::ClearDepthStencil(ref view, depth, stencil) => {
+                if self.is_enabled(storage) {
+                    transfer::clear_depth_stencil(storage, view, depth, stencil);
+                }
+            },
+            Command::Draw(ref call) => {
+                self.draw(storage, call);
+            },
+            Command::DrawIndirect(buf, offset, count, indexed) => {
+                self.draw_indirect(storage, buf, offset, count, indexed);
+            },
+            Command::BeginQuery(q) => {
+                if let Some(object) = storage.queries.get_mut(q.0) {
+                    object.value = 0;
+                    self.active_queries.push(q);
+                }
+            },
+            Command::EndQuery(q) => {
+                self.active_queries.retain(|&a| a != q);
+                if let Some(object) = storage.queries.get_mut(q.0) {
+                    if object.ty == query::QueryType::AnySamplesPassed {
+                        object.value = if object.value != 0 { 1 } else { 0 };
+                    }
+                }
+            },
+            Command::WriteTimestamp(q) => {
+                if let Some(object) = storage.queries.get_mut(q.0) {
+                    let elapsed = self.start_time.elapsed();
+                    object.value = elapsed.as_secs() * 1_000_000_000 + elapsed.subsec_nanos() as u64;
+                }
+            },
+            Command::BeginConditional(q) => {
+                self.conditional = Some(q);
+            },
+            Command::EndConditional => {
+                self.conditional = None;
+            },
+        }
+    }
+
+    fn no_fence_submit(&mut self, cb: &mut command::CommandBuffer) {
+        self.reset_state();
+        let share = self.share.clone();
+        let mut storage = share.storage.borrow_mut();
+        for cmd in cb.buf.iter() {
+            self.process(&mut storage, cmd);
+        }
+    }
+}
+
+impl c::Device for Device {
+    type Resources = Resources;
+    type CommandBuffer = command::CommandBuffer;
+
+    fn get_capabilities(&self) -> &c::Capabilities {
+        &self.share.capabilities
+    }
+
+    fn pin_submitted_resources(&mut self, man: &handle::Manager<Resources>) {
+        self.frame_handles.extend(man);
+    }
+
+    fn submit(&mut self,
+              cb: &mut command::CommandBuffer,
+              access: &com::AccessInfo<Resources>) -> c::SubmissionResult<()>
+    {
+        let _guard = try!(access.take_accesses());
+        self.no_fence_submit(cb);
+        Ok(())
+    }
+
+    fn fenced_submit(&mut self,
+                     cb: &mut command::CommandBuffer,
+                     access: &com::AccessInfo<Resources>,
+                     _after: Option<handle::Fence<Resources>>)
+                     -> c::SubmissionResult<handle::Fence<Resources>> {
+        use core::handle::Producer;
+        try!(self.submit(cb, access));
+        // the execution is synchronous, so the fence is already signaled
+        Ok(self.frame_handles.make_fence(Fence))
+    }
+
+    fn wait_fence(&mut self, _fence: &handle::Fence<Self::Resources>) {}
+
+    fn is_fence_signaled(&mut self, _fence: &handle::Fence<Self::Resources>) -> bool {
+        true
+    }
+
+    fn cleanup(&mut self) {
+        use core::handle::Producer;
+        self.frame_handles.clear();
+        let mut storage = self.share.storage.borrow_mut();
+        self.share.handles.borrow_mut().clean_with(&mut *storage,
+            |s, buffer| { s.buffers.remove(buffer.resource().0); },
+            |s, shader| { s.shaders.remove(shader.0); },
+            |s, program| { s.programs.remove(program.resource().0); },
+            |s, pso| { s.pipelines.remove(pso.0); },
+            |s, texture| { s.textures.remove(texture.resource().0); },
+            |_, _| {}, //SRV
+            |_, _| {}, //UAV
+            |_, _| {}, //RTV
+            |_, _| {}, //DSV
+            |_, _| {}, //sampler
+            |_, _| {}, //fence
+            |s, query| { s.queries.remove(query.resource().0); },
+        );
+    }
+}
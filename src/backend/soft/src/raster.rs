@@ -0,0 +1,826 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The rendering pipeline: vertex fetching and shading, clipping,
+//! rasterization and the output merger.
+
+use std::cmp::{max, min};
+use core::{self as c, pso, state as s, IndexType};
+use core::{MAX_COLOR_TARGETS, MAX_CONSTANT_BUFFERS, MAX_RESOURCE_VIEWS, MAX_SAMPLERS};
+use core::format::{ChannelType, Format, SurfaceType};
+use core::shade::UniformValue;
+use core::target::Rect;
+use core::texture::{SamplerInfo, DepthStencilFlags, RO_DEPTH, RO_STENCIL};
+use command::DrawCall;
+use sample::compare;
+use shade::{Context, PixelInput, PixelOutput, VertexInput, VertexOutput, Varyings, MAX_VARYINGS};
+use storage::{Image, PipelineObject, Pool, Memory, Storage};
+use {data, Buffer, PipelineState, Resources, ResourceView, TargetView};
+
+
+/// Smallest `w` a vertex is allowed to have after clipping.
+const MIN_W: f32 = 1.0e-5;
+
+/// The state bound by the commands, used by the draw calls.
+pub struct State {
+    pub pipeline: Option<PipelineState>,
+    pub vertex_buffers: pso::VertexBufferSet<Resources>,
+    pub constant_buffers: [Option<(Buffer, Option<pso::BufferRange>)>; MAX_CONSTANT_BUFFERS],
+    pub globals: Vec<Option<UniformValue>>,
+    pub resource_views: [Option<ResourceView>; MAX_RESOURCE_VIEWS],
+    pub samplers: [Option<SamplerInfo>; MAX_SAMPLERS],
+    pub targets: pso::PixelTargetSet<Resources>,
+    pub index: Option<(Buffer, IndexType)>,
+    pub scissor: Rect,
+    pub ref_values: s::RefValues,
+}
+
+impl State {
+    pub fn new() -> State {
+        State {
+            pipeline: None,
+            vertex_buffers: pso::VertexBufferSet::new(),
+            constant_buffers: [None; MAX_CONSTANT_BUFFERS],
+            globals: Vec::new(),
+            resource_views: [None; MAX_RESOURCE_VIEWS],
+            samplers: [None; MAX_SAMPLERS],
+            targets: pso::PixelTargetSet::new(),
+            index: None,
+            scissor: Rect { x: 0, y: 0, w: 0, h: 0 },
+            ref_values: s::RefValues {
+                stencil: (0, 0),
+                blend: [0.0; 4],
+            },
+        }
+    }
+}
+
+/// A target image taken out of the storage for the duration of a draw.
+#[derive(Clone, Copy)]
+struct Target {
+    image: usize,
+    level: c::target::Level,
+    layer: usize,
+    format: Format,
+    flags: DepthStencilFlags,
+}
+
+/// The images rendered into by a draw call.
+struct Targets {
+    images: Vec<(usize, Image)>,
+    colors: [Option<Target>; MAX_COLOR_TARGETS],
+    depth: Option<Target>,
+    stencil: Option<Target>,
+}
+
+impl Targets {
+    fn take(textures: &mut Pool<Image>, pixel_targets: &pso::PixelTargetSet<Resources>) -> Targets {
+        let mut images = Vec::new();
+        let mut colors = [None, None, None, None];
+        let (depth, stencil) = {
+            let mut bind = |view: TargetView| -> Option<Target> {
+                let index = view.texture.0;
+                let position = match images.iter().position(|&(i, _)| i == index) {
+                    Some(pos) => pos,
+                    None => match textures.take(index) {
+                        Some(image) => {
+                            images.push((index, image));
+                            images.len() - 1
+                        },
+                        None => {
+                            error!("Target texture {:?} is not available", view.texture);
+                            return None
+                        },
+                    },
+                };
+                Some(Target {
+                    image: position,
+                    level: view.level,
+                    layer: view.layer.unwrap_or(0) as usize,
+                    format: view.format,
+                    flags: view.flags,
+                })
+            };
+            for (color, view) in colors.iter_mut().zip(pixel_targets.colors.iter()) {
+                *color = view.and_then(|v| bind(v));
+            }
+            (pixel_targets.depth.and_then(|v| bind(v)),
+             pixel_targets.stencil.and_then(|v| bind(v)))
+        };
+        Targets {
+            images: images,
+            colors: colors,
+            depth: depth,
+            stencil: stencil,
+        }
+    }
+
+    fn restore(self, textures: &mut Pool<Image>) {
+        for (index, image) in self.images.into_iter() {
+            textures.restore(index, image);
+        }
+    }
+
+    /// Get the size of the area shared by all the targets.
+    fn get_size(&self) -> (usize, usize) {
+        let mut size = (!0, !0);
+        let all = self.colors.iter().chain(Some(&self.depth)).chain(Some(&self.stencil));
+        for target in all.filter_map(|t| t.as_ref()) {
+            let (w, h, _) = self.images[target.image].1.get_level_size(target.level);
+            size = (min(size.0, w), min(size.1, h));
+        }
+        if size.0 == !0 { (0, 0) } else { size }
+    }
+
+    fn texel(&self, target: &Target, x: usize, y: usize) -> &[u8] {
+        self.images[target.image].1.texel(target.level, target.layer, x, y, 0)
+    }
+
+    fn texel_mut(&mut self, target: &Target, x: usize, y: usize) -> &mut [u8] {
+        self.images[target.image].1.texel_mut(target.level, target.layer, x, y, 0)
+    }
+}
+
+/// A vertex in the clip space.
+#[derive(Clone, Copy)]
+struct ClipVertex {
+    position: [f32; 4],
+    varyings: Varyings,
+}
+
+impl ClipVertex {
+    fn lerp(&self, other: &ClipVertex, t: f32) -> ClipVertex {
+        let mut out = *self;
+        for i in 0 .. 4 {
+            out.position[i] += (other.position[i] - self.position[i]) * t;
+        }
+        for (v, o) in out.varyings.iter_mut().zip(other.varyings.iter()) {
+            for i in 0 .. 4 {
+                v[i] += (o[i] - v[i]) * t;
+            }
+        }
+        out
+    }
+
+    /// Signed distance to a clipping plane, positive inside.
+    fn distance(&self, plane: usize) -> f32 {
+        let p = &self.position;
+        match plane {
+            0 => p[3] + p[0],
+            1 => p[3] - p[0],
+            2 => p[3] + p[1],
+            3 => p[3] - p[1],
+            4 => p[3] + p[2],
+            5 => p[3] - p[2],
+            _ => p[3] - MIN_W,
+        }
+    }
+}
+
+const NUM_PLANES: usize = 7;
+
+/// A vertex in the window space: `x` and `y` in pixels, `z` in the
+/// depth range, and `1/w` for the perspective correction.
+#[derive(Clone, Copy)]
+struct WindowVertex {
+    position: [f32; 4],
+    varyings: Varyings,
+}
+
+/// Everything a draw call needs, except the targets.
+struct Pipeline<'a> {
+    object: &'a PipelineObject,
+    context: Context<'a>,
+    ref_values: s::RefValues,
+    scissor: Option<Rect>,
+    size: (usize, usize),
+}
+
+impl<'a> Pipeline<'a> {
+    fn to_window(&self, v: &ClipVertex) -> WindowVertex {
+        let p = &v.position;
+        let q = 1.0 / p[3];
+        WindowVertex {
+            position: [
+                (p[0] * q * 0.5 + 0.5) * self.size.0 as f32,
+                (p[1] * q * 0.5 + 0.5) * self.size.1 as f32,
+                p[2] * q * 0.5 + 0.5,
+                q,
+            ],
+            varyings: v.varyings,
+        }
+    }
+
+    /// Get the pixel rectangle `[x0, x1) x [y0, y1)` that can be written to.
+    fn get_bounds(&self) -> (usize, usize, usize, usize) {
+        let (w, h) = self.size;
+        match self.scissor {
+            Some(r) => {
+                let x0 = min(r.x as usize, w);
+                let y0 = min(r.y as usize, h);
+                (x0, min(x0 + r.w as usize, w), y0, min(y0 + r.h as usize, h))
+            },
+            None => (0, w, 0, h),
+        }
+    }
+
+    fn draw_point(&self, targets: &mut Targets, v: &ClipVertex) -> u64 {
+        if (0 .. NUM_PLANES).any(|i| v.distance(i) < 0.0) {
+            return 0
+        }
+        let wv = self.to_window(v);
+        let (x, y) = (wv.position[0].floor(), wv.position[1].floor());
+        if x < 0.0 || y < 0.0 {
+            return 0
+        }
+        let input = PixelInput {
+            position: [x + 0.5, y + 0.5, wv.position[2], wv.position[3]],
+            front_facing: true,
+            varyings: wv.varyings,
+            ddx: [[0.0; 4]; MAX_VARYINGS],
+            ddy: [[0.0; 4]; MAX_VARYINGS],
+        };
+        self.process_pixel(targets, x as usize, y as usize, 0.0, &input)
+    }
+
+    fn draw_line(&self, targets: &mut Targets, a: &ClipVertex, b: &ClipVertex,
+                 width: usize, depth_offset: f32) -> u64 {
+        // parametric clipping of the segment
+        let (mut t0, mut t1) = (0.0f32, 1.0f32);
+        for i in 0 .. NUM_PLANES {
+            let (da, db) = (a.distance(i), b.distance(i));
+            if da < 0.0 && db < 0.0 {
+                return 0
+            }
+            if da < 0.0 {
+                t0 = t0.max(da / (da - db));
+            } else if db < 0.0 {
+                t1 = t1.min(da / (da - db));
+            }
+        }
+        if t0 >= t1 {
+            return 0
+        }
+        let wa = self.to_window(&a.lerp(b, t0));
+        let wb = self.to_window(&a.lerp(b, t1));
+        let dx = wb.position[0] - wa.position[0];
+        let dy = wb.position[1] - wa.position[1];
+        let x_major = dx.abs() >= dy.abs();
+        let steps = dx.abs().max(dy.abs()).ceil().max(1.0) as usize;
+        let mut passed = 0;
+        for k in 0 .. steps {
+            let t = (k as f32 + 0.5) / steps as f32;
+            let x = wa.position[0] + dx * t;
+            let y = wa.position[1] + dy * t;
+            let z = wa.position[2] + (wb.position[2] - wa.position[2]) * t;
+            let q = wa.position[3] + (wb.position[3] - wa.position[3]) * t;
+            let mut input = PixelInput {
+                position: [0.0, 0.0, z, q],
+                front_facing: true,
+                varyings: [[0.0; 4]; MAX_VARYINGS],
+                ddx: [[0.0; 4]; MAX_VARYINGS],
+                ddy: [[0.0; 4]; MAX_VARYINGS],
+            };
+            for i in 0 .. MAX_VARYINGS {
+                for j in 0 .. 4 {
+                    let va = wa.varyings[i][j] * wa.position[3];
+                    let vb = wb.varyings[i][j] * wb.position[3];
+                    input.varyings[i][j] = (va + (vb - va) * t) / q;
+                }
+            }
+            for w in 0 .. width {
+                let shift = w as f32 - (width - 1) as f32 * 0.5;
+                let (px, py) = if x_major {
+                    (x.floor(), (y + shift).floor())
+                } else {
+                    ((x + shift).floor(), y.floor())
+                };
+                if px < 0.0 || py < 0.0 {
+                    continue
+                }
+                input.position[0] = px + 0.5;
+                input.position[1] = py + 0.5;
+                passed += self.process_pixel(targets, px as usize, py as usize,
+                                             depth_offset, &input);
+            }
+        }
+        passed
+    }
+
+    fn draw_triangle(&self, targets: &mut Targets, vertices: [&ClipVertex; 3]) -> u64 {
+        // clip the triangle into a convex polygon
+        let mut polygon = vec![*vertices[0], *vertices[1], *vertices[2]];
+        for plane in 0 .. NUM_PLANES {
+            if polygon.iter().all(|v| v.distance(plane) >= 0.0) {
+                continue
+            }
+            let mut clipped = Vec::with_capacity(polygon.len() + 1);
+            for i in 0 .. polygon.len() {
+                let a = &polygon[i];
+                let b = &polygon[(i + 1) % polygon.len()];
+                let (da, db) = (a.distance(plane), b.distance(plane));
+                if da >= 0.0 {
+                    clipped.push(*a);
+                }
+                if (da >= 0.0) != (db >= 0.0) {
+                    clipped.push(a.lerp(b, da / (da - db)));
+                }
+            }
+            polygon = clipped;
+            if polygon.len() < 3 {
+                return 0
+            }
+        }
+        let window: Vec<WindowVertex> = polygon.iter().map(|v| self.to_window(v)).collect();
+        // determine the facing from the signed area
+        let mut area = 0.0;
+        for i in 0 .. window.len() {
+            let a = &window[i].position;
+            let b = &window[(i + 1) % window.len()].position;
+            area += a[0] * b[1] - b[0] * a[1];
+        }
+        let rast = &self.object.desc.rasterizer;
+        let front_facing = match rast.front_face {
+            s::FrontFace::CounterClockwise => area > 0.0,
+            s::FrontFace::Clockwise => area < 0.0,
+        };
+        match rast.cull_face {
+            s::CullFace::Front if front_facing => return 0,
+            s::CullFace::Back if !front_facing => return 0,
+            _ => (),
+        }
+        let mut passed = 0;
+        match rast.method {
+            s::RasterMethod::Fill => {
+                for i in 1 .. window.len() - 1 {
+                    passed += self.fill_triangle(targets, [&window[0], &window[i], &window[i + 1]],
+                                                 front_facing);
+                }
+            },
+            s::RasterMethod::Line(width) => {
+                let offset = self.get_depth_offset(targets, 0.0);
+                for i in 0 .. polygon.len() {
+                    let b = &polygon[(i + 1) % polygon.len()];
+                    passed += self.draw_line(targets, &polygon[i], b,
+                                             max(width as usize, 1), offset);
+                }
+            },
+            s::RasterMethod::Point => {
+                for v in polygon.iter() {
+                    passed += self.draw_point(targets, v);
+                }
+            },
+        }
+        passed
+    }
+
+    /// Compute the polygon offset for the given maximum depth slope.
+    fn get_depth_offset(&self, targets: &Targets, slope: f32) -> f32 {
+        match self.object.desc.rasterizer.offset {
+            Some(s::Offset(factor, units)) => {
+                let resolution = match targets.depth.as_ref() {
+                    Some(t) => match targets.images[t.image].1.info.format {
+                        SurfaceType::D16 => 1.0 / 65536.0,
+                        SurfaceType::D32 => 1.0 / 8388608.0,
+                        _ => 1.0 / 16777216.0,
+                    },
+                    None => 0.0,
+                };
+                factor as f32 * slope + units as f32 * resolution
+            },
+            None => 0.0,
+        }
+    }
+
+    fn fill_triangle(&self, targets: &mut Targets, v: [&WindowVertex; 3], front_facing: bool) -> u64 {
+        let (p0, p1, p2) = (v[0].position, v[1].position, v[2].position);
+        let mut area = (p1[0] - p0[0]) * (p2[1] - p0[1]) - (p1[1] - p0[1]) * (p2[0] - p0[0]);
+        if area == 0.0 || !area.is_finite() {
+            return 0
+        }
+        // make the winding counter-clockwise
+        let (p1, p2, v1, v2) = if area < 0.0 {
+            area = -area;
+            (p2, p1, v[2], v[1])
+        } else {
+            (p1, p2, v[1], v[2])
+        };
+        let verts = [v[0], v1, v2];
+        let points = [p0, p1, p2];
+        // edge i is opposite to the vertex i
+        let edges = [(p1, p2), (p2, p0), (p0, p1)];
+        // the derivatives of the barycentric coordinates
+        let mut db_dx = [0.0; 3];
+        let mut db_dy = [0.0; 3];
+        let mut bias = [false; 3];
+        for (i, &(a, b)) in edges.iter().enumerate() {
+            let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+            db_dx[i] = -dy / area;
+            db_dy[i] = dx / area;
+            // top-left fill convention
+            bias[i] = !(dy < 0.0 || (dy == 0.0 && dx < 0.0));
+        }
+        let dq_dx = (0 .. 3).fold(0.0, |sum, i| sum + db_dx[i] * points[i][3]);
+        let dq_dy = (0 .. 3).fold(0.0, |sum, i| sum + db_dy[i] * points[i][3]);
+        let dz_dx = (0 .. 3).fold(0.0, |sum, i| sum + db_dx[i] * points[i][2]);
+        let dz_dy = (0 .. 3).fold(0.0, |sum, i| sum + db_dy[i] * points[i][2]);
+        let depth_offset = self.get_depth_offset(targets, dz_dx.abs().max(dz_dy.abs()));
+        // bounding box
+        let (bx0, bx1, by0, by1) = self.get_bounds();
+        let min_x = points.iter().fold(points[0][0], |m, p| m.min(p[0])).max(0.0);
+        let max_x = points.iter().fold(points[0][0], |m, p| m.max(p[0])).max(0.0);
+        let min_y = points.iter().fold(points[0][1], |m, p| m.min(p[1])).max(0.0);
+        let max_y = points.iter().fold(points[0][1], |m, p| m.max(p[1])).max(0.0);
+        let x0 = max(min_x.floor() as usize, bx0);
+        let x1 = min(max_x.ceil() as usize + 1, bx1);
+        let y0 = max(min_y.floor() as usize, by0);
+        let y1 = min(max_y.ceil() as usize + 1, by1);
+
+        let mut passed = 0;
+        for y in y0 .. y1 {
+            for x in x0 .. x1 {
+                let (fx, fy) = (x as f32 + 0.5, y as f32 + 0.5);
+                let mut bary = [0.0; 3];
+                let mut inside = true;
+                for (i, &(a, b)) in edges.iter().enumerate() {
+                    let e = (b[0] - a[0]) * (fy - a[1]) - (b[1] - a[1]) * (fx - a[0]);
+                    if e < 0.0 || (e == 0.0 && bias[i]) {
+                        inside = false;
+                        break
+                    }
+                    bary[i] = e / area;
+                }
+                if !inside {
+                    continue
+                }
+                let z = (0 .. 3).fold(0.0, |sum, i| sum + bary[i] * points[i][2]);
+                let q = (0 .. 3).fold(0.0, |sum, i| sum + bary[i] * points[i][3]);
+                let mut input = PixelInput {
+                    position: [fx, fy, z, q],
+                    front_facing: front_facing,
+                    varyings: [[0.0; 4]; MAX_VARYINGS],
+                    ddx: [[0.0; 4]; MAX_VARYINGS],
+                    ddy: [[0.0; 4]; MAX_VARYINGS],
+                };
+                for k in 0 .. MAX_VARYINGS {
+                    for j in 0 .. 4 {
+                        let mut n = 0.0;
+                        let mut dn_dx = 0.0;
+                        let mut dn_dy = 0.0;
+                        for i in 0 .. 3 {
+                            let aq = verts[i].varyings[k][j] * points[i][3];
+                            n += bary[i] * aq;
+                            dn_dx += db_dx[i] * aq;
+                            dn_dy += db_dy[i] * aq;
+                        }
+                        let value = n / q;
+                        input.varyings[k][j] = value;
+                        input.ddx[k][j] = (dn_dx - value * dq_dx) / q;
+                        input.ddy[k][j] = (dn_dy - value * dq_dy) / q;
+                    }
+                }
+                passed += self.process_pixel(targets, x, y, depth_offset, &input);
+            }
+        }
+        passed
+    }
+
+    /// Shade a pixel and merge it into the targets, returning the
+    /// number of samples that passed the depth and stencil tests.
+    fn process_pixel(&self, targets: &mut Targets, x: usize, y: usize,
+                     depth_offset: f32, input: &PixelInput) -> u64 {
+        let (bx0, bx1, by0, by1) = self.get_bounds();
+        if x < bx0 || x >= bx1 || y < by0 || y >= by1 {
+            return 0
+        }
+        let mut output = PixelOutput {
+            colors: [[0.0; 4]; MAX_COLOR_TARGETS],
+            depth: None,
+            discard: false,
+        };
+        self.object.program.pixel.run(&self.context, input, &mut output);
+        if output.discard {
+            return 0
+        }
+        let depth = output.depth.unwrap_or(input.position[2] + depth_offset);
+        let depth = depth.max(0.0).min(1.0);
+        if !self.test_depth_stencil(targets, x, y, depth, input.front_facing) {
+            return 0
+        }
+        let desc = &self.object.desc;
+        for slot in 0 .. MAX_COLOR_TARGETS {
+            if let (Some((_, info)), Some(target)) = (desc.color_targets[slot], targets.colors[slot]) {
+                let dst = data::decode(target.format, targets.texel(&target, x, y));
+                let color = blend(&info, target.format.1, output.colors[slot], dst,
+                                  self.ref_values.blend);
+                let mut result = dst;
+                let masks = [s::RED, s::GREEN, s::BLUE, s::ALPHA];
+                for i in 0 .. 4 {
+                    if info.mask.contains(masks[i]) {
+                        result[i] = color[i];
+                    }
+                }
+                data::encode(target.format, result, targets.texel_mut(&target, x, y));
+            }
+        }
+        1
+    }
+
+    fn test_depth_stencil(&self, targets: &mut Targets, x: usize, y: usize,
+                          depth: f32, front_facing: bool) -> bool {
+        let info = match self.object.desc.depth_stencil {
+            Some((_, info)) => info,
+            None => return true,
+        };
+        let side = if front_facing { info.front } else { info.back };
+        let reference = if front_facing { self.ref_values.stencil.0 } else { self.ref_values.stencil.1 };
+        let depth_test = match (info.depth, targets.depth) {
+            (Some(d), Some(target)) => Some((d, target)),
+            _ => None,
+        };
+        let stencil_test = match (side, targets.stencil) {
+            (Some(side), Some(target)) => Some((side, target)),
+            _ => None,
+        };
+        let stored_stencil = stencil_test.map(|(_, target)| {
+            let surface = targets.images[target.image].1.info.format;
+            data::decode_stencil(surface, targets.texel(&target, x, y))
+        }).unwrap_or(0);
+        let stencil_pass = match stencil_test {
+            Some((side, _)) => compare(side.fun, reference & side.mask_read,
+                                       stored_stencil & side.mask_read),
+            None => true,
+        };
+        let depth_pass = stencil_pass && match depth_test {
+            Some((d, target)) => {
+                let surface = targets.images[target.image].1.info.format;
+                let stored = data::decode_depth(surface, targets.texel(&target, x, y));
+                compare(d.fun, depth, stored)
+            },
+            None => true,
+        };
+        // write the stencil
+        if let Some((side, target)) = stencil_test {
+            let op = if !stencil_pass {
+                side.op_fail
+            } else if !depth_pass {
+                side.op_depth_fail
+            } else {
+                side.op_pass
+            };
+            let value = apply_stencil_op(op, stored_stencil, reference);
+            let value = (stored_stencil & !side.mask_write) | (value & side.mask_write);
+            if value != stored_stencil && !target.flags.contains(RO_STENCIL) {
+                let surface = targets.images[target.image].1.info.format;
+                data::encode_stencil(surface, value, targets.texel_mut(&target, x, y));
+            }
+        }
+        // write the depth
+        if let Some((d, target)) = depth_test {
+            if depth_pass && d.write && !target.flags.contains(RO_DEPTH) {
+                let surface = targets.images[target.image].1.info.format;
+                data::encode_depth(surface, depth, targets.texel_mut(&target, x, y));
+            }
+        }
+        stencil_pass && depth_pass
+    }
+}
+
+fn apply_stencil_op(op: s::StencilOp, value: u8, reference: u8) -> u8 {
+    match op {
+        s::StencilOp::Keep => value,
+        s::StencilOp::Zero => 0,
+        s::StencilOp::Replace => reference,
+        s::StencilOp::IncrementClamp => value.saturating_add(1),
+        s::StencilOp::IncrementWrap => value.wrapping_add(1),
+        s::StencilOp::DecrementClamp => value.saturating_sub(1),
+        s::StencilOp::DecrementWrap => value.wrapping_sub(1),
+        s::StencilOp::Invert => !value,
+    }
+}
+
+fn get_blend_factor(factor: s::Factor, src: [f32; 4], dst: [f32; 4],
+                    constant: [f32; 4], channel: usize) -> f32 {
+    let value = |v: s::BlendValue| match v {
+        s::BlendValue::SourceColor => src[channel],
+        s::BlendValue::SourceAlpha => src[3],
+        s::BlendValue::DestColor => dst[channel],
+        s::BlendValue::DestAlpha => dst[3],
+        s::BlendValue::ConstColor => constant[channel],
+        s::BlendValue::ConstAlpha => constant[3],
+    };
+    match factor {
+        s::Factor::Zero => 0.0,
+        s::Factor::One => 1.0,
+        s::Factor::SourceAlphaSaturated if channel == 3 => 1.0,
+        s::Factor::SourceAlphaSaturated => src[3].min(1.0 - dst[3]),
+        s::Factor::ZeroPlus(v) => value(v),
+        s::Factor::OneMinus(v) => 1.0 - value(v),
+    }
+}
+
+/// Blend a source color into the destination, according to the PSO
+/// color target information.
+fn blend(info: &pso::ColorInfo, channel: ChannelType, src: [f32; 4],
+         dst: [f32; 4], constant: [f32; 4]) -> [f32; 4] {
+    let src = match channel {
+        ChannelType::Int | ChannelType::Uint => return src,
+        ChannelType::Unorm | ChannelType::Srgb =>
+            [src[0].max(0.0).min(1.0), src[1].max(0.0).min(1.0),
+             src[2].max(0.0).min(1.0), src[3].max(0.0).min(1.0)],
+        ChannelType::Inorm =>
+            [src[0].max(-1.0).min(1.0), src[1].max(-1.0).min(1.0),
+             src[2].max(-1.0).min(1.0), src[3].max(-1.0).min(1.0)],
+        ChannelType::Float => src,
+    };
+    if info.color.is_none() && info.alpha.is_none() {
+        return src
+    }
+    let color = info.color.unwrap_or_default();
+    let alpha = info.alpha.unwrap_or_default();
+    let mut out = [0.0; 4];
+    for i in 0 .. 4 {
+        let bc = if i == 3 { &alpha } else { &color };
+        let fs = get_blend_factor(bc.source, src, dst, constant, i);
+        let fd = get_blend_factor(bc.destination, src, dst, constant, i);
+        out[i] = match bc.equation {
+            s::Equation::Add => src[i] * fs + dst[i] * fd,
+            s::Equation::Sub => src[i] * fs - dst[i] * fd,
+            s::Equation::RevSub => dst[i] * fd - src[i] * fs,
+            s::Equation::Min => src[i].min(dst[i]),
+            s::Equation::Max => src[i].max(dst[i]),
+        };
+    }
+    out
+}
+
+/// Read a single vertex attribute, returning zeros when out of bounds.
+fn fetch_attribute(memory: Option<&Memory>, offset: usize, format: Format) -> [f32; 4] {
+    let size = data::get_texel_size(format.0);
+    match memory {
+        Some(m) if offset + size <= m.len() => data::decode(format, &m.as_slice()[offset .. offset + size]),
+        _ => [0.0; 4],
+    }
+}
+
+/// Read the index buffer, or generate the sequential indices.
+fn get_indices(storage: &Storage, state: &State, call: &DrawCall) -> Vec<u32> {
+    if !call.indexed {
+        return (call.start .. call.start + call.count).collect()
+    }
+    let (buffer, ty) = match state.index {
+        Some(index) => index,
+        None => {
+            error!("No index buffer bound for an indexed draw call");
+            return Vec::new()
+        },
+    };
+    let memory = match storage.buffers.get(buffer.0) {
+        Some(m) => m.as_slice(),
+        None => return Vec::new(),
+    };
+    let size = match ty {
+        IndexType::U16 => 2,
+        IndexType::U32 => 4,
+    };
+    (call.start .. call.start + call.count).map(|i| {
+        let offset = i as usize * size;
+        let index = if offset + size > memory.len() {
+            0
+        } else if size == 2 {
+            memory[offset] as u32 | (memory[offset + 1] as u32) << 8
+        } else {
+            memory[offset] as u32 | (memory[offset + 1] as u32) << 8 |
+            (memory[offset + 2] as u32) << 16 | (memory[offset + 3] as u32) << 24
+        };
+        max(index as i64 + call.base_vertex as i64, 0) as u32
+    }).collect()
+}
+
+/// Execute a draw call, returning the number of the samples that passed
+/// the depth and stencil tests.
+pub fn draw(storage: &mut Storage, state: &State, call: &DrawCall) -> u64 {
+    let object = match state.pipeline.and_then(|p| storage.pipelines.get(p.0)) {
+        Some(object) => object.clone(),
+        None => {
+            error!("No pipeline state bound for a draw call");
+            return 0
+        },
+    };
+    let desc = &object.desc;
+    match desc.primitive {
+        c::Primitive::PointList | c::Primitive::LineList | c::Primitive::LineStrip |
+        c::Primitive::TriangleList | c::Primitive::TriangleStrip => (),
+        other => {
+            error!("Primitive {:?} is not supported by the software backend", other);
+            return 0
+        },
+    }
+    let indices = get_indices(storage, state, call);
+    let mut targets = Targets::take(&mut storage.textures, &state.targets);
+    let passed = {
+        let storage = &*storage;
+        let mut constant_buffers = [None; MAX_CONSTANT_BUFFERS];
+        for (cb, buf) in constant_buffers.iter_mut().zip(state.constant_buffers.iter()) {
+            *cb = buf.and_then(|(b, range)| storage.buffers.get(b.0).and_then(|m| match range {
+                Some((offset, size)) => m.as_slice().get(offset .. offset + size),
+                None => Some(m.as_slice()),
+            }));
+        }
+        let mut textures = [None; MAX_RESOURCE_VIEWS];
+        for (tex, view) in textures.iter_mut().zip(state.resource_views.iter()) {
+            *tex = view.and_then(|v| storage.textures.get(v.texture.0).map(|image| (image, v)));
+        }
+        let (width, height) = targets.get_size();
+        let (view_w, view_h, _) = state.targets.get_view();
+        let pipeline = Pipeline {
+            object: &object,
+            context: Context::new(constant_buffers, &state.globals, textures, state.samplers),
+            ref_values: state.ref_values,
+            scissor: if desc.scissor { Some(state.scissor) } else { None },
+            size: (min(width, view_w as usize), min(height, view_h as usize)),
+        };
+        let (instance_count, base_instance) = call.instances.unwrap_or((1, 0));
+        let count = indices.len();
+        let mut passed = 0;
+        for instance in 0 .. instance_count {
+            let vertices: Vec<ClipVertex> = indices.iter().map(|&index| {
+                let mut input = VertexInput {
+                    attributes: [[0.0, 0.0, 0.0, 1.0]; c::MAX_VERTEX_ATTRIBUTES],
+                    vertex_id: index,
+                    instance_id: instance,
+                };
+                for (slot, attrib) in desc.attributes.iter().enumerate() {
+                    let (buf_index, elem) = match *attrib {
+                        Some(at) => at,
+                        None => continue,
+                    };
+                    let (buffer, offset) = match state.vertex_buffers.0[slot] {
+                        Some(vb) => vb,
+                        None => continue,
+                    };
+                    let vb_desc = match desc.vertex_buffers[buf_index as usize] {
+                        Some(d) => d,
+                        None => continue,
+                    };
+                    let element = if vb_desc.rate == 0 {
+                        index as usize
+                    } else {
+                        (instance / vb_desc.rate as u32 + base_instance) as usize
+                    };
+                    let address = offset + element * vb_desc.stride as usize + elem.offset as usize;
+                    input.attributes[slot] = fetch_attribute(storage.buffers.get(buffer.0),
+                                                             address, elem.format);
+                }
+                let mut output = VertexOutput {
+                    position: [0.0, 0.0, 0.0, 1.0],
+                    varyings: [[0.0; 4]; MAX_VARYINGS],
+                };
+                object.program.vertex.run(&pipeline.context, &input, &mut output);
+                ClipVertex {
+                    position: output.position,
+                    varyings: output.varyings,
+                }
+            }).collect();
+            let v = &vertices;
+            match desc.primitive {
+                c::Primitive::PointList => for i in 0 .. count {
+                    passed += pipeline.draw_point(&mut targets, &v[i]);
+                },
+                c::Primitive::LineList => for i in 0 .. count / 2 {
+                    passed += pipeline.draw_line(&mut targets, &v[2*i], &v[2*i+1], 1, 0.0);
+                },
+                c::Primitive::LineStrip => for i in 1 .. max(count, 1) {
+                    passed += pipeline.draw_line(&mut targets, &v[i-1], &v[i], 1, 0.0);
+                },
+                c::Primitive::TriangleList => for i in 0 .. count / 3 {
+                    passed += pipeline.draw_triangle(&mut targets, [&v[3*i], &v[3*i+1], &v[3*i+2]]);
+                },
+                c::Primitive::TriangleStrip => for i in 2 .. max(count, 2) {
+                    // every other triangle is flipped to keep the winding
+                    let tri = if i % 2 == 0 {
+                        [&v[i-2], &v[i-1], &v[i]]
+                    } else {
+                        [&v[i-1], &v[i-2], &v[i]]
+                    };
+                    passed += pipeline.draw_triangle(&mut targets, tri);
+                },
+                _ => unreachable!(),
+            }
+        }
+        passed
+    };
+    targets.restore(&mut storage.textures);
+    passed
+}
@@ -0,0 +1,303 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Texture sampling, following the `SamplerInfo` semantics of the
+//! hardware backends.
+
+use std::cmp::min;
+use core::format::{ChannelSource, Format, Swizzle};
+use core::state::Comparison;
+use core::texture::{self as t, FilterMethod, SamplerInfo, WrapMode};
+use storage::Image;
+use data;
+use ResourceView;
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Filter {
+    Nearest,
+    Linear,
+}
+
+/// Get the minification, magnification and mipmap filters of a method.
+fn get_filters(method: FilterMethod) -> (Filter, Filter, Option<Filter>) {
+    match method {
+        FilterMethod::Scale => (Filter::Nearest, Filter::Nearest, None),
+        FilterMethod::Mipmap => (Filter::Nearest, Filter::Nearest, Some(Filter::Nearest)),
+        FilterMethod::Bilinear => (Filter::Linear, Filter::Linear, None),
+        // anisotropy is not emulated
+        FilterMethod::Trilinear | FilterMethod::Anisotropic(_) =>
+            (Filter::Linear, Filter::Linear, Some(Filter::Linear)),
+    }
+}
+
+/// Evaluate a comparison function between a new and a stored value.
+pub fn compare<T: PartialOrd>(fun: Comparison, new: T, stored: T) -> bool {
+    match fun {
+        Comparison::Never => false,
+        Comparison::Less => new < stored,
+        Comparison::LessEqual => new <= stored,
+        Comparison::Equal => new == stored,
+        Comparison::GreaterEqual => new >= stored,
+        Comparison::Greater => new > stored,
+        Comparison::NotEqual => new != stored,
+        Comparison::Always => true,
+    }
+}
+
+fn to_index(v: f32) -> i32 {
+    if v.is_nan() {
+        0
+    } else {
+        v.max(-1.0e6).min(1.0e6).floor() as i32
+    }
+}
+
+fn wrap(coord: i32, size: usize, mode: WrapMode) -> Option<usize> {
+    let size = size as i32;
+    let modulo = |a: i32, b: i32| ((a % b) + b) % b;
+    match mode {
+        WrapMode::Tile => Some(modulo(coord, size) as usize),
+        WrapMode::Mirror => {
+            let m = modulo(coord, 2 * size);
+            let mirrored = if m < size { m } else { 2 * size - 1 - m };
+            Some(mirrored as usize)
+        },
+        WrapMode::Clamp => Some(coord.max(0).min(size - 1) as usize),
+        WrapMode::Border if coord < 0 || coord >= size => None,
+        WrapMode::Border => Some(coord as usize),
+    }
+}
+
+fn apply_swizzle(value: [f32; 4], swizzle: Swizzle) -> [f32; 4] {
+    let pick = |source| match source {
+        ChannelSource::Zero => 0.0,
+        ChannelSource::One => 1.0,
+        ChannelSource::X => value[0],
+        ChannelSource::Y => value[1],
+        ChannelSource::Z => value[2],
+        ChannelSource::W => value[3],
+    };
+    [pick(swizzle.0), pick(swizzle.1), pick(swizzle.2), pick(swizzle.3)]
+}
+
+/// Select the cube face of a direction vector, returning the face
+/// index and the normalized coordinates on it.
+fn get_cube_face(dir: [f32; 3]) -> (usize, f32, f32) {
+    let (ax, ay, az) = (dir[0].abs(), dir[1].abs(), dir[2].abs());
+    let (face, sc, tc, ma) = if ax >= ay && ax >= az {
+        if dir[0] >= 0.0 { (0, -dir[2], -dir[1], ax) } else { (1, dir[2], -dir[1], ax) }
+    } else if ay >= az {
+        if dir[1] >= 0.0 { (2, dir[0], dir[2], ay) } else { (3, dir[0], -dir[2], ay) }
+    } else {
+        if dir[2] >= 0.0 { (4, dir[0], -dir[1], az) } else { (5, -dir[0], -dir[1], az) }
+    };
+    if ma == 0.0 {
+        (face, 0.5, 0.5)
+    } else {
+        (face, 0.5 * (sc / ma + 1.0), 0.5 * (tc / ma + 1.0))
+    }
+}
+
+struct Lookup<'a> {
+    image: &'a Image,
+    format: Format,
+    layer: usize,
+    wrap_mode: (WrapMode, WrapMode, WrapMode),
+    border: [f32; 4],
+    comparison: Option<(Comparison, f32)>,
+}
+
+impl<'a> Lookup<'a> {
+    fn texel(&self, level: t::Level, i: [i32; 3]) -> [f32; 4] {
+        let (w, h, d) = self.image.get_level_size(level);
+        let value = match (wrap(i[0], w, self.wrap_mode.0),
+                           wrap(i[1], h, self.wrap_mode.1),
+                           wrap(i[2], d, self.wrap_mode.2)) {
+            (Some(x), Some(y), Some(z)) =>
+                data::decode(self.format, self.image.texel(level, self.layer, x, y, z)),
+            _ => self.border,
+        };
+        match self.comparison {
+            Some((fun, reference)) => {
+                let v = if compare(fun, reference, value[0]) { 1.0 } else { 0.0 };
+                [v, v, v, 1.0]
+            },
+            None => value,
+        }
+    }
+
+    fn filter(&self, level: t::Level, coord: [f32; 3], dims: usize, filter: Filter) -> [f32; 4] {
+        let (w, h, d) = self.image.get_level_size(level);
+        let size = [w as f32, h as f32, d as f32];
+        match filter {
+            Filter::Nearest => {
+                let mut i = [0; 3];
+                for k in 0 .. dims {
+                    i[k] = to_index(coord[k] * size[k]);
+                }
+                self.texel(level, i)
+            },
+            Filter::Linear => {
+                let mut base = [0; 3];
+                let mut frac = [0.0; 3];
+                for k in 0 .. dims {
+                    let p = coord[k] * size[k] - 0.5;
+                    base[k] = to_index(p);
+                    frac[k] = if p.is_finite() { p - p.floor() } else { 0.0 };
+                }
+                let mut out = [0.0; 4];
+                for corner in 0 .. (1 << dims) {
+                    let mut i = base;
+                    let mut weight = 1.0;
+                    for k in 0 .. dims {
+                        if corner & (1 << k) != 0 {
+                            i[k] += 1;
+                            weight *= frac[k];
+                        } else {
+                            weight *= 1.0 - frac[k];
+                        }
+                    }
+                    let value = self.texel(level, i);
+                    for (o, v) in out.iter_mut().zip(value.iter()) {
+                        *o += weight * v;
+                    }
+                }
+                out
+            },
+        }
+    }
+}
+
+fn get_max_level(image: &Image, view: &ResourceView) -> t::Level {
+    min(view.max, image.info.levels - 1)
+}
+
+/// Compute the level of detail from the screen-space derivatives
+/// of the normalized texture coordinates.
+pub fn get_lod(image: &Image, view: &ResourceView, ddx: [f32; 3], ddy: [f32; 3]) -> f32 {
+    let (w, h, d) = image.get_level_size(view.min);
+    let dims = match image.info.kind {
+        t::Kind::D1(..) | t::Kind::D1Array(..) => 1,
+        t::Kind::D3(..) => 3,
+        _ => 2,
+    };
+    let size = [w as f32, h as f32, d as f32];
+    let (mut lx, mut ly) = (0.0, 0.0);
+    for k in 0 .. dims {
+        lx += (ddx[k] * size[k]) * (ddx[k] * size[k]);
+        ly += (ddy[k] * size[k]) * (ddy[k] * size[k]);
+    }
+    let rho = lx.max(ly).sqrt();
+    if rho > 0.0 { rho.log2() } else { -1000.0 }
+}
+
+/// Sample a texture view at the given coordinates and level of detail.
+/// When a reference value is given and the sampler has a comparison
+/// function, the comparison results are filtered instead of the texels.
+pub fn sample(image: &Image, view: &ResourceView, info: &SamplerInfo,
+              coord: [f32; 3], lod: f32, reference: Option<f32>) -> [f32; 4] {
+    let array_layer = |v: f32, count: Option<t::Layer>| {
+        let max_layer = count.unwrap_or(1).saturating_sub(1) as i32;
+        to_index(v + 0.5).max(0).min(max_layer) as usize
+    };
+    let slices = image.info.kind.get_num_slices();
+    let mut wrap_mode = info.wrap_mode;
+    let (dims, layer, coord) = match image.info.kind {
+        t::Kind::D1(..) => (1, 0, coord),
+        t::Kind::D1Array(..) => (1, view.layer.map_or_else(|| array_layer(coord[1], slices),
+                                                            |l| l as usize), coord),
+        t::Kind::D2(..) => (2, 0, coord),
+        t::Kind::D2Array(..) => (2, view.layer.map_or_else(|| array_layer(coord[2], slices),
+                                                            |l| l as usize), coord),
+        t::Kind::D3(..) => (3, 0, coord),
+        t::Kind::Cube(..) | t::Kind::CubeArray(..) => {
+            // seamless filtering is not emulated
+            wrap_mode = (WrapMode::Clamp, WrapMode::Clamp, WrapMode::Clamp);
+            let (face, u, v) = get_cube_face(coord);
+            (2, face + 6 * view.layer.unwrap_or(0) as usize, [u, v, 0.0])
+        },
+    };
+    let lookup = Lookup {
+        image: image,
+        format: view.format,
+        layer: layer,
+        wrap_mode: wrap_mode,
+        border: info.border.into(),
+        comparison: match (info.comparison, reference) {
+            (Some(fun), Some(r)) => Some((fun, r)),
+            _ => None,
+        },
+    };
+
+    let (lod_min, lod_max): (f32, f32) = (info.lod_range.0.into(), info.lod_range.1.into());
+    let bias: f32 = info.lod_bias.into();
+    let lod = (lod + bias).max(lod_min).min(lod_max);
+    let base = view.min;
+    let max_level = get_max_level(image, view);
+    let (min_filter, mag_filter, mip_filter) = get_filters(info.filter);
+
+    let value = if lod <= 0.0 {
+        lookup.filter(base, coord, dims, mag_filter)
+    } else {
+        match mip_filter {
+            None => lookup.filter(base, coord, dims, min_filter),
+            Some(Filter::Nearest) => {
+                let level = min(base as u32 + (lod + 0.5) as u32, max_level as u32);
+                lookup.filter(level as t::Level, coord, dims, min_filter)
+            },
+            Some(Filter::Linear) => {
+                let l0 = min(base as u32 + lod as u32, max_level as u32) as t::Level;
+                let l1 = min(l0 + 1, max_level);
+                let frac = lod - lod.floor();
+                let a = lookup.filter(l0, coord, dims, min_filter);
+                if l1 == l0 || frac == 0.0 {
+                    a
+                } else {
+                    let b = lookup.filter(l1, coord, dims, min_filter);
+                    [a[0] + (b[0] - a[0]) * frac, a[1] + (b[1] - a[1]) * frac,
+                     a[2] + (b[2] - a[2]) * frac, a[3] + (b[3] - a[3]) * frac]
+                }
+            },
+        }
+    };
+    apply_swizzle(value, view.swizzle)
+}
+
+/// Load a single texel without any filtering, returning zeros
+/// when the coordinates are out of bounds.
+pub fn fetch(image: &Image, view: &ResourceView, coord: [i32; 3], level: t::Level) -> [f32; 4] {
+    let level = view.min as u32 + level as u32;
+    if level > get_max_level(image, view) as u32 {
+        return [0.0; 4]
+    }
+    let level = level as t::Level;
+    let (w, h, d) = image.get_level_size(level);
+    let (layer, z) = match image.info.kind {
+        t::Kind::D3(..) => (0, coord[2]),
+        t::Kind::D1Array(..) => (view.layer.map_or(coord[1], |l| l as i32), 0),
+        _ => (view.layer.map_or(coord[2], |l| l as i32), 0),
+    };
+    let y = match image.info.kind {
+        t::Kind::D1(..) | t::Kind::D1Array(..) => 0,
+        _ => coord[1],
+    };
+    if coord[0] < 0 || y < 0 || z < 0 || layer < 0 ||
+        coord[0] as usize >= w || y as usize >= h || z as usize >= d ||
+        layer as usize >= image.get_num_layers() {
+        return [0.0; 4]
+    }
+    let texel = image.texel(level, layer as usize, coord[0] as usize, y as usize, z as usize);
+    apply_swizzle(data::decode(view.format, texel), view.swizzle)
+}
@@ -0,0 +1,412 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shaders of the software backend are plain Rust code: either closures
+//! or types implementing `VertexShader` and `PixelShader`. Since there is
+//! no source to reflect, each shader comes with an `Interface` declaring
+//! the names and slots its parameters are linked with by the PSO.
+
+use std::{cmp, mem, ptr};
+use core::{MAX_COLOR_TARGETS, MAX_CONSTANT_BUFFERS, MAX_RESOURCE_VIEWS,
+           MAX_SAMPLERS, MAX_VERTEX_ATTRIBUTES};
+use core::{AttributeSlot, ColorSlot, ConstantBufferSlot, ResourceViewSlot, SamplerSlot};
+use core::memory::Pod;
+use core::shade::{self as s, BaseType, ContainerType, Location, ProgramInfo,
+                  SamplerType, TextureType, UniformValue};
+use core::target::Level;
+use core::texture::{FilterMethod, SamplerInfo, WrapMode};
+use storage::Image;
+use {sample, ResourceView};
+
+
+/// Maximum number of interpolated values passed from the vertex
+/// to the pixel shader.
+pub const MAX_VARYINGS: usize = 8;
+
+/// Values interpolated across a primitive.
+pub type Varyings = [[f32; 4]; MAX_VARYINGS];
+
+/// Input of a single vertex shader invocation.
+#[derive(Clone, Copy, Debug)]
+pub struct VertexInput {
+    /// Attribute values, indexed by the slot. Integer attributes are
+    /// converted to floats, missing components are (0, 0, 0, 1).
+    pub attributes: [[f32; 4]; MAX_VERTEX_ATTRIBUTES],
+    /// Index of the vertex, including the base vertex offset
+    pub vertex_id: u32,
+    /// Index of the instance, not including the base instance offset
+    pub instance_id: u32,
+}
+
+/// Output of a single vertex shader invocation.
+#[derive(Clone, Copy, Debug)]
+pub struct VertexOutput {
+    /// Clip-space position, following the OpenGL conventions
+    pub position: [f32; 4],
+    /// Values to interpolate for the pixel shader
+    pub varyings: Varyings,
+}
+
+/// Input of a single pixel shader invocation.
+#[derive(Clone, Copy, Debug)]
+pub struct PixelInput {
+    /// Window coordinates of the pixel center, depth and `1/w`
+    pub position: [f32; 4],
+    /// Whether the primitive is facing the viewer
+    pub front_facing: bool,
+    /// Perspective-correct interpolated values
+    pub varyings: Varyings,
+    /// Derivatives of the varyings along the window X axis
+    pub ddx: Varyings,
+    /// Derivatives of the varyings along the window Y axis
+    pub ddy: Varyings,
+}
+
+/// Output of a single pixel shader invocation.
+#[derive(Clone, Copy, Debug)]
+pub struct PixelOutput {
+    /// Colors of the targets, indexed by the output slot
+    pub colors: [[f32; 4]; MAX_COLOR_TARGETS],
+    /// Depth override, the interpolated depth is used by default
+    pub depth: Option<f32>,
+    /// Set to discard the pixel
+    pub discard: bool,
+}
+
+/// A vertex shader.
+pub trait VertexShader: Send + Sync {
+    /// Process a single vertex.
+    fn run(&self, &Context, &VertexInput, &mut VertexOutput);
+}
+
+impl<F> VertexShader for F
+    where F: Fn(&Context, &VertexInput, &mut VertexOutput) + Send + Sync
+{
+    fn run(&self, ctx: &Context, input: &VertexInput, output: &mut VertexOutput) {
+        self(ctx, input, output)
+    }
+}
+
+/// A pixel shader.
+pub trait PixelShader: Send + Sync {
+    /// Process a single pixel.
+    fn run(&self, &Context, &PixelInput, &mut PixelOutput);
+}
+
+impl<F> PixelShader for F
+    where F: Fn(&Context, &PixelInput, &mut PixelOutput) + Send + Sync
+{
+    fn run(&self, ctx: &Context, input: &PixelInput, output: &mut PixelOutput) {
+        self(ctx, input, output)
+    }
+}
+
+/// The parameters of a shader, as seen by the PSO linking. Each kind of
+/// parameter gets the slots assigned in the declaration order, and the
+/// parameters shared by the vertex and pixel shaders need to be declared
+/// with the same name at the same slot in both.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interface {
+    info: ProgramInfo,
+}
+
+impl Interface {
+    /// Create an empty interface.
+    pub fn new() -> Interface {
+        Interface {
+            info: ProgramInfo {
+                vertex_attributes: Vec::new(),
+                globals: Vec::new(),
+                constant_buffers: Vec::new(),
+                textures: Vec::new(),
+                unordereds: Vec::new(),
+                samplers: Vec::new(),
+                outputs: Vec::new(),
+                output_depth: false,
+                knows_outputs: true,
+            },
+        }
+    }
+
+    /// Declare a vertex attribute.
+    pub fn attribute(mut self, name: &str, base_type: BaseType, container: ContainerType) -> Interface {
+        let slot = self.info.vertex_attributes.len() as AttributeSlot;
+        self.info.vertex_attributes.push(s::AttributeVar {
+            name: name.to_string(),
+            slot: slot,
+            base_type: base_type,
+            container: container,
+        });
+        self
+    }
+
+    /// Declare a global constant.
+    pub fn global(mut self, name: &str, base_type: BaseType, container: ContainerType) -> Interface {
+        let location = self.info.globals.len() as Location;
+        self.info.globals.push(s::ConstVar {
+            name: name.to_string(),
+            location: location,
+            count: 1,
+            base_type: base_type,
+            container: container,
+        });
+        self
+    }
+
+    /// Declare a constant buffer of the given size in bytes.
+    pub fn constant_buffer(mut self, name: &str, size: usize) -> Interface {
+        let slot = self.info.constant_buffers.len() as ConstantBufferSlot;
+        self.info.constant_buffers.push(s::ConstantBufferVar {
+            name: name.to_string(),
+            slot: slot,
+            size: size,
+            usage: s::Usage::empty(),
+            elements: Vec::new(),
+        });
+        self
+    }
+
+    /// Declare a texture.
+    pub fn texture(mut self, name: &str, ty: TextureType) -> Interface {
+        let slot = self.info.textures.len() as ResourceViewSlot;
+        self.info.textures.push(s::TextureVar {
+            name: name.to_string(),
+            slot: slot,
+            base_type: BaseType::F32,
+            ty: ty,
+            usage: s::Usage::empty(),
+        });
+        self
+    }
+
+    /// Declare a sampler.
+    pub fn sampler(mut self, name: &str, ty: SamplerType) -> Interface {
+        let slot = self.info.samplers.len() as SamplerSlot;
+        self.info.samplers.push(s::SamplerVar {
+            name: name.to_string(),
+            slot: slot,
+            ty: ty,
+            usage: s::Usage::empty(),
+        });
+        self
+    }
+
+    /// Declare a color output of the pixel shader.
+    pub fn output(mut self, name: &str, base_type: BaseType, container: ContainerType) -> Interface {
+        let slot = self.info.outputs.len() as ColorSlot;
+        self.info.outputs.push(s::OutputVar {
+            name: name.to_string(),
+            slot: slot,
+            base_type: base_type,
+            container: container,
+        });
+        self
+    }
+
+    /// Declare that the pixel shader writes the depth.
+    pub fn output_depth(mut self) -> Interface {
+        self.info.output_depth = true;
+        self
+    }
+
+    #[doc(hidden)]
+    pub fn with_usage(mut self, usage: s::Usage) -> Interface {
+        for cb in self.info.constant_buffers.iter_mut() {
+            cb.usage = usage;
+        }
+        for tex in self.info.textures.iter_mut() {
+            tex.usage = usage;
+        }
+        for sm in self.info.samplers.iter_mut() {
+            sm.usage = usage;
+        }
+        self
+    }
+
+    #[doc(hidden)]
+    pub fn get_info(&self) -> &ProgramInfo {
+        &self.info
+    }
+}
+
+/// Merge the parameters of one stage into the program, checking that
+/// the parameters of the same name are bound to the same slot.
+fn merge<T, N, S, U>(program: &mut Vec<T>, stage: &[T], name: N, slot: S, usage: U)
+                     -> Result<(), String> where
+    T: Clone,
+    N: Fn(&T) -> &str,
+    S: Fn(&T) -> usize,
+    U: Fn(&mut T, &T),
+{
+    for var in stage.iter() {
+        match program.iter().position(|p| name(p) == name(var) || slot(p) == slot(var)) {
+            Some(i) if name(&program[i]) == name(var) && slot(&program[i]) == slot(var) => {
+                usage(&mut program[i], var)
+            },
+            Some(i) => return Err(format!("Parameter {} collides with {} at slot {}",
+                                          name(var), name(&program[i]), slot(&program[i]))),
+            None => program.push(var.clone()),
+        }
+    }
+    Ok(())
+}
+
+/// Build the program information from the interfaces of the stages.
+pub fn link(vertex: &Interface, pixel: &Interface) -> Result<ProgramInfo, String> {
+    let mut info = vertex.info.clone();
+    info.outputs = pixel.info.outputs.clone();
+    info.output_depth = pixel.info.output_depth;
+    try!(merge(&mut info.globals, &pixel.info.globals,
+               |v| v.name.as_str(), |v| v.location, |_, _| ()));
+    try!(merge(&mut info.constant_buffers, &pixel.info.constant_buffers,
+               |v| v.name.as_str(), |v| v.slot as usize, |p, v| {
+                   p.usage = p.usage | v.usage;
+                   p.size = cmp::max(p.size, v.size);
+               }));
+    try!(merge(&mut info.textures, &pixel.info.textures,
+               |v| v.name.as_str(), |v| v.slot as usize, |p, v| p.usage = p.usage | v.usage));
+    try!(merge(&mut info.samplers, &pixel.info.samplers,
+               |v| v.name.as_str(), |v| v.slot as usize, |p, v| p.usage = p.usage | v.usage));
+    Ok(info)
+}
+
+/// Resources bound for the shader execution.
+pub struct Context<'a> {
+    constant_buffers: [Option<&'a [u8]>; MAX_CONSTANT_BUFFERS],
+    globals: &'a [Option<UniformValue>],
+    textures: [Option<(&'a Image, ResourceView)>; MAX_RESOURCE_VIEWS],
+    samplers: [Option<SamplerInfo>; MAX_SAMPLERS],
+}
+
+impl<'a> Context<'a> {
+    #[doc(hidden)]
+    pub fn new(constant_buffers: [Option<&'a [u8]>; MAX_CONSTANT_BUFFERS],
+               globals: &'a [Option<UniformValue>],
+               textures: [Option<(&'a Image, ResourceView)>; MAX_RESOURCE_VIEWS],
+               samplers: [Option<SamplerInfo>; MAX_SAMPLERS])
+               -> Context<'a> {
+        Context {
+            constant_buffers: constant_buffers,
+            globals: globals,
+            textures: textures,
+            samplers: samplers,
+        }
+    }
+
+    /// Get the contents of a constant buffer, empty if it's not bound.
+    pub fn get_constant_buffer(&self, slot: ConstantBufferSlot) -> &[u8] {
+        self.constant_buffers[slot as usize].unwrap_or(&[])
+    }
+
+    /// Read a structure from the beginning of a constant buffer.
+    /// The bytes past the end of the buffer are read as zeros.
+    pub fn get_constants<T: Pod>(&self, slot: ConstantBufferSlot) -> T {
+        let data = self.get_constant_buffer(slot);
+        let size = cmp::min(data.len(), mem::size_of::<T>());
+        unsafe {
+            let mut value: T = mem::zeroed();
+            ptr::copy_nonoverlapping(data.as_ptr(), &mut value as *mut T as *mut u8, size);
+            value
+        }
+    }
+
+    /// Get the value of a global constant, if it was set.
+    pub fn get_global(&self, location: Location) -> Option<UniformValue> {
+        self.globals.get(location).and_then(|v| *v)
+    }
+
+    fn get_texture(&self, slot: ResourceViewSlot) -> Option<(&'a Image, ResourceView)> {
+        let tex = self.textures[slot as usize];
+        if tex.is_none() {
+            error!("Texture at slot {} is not bound", slot);
+        }
+        tex
+    }
+
+    fn get_sampler(&self, slot: SamplerSlot) -> SamplerInfo {
+        match self.samplers[slot as usize] {
+            Some(info) => info,
+            None => {
+                error!("Sampler at slot {} is not bound", slot);
+                SamplerInfo::new(FilterMethod::Scale, WrapMode::Clamp)
+            },
+        }
+    }
+
+    /// Sample a texture at the base level of detail.
+    ///
+    /// The coordinates are normalized, with the array layer in the
+    /// component following the last used one. Cube maps are sampled by
+    /// the direction vector.
+    pub fn sample(&self, texture: ResourceViewSlot, sampler: SamplerSlot,
+                  coord: [f32; 3]) -> [f32; 4] {
+        self.sample_level(texture, sampler, coord, 0.0)
+    }
+
+    /// Sample a texture at an explicit level of detail.
+    pub fn sample_level(&self, texture: ResourceViewSlot, sampler: SamplerSlot,
+                        coord: [f32; 3], lod: f32) -> [f32; 4] {
+        let info = self.get_sampler(sampler);
+        match self.get_texture(texture) {
+            Some((image, view)) => sample::sample(image, &view, &info, coord, lod, None),
+            None => [0.0; 4],
+        }
+    }
+
+    /// Sample a texture, computing the level of detail from the
+    /// derivatives of the coordinates, like the `ddx` and `ddy`
+    /// members of the `PixelInput`.
+    pub fn sample_grad(&self, texture: ResourceViewSlot, sampler: SamplerSlot,
+                       coord: [f32; 3], ddx: [f32; 3], ddy: [f32; 3]) -> [f32; 4] {
+        let info = self.get_sampler(sampler);
+        match self.get_texture(texture) {
+            Some((image, view)) => {
+                let lod = sample::get_lod(image, &view, ddx, ddy);
+                sample::sample(image, &view, &info, coord, lod, None)
+            },
+            None => [0.0; 4],
+        }
+    }
+
+    /// Sample a depth texture with the comparison function of
+    /// the sampler, returning the filtered result of the comparisons.
+    pub fn sample_compare(&self, texture: ResourceViewSlot, sampler: SamplerSlot,
+                          coord: [f32; 3], reference: f32) -> f32 {
+        let info = self.get_sampler(sampler);
+        match self.get_texture(texture) {
+            Some((image, view)) =>
+                sample::sample(image, &view, &info, coord, 0.0, Some(reference))[0],
+            None => 0.0,
+        }
+    }
+
+    /// Load a single texel by its integer coordinates.
+    pub fn fetch(&self, texture: ResourceViewSlot, coord: [i32; 3], level: Level) -> [f32; 4] {
+        match self.get_texture(texture) {
+            Some((image, view)) => sample::fetch(image, &view, coord, level),
+            None => [0.0; 4],
+        }
+    }
+
+    /// Get the size of a texture level, in texels.
+    pub fn get_texture_size(&self, texture: ResourceViewSlot, level: Level) -> [u32; 3] {
+        match self.get_texture(texture) {
+            Some((image, view)) => {
+                let (w, h, d) = image.get_level_size(view.min + level);
+                [w as u32, h as u32, d as u32]
+            },
+            None => [0; 3],
+        }
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Backing memory of the buffers, textures and other objects.
+
+use std::cmp::max;
+use std::slice;
+use std::sync::Arc;
+use core::{pso, query, texture as t};
+use core::format::{ChannelType, Format};
+use data;
+use shade::{VertexShader, PixelShader, Interface};
+
+
+/// A set of objects addressed by plain indices, which are
+/// reused after the objects get removed.
+pub struct Pool<T> {
+    objects: Vec<Option<T>>,
+    free: Vec<usize>,
+}
+
+impl<T> Pool<T> {
+    pub fn new() -> Pool<T> {
+        Pool {
+            objects: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, object: T) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.objects[index] = Some(object);
+                index
+            },
+            None => {
+                self.objects.push(Some(object));
+                self.objects.len() - 1
+            },
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        let object = self.take(index);
+        if object.is_some() {
+            self.free.push(index);
+        }
+        object
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.objects.get(index).and_then(|o| o.as_ref())
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.objects.get_mut(index).and_then(|o| o.as_mut())
+    }
+
+    /// Temporarily take an object out, without freeing the index.
+    pub fn take(&mut self, index: usize) -> Option<T> {
+        self.objects.get_mut(index).and_then(|o| o.take())
+    }
+
+    /// Return an object previously obtained with `take`.
+    pub fn restore(&mut self, index: usize, object: T) {
+        debug_assert!(self.objects[index].is_none());
+        self.objects[index] = Some(object);
+    }
+}
+
+/// Zero-initialized heap memory, aligned for any element type.
+pub struct Memory {
+    words: Vec<u64>,
+    size: usize,
+}
+
+impl Memory {
+    pub fn new(size: usize) -> Memory {
+        Memory {
+            words: vec![0; (size + 7) / 8],
+            size: size,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.words.as_ptr() as *const u8, self.size) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.words.as_mut_ptr() as *mut u8, self.size) }
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.words.as_mut_ptr() as *mut u8
+    }
+}
+
+/// Texture storage. The layers (array slices times cube faces) are
+/// stored one after another, each holding the complete mip chain,
+/// with the rows of texels tightly packed.
+pub struct Image {
+    pub info: t::Info,
+    pub channel: ChannelType,
+    pub memory: Memory,
+    layer_size: usize,
+    level_offsets: Vec<usize>,
+}
+
+impl Image {
+    pub fn new(info: t::Info, channel: ChannelType) -> Image {
+        let texel_size = data::get_texel_size(info.format);
+        let mut level_offsets = Vec::with_capacity(info.levels as usize);
+        let mut layer_size = 0;
+        for level in 0 .. info.levels {
+            let (w, h, d, _) = info.kind.get_level_dimensions(level);
+            level_offsets.push(layer_size);
+            layer_size += max(w, 1) as usize * max(h, 1) as usize *
+                          Image::get_depth(info.kind, d) * texel_size;
+        }
+        let num_layers = Image::count_layers(info.kind);
+        Image {
+            info: info,
+            channel: channel,
+            memory: Memory::new(layer_size * num_layers),
+            layer_size: layer_size,
+            level_offsets: level_offsets,
+        }
+    }
+
+    fn count_layers(kind: t::Kind) -> usize {
+        let faces = if kind.is_cube() { 6 } else { 1 };
+        kind.get_num_slices().unwrap_or(1) as usize * faces
+    }
+
+    fn get_depth(kind: t::Kind, depth: t::Size) -> usize {
+        match kind {
+            t::Kind::D3(..) => max(depth, 1) as usize,
+            _ => 1,
+        }
+    }
+
+    pub fn get_format(&self) -> Format {
+        Format(self.info.format, self.channel)
+    }
+
+    pub fn get_texel_size(&self) -> usize {
+        data::get_texel_size(self.info.format)
+    }
+
+    pub fn get_num_layers(&self) -> usize {
+        Image::count_layers(self.info.kind)
+    }
+
+    /// Get the (width, height, depth) of a mip level, all at least 1.
+    pub fn get_level_size(&self, level: t::Level) -> (usize, usize, usize) {
+        let (w, h, d, _) = self.info.kind.get_level_dimensions(level);
+        (max(w, 1) as usize, max(h, 1) as usize, Image::get_depth(self.info.kind, d))
+    }
+
+    pub fn get_texel_offset(&self, level: t::Level, layer: usize,
+                            x: usize, y: usize, z: usize) -> usize {
+        let (w, h, _) = self.get_level_size(level);
+        let index = (z * h + y) * w + x;
+        layer * self.layer_size + self.level_offsets[level as usize] +
+            index * self.get_texel_size()
+    }
+
+    pub fn texel(&self, level: t::Level, layer: usize, x: usize, y: usize, z: usize) -> &[u8] {
+        let offset = self.get_texel_offset(level, layer, x, y, z);
+        &self.memory.as_slice()[offset .. offset + self.get_texel_size()]
+    }
+
+    pub fn texel_mut(&mut self, level: t::Level, layer: usize,
+                     x: usize, y: usize, z: usize) -> &mut [u8] {
+        let offset = self.get_texel_offset(level, layer, x, y, z);
+        let size = self.get_texel_size();
+        &mut self.memory.as_mut_slice()[offset .. offset + size]
+    }
+
+    /// Get the first layer selected by a cube face and a depth offset.
+    pub fn get_layer(&self, face: Option<t::CubeFace>, zoffset: t::Size) -> usize {
+        zoffset as usize + face.map_or(0, |f| f as usize)
+    }
+
+    /// Get the byte offsets of all the texel rows covered by an image
+    /// region, slice by slice, or `None` if the region is out of bounds.
+    pub fn get_rows(&self, face: Option<t::CubeFace>, info: &t::RawImageInfo)
+                    -> Option<Vec<usize>> {
+        if info.mipmap >= self.info.levels {
+            return None
+        }
+        let (w, h, d) = self.get_level_size(info.mipmap);
+        let (x, y, z) = (info.xoffset as usize, info.yoffset as usize, info.zoffset as usize);
+        let width = max(info.width, 1) as usize;
+        let height = max(info.height, 1) as usize;
+        let slices = if face.is_some() { 1 } else { max(info.depth, 1) as usize };
+        if x + width > w || y + height > h {
+            return None
+        }
+        let is_3d = match self.info.kind {
+            t::Kind::D3(..) => true,
+            _ => false,
+        };
+        let mut rows = Vec::with_capacity(height * slices);
+        if is_3d {
+            if z + slices > d {
+                return None
+            }
+            for k in 0 .. slices {
+                for j in 0 .. height {
+                    rows.push(self.get_texel_offset(info.mipmap, 0, x, y + j, z + k));
+                }
+            }
+        } else {
+            let base = self.get_layer(face, info.zoffset);
+            if base + slices > self.get_num_layers() {
+                return None
+            }
+            for k in 0 .. slices {
+                for j in 0 .. height {
+                    rows.push(self.get_texel_offset(info.mipmap, base + k, x, y + j, 0));
+                }
+            }
+        }
+        Some(rows)
+    }
+}
+
+/// A compiled shader object, with the interface it exposes.
+pub enum ShaderObject {
+    Vertex(Arc<VertexShader>, Interface),
+    Pixel(Arc<PixelShader>, Interface),
+}
+
+#[derive(Clone)]
+pub struct ProgramObject {
+    pub vertex: Arc<VertexShader>,
+    pub pixel: Arc<PixelShader>,
+}
+
+#[derive(Clone)]
+pub struct PipelineObject {
+    pub program: ProgramObject,
+    pub desc: pso::Descriptor,
+}
+
+pub struct QueryObject {
+    pub ty: query::QueryType,
+    pub value: u64,
+}
+
+/// All the objects owned by a device.
+pub struct Storage {
+    pub buffers: Pool<Memory>,
+    pub textures: Pool<Image>,
+    pub shaders: Pool<ShaderObject>,
+    pub programs: Pool<ProgramObject>,
+    pub pipelines: Pool<PipelineObject>,
+    pub queries: Pool<QueryObject>,
+}
+
+impl Storage {
+    pub fn new() -> Storage {
+        Storage {
+            buffers: Pool::new(),
+            textures: Pool::new(),
+            shaders: Pool::new(),
+            programs: Pool::new(),
+            pipelines: Pool::new(),
+            queries: Pool::new(),
+        }
+    }
+}
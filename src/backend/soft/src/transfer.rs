@@ -0,0 +1,348 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Copies, blits, clears and other operations on the resource memory.
+
+use std::cmp::{max, min};
+use core::command::{BlitFilter, ClearColor};
+use core::format::Format;
+use core::target::{self, Depth, Stencil};
+use core::texture as t;
+use command::TextureRegion;
+use storage::{Image, Storage};
+use {data, Buffer, ResourceView, TargetView, Texture};
+
+
+/// Get the layer and the depth slice of the `k`-th slice of a region.
+fn get_slice(image: &Image, region: &TextureRegion, k: usize) -> (usize, usize) {
+    match image.info.kind {
+        t::Kind::D3(..) => (0, region.info.zoffset as usize + k),
+        _ => (image.get_layer(region.face, region.info.zoffset) + k, 0),
+    }
+}
+
+fn get_num_slices(region: &TextureRegion) -> usize {
+    if region.face.is_some() { 1 } else { max(region.info.depth, 1) as usize }
+}
+
+/// Read the texels of a region into tightly packed rows.
+fn read_region(image: &Image, region: &TextureRegion) -> Option<Vec<u8>> {
+    let rows = match image.get_rows(region.face, &region.info) {
+        Some(rows) => rows,
+        None => {
+            error!("Region {:?} is out of the texture bounds", region.info);
+            return None
+        },
+    };
+    let row_size = max(region.info.width, 1) as usize * image.get_texel_size();
+    let memory = image.memory.as_slice();
+    let mut out = Vec::with_capacity(rows.len() * row_size);
+    for offset in rows {
+        out.extend_from_slice(&memory[offset .. offset + row_size]);
+    }
+    Some(out)
+}
+
+/// Write tightly packed rows of texels into a region.
+fn write_region(image: &mut Image, region: &TextureRegion, data: &[u8]) {
+    let rows = match image.get_rows(region.face, &region.info) {
+        Some(rows) => rows,
+        None => {
+            error!("Region {:?} is out of the texture bounds", region.info);
+            return
+        },
+    };
+    let row_size = max(region.info.width, 1) as usize * image.get_texel_size();
+    if data.len() < rows.len() * row_size {
+        error!("Not enough data ({} bytes) to update the region {:?}", data.len(), region.info);
+        return
+    }
+    let memory = image.memory.as_mut_slice();
+    for (offset, row) in rows.into_iter().zip(data.chunks(row_size)) {
+        memory[offset .. offset + row_size].copy_from_slice(row);
+    }
+}
+
+pub fn copy_buffer(storage: &mut Storage, src: Buffer, dst: Buffer,
+                   src_offset: usize, dst_offset: usize, size: usize) {
+    let data = match storage.buffers.get(src.0) {
+        Some(m) if src_offset + size <= m.len() => m.as_slice()[src_offset .. src_offset + size].to_vec(),
+        _ => {
+            error!("Source range {}+{} of the buffer copy is out of bounds", src_offset, size);
+            return
+        },
+    };
+    update_buffer(storage, dst, &data, dst_offset);
+}
+
+pub fn update_buffer(storage: &mut Storage, buf: Buffer, data: &[u8], offset: usize) {
+    match storage.buffers.get_mut(buf.0) {
+        Some(m) if offset + data.len() <= m.len() =>
+            m.as_mut_slice()[offset .. offset + data.len()].copy_from_slice(data),
+        _ => error!("Range {}+{} of the buffer update is out of bounds", offset, data.len()),
+    }
+}
+
+pub fn copy_buffer_to_texture(storage: &mut Storage, src: Buffer, offset: usize, dst: &TextureRegion) {
+    let data = match storage.buffers.get(src.0) {
+        Some(m) if offset <= m.len() => m.as_slice()[offset ..].to_vec(),
+        _ => {
+            error!("Source offset {} of the buffer copy is out of bounds", offset);
+            return
+        },
+    };
+    update_texture(storage, dst, &data);
+}
+
+pub fn copy_texture_to_buffer(storage: &mut Storage, src: &TextureRegion, dst: Buffer, offset: usize) {
+    let data = match storage.textures.get(src.texture.0).and_then(|image| read_region(image, src)) {
+        Some(data) => data,
+        None => return,
+    };
+    update_buffer(storage, dst, &data, offset);
+}
+
+pub fn update_texture(storage: &mut Storage, dst: &TextureRegion, data: &[u8]) {
+    if let Some(image) = storage.textures.get_mut(dst.texture.0) {
+        write_region(image, dst, data);
+    }
+}
+
+pub fn copy_texture(storage: &mut Storage, src: &TextureRegion, dst: &TextureRegion) {
+    let data = match storage.textures.get(src.texture.0).and_then(|image| read_region(image, src)) {
+        Some(data) => data,
+        None => return,
+    };
+    if let Some(image) = storage.textures.get_mut(dst.texture.0) {
+        let expected = max(dst.info.width, 1) as usize * max(dst.info.height, 1) as usize *
+                       get_num_slices(dst) * image.get_texel_size();
+        if expected != data.len() {
+            error!("Texture copy regions {:?} and {:?} do not match", src.info, dst.info);
+            return
+        }
+        write_region(image, dst, &data);
+    }
+}
+
+pub fn blit(storage: &mut Storage, src: &TextureRegion, dst: &TextureRegion,
+            mirror: target::Mirror, filter: BlitFilter) {
+    let source = match storage.textures.take(src.texture.0) {
+        Some(image) => image,
+        None => return,
+    };
+    let (src_w, src_h, _) = source.get_level_size(src.info.mipmap);
+    let raw = data::is_depth_stencil(source.info.format);
+    if src.info.xoffset as usize + src.info.width as usize > src_w ||
+       src.info.yoffset as usize + max(src.info.height, 1) as usize > src_h {
+        error!("Blit source region {:?} is out of bounds", src.info);
+        storage.textures.restore(src.texture.0, source);
+        return
+    }
+    {
+        let mut copy = |image: &mut Image| {
+            let (dst_w, dst_h, _) = image.get_level_size(dst.info.mipmap);
+            if dst.info.xoffset as usize + dst.info.width as usize > dst_w ||
+               dst.info.yoffset as usize + max(dst.info.height, 1) as usize > dst_h {
+                error!("Blit destination region {:?} is out of bounds", dst.info);
+                return
+            }
+            if raw && image.info.format != source.info.format {
+                error!("Depth-stencil blits need matching formats");
+                return
+            }
+            let (src_format, dst_format) = (source.get_format(), image.get_format());
+            let (sw, sh) = (max(src.info.width, 1) as f32, max(src.info.height, 1) as f32);
+            let (dw, dh) = (max(dst.info.width, 1), max(dst.info.height, 1));
+            for k in 0 .. min(get_num_slices(src), get_num_slices(dst)) {
+                let (src_layer, src_z) = get_slice(&source, src, k);
+                let (dst_layer, dst_z) = get_slice(image, dst, k);
+                for j in 0 .. dh {
+                    let mut v = (j as f32 + 0.5) / dh as f32;
+                    if mirror.intersects(target::MIRROR_Y) {
+                        v = 1.0 - v;
+                    }
+                    for i in 0 .. dw {
+                        let mut u = (i as f32 + 0.5) / dw as f32;
+                        if mirror.intersects(target::MIRROR_X) {
+                            u = 1.0 - u;
+                        }
+                        let x = dst.info.xoffset as usize + i as usize;
+                        let y = dst.info.yoffset as usize + j as usize;
+                        // the source coordinates relative to the region
+                        let (fx, fy) = (u * sw - 0.5, v * sh - 0.5);
+                        let fetch = |x: f32, y: f32| -> (usize, usize) {
+                            let cx = x.max(0.0).min(sw - 1.0) as usize + src.info.xoffset as usize;
+                            let cy = y.max(0.0).min(sh - 1.0) as usize + src.info.yoffset as usize;
+                            (cx, cy)
+                        };
+                        if raw || filter == BlitFilter::Nearest {
+                            let (sx, sy) = fetch(fx.round(), fy.round());
+                            let texel = source.texel(src.info.mipmap, src_layer, sx, sy, src_z);
+                            let out = image.texel_mut(dst.info.mipmap, dst_layer, x, y, dst_z);
+                            if raw {
+                                out.copy_from_slice(texel);
+                            } else {
+                                data::encode(dst_format, data::decode(src_format, texel), out);
+                            }
+                        } else {
+                            let (x0, y0) = (fx.floor(), fy.floor());
+                            let (ax, ay) = (fx - x0, fy - y0);
+                            let mut value = [0.0; 4];
+                            let taps = [(0.0, 0.0, (1.0 - ax) * (1.0 - ay)), (1.0, 0.0, ax * (1.0 - ay)),
+                                        (0.0, 1.0, (1.0 - ax) * ay), (1.0, 1.0, ax * ay)];
+                            for &(ox, oy, weight) in taps.iter() {
+                                let (sx, sy) = fetch(x0 + ox, y0 + oy);
+                                let texel = source.texel(src.info.mipmap, src_layer, sx, sy, src_z);
+                                let color = data::decode(src_format, texel);
+                                for c in 0 .. 4 {
+                                    value[c] += color[c] * weight;
+                                }
+                            }
+                            data::encode(dst_format, value,
+                                         image.texel_mut(dst.info.mipmap, dst_layer, x, y, dst_z));
+                        }
+                    }
+                }
+            }
+        };
+        if src.texture == dst.texture {
+            // blitting within the same texture reads from a snapshot
+            let mut target = Image::new(source.info, source.channel);
+            target.memory.as_mut_slice().copy_from_slice(source.memory.as_slice());
+            copy(&mut target);
+            storage.textures.restore(src.texture.0, target);
+            return
+        }
+        if let Some(image) = storage.textures.get_mut(dst.texture.0) {
+            copy(image);
+        }
+    }
+    storage.textures.restore(src.texture.0, source);
+}
+
+/// Resolve a multisampled texture. The multisampled textures only store
+/// a single sample, so this is a plain copy.
+pub fn resolve(storage: &mut Storage, src: Texture, dst: Texture) {
+    let data = match storage.textures.get(src.0) {
+        Some(image) => image.memory.as_slice().to_vec(),
+        None => return,
+    };
+    if let Some(image) = storage.textures.get_mut(dst.0) {
+        let size = min(data.len(), image.memory.len());
+        let (w, h, _) = image.get_level_size(0);
+        // only the top level of the first layer gets resolved
+        let size = min(size, w * h * image.get_texel_size());
+        image.memory.as_mut_slice()[.. size].copy_from_slice(&data[.. size]);
+    }
+}
+
+/// Fill the levels of a view below the most detailed one by
+/// downsampling with a box filter.
+pub fn generate_mipmap(storage: &mut Storage, view: &ResourceView) {
+    let image = match storage.textures.get_mut(view.texture.0) {
+        Some(image) => image,
+        None => return,
+    };
+    let format = Format(image.info.format, view.format.1);
+    let is_3d = match image.info.kind {
+        t::Kind::D3(..) => true,
+        _ => false,
+    };
+    let last = min(view.max, image.info.levels - 1);
+    for level in view.min + 1 .. last + 1 {
+        let (sw, sh, sd) = image.get_level_size(level - 1);
+        let (w, h, d) = image.get_level_size(level);
+        for layer in 0 .. image.get_num_layers() {
+            for z in 0 .. d {
+                for y in 0 .. h {
+                    for x in 0 .. w {
+                        let mut value = [0.0; 4];
+                        let mut count = 0.0;
+                        let zs = if is_3d { min(2 * z + 1, sd - 1) } else { 0 };
+                        for sz in 2 * z .. zs + 1 {
+                            for sy in 2 * y .. min(2 * y + 2, sh) {
+                                for sx in 2 * x .. min(2 * x + 2, sw) {
+                                    let color = data::decode(format, image.texel(level - 1, layer, sx, sy, sz));
+                                    for c in 0 .. 4 {
+                                        value[c] += color[c];
+                                    }
+                                    count += 1.0;
+                                }
+                            }
+                        }
+                        for c in value.iter_mut() {
+                            *c /= count;
+                        }
+                        data::encode(format, value, image.texel_mut(level, layer, x, y, z));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Get the layers covered by a target view.
+fn get_target_layers(image: &Image, view: &TargetView) -> ::std::ops::Range<usize> {
+    match view.layer {
+        Some(layer) => layer as usize .. layer as usize + 1,
+        None => 0 .. image.get_num_layers(),
+    }
+}
+
+pub fn clear_color(storage: &mut Storage, view: &TargetView, value: ClearColor) {
+    let image = match storage.textures.get_mut(view.texture.0) {
+        Some(image) => image,
+        None => return,
+    };
+    let color = match value {
+        ClearColor::Float(v) => v,
+        ClearColor::Int(v) => [v[0] as f32, v[1] as f32, v[2] as f32, v[3] as f32],
+        ClearColor::Uint(v) => [v[0] as f32, v[1] as f32, v[2] as f32, v[3] as f32],
+    };
+    let mut texel = vec![0; image.get_texel_size()];
+    data::encode(view.format, color, &mut texel);
+    let (w, h, d) = image.get_level_size(view.level);
+    for layer in get_target_layers(image, view) {
+        for z in 0 .. d {
+            for y in 0 .. h {
+                for x in 0 .. w {
+                    image.texel_mut(view.level, layer, x, y, z).copy_from_slice(&texel);
+                }
+            }
+        }
+    }
+}
+
+pub fn clear_depth_stencil(storage: &mut Storage, view: &TargetView,
+                           depth: Option<Depth>, stencil: Option<Stencil>) {
+    let image = match storage.textures.get_mut(view.texture.0) {
+        Some(image) => image,
+        None => return,
+    };
+    let surface = image.info.format;
+    let (w, h, _) = image.get_level_size(view.level);
+    for layer in get_target_layers(image, view) {
+        for y in 0 .. h {
+            for x in 0 .. w {
+                let texel = image.texel_mut(view.level, layer, x, y, 0);
+                if let Some(d) = depth {
+                    data::encode_depth(surface, d, texel);
+                }
+                if let Some(s) = stencil {
+                    data::encode_stencil(surface, s, texel);
+                }
+            }
+        }
+    }
+}
@@ -261,6 +261,28 @@ impl command::Buffer<Resources> for Buffer {
         unimplemented!()
     }
 
+    fn copy_texture_to_texture(&mut self,
+                               _: native::Texture, _: tex::Kind,
+                               _: Option<tex::CubeFace>, _: tex::RawImageInfo,
+                               _: native::Texture, _: tex::Kind,
+                               _: Option<tex::CubeFace>, _: tex::RawImageInfo) {
+        error!("Texture copies are not supported");
+    }
+
+    fn blit_texture(&mut self,
+                    _: native::Texture, _: tex::Kind,
+                    _: Option<tex::CubeFace>, _: tex::RawImageInfo,
+                    _: native::Texture, _: tex::Kind,
+                    _: Option<tex::CubeFace>, _: tex::RawImageInfo,
+                    _: target::Mirror, _: command::BlitFilter) {
+        error!("Texture blits are not supported");
+    }
+
+    fn resolve_texture(&mut self, _: native::Texture, _: tex::Kind,
+                       _: native::Texture, _: tex::Kind, _: core::format::Format) {
+        error!("Multisampled texture resolves are not supported");
+    }
+
     fn update_buffer(&mut self, _: native::Buffer, _: &[u8], _: usize) {}
     fn update_texture(&mut self, _: native::Texture, _: tex::Kind, _: Option<tex::CubeFace>,
                       _: &[u8], _: tex::RawImageInfo) {}
@@ -293,6 +315,22 @@ impl command::Buffer<Resources> for Buffer {
     fn call_draw(&mut self, _: VertexCount, _: VertexCount, _: Option<command::InstanceParams>) {}
     fn call_draw_indexed(&mut self, _: VertexCount, _: VertexCount,
                          _: VertexCount, _: Option<command::InstanceParams>) {}
+    fn call_draw_indirect(&mut self, _: native::Buffer, _: usize, _: usize) {}
+    fn call_draw_indexed_indirect(&mut self, _: native::Buffer, _: usize, _: usize) {}
+    fn call_dispatch(&mut self, _: [u32; 3]) {}
+    fn begin_query(&mut self, _: ()) {
+        error!("Queries are not supported");
+    }
+    fn end_query(&mut self, _: ()) {
+        error!("Queries are not supported");
+    }
+    fn write_timestamp(&mut self, _: ()) {
+        error!("Queries are not supported");
+    }
+    fn begin_conditional(&mut self, _: ()) {
+        error!("Conditional rendering is not supported");
+    }
+    fn end_conditional(&mut self) {}
 }
 
 
@@ -311,15 +349,25 @@ impl GraphicsQueue {
             max_index_count: 0,
             max_texture_size: 0,
             max_patch_size: 0,
+            constant_buffer_alignment: 256,
             instance_base_supported: false,
             instance_call_supported: false,
             instance_rate_supported: false,
             vertex_base_supported: false,
             srgb_color_supported: false,
             constant_buffer_supported: false,
+            constant_buffer_range_supported: false,
             unordered_access_view_supported: false,
             separate_blending_slots_supported: false,
             copy_buffer_supported: true,
+            copy_texture_supported: false,
+            blit_texture_supported: false,
+            resolve_texture_supported: false,
+            compute_supported: false,
+            draw_indirect_supported: false,
+            bc_compression_supported: false,
+            etc2_compression_supported: false,
+            astc_compression_supported: false,
         };
         GraphicsQueue {
             share: share,
@@ -486,6 +534,7 @@ impl core::Device for GraphicsQueue {
             |vk, fence| unsafe {
                 vk.DestroyFence(dev, fence.0, ptr::null());
             },
+            |_, _| (), //query
         );
     }
 }
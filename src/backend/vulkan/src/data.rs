@@ -254,6 +254,139 @@ pub fn map_format(surface: SurfaceType, chan: ChannelType) -> Option<vk::Format>
             Float => vk::FORMAT_D32_SFLOAT,
             _ => return None,
         },
+        BC1_R8_G8_B8_A8 => match chan {
+            Unorm => vk::FORMAT_BC1_RGBA_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_BC1_RGBA_SRGB_BLOCK,
+            _ => return None,
+        },
+        BC2_R8_G8_B8_A8 => match chan {
+            Unorm => vk::FORMAT_BC2_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_BC2_SRGB_BLOCK,
+            _ => return None,
+        },
+        BC3_R8_G8_B8_A8 => match chan {
+            Unorm => vk::FORMAT_BC3_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_BC3_SRGB_BLOCK,
+            _ => return None,
+        },
+        BC4_R8 => match chan {
+            Inorm => vk::FORMAT_BC4_SNORM_BLOCK,
+            Unorm => vk::FORMAT_BC4_UNORM_BLOCK,
+            _ => return None,
+        },
+        BC5_R8_G8 => match chan {
+            Inorm => vk::FORMAT_BC5_SNORM_BLOCK,
+            Unorm => vk::FORMAT_BC5_UNORM_BLOCK,
+            _ => return None,
+        },
+        BC6H_R16_G16_B16_UF => match chan {
+            Float => vk::FORMAT_BC6H_UFLOAT_BLOCK,
+            _ => return None,
+        },
+        BC6H_R16_G16_B16_SF => match chan {
+            Float => vk::FORMAT_BC6H_SFLOAT_BLOCK,
+            _ => return None,
+        },
+        BC7_R8_G8_B8_A8 => match chan {
+            Unorm => vk::FORMAT_BC7_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_BC7_SRGB_BLOCK,
+            _ => return None,
+        },
+        ETC2_R8_G8_B8 => match chan {
+            Unorm => vk::FORMAT_ETC2_R8G8B8_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ETC2_R8G8B8_SRGB_BLOCK,
+            _ => return None,
+        },
+        ETC2_R8_G8_B8_A1 => match chan {
+            Unorm => vk::FORMAT_ETC2_R8G8B8A1_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ETC2_R8G8B8A1_SRGB_BLOCK,
+            _ => return None,
+        },
+        ETC2_R8_G8_B8_A8 => match chan {
+            Unorm => vk::FORMAT_ETC2_R8G8B8A8_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ETC2_R8G8B8A8_SRGB_BLOCK,
+            _ => return None,
+        },
+        EAC_R11 => match chan {
+            Inorm => vk::FORMAT_EAC_R11_SNORM_BLOCK,
+            Unorm => vk::FORMAT_EAC_R11_UNORM_BLOCK,
+            _ => return None,
+        },
+        EAC_R11_G11 => match chan {
+            Inorm => vk::FORMAT_EAC_R11G11_SNORM_BLOCK,
+            Unorm => vk::FORMAT_EAC_R11G11_UNORM_BLOCK,
+            _ => return None,
+        },
+        ASTC_4x4 => match chan {
+            Unorm => vk::FORMAT_ASTC_4x4_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_4x4_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_5x4 => match chan {
+            Unorm => vk::FORMAT_ASTC_5x4_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_5x4_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_5x5 => match chan {
+            Unorm => vk::FORMAT_ASTC_5x5_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_5x5_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_6x5 => match chan {
+            Unorm => vk::FORMAT_ASTC_6x5_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_6x5_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_6x6 => match chan {
+            Unorm => vk::FORMAT_ASTC_6x6_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_6x6_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_8x5 => match chan {
+            Unorm => vk::FORMAT_ASTC_8x5_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_8x5_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_8x6 => match chan {
+            Unorm => vk::FORMAT_ASTC_8x6_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_8x6_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_8x8 => match chan {
+            Unorm => vk::FORMAT_ASTC_8x8_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_8x8_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_10x5 => match chan {
+            Unorm => vk::FORMAT_ASTC_10x5_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_10x5_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_10x6 => match chan {
+            Unorm => vk::FORMAT_ASTC_10x6_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_10x6_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_10x8 => match chan {
+            Unorm => vk::FORMAT_ASTC_10x8_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_10x8_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_10x10 => match chan {
+            Unorm => vk::FORMAT_ASTC_10x10_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_10x10_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_12x10 => match chan {
+            Unorm => vk::FORMAT_ASTC_12x10_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_12x10_SRGB_BLOCK,
+            _ => return None,
+        },
+        ASTC_12x12 => match chan {
+            Unorm => vk::FORMAT_ASTC_12x12_UNORM_BLOCK,
+            Srgb  => vk::FORMAT_ASTC_12x12_SRGB_BLOCK,
+            _ => return None,
+        },
     })
 }
 
@@ -349,6 +349,10 @@ impl core::Factory<R> for Factory {
         use core::handle::Producer;
         use mirror::reflect_spirv_module;
         use native::Shader;
+        let reflection = match reflect_spirv_module(code) {
+            Ok(reflection) => reflection,
+            Err(e) => return Err(core::shade::CreateShaderError::CompilationFailed(e.to_string())),
+        };
         let info = vk::ShaderModuleCreateInfo {
             sType: vk::STRUCTURE_TYPE_SHADER_MODULE_CREATE_INFO,
             pNext: ptr::null(),
@@ -361,7 +365,6 @@ impl core::Factory<R> for Factory {
         assert_eq!(vk::SUCCESS, unsafe {
             vk.CreateShaderModule(dev, &info, ptr::null(), &mut shader)
         });
-        let reflection = reflect_spirv_module(code);
         let shader = Shader {
             shader: shader,
             reflection: reflection,
@@ -391,8 +394,8 @@ impl core::Factory<R> for Factory {
         let prog = match shader_set.clone() {
             core::ShaderSet::Simple(vs, ps) => {
                 let (vs, ps) = (vs.reference(fh), ps.reference(fh));
-                populate_info(&mut info, s::Stage::Vertex, &vs.reflection);
-                populate_info(&mut info, s::Stage::Pixel, &ps.reflection);
+                try!(populate_info(&mut info, s::Stage::Vertex, &vs.reflection));
+                try!(populate_info(&mut info, s::Stage::Pixel, &ps.reflection));
                 native::Program {
                     vertex: vs.shader,
                     geometry: None,
@@ -401,9 +404,9 @@ impl core::Factory<R> for Factory {
             }
             core::ShaderSet::Geometry(vs, gs, ps) => {
                 let (vs, gs, ps) = (vs.reference(fh), gs.reference(fh), ps.reference(fh));
-                populate_info(&mut info, s::Stage::Vertex, &vs.reflection);
-                populate_info(&mut info, s::Stage::Geometry, &gs.reflection);
-                populate_info(&mut info, s::Stage::Pixel, &ps.reflection);
+                try!(populate_info(&mut info, s::Stage::Vertex, &vs.reflection));
+                try!(populate_info(&mut info, s::Stage::Geometry, &gs.reflection));
+                try!(populate_info(&mut info, s::Stage::Pixel, &ps.reflection));
                 native::Program {
                     vertex: vs.shader,
                     geometry: Some(gs.shader),
@@ -411,6 +414,9 @@ impl core::Factory<R> for Factory {
                 }
             },
             core::ShaderSet::Tessellated(..) => unimplemented!(),
+            core::ShaderSet::Compute(..) => {
+                return Err("Compute programs are not supported".into());
+            },
         };
 
         Ok(self.share.handles.lock().unwrap().make_program(prog, info))
@@ -867,6 +873,15 @@ impl core::Factory<R> for Factory {
             self.share.handles.lock().unwrap().make_dsv(view, htex, dim))
     }
 
+    fn create_query(&mut self, ty: core::query::QueryType)
+                    -> Result<h::Query<R>, core::query::CreationError> {
+        Err(core::query::CreationError::UnsupportedType(ty))
+    }
+
+    fn get_query_result(&mut self, _: &h::Query<R>) -> Option<u64> {
+        None
+    }
+
     fn create_sampler(&mut self, info: texture::SamplerInfo) -> h::Sampler<R> {
         use core::handle::Producer;
 
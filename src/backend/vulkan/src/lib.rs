@@ -17,7 +17,6 @@ extern crate log;
 extern crate shared_library;
 extern crate gfx_core as core;
 extern crate vk_sys as vk;
-extern crate spirv_utils;
 
 use std::{fmt, iter, mem, ptr};
 use std::sync::{Arc, Mutex};
@@ -31,7 +30,7 @@ mod command;
 pub mod data;
 mod factory;
 mod native;
-mod mirror;
+pub mod mirror;
 
 struct PhysicalDeviceInfo {
     device: vk::PhysicalDevice,
@@ -332,6 +331,7 @@ impl core::Resources for Resources {
     type DepthStencilView     = native::TextureView;
     type Sampler              = vk::Sampler;
     type Fence                = Fence;
+    type Query                = ();
     type Mapping              = factory::MappingGate;
 }
 
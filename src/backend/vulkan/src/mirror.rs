@@ -1,392 +1,786 @@
-
-use spirv_utils::{self, desc, instruction};
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reflection of SPIR-V modules into `shade::ProgramInfo`.
+//!
+//! The module is parsed straight from its words, keeping only the
+//! debug names, the decorations and the types of the global variables.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
 use core;
 use core::shade::{self, BaseType, ContainerType, TextureType};
 
+type Id = u32;
+
+const MAGIC_NUMBER: u32 = 0x0723_0203;
+
+const OP_NAME: u32 = 5;
+const OP_MEMBER_NAME: u32 = 6;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+const OP_GROUP_DECORATE: u32 = 74;
+const OP_GROUP_MEMBER_DECORATE: u32 = 75;
+
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_OUTPUT: u32 = 3;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+const DECORATION_BLOCK: u32 = 2;
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ROW_MAJOR: u32 = 4;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+const BUILT_IN_FRAG_DEPTH: u32 = 22;
+
+const DIM_1D: u32 = 0;
+const DIM_2D: u32 = 1;
+const DIM_3D: u32 = 2;
+const DIM_CUBE: u32 = 3;
+const DIM_RECT: u32 = 4;
+const DIM_BUFFER: u32 = 5;
+
+/// An error parsing a SPIR-V module.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParseError {
+    /// The size of the code, in bytes, is not a multiple of 4.
+    Size(usize),
+    /// The module doesn't start with the SPIR-V magic number.
+    MagicNumber(u32),
+    /// The module ends in the middle of an instruction.
+    Truncated,
+    /// An instruction with the given opcode is shorter than it has to be.
+    Instruction(u32),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::Size(size) => write!(f, "SPIR-V size of {} bytes is not a multiple of 4", size),
+            ParseError::MagicNumber(magic) => write!(f, "Invalid SPIR-V magic number {:#x}", magic),
+            ParseError::Truncated => write!(f, "{}", self.description()),
+            ParseError::Instruction(op) => write!(f, "Malformed SPIR-V instruction with opcode {}", op),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        match *self {
+            ParseError::Size(_) => "The SPIR-V size is not a multiple of 4",
+            ParseError::MagicNumber(_) => "Invalid SPIR-V magic number",
+            ParseError::Truncated => "The SPIR-V module is truncated",
+            ParseError::Instruction(_) => "Malformed SPIR-V instruction",
+        }
+    }
+}
+
+/// A decoration kind, with its first operand if any.
+type Decoration = (u32, u32);
+
+fn find_decoration(decorations: &[Decoration], kind: u32) -> Option<u32> {
+    decorations.iter().find(|&&(k, _)| k == kind).map(|&(_, value)| value)
+}
+
+fn has_decoration(decorations: &[Decoration], kind: u32) -> bool {
+    find_decoration(decorations, kind).is_some()
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Image {
+    sampled_type: Id,
+    dim: u32,
+    depth: u32,
+    arrayed: bool,
+    multisampled: bool,
+    /// 1 if the image is used with a sampler, 2 if it is a storage image.
+    sampled: u32,
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub struct Variable {
-    id: desc::Id,
-    name: String,
-    ty: desc::Id,
-    storage_class: desc::StorageClass,
-    decoration: Vec<instruction::Decoration>,
+enum Type {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector(Id, u32),
+    /// Column type and the number of columns.
+    Matrix(Id, u32),
+    Image(Image),
+    Sampler,
+    SampledImage(Id),
+    /// Element type and the id of the length constant.
+    Array(Id, Id),
+    RuntimeArray(Id),
+    Struct(Vec<Id>),
+    Pointer(Id),
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub struct EntryPoint {
+struct EntryPoint {
     name: String,
     stage: shade::Stage,
-    interface: Box<[desc::Id]>,
+    interface: Vec<Id>,
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub enum Ty {
-    Basic(BaseType, ContainerType),
-    Image(BaseType, TextureType),
-    Struct(Vec<Type>),
-    Sampler,
+struct Variable {
+    id: Id,
+    /// Type of the variable, without the pointer indirection.
+    ty: Id,
+    storage_class: u32,
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub struct Type {
-    id: desc::Id,
-    ty: Ty,
-    decoration: Vec<instruction::Decoration>,
+/// The parts of a SPIR-V module needed for the reflection.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SpirvReflection {
+    entry_points: Vec<EntryPoint>,
+    variables: Vec<Variable>,
+    types: BTreeMap<Id, Type>,
+    constants: BTreeMap<Id, u32>,
+    names: BTreeMap<Id, String>,
+    member_names: BTreeMap<(Id, u32), String>,
+    decorations: BTreeMap<Id, Vec<Decoration>>,
+    member_decorations: BTreeMap<(Id, u32), Vec<Decoration>>,
 }
 
-fn map_execution_model_to_stage(model: desc::ExecutionModel) -> Option<shade::Stage> {
-    use spirv_utils::desc::ExecutionModel::*;
+fn map_execution_model_to_stage(model: u32) -> Option<shade::Stage> {
     match model {
-        Vertex => Some(shade::Stage::Vertex),
-        Geometry => Some(shade::Stage::Geometry),
-        Fragment => Some(shade::Stage::Pixel),
-
+        0 => Some(shade::Stage::Vertex),
+        1 => Some(shade::Stage::Hull),
+        2 => Some(shade::Stage::Domain),
+        3 => Some(shade::Stage::Geometry),
+        4 => Some(shade::Stage::Pixel),
+        5 => Some(shade::Stage::Compute),
         _ => None,
     }
 }
 
-fn map_name_by_id<'a>(module: &'a spirv_utils::RawModule, id: desc::Id) -> Option<&'a str> {
-    module.uses(id).filter_map(|instr| {
-        match *instr {
-            instruction::Instruction::Name { ref name, .. } => {
-                Some(&name[..])
-            },
-            _ => None,
+/// Read a nul-terminated string, returning it with the number of words it takes.
+fn read_string(words: &[u32]) -> (String, usize) {
+    let mut bytes = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        for k in 0 .. 4 {
+            let byte = (word >> (k * 8)) as u8;
+            if byte == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), i + 1)
+            }
+            bytes.push(byte);
         }
-    }).next()
+    }
+    (String::from_utf8_lossy(&bytes).into_owned(), words.len())
 }
 
-fn map_member_name_by_id<'a>(module: &'a spirv_utils::RawModule, id: desc::Id, member_id: u32) -> Option<&'a str> {
-    module.uses(id).filter_map(|instr| {
-        match *instr {
-            instruction::Instruction::MemberName { ref name, member, .. } if member == member_id => {
-                Some(&name[..])
+/// Parse a SPIR-V module.
+pub fn reflect_spirv_module(code: &[u8]) -> Result<SpirvReflection, ParseError> {
+    if code.len() % 4 != 0 {
+        return Err(ParseError::Size(code.len()))
+    }
+    let mut words: Vec<u32> = code.chunks(4).map(|c| {
+        c[0] as u32 | (c[1] as u32) << 8 | (c[2] as u32) << 16 | (c[3] as u32) << 24
+    }).collect();
+    let magic = words.first().cloned();
+    match magic {
+        Some(MAGIC_NUMBER) => (),
+        Some(magic) if magic.swap_bytes() == MAGIC_NUMBER => {
+            for word in words.iter_mut() {
+                *word = word.swap_bytes();
+            }
+        },
+        Some(magic) => return Err(ParseError::MagicNumber(magic)),
+        None => return Err(ParseError::Truncated),
+    }
+    if words.len() < 5 {
+        return Err(ParseError::Truncated)
+    }
+
+    let mut module = SpirvReflection::default();
+    // decorations applied through the groups are resolved at the end,
+    // once all the decorations of the groups are known
+    let mut group_targets = Vec::new();
+    let mut group_member_targets = Vec::new();
+    let mut pos = 5;
+    while pos < words.len() {
+        let op = words[pos] & 0xFFFF;
+        let count = (words[pos] >> 16) as usize;
+        if count == 0 || pos + count > words.len() {
+            return Err(ParseError::Truncated)
+        }
+        let args = &words[pos + 1 .. pos + count];
+        pos += count;
+        let min_args = match op {
+            OP_NAME | OP_TYPE_SAMPLED_IMAGE | OP_TYPE_RUNTIME_ARRAY | OP_DECORATE |
+            OP_TYPE_FLOAT => 2,
+            OP_MEMBER_NAME | OP_ENTRY_POINT | OP_TYPE_INT | OP_TYPE_VECTOR | OP_TYPE_MATRIX |
+            OP_TYPE_ARRAY | OP_TYPE_POINTER | OP_CONSTANT | OP_VARIABLE | OP_MEMBER_DECORATE => 3,
+            OP_TYPE_IMAGE => 8,
+            OP_TYPE_BOOL | OP_TYPE_SAMPLER | OP_TYPE_STRUCT | OP_GROUP_DECORATE |
+            OP_GROUP_MEMBER_DECORATE => 1,
+            _ => 0,
+        };
+        if args.len() < min_args {
+            return Err(ParseError::Instruction(op))
+        }
+        match op {
+            OP_NAME => {
+                module.names.insert(args[0], read_string(&args[1..]).0);
             },
-            _ => None,
+            OP_MEMBER_NAME => {
+                module.member_names.insert((args[0], args[1]), read_string(&args[2..]).0);
+            },
+            OP_ENTRY_POINT => {
+                let (name, length) = read_string(&args[2..]);
+                match map_execution_model_to_stage(args[0]) {
+                    Some(stage) => module.entry_points.push(EntryPoint {
+                        name: name,
+                        stage: stage,
+                        interface: args[2 + length ..].to_vec(),
+                    }),
+                    None => warn!("Unsupported execution model {} of {}", args[0], name),
+                }
+            },
+            OP_TYPE_BOOL => {
+                module.types.insert(args[0], Type::Bool);
+            },
+            OP_TYPE_INT => {
+                module.types.insert(args[0], Type::Int { width: args[1], signed: args[2] != 0 });
+            },
+            OP_TYPE_FLOAT => {
+                module.types.insert(args[0], Type::Float { width: args[1] });
+            },
+            OP_TYPE_VECTOR => {
+                module.types.insert(args[0], Type::Vector(args[1], args[2]));
+            },
+            OP_TYPE_MATRIX => {
+                module.types.insert(args[0], Type::Matrix(args[1], args[2]));
+            },
+            OP_TYPE_IMAGE => {
+                module.types.insert(args[0], Type::Image(Image {
+                    sampled_type: args[1],
+                    dim: args[2],
+                    depth: args[3],
+                    arrayed: args[4] != 0,
+                    multisampled: args[5] != 0,
+                    sampled: args[6],
+                }));
+            },
+            OP_TYPE_SAMPLER => {
+                module.types.insert(args[0], Type::Sampler);
+            },
+            OP_TYPE_SAMPLED_IMAGE => {
+                module.types.insert(args[0], Type::SampledImage(args[1]));
+            },
+            OP_TYPE_ARRAY => {
+                module.types.insert(args[0], Type::Array(args[1], args[2]));
+            },
+            OP_TYPE_RUNTIME_ARRAY => {
+                module.types.insert(args[0], Type::RuntimeArray(args[1]));
+            },
+            OP_TYPE_STRUCT => {
+                module.types.insert(args[0], Type::Struct(args[1..].to_vec()));
+            },
+            OP_TYPE_POINTER => {
+                module.types.insert(args[0], Type::Pointer(args[2]));
+            },
+            OP_CONSTANT => {
+                // only the lower word is needed, for the array lengths
+                module.constants.insert(args[1], args[2]);
+            },
+            OP_VARIABLE => {
+                // the type of every variable is an OpTypePointer
+                let ty = match module.types.get(&args[0]) {
+                    Some(&Type::Pointer(pointee)) => pointee,
+                    _ => return Err(ParseError::Instruction(op)),
+                };
+                module.variables.push(Variable {
+                    id: args[1],
+                    ty: ty,
+                    storage_class: args[2],
+                });
+            },
+            OP_DECORATE => {
+                let value = args.get(2).cloned().unwrap_or(0);
+                module.decorations.entry(args[0]).or_insert_with(Vec::new).push((args[1], value));
+            },
+            OP_MEMBER_DECORATE => {
+                let value = args.get(3).cloned().unwrap_or(0);
+                module.member_decorations.entry((args[0], args[1])).or_insert_with(Vec::new)
+                      .push((args[2], value));
+            },
+            OP_GROUP_DECORATE => {
+                for &target in args[1..].iter() {
+                    group_targets.push((args[0], target));
+                }
+            },
+            OP_GROUP_MEMBER_DECORATE => {
+                for pair in args[1..].chunks(2) {
+                    if pair.len() != 2 {
+                        return Err(ParseError::Instruction(op))
+                    }
+                    group_member_targets.push((args[0], (pair[0], pair[1])));
+                }
+            },
+            _ => (),
         }
-    }).next()
+    }
+
+    for (group, target) in group_targets {
+        let decorations = module.decorations.get(&group).cloned().unwrap_or_else(Vec::new);
+        module.decorations.entry(target).or_insert_with(Vec::new).extend(decorations);
+    }
+    for (group, member) in group_member_targets {
+        let decorations = module.decorations.get(&group).cloned().unwrap_or_else(Vec::new);
+        module.member_decorations.entry(member).or_insert_with(Vec::new).extend(decorations);
+    }
+    Ok(module)
 }
 
-fn map_decorations_by_id(module: &spirv_utils::RawModule, id: desc::Id) -> Vec<instruction::Decoration> {
-    module.uses(id).filter_map(|instr| {
-        match *instr {
-            instruction::Instruction::Decorate { ref decoration, .. } => {
-                Some(decoration.clone())
-            },
-            // TODO: GroupDecorations
+impl SpirvReflection {
+    fn get_name(&self, id: Id) -> &str {
+        self.names.get(&id).map_or("", |name| &name[..])
+    }
+
+    fn get_member_name(&self, id: Id, member: u32) -> &str {
+        self.member_names.get(&(id, member)).map_or("", |name| &name[..])
+    }
+
+    fn get_decorations(&self, id: Id) -> &[Decoration] {
+        self.decorations.get(&id).map_or(&[][..], |decorations| &decorations[..])
+    }
+
+    fn get_member_decorations(&self, id: Id, member: u32) -> &[Decoration] {
+        self.member_decorations.get(&(id, member)).map_or(&[][..], |decorations| &decorations[..])
+    }
+
+    fn get_array_length(&self, length: Id) -> Result<usize, String> {
+        self.constants.get(&length).map(|&len| len as usize)
+            .ok_or_else(|| format!("Array length {} is not a constant", length))
+    }
+
+    fn get_base_type(&self, ty: Id) -> Option<BaseType> {
+        match self.types.get(&ty) {
+            Some(&Type::Bool) => Some(BaseType::Bool),
+            Some(&Type::Int { width: 32, signed: false }) => Some(BaseType::U32),
+            Some(&Type::Int { width: 32, signed: true }) => Some(BaseType::I32),
+            Some(&Type::Float { width: 32 }) => Some(BaseType::F32),
+            Some(&Type::Float { width: 64 }) => Some(BaseType::F64),
             _ => None,
         }
-    }).collect::<Vec<_>>()
-}
+    }
 
-fn map_member_decorations_by_id(module: &spirv_utils::RawModule, id: desc::Id, member_id: u32) -> Vec<instruction::Decoration> {
-    module.uses(id).filter_map(|instr| {
-        match *instr {
-            instruction::Instruction::MemberDecorate { ref decoration, member, .. } if member == member_id => {
-                Some(decoration.clone())
+    /// Get the type of a scalar, vector or matrix. The layout of a matrix
+    /// is given by the decorations of the member holding it.
+    fn get_basic_type(&self, ty: Id, row_major: bool) -> Option<(BaseType, ContainerType)> {
+        match self.types.get(&ty) {
+            Some(&Type::Vector(component, len)) => {
+                self.get_base_type(component).map(|base| (base, ContainerType::Vector(len as u8)))
             },
-            // TODO: GroupDecorations
-            _ => None,
+            Some(&Type::Matrix(column, cols)) => match self.types.get(&column) {
+                Some(&Type::Vector(component, rows)) => self.get_base_type(component).map(|base| {
+                    let format = if row_major {
+                        shade::MatrixFormat::RowMajor
+                    } else {
+                        shade::MatrixFormat::ColumnMajor
+                    };
+                    (base, ContainerType::Matrix(format, rows as u8, cols as u8))
+                }),
+                _ => None,
+            },
+            _ => self.get_base_type(ty).map(|base| (base, ContainerType::Single)),
         }
-    }).collect::<Vec<_>>()
-}
-
-fn map_image_to_texture_type(dim: desc::Dim, arrayed: bool, multisampled: bool) -> TextureType {
-    use spirv_utils::desc::Dim;
-    let arrayed = if arrayed { shade::IsArray::Array } else { shade::IsArray::NoArray };
-    let multisampled = if multisampled { shade::IsMultiSample::MultiSample } else { shade::IsMultiSample::NoMultiSample };
-    match dim {
-        Dim::_1D => shade::TextureType::D1(arrayed),
-        Dim::_2D => shade::TextureType::D2(arrayed, multisampled),
-        Dim::_3D => shade::TextureType::D3,
-        Dim::Cube => shade::TextureType::Cube(arrayed),
-        Dim::Buffer => shade::TextureType::Buffer,
-
-        _ => unimplemented!(),
     }
-}
 
-fn map_scalar_to_basetype(instr: &instruction::Instruction) -> Option<BaseType> {
-    use spirv_utils::instruction::Instruction;
-    match *instr {
-        Instruction::TypeBool { .. } => Some(BaseType::Bool),
-        Instruction::TypeInt { width: 32, signed: false, .. } => Some(BaseType::U32),
-        Instruction::TypeInt { width: 32, signed: true, .. } => Some(BaseType::I32),
-        Instruction::TypeFloat { width: 32, ..} => Some(BaseType::F32),
-        Instruction::TypeFloat { width: 64, ..} => Some(BaseType::F64),
+    /// Get the size in bytes of a member of a block, following its layout decorations.
+    fn get_size(&self, ty: Id, member_decorations: &[Decoration]) -> Result<usize, String> {
+        match self.types.get(&ty) {
+            Some(&Type::Bool) => Ok(4),
+            Some(&Type::Int { width, .. }) | Some(&Type::Float { width }) => Ok(width as usize / 8),
+            Some(&Type::Vector(component, len)) => {
+                self.get_size(component, &[]).map(|size| size * len as usize)
+            },
+            Some(&Type::Matrix(column, cols)) => {
+                let stride = match find_decoration(member_decorations, DECORATION_MATRIX_STRIDE) {
+                    Some(stride) => stride as usize,
+                    None => return Err(format!("Missing matrix stride of type {}", ty)),
+                };
+                if has_decoration(member_decorations, DECORATION_ROW_MAJOR) {
+                    match self.types.get(&column) {
+                        Some(&Type::Vector(_, rows)) => Ok(rows as usize * stride),
+                        _ => Err(format!("Invalid matrix column type {}", column)),
+                    }
+                } else {
+                    Ok(cols as usize * stride)
+                }
+            },
+            Some(&Type::Array(_, length)) => {
+                let stride = match find_decoration(self.get_decorations(ty), DECORATION_ARRAY_STRIDE) {
+                    Some(stride) => stride as usize,
+                    None => return Err(format!("Missing array stride of type {}", ty)),
+                };
+                self.get_array_length(length).map(|len| len * stride)
+            },
+            Some(&Type::RuntimeArray(_)) => Ok(0),
+            Some(&Type::Struct(ref fields)) => {
+                let mut size = 0;
+                for (i, &field) in fields.iter().enumerate() {
+                    let decorations = self.get_member_decorations(ty, i as u32);
+                    let offset = match find_decoration(decorations, DECORATION_OFFSET) {
+                        Some(offset) => offset as usize,
+                        None => return Err(format!("Missing offset of member {} in type {}", i, ty)),
+                    };
+                    size = ::std::cmp::max(size, offset + try!(self.get_size(field, decorations)));
+                }
+                Ok(size)
+            },
+            _ => Err(format!("Type {} can't be a part of a block", ty)),
+        }
+    }
 
-        _ => None,
+    /// Add the constants of a block member, flattening the structures and their arrays.
+    fn reflect_constant(&self, ty: Id, name: String, offset: usize, row_major: bool,
+                        elements: &mut Vec<shade::ConstVar>) -> Result<(), String> {
+        if let Some((base_type, container)) = self.get_basic_type(ty, row_major) {
+            elements.push(shade::ConstVar {
+                name: name,
+                location: offset as shade::Location,
+                count: 1,
+                base_type: base_type,
+                container: container,
+            });
+            return Ok(())
+        }
+        match self.types.get(&ty) {
+            Some(&Type::Struct(ref fields)) => {
+                self.reflect_members(ty, fields, &format!("{}.", name), offset, elements)
+            },
+            Some(&Type::Array(element, length)) => {
+                let count = try!(self.get_array_length(length));
+                if let Some((base_type, container)) = self.get_basic_type(element, row_major) {
+                    elements.push(shade::ConstVar {
+                        name: name,
+                        location: offset as shade::Location,
+                        count: count,
+                        base_type: base_type,
+                        container: container,
+                    });
+                    return Ok(())
+                }
+                let stride = match find_decoration(self.get_decorations(ty), DECORATION_ARRAY_STRIDE) {
+                    Some(stride) => stride as usize,
+                    None => return Err(format!("Missing array stride of {}", name)),
+                };
+                for k in 0 .. count {
+                    try!(self.reflect_constant(element, format!("{}[{}]", name, k),
+                                               offset + k * stride, row_major, elements));
+                }
+                Ok(())
+            },
+            _ => Err(format!("Unsupported type of constant {}", name)),
+        }
     }
-}
 
-fn map_instruction_to_type(module: &spirv_utils::RawModule, instr: &instruction::Instruction) -> Option<Type> {
-    use spirv_utils::instruction::{Decoration, Instruction};
-    let id_ty = match *instr {
-        Instruction::TypeBool { result_type } => {
-            Some((result_type, Ty::Basic(map_scalar_to_basetype(instr).unwrap(), ContainerType::Single)))
-        },
-        Instruction::TypeInt { result_type, width: 32, signed: false } => {
-            Some((result_type, Ty::Basic(map_scalar_to_basetype(instr).unwrap(), ContainerType::Single)))
-        },
-        Instruction::TypeInt { result_type, width: 32, signed: true } => {
-            Some((result_type, Ty::Basic(map_scalar_to_basetype(instr).unwrap(), ContainerType::Single)))
-        },
-        Instruction::TypeFloat { result_type, width: 32 } => {
-            Some((result_type, Ty::Basic(map_scalar_to_basetype(instr).unwrap(), ContainerType::Single)))
-        },
-        Instruction::TypeFloat { result_type, width: 64 } => {
-            Some((result_type, Ty::Basic(map_scalar_to_basetype(instr).unwrap(), ContainerType::Single)))
-        },
-        Instruction::TypeVector { result_type, type_id, len } => {
-            let comp_ty = module.def(type_id).unwrap();
-            Some((result_type, Ty::Basic(map_scalar_to_basetype(comp_ty).unwrap(), ContainerType::Vector(len as u8))))
-        },
-        Instruction::TypeMatrix { result_type, type_id, cols } => {
-            let (base, rows) = match *module.def(type_id).unwrap() {
-                Instruction::TypeVector { type_id, len, .. } => {
-                    let comp_ty = module.def(type_id).unwrap();
-                    (map_scalar_to_basetype(comp_ty).unwrap(), len)
-                },
-                _ => unreachable!(), // SPIR-V module would be invalid
+    fn reflect_members(&self, ty: Id, fields: &[Id], prefix: &str, base_offset: usize,
+                       elements: &mut Vec<shade::ConstVar>) -> Result<(), String> {
+        for (i, &field) in fields.iter().enumerate() {
+            let decorations = self.get_member_decorations(ty, i as u32);
+            let name = format!("{}{}", prefix, self.get_member_name(ty, i as u32));
+            let offset = match find_decoration(decorations, DECORATION_OFFSET) {
+                Some(offset) => offset as usize,
+                None => return Err(format!("Missing offset of {}", name)),
             };
+            let row_major = has_decoration(decorations, DECORATION_ROW_MAJOR);
+            try!(self.reflect_constant(field, name, base_offset + offset, row_major, elements));
+        }
+        Ok(())
+    }
 
-            let decoration = map_decorations_by_id(&module, result_type.into());
+    /// Check if the variable is a built-in, or a block of them like `gl_PerVertex`.
+    fn is_built_in(&self, var: &Variable) -> bool {
+        if has_decoration(self.get_decorations(var.id), DECORATION_BUILT_IN) {
+            return true
+        }
+        let mut ty = var.ty;
+        while let Some(&Type::Array(element, _)) = self.types.get(&ty) {
+            ty = element;
+        }
+        match self.types.get(&ty) {
+            Some(&Type::Struct(_)) => {
+                has_decoration(self.get_member_decorations(ty, 0), DECORATION_BUILT_IN)
+            },
+            _ => false,
+        }
+    }
 
-            // NOTE: temporary value, changes might be needed later depending on the decorations of the variable
-            let matrix_format = if decoration.iter().find(|deco| **deco == Decoration::RowMajor).is_some() {
-                shade::MatrixFormat::RowMajor
-            } else {
-                shade::MatrixFormat::ColumnMajor
-            };
+    fn get_location(&self, var: &Variable) -> Result<u32, String> {
+        find_decoration(self.get_decorations(var.id), DECORATION_LOCATION)
+            .ok_or_else(|| format!("Missing location of {}", self.get_name(var.id)))
+    }
 
-            Some((result_type, Ty::Basic(base, ContainerType::Matrix(matrix_format, rows as u8, cols as u8))))
-        },
-        Instruction::TypeStruct { result_type, ref fields } => {
-            Some((
-                result_type,
-                Ty::Struct(
-                    fields.iter().filter_map(|field| // TODO: should be `map()`, currently to ignore unsupported types
-                        map_instruction_to_type(module, module.def(*field).unwrap())
-                    ).collect::<Vec<_>>()
-                )
-            ))
-        },
-        Instruction::TypeSampler { result_type } => {
-            Some((result_type, Ty::Sampler))
-        },
-        Instruction::TypeImage { result_type, type_id, dim, arrayed, multisampled, .. } => {
-            Some((result_type, Ty::Image(map_scalar_to_basetype(module.def(type_id).unwrap()).unwrap(), map_image_to_texture_type(dim, arrayed, multisampled))))
-        },
+    /// Get the binding of a resource. Only the first descriptor set is used by the backend.
+    fn get_binding(&self, var: &Variable, name: &str) -> Result<u8, String> {
+        let decorations = self.get_decorations(var.id);
+        match find_decoration(decorations, DECORATION_DESCRIPTOR_SET) {
+            Some(0) | None => (),
+            Some(set) => return Err(format!("Resource {} is in the descriptor set {}, only the set 0 is supported",
+                                            name, set)),
+        }
+        // glslang leaves out the binding 0
+        Ok(find_decoration(decorations, DECORATION_BINDING).unwrap_or(0) as u8)
+    }
+}
 
-        _ => None,
+fn map_texture_type(image: &Image) -> Option<TextureType> {
+    let arrayed = if image.arrayed { shade::IsArray::Array } else { shade::IsArray::NoArray };
+    let multisampled = if image.multisampled {
+        shade::IsMultiSample::MultiSample
+    } else {
+        shade::IsMultiSample::NoMultiSample
     };
-
-    id_ty.map(|(id, ty)| Type {
-            id: id.into(),
-            ty: ty,
-            decoration: map_decorations_by_id(&module, id.into()),
-        })
+    match image.dim {
+        DIM_1D => Some(TextureType::D1(arrayed)),
+        DIM_2D | DIM_RECT => Some(TextureType::D2(arrayed, multisampled)),
+        DIM_3D => Some(TextureType::D3),
+        DIM_CUBE => Some(TextureType::Cube(arrayed)),
+        DIM_BUFFER => Some(TextureType::Buffer),
+        // subpass inputs
+        _ => None,
+    }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub struct SpirvReflection {
-    entry_points: Vec<EntryPoint>,
-    variables: Vec<Variable>,
-    types: Vec<Type>,
+fn add_texture(info: &mut shade::ProgramInfo, usage: shade::Usage, reflection: &SpirvReflection,
+               name: &str, slot: u8, image: &Image) -> Result<(), String> {
+    if let Some(t) = info.textures.iter_mut().find(|t| t.name == name) {
+        t.usage = t.usage | usage;
+        return Ok(())
+    }
+    let ty = match map_texture_type(image) {
+        Some(ty) => ty,
+        None => return Err(format!("Unsupported dimension {} of texture {}", image.dim, name)),
+    };
+    let base_type = match reflection.get_base_type(image.sampled_type) {
+        Some(base) => base,
+        None => return Err(format!("Unsupported texel type of texture {}", name)),
+    };
+    info.textures.push(shade::TextureVar {
+        name: name.to_owned(),
+        slot: slot as core::ResourceViewSlot,
+        base_type: base_type,
+        ty: ty,
+        usage: usage,
+    });
+    Ok(())
 }
 
-pub fn reflect_spirv_module(code: &[u8]) -> SpirvReflection {
-    use spirv_utils::instruction::Instruction;
+fn add_sampler(info: &mut shade::ProgramInfo, usage: shade::Usage, name: &str, slot: u8,
+               comparison: shade::IsComparison) {
+    if let Some(s) = info.samplers.iter_mut().find(|s| s.name == name) {
+        s.usage = s.usage | usage;
+        return
+    }
+    info.samplers.push(shade::SamplerVar {
+        name: name.to_owned(),
+        slot: slot as core::SamplerSlot,
+        ty: shade::SamplerType(comparison, shade::IsRect::NoRect),
+        usage: usage,
+    });
+}
 
-    let module = spirv_utils::RawModule::read_module(code).expect("Unable to parse SPIR-V module");
+fn add_unordered(info: &mut shade::ProgramInfo, usage: shade::Usage, name: &str, slot: u8) {
+    if let Some(u) = info.unordereds.iter_mut().find(|u| u.name == name) {
+        u.usage = u.usage | usage;
+        return
+    }
+    info.unordereds.push(shade::UnorderedVar {
+        name: name.to_owned(),
+        slot: slot as core::UnorderedViewSlot,
+        usage: usage,
+    });
+}
 
-    let mut entry_points = Vec::new();
-    let mut variables = Vec::new();
-    let mut types = Vec::new();
-    for instr in module.instructions() {
-        match *instr {
-            Instruction::EntryPoint { execution_model, ref name, ref interface, .. } => {
-                if let Some(stage) = map_execution_model_to_stage(execution_model) {
-                    entry_points.push(EntryPoint {
-                        name: name.clone(),
-                        stage: stage,
-                        interface: interface.clone(),
-                    });
+/// Reflect the images and samplers.
+fn reflect_opaque(info: &mut shade::ProgramInfo, usage: shade::Usage, reflection: &SpirvReflection,
+                  var: &Variable) -> Result<(), String> {
+    let name = reflection.get_name(var.id);
+    let slot = try!(reflection.get_binding(var, name));
+    match reflection.types.get(&var.ty) {
+        Some(&Type::Image(ref image)) if image.sampled == 2 => {
+            add_unordered(info, usage, name, slot);
+            Ok(())
+        },
+        Some(&Type::Image(ref image)) => add_texture(info, usage, reflection, name, slot, image),
+        Some(&Type::Sampler) => {
+            // the samplers are named after their textures, with a trailing underscore
+            add_sampler(info, usage, name.trim_right_matches('_'), slot, shade::IsComparison::NoCompare);
+            Ok(())
+        },
+        Some(&Type::SampledImage(image)) => match reflection.types.get(&image) {
+            // combined image samplers are seen as a texture and a sampler with the same name
+            Some(&Type::Image(ref image)) => {
+                let comparison = if image.depth == 1 {
+                    shade::IsComparison::Compare
                 } else {
-                    error!("Unsupported execution model: {:?}", execution_model);
-                }
-            },
-            Instruction::Variable { result_type, result_id, storage_class, .. } => {
-                let decoration = map_decorations_by_id(&module, result_id.into());
-                let ty = {
-                    // remove indirection layer as the type of every variable is a OpTypePointer
-                    let ptr_ty = module.def::<desc::TypeId>(result_type.into()).unwrap();
-                    match *ptr_ty {
-                        Instruction::TypePointer { ref pointee, .. } => *pointee,
-                        _ => unreachable!(), // SPIR-V module would be invalid
-                    }
-                };
-
-                // Every variable MUST have an name annotation
-                // `glslang` seems to emit empty strings for uniforms with struct type,
-                // therefore we need to retrieve the name from the struct decl
-                let name = {
-                    let name = map_name_by_id(&module, result_id.into()).expect("Missing name annotation");
-                    if name.is_empty() {
-                        map_name_by_id(&module, ty.into()).expect("Missing name annotation")
-                    } else {
-                        name
-                    }
+                    shade::IsComparison::NoCompare
                 };
-
-                variables.push(Variable {
-                    id: result_id.into(),
-                    name: name.into(),
-                    ty: ty.into(),
-                    storage_class: storage_class,
-                    decoration: decoration,
-                });
-            },
-
-            _ => {
-                // Reflect types, if we have OpTypeXXX
-                if let Some(ty) = map_instruction_to_type(&module, instr) {
-                    types.push(ty);
-                }
+                add_sampler(info, usage, name, slot, comparison);
+                add_texture(info, usage, reflection, name, slot, image)
             },
-        }
+            _ => Err(format!("Invalid image type of {}", name)),
+        },
+        Some(&Type::Array(..)) | Some(&Type::RuntimeArray(..)) => {
+            Err(format!("Arrays of resources are not supported: {}", name))
+        },
+        _ => Err(format!("Unsupported uniform type of {}", name)),
     }
+}
 
-    SpirvReflection {
-        entry_points: entry_points,
-        variables: variables,
-        types: types,
+/// Reflect the uniform and storage blocks.
+fn reflect_block(info: &mut shade::ProgramInfo, usage: shade::Usage, reflection: &SpirvReflection,
+                 var: &Variable) -> Result<(), String> {
+    let fields = match reflection.types.get(&var.ty) {
+        Some(&Type::Struct(ref fields)) => fields,
+        Some(&Type::Array(..)) | Some(&Type::RuntimeArray(..)) => {
+            return Err(format!("Arrays of blocks are not supported: {}", reflection.get_name(var.id)))
+        },
+        _ => return Err(format!("Block {} is not a structure", reflection.get_name(var.id))),
+    };
+    // blocks are known by the name of their type, as in GLSL,
+    // while the variable is often left unnamed
+    let name = match reflection.get_name(var.ty) {
+        "" => reflection.get_name(var.id),
+        name => name,
+    }.to_owned();
+    let slot = try!(reflection.get_binding(var, &name));
+    let decorations = reflection.get_decorations(var.ty);
+    if var.storage_class == STORAGE_STORAGE_BUFFER || has_decoration(decorations, DECORATION_BUFFER_BLOCK) {
+        add_unordered(info, usage, &name, slot);
+        return Ok(())
+    }
+    if !has_decoration(decorations, DECORATION_BLOCK) {
+        return Err(format!("Uniform {} is not a block", name))
+    }
+    if let Some(cb) = info.constant_buffers.iter_mut().find(|cb| cb.name == name) {
+        cb.usage = cb.usage | usage;
+        return Ok(())
     }
+    let mut elements = Vec::new();
+    try!(reflection.reflect_members(var.ty, fields, "", 0, &mut elements));
+    let size = try!(reflection.get_size(var.ty, &[]));
+    info.constant_buffers.push(shade::ConstantBufferVar {
+        name: name,
+        slot: slot as core::ConstantBufferSlot,
+        size: size,
+        usage: usage,
+        elements: elements,
+    });
+    Ok(())
 }
 
-pub fn populate_info(info: &mut shade::ProgramInfo, stage: shade::Stage, reflection: &SpirvReflection) {
-    if stage == shade::Stage::Vertex {
-        // record vertex attributes
-        let entry_point = reflection.entry_points.iter().find(|ep| ep.name == "main" && ep.stage == stage).expect("Couln't find entry point!");
-        for attrib in entry_point.interface.iter() {
-            if let Some(var) = reflection.variables.iter().find(|var| var.id == *attrib) {
-                if var.storage_class == desc::StorageClass::Input {
-                    let attrib_name = var.name.clone();
-                    let slot = var.decoration.iter()
-                                     .find(|dec| if let &instruction::Decoration::Location(..) = *dec { true } else { false })
-                                     .map(|dec| if let instruction::Decoration::Location(slot) = *dec { Some(slot) } else { None })
-                                     .expect("Missing location decoration").unwrap();
-                    let ty = reflection.types.iter().find(|ty| ty.id == var.ty).unwrap();
-                    if let Ty::Basic(base, container) = ty.ty {
-                        info.vertex_attributes.push(shade::AttributeVar {
-                            name: attrib_name,
-                            slot: slot as core::AttributeSlot,
-                            base_type: base,
-                            container: container,
-                        });
-                    } else {
-                        error!("Unsupported type as vertex attribute: {:?}", ty.ty);
-                    }
+/// Add the interface of the module's entry point for the given stage to the program info.
+/// Vertex attributes and pixel outputs are taken from the interface of the entry
+/// point, while all the resources of the module are reflected.
+pub fn populate_info(info: &mut shade::ProgramInfo, stage: shade::Stage, reflection: &SpirvReflection)
+                     -> Result<(), String> {
+    let usage = stage.into();
+    let entry_point = match reflection.entry_points.iter()
+                                      .find(|ep| ep.stage == stage && ep.name == "main")
+                                      .or_else(|| reflection.entry_points.iter().find(|ep| ep.stage == stage)) {
+        Some(ep) => ep,
+        None => return Err(format!("Missing entry point of the {:?} stage", stage)),
+    };
+
+    for id in entry_point.interface.iter() {
+        let var = match reflection.variables.iter().find(|var| var.id == *id) {
+            Some(var) => var,
+            None => return Err(format!("Missing interface variable {} of {}", id, entry_point.name)),
+        };
+        let name = reflection.get_name(var.id);
+        match (stage, var.storage_class) {
+            (shade::Stage::Vertex, STORAGE_INPUT) if !reflection.is_built_in(var) => {
+                let slot = try!(reflection.get_location(var));
+                match reflection.get_basic_type(var.ty, false) {
+                    Some((base_type, container)) => info.vertex_attributes.push(shade::AttributeVar {
+                        name: name.to_owned(),
+                        slot: slot as core::AttributeSlot,
+                        base_type: base_type,
+                        container: container,
+                    }),
+                    None => return Err(format!("Unsupported type of vertex attribute {}", name)),
                 }
-            } else {
-                error!("Missing vertex attribute reflection: {:?}", attrib);
-            }
-        }
-    } else if stage == shade::Stage::Pixel {
-        // record pixel outputs
-        if let Some(entry_point) = reflection.entry_points.iter().find(|ep| ep.name == "main" && ep.stage == stage) {
-            for out in entry_point.interface.iter() {
-                if let Some(var) = reflection.variables.iter().find(|var| var.id == *out) {
-                    if var.storage_class == desc::StorageClass::Output {
-                        let target_name = var.name.clone();
-                        let slot = var.decoration.iter()
-                                         .find(|dec| if let &instruction::Decoration::Location(..) = *dec { true } else { false })
-                                         .map(|dec| if let instruction::Decoration::Location(slot) = *dec { Some(slot) } else { None })
-                                         .expect("Missing location decoration").unwrap();
-                        let ty = reflection.types.iter().find(|ty| ty.id == var.ty).unwrap();
-                        if let Ty::Basic(base, container) = ty.ty {
-                            info.outputs.push(shade::OutputVar {
-                                name: target_name,
-                                slot: slot as core::ColorSlot,
-                                base_type: base,
-                                container: container,
-                            });
-                        } else {
-                            error!("Unsupported type as pixel shader output: {:?}", ty.ty);
-                        }
-                    }
-                } else {
-                    error!("Missing pixel shader output reflection: {:?}", out);
+            },
+            (shade::Stage::Pixel, STORAGE_OUTPUT) => {
+                match find_decoration(reflection.get_decorations(var.id), DECORATION_BUILT_IN) {
+                    Some(BUILT_IN_FRAG_DEPTH) => {
+                        info.output_depth = true;
+                        continue
+                    },
+                    Some(built_in) => {
+                        warn!("Unhandled pixel shader built-in output {}: {}", built_in, name);
+                        continue
+                    },
+                    None => (),
                 }
-            }
+                let slot = try!(reflection.get_location(var));
+                match reflection.get_basic_type(var.ty, false) {
+                    Some((base_type, container)) => info.outputs.push(shade::OutputVar {
+                        name: name.to_owned(),
+                        slot: slot as core::ColorSlot,
+                        base_type: base_type,
+                        container: container,
+                    }),
+                    None => return Err(format!("Unsupported type of pixel shader output {}", name)),
+                }
+            },
+            _ => (),
         }
     }
+    if stage == shade::Stage::Pixel {
+        info.knows_outputs = true;
+    }
 
-    // Handle resources
-    // We use only one descriptor set currently
     for var in reflection.variables.iter() {
-        use spirv_utils::desc::StorageClass::*;
         match var.storage_class {
-            Uniform | UniformConstant => {
-                if let Some(ty) = reflection.types.iter().find(|ty| ty.id == var.ty) {
-                    // constant buffers
-                    match ty.ty {
-                        Ty::Struct(ref fields) => {
-                            let elements = Vec::new();
-                            for field in fields {
-                                // TODO:
-                            }
-
-                            let buffer_name = var.name.clone();
-                            let slot = var.decoration.iter()
-                                             .find(|dec| if let &instruction::Decoration::Binding(..) = *dec { true } else { false })
-                                             .map(|dec| if let instruction::Decoration::Binding(slot) = *dec { Some(slot) } else { None })
-                                             .expect("Missing binding decoration").unwrap();
-                            info.constant_buffers.push(shade::ConstantBufferVar {
-                                name: buffer_name,
-                                slot: slot as core::ConstantBufferSlot,
-                                size: 0, // TODO:
-                                usage: shade::VERTEX | shade::GEOMETRY | shade::PIXEL, // TODO:
-                                elements: elements,
-                            });
-                        },
-
-                        Ty::Sampler => {
-                            let sampler_name = var.name.trim_right_matches('_');
-                            let slot = var.decoration.iter()
-                                             .find(|dec| if let &instruction::Decoration::Binding(..) = *dec { true } else { false })
-                                             .map(|dec| if let instruction::Decoration::Binding(slot) = *dec { Some(slot) } else { None })
-                                             .expect("Missing binding decoration").unwrap();
-                            info.samplers.push(shade::SamplerVar {
-                                name: sampler_name.to_owned(),
-                                slot: slot as core::SamplerSlot,
-                                ty: shade::SamplerType(shade::IsComparison::NoCompare, shade::IsRect::NoRect), // TODO:
-                                usage: shade::VERTEX | shade::GEOMETRY | shade::PIXEL, // TODO:
-                            });
-                        },
-
-                        Ty::Image(base_type, texture_type) => {
-                            let texture_name = var.name.clone();
-                            let slot = var.decoration.iter()
-                                             .find(|dec| if let &instruction::Decoration::Binding(..) = *dec { true } else { false })
-                                             .map(|dec| if let instruction::Decoration::Binding(slot) = *dec { Some(slot) } else { None })
-                                             .expect("Missing binding decoration").unwrap();
-                            info.textures.push(shade::TextureVar {
-                                name: texture_name,
-                                slot: slot as core::ResourceViewSlot,
-                                base_type: base_type,
-                                ty: texture_type,
-                                usage: shade::VERTEX | shade::GEOMETRY | shade::PIXEL, // TODO:
-                            });
-                        },
-
-                        _ => (),
-                    }
-                } else {
-                    error!("Unsupported uniform type: {:?}", var.ty);
-                }
+            STORAGE_UNIFORM_CONSTANT => try!(reflect_opaque(info, usage, reflection, var)),
+            STORAGE_UNIFORM | STORAGE_STORAGE_BUFFER => try!(reflect_block(info, usage, reflection, var)),
+            STORAGE_PUSH_CONSTANT => {
+                warn!("Push constants are not supported: {}", reflection.get_name(var.ty))
             },
             _ => (),
         }
     }
+    Ok(())
 }
@@ -91,6 +91,8 @@ pub enum Role {
     Constant,
     /// Staging buffer
     Staging,
+    /// Indirect draw arguments buffer
+    Indirect,
 }
 
 /// An information block that is immutable and associated to each buffer.
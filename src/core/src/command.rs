@@ -18,7 +18,8 @@ use std::ops::Deref;
 use std::collections::hash_set::{self, HashSet};
 use {Resources, IndexType, InstanceCount, VertexCount,
      SubmissionResult, SubmissionError};
-use {state, target, pso, shade, texture, handle};
+use {state, target, pso, shade, format, texture, handle};
+use memory::Pod;
 
 /// A universal clear color supporting integet formats
 /// as well as the standard floating-point.
@@ -35,6 +36,44 @@ pub enum ClearColor {
 /// Optional instance parameters: (instance count, buffer offset)
 pub type InstanceParams = (InstanceCount, VertexCount);
 
+/// Arguments of a single non-indexed indirect draw call, as they are
+/// laid out in the indirect buffer.
+#[allow(missing_docs)]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct DrawIndirectArgs {
+    pub vertex_count: VertexCount,
+    pub instance_count: InstanceCount,
+    pub first_vertex: VertexCount,
+    pub first_instance: InstanceCount,
+}
+
+unsafe impl Pod for DrawIndirectArgs {}
+
+/// Arguments of a single indexed indirect draw call, as they are
+/// laid out in the indirect buffer.
+#[allow(missing_docs)]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct DrawIndexedIndirectArgs {
+    pub index_count: VertexCount,
+    pub instance_count: InstanceCount,
+    pub first_index: VertexCount,
+    pub base_vertex: i32,
+    pub first_instance: InstanceCount,
+}
+
+unsafe impl Pod for DrawIndexedIndirectArgs {}
+
+/// Filtering applied when blitting between regions of different sizes.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum BlitFilter {
+    /// Pick the closest texel
+    Nearest,
+    /// Linearly interpolate the neighbouring texels
+    Linear,
+}
+
 /// An interface of the abstract command buffer. It collects commands in an
 /// efficient API-specific manner, to be ready for execution on the device.
 #[allow(missing_docs)]
@@ -78,6 +117,23 @@ pub trait Buffer<R: Resources>: Send {
                               src: R::Texture, texture::Kind,
                               Option<texture::CubeFace>, texture::RawImageInfo,
                               dst: R::Buffer, dst_offset_bytes: usize);
+    /// Copy part of a texture to another texture of a compatible format
+    fn copy_texture_to_texture(&mut self,
+                               src: R::Texture, texture::Kind,
+                               Option<texture::CubeFace>, texture::RawImageInfo,
+                               dst: R::Texture, texture::Kind,
+                               Option<texture::CubeFace>, texture::RawImageInfo);
+    /// Copy part of a texture to another, scaling and filtering it
+    fn blit_texture(&mut self,
+                    src: R::Texture, texture::Kind,
+                    Option<texture::CubeFace>, texture::RawImageInfo,
+                    dst: R::Texture, texture::Kind,
+                    Option<texture::CubeFace>, texture::RawImageInfo,
+                    target::Mirror, BlitFilter);
+    /// Resolve a multisampled texture into a single-sampled one,
+    /// interpreting the texels with the given format
+    fn resolve_texture(&mut self, src: R::Texture, texture::Kind,
+                       dst: R::Texture, texture::Kind, format::Format);
     /// Update a vertex/index/uniform buffer
     fn update_buffer(&mut self, R::Buffer, data: &[u8], offset: usize);
     /// Update a texture
@@ -92,6 +148,24 @@ pub trait Buffer<R: Resources>: Send {
     fn call_draw(&mut self, VertexCount, VertexCount, Option<InstanceParams>);
     /// Draw a primitive with index buffer
     fn call_draw_indexed(&mut self, VertexCount, VertexCount, VertexCount, Option<InstanceParams>);
+    /// Draw a number of primitives, reading `DrawIndirectArgs` records from
+    /// a buffer, starting at the byte offset
+    fn call_draw_indirect(&mut self, R::Buffer, offset_bytes: usize, count: usize);
+    /// Draw a number of primitives with index buffer, reading
+    /// `DrawIndexedIndirectArgs` records from a buffer, starting at the byte offset
+    fn call_draw_indexed_indirect(&mut self, R::Buffer, offset_bytes: usize, count: usize);
+    /// Dispatch a compute workload with the given number of work groups
+    fn call_dispatch(&mut self, [u32; 3]);
+    /// Start gathering information into a query
+    fn begin_query(&mut self, R::Query);
+    /// Stop gathering information into a query
+    fn end_query(&mut self, R::Query);
+    /// Write the GPU time into a timestamp query, once the previous commands are done
+    fn write_timestamp(&mut self, R::Query);
+    /// Start skipping draw calls if the query result says nothing passed
+    fn begin_conditional(&mut self, R::Query);
+    /// Stop the conditional rendering
+    fn end_conditional(&mut self);
 }
 
 macro_rules! impl_clear {
@@ -139,6 +213,7 @@ impl From<u32> for ClearColor {
 pub struct AccessInfo<R: Resources> {
     mapped_reads: HashSet<handle::RawBuffer<R>>,
     mapped_writes: HashSet<handle::RawBuffer<R>>,
+    queries: HashSet<handle::Query<R>>,
 }
 
 impl<R: Resources> AccessInfo<R> {
@@ -147,6 +222,7 @@ impl<R: Resources> AccessInfo<R> {
         AccessInfo {
             mapped_reads: HashSet::new(),
             mapped_writes: HashSet::new(),
+            queries: HashSet::new(),
         }
     }
 
@@ -154,6 +230,7 @@ impl<R: Resources> AccessInfo<R> {
     pub fn clear(&mut self) {
         self.mapped_reads.clear();
         self.mapped_writes.clear();
+        self.queries.clear();
     }
 
     /// Register a buffer read access
@@ -170,6 +247,13 @@ impl<R: Resources> AccessInfo<R> {
         }
     }
 
+    /// Register a query that the GPU will write the results of
+    pub fn query_write(&mut self, query: &handle::Query<R>) {
+        // the previous result is not relevant any more
+        query.mark_encoded();
+        self.queries.insert(query.clone());
+    }
+
     /// Returns the mapped buffers that The GPU will read from
     pub fn mapped_reads(&self) -> AccessInfoBuffers<R> {
         self.mapped_reads.iter()
@@ -190,6 +274,16 @@ impl<R: Resources> AccessInfo<R> {
         !self.mapped_writes.is_empty()
     }
 
+    /// Returns the queries that the GPU will write the results of
+    pub fn queries(&self) -> AccessInfoQueries<R> {
+        self.queries.iter()
+    }
+
+    /// Is there any outstanding query ?
+    pub fn has_queries(&self) -> bool {
+        !self.queries.is_empty()
+    }
+
     /// Takes all the accesses necessary for submission
     pub fn take_accesses(&self) -> SubmissionResult<AccessGuard<R>> {
         for buffer in self.mapped_reads().chain(self.mapped_writes()) {
@@ -199,6 +293,9 @@ impl<R: Resources> AccessInfo<R> {
                 }
             }
         }
+        for query in self.queries() {
+            query.mark_submitted();
+        }
         Ok(AccessGuard { inner: self })
     }
 }
@@ -206,6 +303,9 @@ impl<R: Resources> AccessInfo<R> {
 #[allow(missing_docs)]
 pub type AccessInfoBuffers<'a, R> = hash_set::Iter<'a, handle::RawBuffer<R>>;
 
+#[allow(missing_docs)]
+pub type AccessInfoQueries<'a, R> = hash_set::Iter<'a, handle::Query<R>>;
+
 #[allow(missing_docs)]
 pub struct AccessGuard<'a, R: Resources> {
     inner: &'a AccessInfo<R>,
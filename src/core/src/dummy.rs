@@ -14,10 +14,18 @@
 
 //! Dummy backend implementation to test the code for compile errors
 //! outside of the graphics development environment.
+//!
+//! The `DummyFactory` creates handles without any resources behind them,
+//! which allows unit testing the code that builds on top of a factory.
 
+use std::{mem, slice};
+use std::collections::HashMap;
 use {Capabilities, Device, SubmissionResult, Resources, IndexType, VertexCount};
-use {state, target, handle, mapping, pso, shade, texture};
+use {ShaderSet, buffer, factory as f, state, target, format, handle, mapping,
+     memory, pso, query, shade, texture};
 use command::{self, AccessInfo};
+use handle::Producer;
+use memory::Bind;
 
 /// Dummy device which does minimal work, just to allow testing
 /// gfx-rs apps for compilation.
@@ -30,32 +38,61 @@ pub struct DummyDevice {
 pub enum DummyResources {}
 
 impl Resources for DummyResources {
-    type Buffer               = ();
-    type Shader               = ();
-    type Program              = ();
-    type PipelineStateObject  = ();
-    type Texture              = ();
-    type ShaderResourceView   = ();
-    type UnorderedAccessView  = ();
-    type RenderTargetView     = ();
-    type DepthStencilView     = ();
-    type Sampler              = ();
+    type Buffer               = DummyId;
+    type Shader               = DummyId;
+    type Program              = DummyId;
+    type PipelineStateObject  = DummyId;
+    type Texture              = DummyId;
+    type ShaderResourceView   = DummyId;
+    type UnorderedAccessView  = DummyId;
+    type RenderTargetView     = DummyId;
+    type DepthStencilView     = DummyId;
+    type Sampler              = DummyId;
     type Fence                = DummyFence;
+    type Query                = DummyId;
     type Mapping              = DummyMapping;
 }
 
+/// Dummy resource, which is nothing but a unique identifier.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DummyId(pub usize);
+
 /// Dummy fence that does nothing.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct DummyFence;
 
-/// Dummy mapping which will crash on use.
+/// Dummy mapping, backed by plain memory that is never read by anything else.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct DummyMapping;
+pub struct DummyMapping {
+    // `u64` words, so that any element type is properly aligned
+    memory: Vec<u64>,
+}
+
+impl DummyMapping {
+    fn new(size: usize) -> DummyMapping {
+        let word = mem::size_of::<u64>();
+        DummyMapping {
+            memory: vec![0; (size + word - 1) / word],
+        }
+    }
+
+    fn pointer(&self) -> *mut u8 {
+        self.memory.as_ptr() as *mut u8
+    }
+}
 
 impl mapping::Gate<DummyResources> for DummyMapping {
-    unsafe fn set<T>(&self, _index: usize, _val: T) { unimplemented!() }
-    unsafe fn slice<'a, 'b, T>(&'a self, _len: usize) -> &'b [T] { unimplemented!() }
-    unsafe fn mut_slice<'a, 'b, T>(&'a self, _len: usize) -> &'b mut [T] { unimplemented!() }
+    unsafe fn set<T>(&self, index: usize, val: T) {
+        *(self.pointer() as *mut T).offset(index as isize) = val;
+    }
+
+    unsafe fn slice<'a, 'b, T>(&'a self, len: usize) -> &'b [T] {
+        slice::from_raw_parts(self.pointer() as *const T, len)
+    }
+
+    unsafe fn mut_slice<'a, 'b, T>(&'a self, len: usize) -> &'b mut [T] {
+        slice::from_raw_parts_mut(self.pointer() as *mut T, len)
+    }
 }
 
 impl DummyDevice {
@@ -66,15 +103,25 @@ impl DummyDevice {
             max_index_count: 0,
             max_texture_size: 0,
             max_patch_size: 0,
+            constant_buffer_alignment: 0,
             instance_base_supported: false,
             instance_call_supported: false,
             instance_rate_supported: false,
             vertex_base_supported: false,
             srgb_color_supported: false,
             constant_buffer_supported: false,
+            constant_buffer_range_supported: false,
             unordered_access_view_supported: false,
             separate_blending_slots_supported: false,
             copy_buffer_supported: false,
+            copy_texture_supported: false,
+            blit_texture_supported: false,
+            resolve_texture_supported: false,
+            compute_supported: false,
+            draw_indirect_supported: false,
+            bc_compression_supported: false,
+            etc2_compression_supported: false,
+            astc_compression_supported: false,
         };
         DummyDevice {
             capabilities: caps,
@@ -86,7 +133,7 @@ impl DummyDevice {
 pub struct DummyCommandBuffer;
 impl command::Buffer<DummyResources> for DummyCommandBuffer {
     fn reset(&mut self) {}
-    fn bind_pipeline_state(&mut self, _: ()) {}
+    fn bind_pipeline_state(&mut self, _: DummyId) {}
     fn bind_vertex_buffers(&mut self, _: pso::VertexBufferSet<DummyResources>) {}
     fn bind_constant_buffers(&mut self, _: &[pso::ConstantBufferParam<DummyResources>]) {}
     fn bind_global_constant(&mut self, _: shade::Location, _: shade::UniformValue) {}
@@ -94,30 +141,293 @@ impl command::Buffer<DummyResources> for DummyCommandBuffer {
     fn bind_unordered_views(&mut self, _: &[pso::UnorderedViewParam<DummyResources>]) {}
     fn bind_samplers(&mut self, _: &[pso::SamplerParam<DummyResources>]) {}
     fn bind_pixel_targets(&mut self, _: pso::PixelTargetSet<DummyResources>) {}
-    fn bind_index(&mut self, _: (), _: IndexType) {}
+    fn bind_index(&mut self, _: DummyId, _: IndexType) {}
     fn set_scissor(&mut self, _: target::Rect) {}
     fn set_ref_values(&mut self, _: state::RefValues) {}
-    fn copy_buffer(&mut self, _: (), _: (),
+    fn copy_buffer(&mut self, _: DummyId, _: DummyId,
                    _: usize, _: usize,
                    _: usize) {}
     fn copy_buffer_to_texture(&mut self,
-                              _: (), _: usize,
-                              _: (), _: texture::Kind,
+                              _: DummyId, _: usize,
+                              _: DummyId, _: texture::Kind,
                               _: Option<texture::CubeFace>, _: texture::RawImageInfo) {}
     fn copy_texture_to_buffer(&mut self,
-                              _: (), _: texture::Kind,
+                              _: DummyId, _: texture::Kind,
                               _: Option<texture::CubeFace>, _: texture::RawImageInfo,
-                              _: (), _: usize) {}
-    fn update_buffer(&mut self, _: (), _: &[u8], _: usize) {}
-    fn update_texture(&mut self, _: (), _: texture::Kind, _: Option<texture::CubeFace>,
+                              _: DummyId, _: usize) {}
+    fn copy_texture_to_texture(&mut self,
+                               _: DummyId, _: texture::Kind,
+                               _: Option<texture::CubeFace>, _: texture::RawImageInfo,
+                               _: DummyId, _: texture::Kind,
+                               _: Option<texture::CubeFace>, _: texture::RawImageInfo) {}
+    fn blit_texture(&mut self,
+                    _: DummyId, _: texture::Kind,
+                    _: Option<texture::CubeFace>, _: texture::RawImageInfo,
+                    _: DummyId, _: texture::Kind,
+                    _: Option<texture::CubeFace>, _: texture::RawImageInfo,
+                    _: target::Mirror, _: command::BlitFilter) {}
+    fn resolve_texture(&mut self, _: DummyId, _: texture::Kind,
+                       _: DummyId, _: texture::Kind, _: format::Format) {}
+    fn update_buffer(&mut self, _: DummyId, _: &[u8], _: usize) {}
+    fn update_texture(&mut self, _: DummyId, _: texture::Kind, _: Option<texture::CubeFace>,
                       _: &[u8], _: texture::RawImageInfo) {}
-    fn generate_mipmap(&mut self, _: ()) {}
-    fn clear_color(&mut self, _: (), _: command::ClearColor) {}
-    fn clear_depth_stencil(&mut self, _: (), _: Option<target::Depth>,
+    fn generate_mipmap(&mut self, _: DummyId) {}
+    fn clear_color(&mut self, _: DummyId, _: command::ClearColor) {}
+    fn clear_depth_stencil(&mut self, _: DummyId, _: Option<target::Depth>,
                            _: Option<target::Stencil>) {}
     fn call_draw(&mut self, _: VertexCount, _: VertexCount, _: Option<command::InstanceParams>) {}
     fn call_draw_indexed(&mut self, _: VertexCount, _: VertexCount,
                          _: VertexCount, _: Option<command::InstanceParams>) {}
+    fn call_draw_indirect(&mut self, _: DummyId, _: usize, _: usize) {}
+    fn call_draw_indexed_indirect(&mut self, _: DummyId, _: usize, _: usize) {}
+    fn call_dispatch(&mut self, _: [u32; 3]) {}
+    fn begin_query(&mut self, _: DummyId) {}
+    fn end_query(&mut self, _: DummyId) {}
+    fn write_timestamp(&mut self, _: DummyId) {}
+    fn begin_conditional(&mut self, _: DummyId) {}
+    fn end_conditional(&mut self) {}
+}
+
+/// A command recorded by the `RecordingCommandBuffer`. The variants
+/// mirror the methods of `command::Buffer`, with the same arguments.
+#[allow(missing_docs)]
+#[derive(Clone, Debug)]
+pub enum RecordedCommand {
+    BindPipelineState(DummyId),
+    BindVertexBuffers(pso::VertexBufferSet<DummyResources>),
+    BindConstantBuffers(Vec<pso::ConstantBufferParam<DummyResources>>),
+    BindGlobalConstant(shade::Location, shade::UniformValue),
+    BindResourceViews(Vec<pso::ResourceViewParam<DummyResources>>),
+    BindUnorderedViews(Vec<pso::UnorderedViewParam<DummyResources>>),
+    BindSamplers(Vec<pso::SamplerParam<DummyResources>>),
+    BindPixelTargets(pso::PixelTargetSet<DummyResources>),
+    BindIndex(DummyId, IndexType),
+    SetScissor(target::Rect),
+    SetRefValues(state::RefValues),
+    CopyBuffer(DummyId, DummyId, usize, usize, usize),
+    CopyBufferToTexture(DummyId, usize, DummyId, Option<texture::CubeFace>, texture::RawImageInfo),
+    CopyTextureToBuffer(DummyId, Option<texture::CubeFace>, texture::RawImageInfo, DummyId, usize),
+    CopyTextureToTexture(DummyId, Option<texture::CubeFace>, texture::RawImageInfo,
+                         DummyId, Option<texture::CubeFace>, texture::RawImageInfo),
+    BlitTexture(DummyId, Option<texture::CubeFace>, texture::RawImageInfo,
+                DummyId, Option<texture::CubeFace>, texture::RawImageInfo,
+                target::Mirror, command::BlitFilter),
+    ResolveTexture(DummyId, DummyId, format::Format),
+    UpdateBuffer(DummyId, Vec<u8>, usize),
+    UpdateTexture(DummyId, Option<texture::CubeFace>, Vec<u8>, texture::RawImageInfo),
+    GenerateMipmap(DummyId),
+    ClearColor(DummyId, command::ClearColor),
+    ClearDepthStencil(DummyId, Option<target::Depth>, Option<target::Stencil>),
+    Draw(VertexCount, VertexCount, Option<command::InstanceParams>),
+    DrawIndexed(VertexCount, VertexCount, VertexCount, Option<command::InstanceParams>),
+    DrawIndirect(DummyId, usize, usize),
+    DrawIndexedIndirect(DummyId, usize, usize),
+    Dispatch([u32; 3]),
+    BeginQuery(DummyId),
+    EndQuery(DummyId),
+    WriteTimestamp(DummyId),
+    BeginConditional(DummyId),
+    EndConditional,
+}
+
+/// Command buffer recording all the calls, so that the commands
+/// produced by an `Encoder` can be inspected.
+///
+/// The resources are the `DummyId`s given out by the `DummyFactory`.
+pub struct RecordingCommandBuffer {
+    /// Recorded commands, in the order of the calls.
+    pub commands: Vec<RecordedCommand>,
+}
+
+impl RecordingCommandBuffer {
+    /// Create a new empty recording command buffer.
+    pub fn new() -> RecordingCommandBuffer {
+        RecordingCommandBuffer {
+            commands: Vec::new(),
+        }
+    }
+
+    /// Count the number of the draw calls, including the indirect ones.
+    pub fn count_draws(&self) -> usize {
+        self.commands.iter().filter(|com| match **com {
+            RecordedCommand::Draw(..) |
+            RecordedCommand::DrawIndexed(..) |
+            RecordedCommand::DrawIndirect(..) |
+            RecordedCommand::DrawIndexedIndirect(..) => true,
+            _ => false,
+        }).count()
+    }
+
+    /// Count the number of times the pipeline state has been bound.
+    pub fn count_pso_binds(&self, pso: &handle::RawPipelineState<DummyResources>) -> usize {
+        let id = *pso.resource();
+        self.commands.iter().filter(|com| match **com {
+            RecordedCommand::BindPipelineState(bound) => bound == id,
+            _ => false,
+        }).count()
+    }
+}
+
+impl command::Buffer<DummyResources> for RecordingCommandBuffer {
+    fn reset(&mut self) {
+        self.commands.clear();
+    }
+
+    fn bind_pipeline_state(&mut self, pso: DummyId) {
+        self.commands.push(RecordedCommand::BindPipelineState(pso));
+    }
+
+    fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<DummyResources>) {
+        self.commands.push(RecordedCommand::BindVertexBuffers(vbs));
+    }
+
+    fn bind_constant_buffers(&mut self, cbs: &[pso::ConstantBufferParam<DummyResources>]) {
+        self.commands.push(RecordedCommand::BindConstantBuffers(cbs.to_vec()));
+    }
+
+    fn bind_global_constant(&mut self, loc: shade::Location, value: shade::UniformValue) {
+        self.commands.push(RecordedCommand::BindGlobalConstant(loc, value));
+    }
+
+    fn bind_resource_views(&mut self, srvs: &[pso::ResourceViewParam<DummyResources>]) {
+        self.commands.push(RecordedCommand::BindResourceViews(srvs.to_vec()));
+    }
+
+    fn bind_unordered_views(&mut self, uavs: &[pso::UnorderedViewParam<DummyResources>]) {
+        self.commands.push(RecordedCommand::BindUnorderedViews(uavs.to_vec()));
+    }
+
+    fn bind_samplers(&mut self, ss: &[pso::SamplerParam<DummyResources>]) {
+        self.commands.push(RecordedCommand::BindSamplers(ss.to_vec()));
+    }
+
+    fn bind_pixel_targets(&mut self, pts: pso::PixelTargetSet<DummyResources>) {
+        self.commands.push(RecordedCommand::BindPixelTargets(pts));
+    }
+
+    fn bind_index(&mut self, buf: DummyId, itype: IndexType) {
+        self.commands.push(RecordedCommand::BindIndex(buf, itype));
+    }
+
+    fn set_scissor(&mut self, rect: target::Rect) {
+        self.commands.push(RecordedCommand::SetScissor(rect));
+    }
+
+    fn set_ref_values(&mut self, rv: state::RefValues) {
+        self.commands.push(RecordedCommand::SetRefValues(rv));
+    }
+
+    fn copy_buffer(&mut self, src: DummyId, dst: DummyId,
+                   src_offset_bytes: usize, dst_offset_bytes: usize,
+                   size_bytes: usize) {
+        self.commands.push(RecordedCommand::CopyBuffer(src, dst, src_offset_bytes,
+                                                       dst_offset_bytes, size_bytes));
+    }
+
+    fn copy_buffer_to_texture(&mut self,
+                              src: DummyId, src_offset_bytes: usize,
+                              dst: DummyId, _: texture::Kind,
+                              face: Option<texture::CubeFace>, img: texture::RawImageInfo) {
+        self.commands.push(RecordedCommand::CopyBufferToTexture(src, src_offset_bytes,
+                                                                dst, face, img));
+    }
+
+    fn copy_texture_to_buffer(&mut self,
+                              src: DummyId, _: texture::Kind,
+                              face: Option<texture::CubeFace>, img: texture::RawImageInfo,
+                              dst: DummyId, dst_offset_bytes: usize) {
+        self.commands.push(RecordedCommand::CopyTextureToBuffer(src, face, img,
+                                                                dst, dst_offset_bytes));
+    }
+
+    fn copy_texture_to_texture(&mut self,
+                               src: DummyId, _: texture::Kind,
+                               src_face: Option<texture::CubeFace>, src_img: texture::RawImageInfo,
+                               dst: DummyId, _: texture::Kind,
+                               dst_face: Option<texture::CubeFace>, dst_img: texture::RawImageInfo) {
+        self.commands.push(RecordedCommand::CopyTextureToTexture(src, src_face, src_img,
+                                                                 dst, dst_face, dst_img));
+    }
+
+    fn blit_texture(&mut self,
+                    src: DummyId, _: texture::Kind,
+                    src_face: Option<texture::CubeFace>, src_img: texture::RawImageInfo,
+                    dst: DummyId, _: texture::Kind,
+                    dst_face: Option<texture::CubeFace>, dst_img: texture::RawImageInfo,
+                    mirror: target::Mirror, filter: command::BlitFilter) {
+        self.commands.push(RecordedCommand::BlitTexture(src, src_face, src_img,
+                                                        dst, dst_face, dst_img,
+                                                        mirror, filter));
+    }
+
+    fn resolve_texture(&mut self, src: DummyId, _: texture::Kind,
+                       dst: DummyId, _: texture::Kind, format: format::Format) {
+        self.commands.push(RecordedCommand::ResolveTexture(src, dst, format));
+    }
+
+    fn update_buffer(&mut self, buf: DummyId, data: &[u8], offset_bytes: usize) {
+        self.commands.push(RecordedCommand::UpdateBuffer(buf, data.to_vec(), offset_bytes));
+    }
+
+    fn update_texture(&mut self, tex: DummyId, _: texture::Kind, face: Option<texture::CubeFace>,
+                      data: &[u8], img: texture::RawImageInfo) {
+        self.commands.push(RecordedCommand::UpdateTexture(tex, face, data.to_vec(), img));
+    }
+
+    fn generate_mipmap(&mut self, srv: DummyId) {
+        self.commands.push(RecordedCommand::GenerateMipmap(srv));
+    }
+
+    fn clear_color(&mut self, target: DummyId, value: command::ClearColor) {
+        self.commands.push(RecordedCommand::ClearColor(target, value));
+    }
+
+    fn clear_depth_stencil(&mut self, target: DummyId, depth: Option<target::Depth>,
+                           stencil: Option<target::Stencil>) {
+        self.commands.push(RecordedCommand::ClearDepthStencil(target, depth, stencil));
+    }
+
+    fn call_draw(&mut self, start: VertexCount, count: VertexCount,
+                 instances: Option<command::InstanceParams>) {
+        self.commands.push(RecordedCommand::Draw(start, count, instances));
+    }
+
+    fn call_draw_indexed(&mut self, start: VertexCount, count: VertexCount,
+                         base: VertexCount, instances: Option<command::InstanceParams>) {
+        self.commands.push(RecordedCommand::DrawIndexed(start, count, base, instances));
+    }
+
+    fn call_draw_indirect(&mut self, buf: DummyId, offset_bytes: usize, count: usize) {
+        self.commands.push(RecordedCommand::DrawIndirect(buf, offset_bytes, count));
+    }
+
+    fn call_draw_indexed_indirect(&mut self, buf: DummyId, offset_bytes: usize, count: usize) {
+        self.commands.push(RecordedCommand::DrawIndexedIndirect(buf, offset_bytes, count));
+    }
+
+    fn call_dispatch(&mut self, count: [u32; 3]) {
+        self.commands.push(RecordedCommand::Dispatch(count));
+    }
+
+    fn begin_query(&mut self, query: DummyId) {
+        self.commands.push(RecordedCommand::BeginQuery(query));
+    }
+
+    fn end_query(&mut self, query: DummyId) {
+        self.commands.push(RecordedCommand::EndQuery(query));
+    }
+
+    fn write_timestamp(&mut self, query: DummyId) {
+        self.commands.push(RecordedCommand::WriteTimestamp(query));
+    }
+
+    fn begin_conditional(&mut self, query: DummyId) {
+        self.commands.push(RecordedCommand::BeginConditional(query));
+    }
+
+    fn end_conditional(&mut self) {
+        self.commands.push(RecordedCommand::EndConditional);
+    }
 }
 
 impl Device for DummyDevice {
@@ -132,7 +442,7 @@ impl Device for DummyDevice {
               _: &mut DummyCommandBuffer,
               _: &AccessInfo<Self::Resources>)
               -> SubmissionResult<()> {
-        unimplemented!()
+        Ok(())
     }
 
     fn fenced_submit(&mut self,
@@ -140,12 +450,346 @@ impl Device for DummyDevice {
                      _: &AccessInfo<Self::Resources>,
                      _after: Option<handle::Fence<Self::Resources>>)
                      -> SubmissionResult<handle::Fence<Self::Resources>> {
-        unimplemented!()
+        // nothing is executed, so the fence is signalled right away
+        Ok(handle::Manager::new().make_fence(DummyFence))
     }
 
-    fn wait_fence(&mut self, _: &handle::Fence<Self::Resources>) {
-        unimplemented!()
-    }
+    fn wait_fence(&mut self, _: &handle::Fence<Self::Resources>) {}
 
     fn cleanup(&mut self) {}
 }
+
+/// Dummy factory, which creates handles with no resources behind them.
+/// It validates the creation parameters the way real backends do, so the
+/// code using a `Factory` can be tested without a graphics context.
+pub struct DummyFactory {
+    capabilities: Capabilities,
+    handles: handle::Manager<DummyResources>,
+    program_info: shade::ProgramInfo,
+    query_results: HashMap<DummyId, u64>,
+    next_id: usize,
+}
+
+impl DummyFactory {
+    /// Create a new dummy factory. The created programs have no inputs
+    /// nor outputs until `set_program_info` is called.
+    pub fn new() -> DummyFactory {
+        let caps = Capabilities {
+            max_vertex_count: !0,
+            max_index_count: !0,
+            max_texture_size: 16384,
+            max_patch_size: 32,
+            constant_buffer_alignment: 256,
+            instance_base_supported: true,
+            instance_call_supported: true,
+            instance_rate_supported: true,
+            vertex_base_supported: true,
+            srgb_color_supported: true,
+            constant_buffer_supported: true,
+            constant_buffer_range_supported: true,
+            unordered_access_view_supported: true,
+            separate_blending_slots_supported: true,
+            copy_buffer_supported: true,
+            copy_texture_supported: true,
+            blit_texture_supported: true,
+            resolve_texture_supported: true,
+            compute_supported: true,
+            draw_indirect_supported: true,
+            bc_compression_supported: true,
+            etc2_compression_supported: true,
+            astc_compression_supported: true,
+        };
+        DummyFactory {
+            capabilities: caps,
+            handles: handle::Manager::new(),
+            program_info: shade::ProgramInfo {
+                vertex_attributes: Vec::new(),
+                globals: Vec::new(),
+                constant_buffers: Vec::new(),
+                textures: Vec::new(),
+                unordereds: Vec::new(),
+                samplers: Vec::new(),
+                outputs: Vec::new(),
+                output_depth: false,
+                knows_outputs: true,
+            },
+            query_results: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Set the reflection info to be reported by the programs created
+    /// afterwards, as if it was obtained from the shader code.
+    pub fn set_program_info(&mut self, info: shade::ProgramInfo) {
+        self.program_info = info;
+    }
+
+    /// Replace the reported capabilities, for example to test
+    /// how the unsupported features are handled.
+    pub fn set_capabilities(&mut self, caps: Capabilities) {
+        self.capabilities = caps;
+    }
+
+    /// Set the result reported for a query, as if the GPU had written it.
+    pub fn set_query_result(&mut self, query: DummyId, result: u64) {
+        self.query_results.insert(query, result);
+    }
+
+    fn make_id(&mut self) -> DummyId {
+        self.next_id += 1;
+        DummyId(self.next_id)
+    }
+
+    fn check_buffer(&self, info: &buffer::Info) -> Result<(), buffer::CreationError> {
+        if info.bind.intersects(memory::RENDER_TARGET | memory::DEPTH_STENCIL) {
+            return Err(buffer::CreationError::UnsupportedBind(info.bind))
+        }
+        if info.bind.contains(memory::UNORDERED_ACCESS) &&
+            !self.capabilities.unordered_access_view_supported {
+            return Err(buffer::CreationError::UnsupportedBind(info.bind))
+        }
+        match info.role {
+            buffer::Role::Index if info.stride != 2 && info.stride != 4 => {
+                error!("Only U16 and U32 index buffers are allowed");
+                Err(buffer::CreationError::Other)
+            },
+            buffer::Role::Constant if !self.capabilities.constant_buffer_supported =>
+                Err(buffer::CreationError::Other),
+            _ => Ok(()),
+        }
+    }
+
+    fn create_buffer_internal(&mut self, info: buffer::Info) -> handle::RawBuffer<DummyResources> {
+        let mapping = match info.usage {
+            memory::Usage::Upload | memory::Usage::Download => Some(DummyMapping::new(info.size)),
+            memory::Usage::Data | memory::Usage::Dynamic => None,
+        };
+        let id = self.make_id();
+        self.handles.make_buffer(id, info, mapping)
+    }
+
+    fn check_texture_data(&self, desc: &texture::Info, data: &[&[u8]]) -> Result<(), texture::CreationError> {
+        let num_slices = desc.kind.get_num_slices().unwrap_or(1) as usize;
+        let num_faces = if desc.kind.is_cube() { 6 } else { 1 };
+        if data.len() != num_slices * num_faces * desc.levels as usize {
+            error!("Texture expects {} slices {} faces {} mips, given {} data chunks instead",
+                num_slices, num_faces, desc.levels, data.len());
+            return Err(texture::CreationError::Data(data.len()))
+        }
+        let bytes = desc.format.get_total_bits() as usize / 8;
+        for (i, chunk) in data.iter().enumerate() {
+            let level = (i % desc.levels as usize) as target::Level;
+            let mut image = desc.to_image_info(level);
+            if num_slices * num_faces > 1 {
+                image.depth = 1;
+            }
+            let size = image.get_block_count(desc.format) * bytes;
+            if chunk.len() != size {
+                error!("Texture level {} expects {} bytes, given {} instead",
+                    level, size, chunk.len());
+                return Err(texture::CreationError::Data(chunk.len()))
+            }
+        }
+        Ok(())
+    }
+
+    fn check_target(&self, info: &texture::Info, level: target::Level, layer: Option<target::Layer>)
+                    -> Result<(), f::TargetViewError> {
+        if level >= info.levels {
+            return Err(f::TargetViewError::Level(level))
+        }
+        match (layer, info.kind.get_num_slices()) {
+            (Some(l), None) if l != 0 =>
+                Err(f::TargetViewError::Layer(texture::LayerError::NotExpected(info.kind))),
+            (Some(l), Some(n)) if l >= n =>
+                Err(f::TargetViewError::Layer(texture::LayerError::OutOfBounds(l, n))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl f::Factory<DummyResources> for DummyFactory {
+    fn get_capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    fn create_buffer_raw(&mut self, info: buffer::Info)
+                         -> Result<handle::RawBuffer<DummyResources>, buffer::CreationError> {
+        try!(self.check_buffer(&info));
+        Ok(self.create_buffer_internal(info))
+    }
+
+    fn create_buffer_immutable_raw(&mut self, data: &[u8], stride: usize, role: buffer::Role, bind: Bind)
+                                   -> Result<handle::RawBuffer<DummyResources>, buffer::CreationError> {
+        let info = buffer::Info {
+            role: role,
+            usage: memory::Usage::Data,
+            bind: bind,
+            size: data.len(),
+            stride: stride,
+        };
+        try!(self.check_buffer(&info));
+        Ok(self.create_buffer_internal(info))
+    }
+
+    fn create_pipeline_state_raw(&mut self, program: &handle::Program<DummyResources>, _: &pso::Descriptor)
+                                 -> Result<handle::RawPipelineState<DummyResources>, pso::CreationError> {
+        let id = self.make_id();
+        Ok(self.handles.make_pso(id, program))
+    }
+
+    fn create_program(&mut self, _: &ShaderSet<DummyResources>)
+                      -> Result<handle::Program<DummyResources>, shade::CreateProgramError> {
+        let id = self.make_id();
+        Ok(self.handles.make_program(id, self.program_info.clone()))
+    }
+
+    fn create_shader(&mut self, _: shade::Stage, _: &[u8])
+                     -> Result<handle::Shader<DummyResources>, shade::CreateShaderError> {
+        let id = self.make_id();
+        Ok(self.handles.make_shader(id))
+    }
+
+    fn create_sampler(&mut self, info: texture::SamplerInfo) -> handle::Sampler<DummyResources> {
+        let id = self.make_id();
+        self.handles.make_sampler(id, info)
+    }
+
+    fn create_query(&mut self, ty: query::QueryType)
+                    -> Result<handle::Query<DummyResources>, query::CreationError> {
+        let id = self.make_id();
+        Ok(self.handles.make_query(id, ty))
+    }
+
+    fn get_query_result(&mut self, query: &handle::Query<DummyResources>) -> Option<u64> {
+        // nothing is ever executed, the results are given by `set_query_result`
+        self.query_results.get(query.resource()).cloned()
+    }
+
+    fn read_mapping<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<DummyResources, T>)
+                               -> Result<mapping::Reader<'b, DummyResources, T>, mapping::Error>
+        where T: Copy
+    {
+        unsafe { mapping::read(buf.raw(), |_| ()) }
+    }
+
+    fn write_mapping<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<DummyResources, T>)
+                                -> Result<mapping::Writer<'b, DummyResources, T>, mapping::Error>
+        where T: Copy
+    {
+        unsafe { mapping::write(buf.raw(), |_| ()) }
+    }
+
+    fn create_texture_raw(&mut self, desc: texture::Info, channel: Option<format::ChannelType>,
+                          data_opt: Option<&[&[u8]]>)
+                          -> Result<handle::RawTexture<DummyResources>, texture::CreationError> {
+        if desc.levels == 0 {
+            return Err(texture::CreationError::Size(0))
+        }
+        let (w, h, d, aa) = desc.kind.get_dimensions();
+        for &size in [w, h, d].iter() {
+            if size as usize > self.capabilities.max_texture_size {
+                return Err(texture::CreationError::Size(size))
+            }
+        }
+        if aa != texture::AaMode::Single && desc.levels > 1 {
+            return Err(texture::CreationError::Samples(aa))
+        }
+        if desc.format.is_compressed() {
+            // compressed textures can only be sampled, and need two dimensions
+            let is_1d = match desc.kind {
+                texture::Kind::D1(..) | texture::Kind::D1Array(..) => true,
+                _ => false,
+            };
+            if is_1d || desc.bind.intersects(memory::RENDER_TARGET | memory::DEPTH_STENCIL |
+                                             memory::UNORDERED_ACCESS) {
+                return Err(texture::CreationError::Format(desc.format, channel))
+            }
+        }
+        if let Some(data) = data_opt {
+            try!(self.check_texture_data(&desc, data));
+        }
+        let id = self.make_id();
+        Ok(self.handles.make_texture(id, desc))
+    }
+
+    fn view_buffer_as_shader_resource_raw(&mut self, hbuf: &handle::RawBuffer<DummyResources>)
+                                          -> Result<handle::RawShaderResourceView<DummyResources>,
+                                                    f::ResourceViewError> {
+        if !hbuf.get_info().bind.contains(memory::SHADER_RESOURCE) {
+            return Err(f::ResourceViewError::NoBindFlag)
+        }
+        let id = self.make_id();
+        Ok(self.handles.make_buffer_srv(id, hbuf))
+    }
+
+    fn view_buffer_as_unordered_access_raw(&mut self, hbuf: &handle::RawBuffer<DummyResources>)
+                                           -> Result<handle::RawUnorderedAccessView<DummyResources>,
+                                                     f::ResourceViewError> {
+        if !hbuf.get_info().bind.contains(memory::UNORDERED_ACCESS) {
+            return Err(f::ResourceViewError::NoBindFlag)
+        }
+        let id = self.make_id();
+        Ok(self.handles.make_buffer_uav(id, hbuf))
+    }
+
+    fn view_texture_as_shader_resource_raw(&mut self, htex: &handle::RawTexture<DummyResources>,
+                                           desc: texture::ResourceDesc)
+                                           -> Result<handle::RawShaderResourceView<DummyResources>,
+                                                     f::ResourceViewError> {
+        let info = htex.get_info();
+        if !info.bind.contains(memory::SHADER_RESOURCE) {
+            return Err(f::ResourceViewError::NoBindFlag)
+        }
+        if desc.min >= info.levels || desc.max < desc.min {
+            return Err(f::ResourceViewError::Unsupported)
+        }
+        match (desc.layer, info.kind.get_num_slices()) {
+            (Some(l), None) if l != 0 =>
+                return Err(f::ResourceViewError::Layer(texture::LayerError::NotExpected(info.kind))),
+            (Some(l), Some(n)) if l >= n =>
+                return Err(f::ResourceViewError::Layer(texture::LayerError::OutOfBounds(l, n))),
+            _ => (),
+        }
+        let id = self.make_id();
+        Ok(self.handles.make_texture_srv(id, htex))
+    }
+
+    fn view_texture_as_unordered_access_raw(&mut self, htex: &handle::RawTexture<DummyResources>)
+                                            -> Result<handle::RawUnorderedAccessView<DummyResources>,
+                                                      f::ResourceViewError> {
+        if !htex.get_info().bind.contains(memory::UNORDERED_ACCESS) {
+            return Err(f::ResourceViewError::NoBindFlag)
+        }
+        let id = self.make_id();
+        Ok(self.handles.make_texture_uav(id, htex))
+    }
+
+    fn view_texture_as_render_target_raw(&mut self, htex: &handle::RawTexture<DummyResources>,
+                                         desc: texture::RenderDesc)
+                                         -> Result<handle::RawRenderTargetView<DummyResources>,
+                                                   f::TargetViewError> {
+        let info = htex.get_info().clone();
+        if !info.bind.contains(memory::RENDER_TARGET) {
+            return Err(f::TargetViewError::NoBindFlag)
+        }
+        try!(self.check_target(&info, desc.level, desc.layer));
+        let dim = info.kind.get_level_dimensions(desc.level);
+        let id = self.make_id();
+        Ok(self.handles.make_rtv(id, htex, dim))
+    }
+
+    fn view_texture_as_depth_stencil_raw(&mut self, htex: &handle::RawTexture<DummyResources>,
+                                         desc: texture::DepthStencilDesc)
+                                         -> Result<handle::RawDepthStencilView<DummyResources>,
+                                                   f::TargetViewError> {
+        let info = htex.get_info().clone();
+        if !info.bind.contains(memory::DEPTH_STENCIL) {
+            return Err(f::TargetViewError::NoBindFlag)
+        }
+        try!(self.check_target(&info, desc.level, desc.layer));
+        let dim = info.kind.get_level_dimensions(desc.level);
+        let id = self.make_id();
+        Ok(self.handles.make_dsv(id, htex, dim))
+    }
+}
@@ -19,9 +19,9 @@
 
 use std::error::Error;
 use std::{mem, fmt};
-use {buffer, handle, format, mapping, pso, shade, target, texture};
+use {buffer, handle, format, mapping, mipmap, pso, query, shade, target, texture};
 use {Capabilities, Resources, ShaderSet,
-     VertexShader, HullShader, DomainShader, GeometryShader, PixelShader};
+     VertexShader, HullShader, DomainShader, GeometryShader, PixelShader, ComputeShader};
 use memory::{Usage, Typed, Pod, cast_slice};
 use memory::{Bind, RENDER_TARGET, DEPTH_STENCIL, SHADER_RESOURCE, UNORDERED_ACCESS};
 
@@ -251,9 +251,21 @@ pub trait Factory<R: Resources> {
     fn create_shader_pixel(&mut self, code: &[u8]) -> Result<PixelShader<R>, shade::CreateShaderError> {
         self.create_shader(shade::Stage::Pixel, code).map(|s| PixelShader(s))
     }
+    /// Compiles a `ComputeShader` from source.
+    fn create_shader_compute(&mut self, code: &[u8]) -> Result<ComputeShader<R>, shade::CreateShaderError> {
+        self.create_shader(shade::Stage::Compute, code).map(|s| ComputeShader(s))
+    }
 
     fn create_sampler(&mut self, texture::SamplerInfo) -> handle::Sampler<R>;
 
+    /// Creates a new query of the given type.
+    fn create_query(&mut self, query::QueryType) -> Result<handle::Query<R>, query::CreationError>;
+
+    /// Returns the result of a query, if it is available.
+    /// This never blocks: `None` is returned until the commands ending
+    /// the query are submitted and executed by the device.
+    fn get_query_result(&mut self, &handle::Query<R>) -> Option<u64>;
+
     /// Acquire a mapping Reader
     ///
     /// See `write_mapping` for more information.
@@ -274,6 +286,22 @@ pub trait Factory<R: Resources> {
                                           mapping::Error>
         where T: Copy;
 
+    /// Acquire a mapping Writer to one of the `num_regions` equal regions of the buffer.
+    ///
+    /// This is meant for the buffers streamed every frame, written at the region of the
+    /// frame index modulo `num_regions`. With persistent mappings, only the GPU accesses
+    /// since the last write to this region are waited for, instead of all of them.
+    /// The GPU accesses following this call are assumed to only read this region.
+    /// Fails with `InvalidRegion` if `region` is not less than `num_regions`.
+    fn write_mapping_region<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>,
+                                       region: usize, num_regions: usize)
+                                       -> Result<mapping::Writer<'b, R, T>,
+                                                 mapping::Error>
+        where T: Copy
+    {
+        self.write_mapping(buf).and_then(|writer| writer.into_region(region, num_regions))
+    }
+
     /// Create a new empty raw texture with no data. The channel type parameter is a hint,
     /// required to assist backends that have no concept of typeless formats (OpenGL).
     /// The initial data, if given, has to be provided for all mip levels and slices:
@@ -430,6 +458,44 @@ pub trait Factory<R: Resources> {
         self.create_texture_immutable_u8::<T>(kind, &raw_data[.. data.len()])
     }
 
+    /// Like `create_texture_immutable_u8`, but the rest of the mipmap chain can be
+    /// generated on the CPU from the top levels.
+    fn create_texture_immutable_u8_with_mipmaps<T: format::TextureFormat>(&mut self, kind: texture::Kind,
+                                                mipmap: texture::Mipmap, data: &[&[u8]])
+                                                -> Result<(handle::Texture<R, T::Surface>,
+                                                           handle::ShaderResourceView<R, T::View>),
+                                                          CombinedError>
+    {
+        match mipmap {
+            texture::Mipmap::Provided => self.create_texture_immutable_u8::<T>(kind, data),
+            texture::Mipmap::Generated(filter) => {
+                let surface = <T::Surface as format::SurfaceTyped>::get_surface_type();
+                let cty = <T::Channel as format::ChannelTyped>::get_channel_type();
+                let chain = try!(mipmap::generate(kind, format::Format(surface, cty), filter, data));
+                let chain_data: Vec<&[u8]> = chain.iter().map(|level| &level[..]).collect();
+                self.create_texture_immutable_u8::<T>(kind, &chain_data)
+            },
+        }
+    }
+
+    /// Like `create_texture_immutable`, but the rest of the mipmap chain can be
+    /// generated on the CPU from the top levels.
+    fn create_texture_immutable_with_mipmaps<T: format::TextureFormat>(
+        &mut self,
+        kind: texture::Kind,
+        mipmap: texture::Mipmap,
+        data: &[&[<T::Surface as format::SurfaceTyped>::DataType]])
+        -> Result<(handle::Texture<R, T::Surface>, handle::ShaderResourceView<R, T::View>),
+                  CombinedError>
+    {
+        let mut raw_data: [&[u8]; 0x100] = [&[]; 0x100];
+        assert!(data.len() <= raw_data.len());
+        for (rd, d) in raw_data.iter_mut().zip(data.iter()) {
+            *rd = cast_slice(*d);
+        }
+        self.create_texture_immutable_u8_with_mipmaps::<T>(kind, mipmap, &raw_data[.. data.len()])
+    }
+
     fn create_render_target<T: format::RenderFormat + format::TextureFormat>
                            (&mut self, width: texture::Size, height: texture::Size)
                             -> Result<(handle::Texture<R, T::Surface>,
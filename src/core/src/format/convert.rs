@@ -0,0 +1,428 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! CPU-side conversion of pixel data between formats.
+//!
+//! Texels are decoded into RGBA components, the way a shader would see
+//! them: normalized channels are mapped to `[0, 1]` or `[-1, 1]`, sRGB
+//! values are moved into the linear space, and integer channels keep their
+//! value. Encoding does the opposite, clamping to the range of the channel.
+//! Missing components are read as `(0, 0, 0, 1)`.
+//!
+//! Packed formats follow the bit layout of the matching OpenGL packed
+//! pixel types, e.g. `R5_G6_B5` keeps red in the high bits and `D24_S8`
+//! keeps the depth above the stencil. Integer values are carried as `f32`,
+//! so 32-bit integers above 2^24 are rounded unless the formats match.
+//!
+//! ```
+//! use gfx_core::format::{ChannelType, Format, SurfaceType};
+//! use gfx_core::format::convert;
+//!
+//! let srgb = Format(SurfaceType::R8_G8_B8_A8, ChannelType::Srgb);
+//! let bgra = Format(SurfaceType::B8_G8_R8_A8, ChannelType::Unorm);
+//! let data = convert::convert(&[0xFF, 0x80, 0, 0xFF], srgb, bgra).unwrap();
+//! assert_eq!(data, vec![0, 0x37, 0xFF, 0xFF]);
+//! ```
+
+use std::error::Error as StdError;
+use std::{f32, fmt, mem};
+use format::{ChannelType, Format, SurfaceType};
+
+/// An error converting pixel data.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Error {
+    /// The format is block-compressed, and can't be converted texel by texel.
+    Compressed(Format),
+    /// The data size is not a multiple of the texel size.
+    Size(usize),
+    /// The source and the destination hold a different number of texels.
+    TexelCountMismatch {
+        /// Number of texels in the source.
+        src: usize,
+        /// Number of texels in the destination.
+        dst: usize,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Error::*;
+        match *self {
+            Compressed(ref format) => write!(f, "{}: {:?}", self.description(), format),
+            Size(size) => write!(f, "{}: {} bytes", self.description(), size),
+            TexelCountMismatch { src, dst } =>
+                write!(f, "{}: {} to {}", self.description(), src, dst),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        use self::Error::*;
+        match *self {
+            Compressed(_) => "Compressed formats can't be converted",
+            Size(_) => "The data size is not a multiple of the texel size",
+            TexelCountMismatch {..} => "The source and the destination have different texel counts",
+        }
+    }
+}
+
+/// Location of a channel inside a texel: (bit offset, bit count).
+type Channel = Option<(u8, u8)>;
+
+fn get_channels(surface: SurfaceType) -> [Channel; 4] {
+    use format::SurfaceType::*;
+    match surface {
+        R4_G4           => [Some((0, 4)), Some((4, 4)), None, None],
+        R4_G4_B4_A4     => [Some((12, 4)), Some((8, 4)), Some((4, 4)), Some((0, 4))],
+        R5_G5_B5_A1     => [Some((11, 5)), Some((6, 5)), Some((1, 5)), Some((0, 1))],
+        R5_G6_B5        => [Some((11, 5)), Some((5, 6)), Some((0, 5)), None],
+        R8              => [Some((0, 8)), None, None, None],
+        R8_G8           => [Some((0, 8)), Some((8, 8)), None, None],
+        R8_G8_B8_A8     => [Some((0, 8)), Some((8, 8)), Some((16, 8)), Some((24, 8))],
+        R10_G10_B10_A2  => [Some((0, 10)), Some((10, 10)), Some((20, 10)), Some((30, 2))],
+        R11_G11_B10     => [Some((0, 11)), Some((11, 11)), Some((22, 10)), None],
+        R16             => [Some((0, 16)), None, None, None],
+        R16_G16         => [Some((0, 16)), Some((16, 16)), None, None],
+        R16_G16_B16     => [Some((0, 16)), Some((16, 16)), Some((32, 16)), None],
+        R16_G16_B16_A16 => [Some((0, 16)), Some((16, 16)), Some((32, 16)), Some((48, 16))],
+        R32             => [Some((0, 32)), None, None, None],
+        R32_G32         => [Some((0, 32)), Some((32, 32)), None, None],
+        R32_G32_B32     => [Some((0, 32)), Some((32, 32)), Some((64, 32)), None],
+        R32_G32_B32_A32 => [Some((0, 32)), Some((32, 32)), Some((64, 32)), Some((96, 32))],
+        B8_G8_R8_A8     => [Some((16, 8)), Some((8, 8)), Some((0, 8)), Some((24, 8))],
+        D16             => [Some((0, 16)), None, None, None],
+        D24 | D24_S8    => [Some((8, 24)), None, None, None],
+        D32             => [Some((0, 32)), None, None, None],
+        // the compressed formats are not stored texel by texel
+        _               => [None, None, None, None],
+    }
+}
+
+fn get_mask(bits: u8) -> u64 {
+    (1u64 << bits) - 1
+}
+
+fn get_bits(texel: &[u8], offset: u8, bits: u8) -> u32 {
+    let start = (offset / 8) as usize;
+    let shift = offset % 8;
+    let count = ((shift + bits + 7) / 8) as usize;
+    let mut word = 0u64;
+    for (i, &byte) in texel[start .. start + count].iter().enumerate() {
+        word |= (byte as u64) << (i * 8);
+    }
+    ((word >> shift) & get_mask(bits)) as u32
+}
+
+fn set_bits(texel: &mut [u8], offset: u8, bits: u8, value: u32) {
+    let start = (offset / 8) as usize;
+    let shift = offset % 8;
+    let count = ((shift + bits + 7) / 8) as usize;
+    let mask = get_mask(bits) << shift;
+    let mut word = 0u64;
+    for (i, &byte) in texel[start .. start + count].iter().enumerate() {
+        word |= (byte as u64) << (i * 8);
+    }
+    word = (word & !mask) | (((value as u64) << shift) & mask);
+    for (i, byte) in texel[start .. start + count].iter_mut().enumerate() {
+        *byte = (word >> (i * 8)) as u8;
+    }
+}
+
+fn sign_extend(raw: u32, bits: u8) -> i32 {
+    let shift = 32 - bits as u32;
+    ((raw << shift) as i32) >> shift
+}
+
+/// Decode a floating-point value with a 5-bit exponent, as used by
+/// the half floats and the packed unsigned 11- and 10-bit floats.
+fn decode_float(raw: u32, mantissa_bits: u8, signed: bool) -> f32 {
+    let scale = (1u32 << mantissa_bits) as f32;
+    let mantissa = raw & (get_mask(mantissa_bits) as u32);
+    let exponent = ((raw >> mantissa_bits) & 0x1F) as i32;
+    let value = match exponent {
+        0 => mantissa as f32 / scale * 2f32.powi(-14),
+        0x1F if mantissa == 0 => f32::INFINITY,
+        0x1F => f32::NAN,
+        _ => (1.0 + mantissa as f32 / scale) * 2f32.powi(exponent - 15),
+    };
+    if signed && (raw >> (mantissa_bits + 5)) & 1 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+fn encode_float(value: f32, mantissa_bits: u8, signed: bool) -> u32 {
+    let sign = if signed && value.is_sign_negative() {
+        1 << (mantissa_bits + 5)
+    } else {
+        0
+    };
+    let abs = if signed { value.abs() } else { value.max(0.0) };
+    if abs.is_nan() {
+        return sign | (0x1F << mantissa_bits) | 1;
+    }
+    let scale = (1u32 << mantissa_bits) as f32;
+    let min_normal = 2f32.powi(-14);
+    let bits = if abs < min_normal {
+        // denormals, may round up into the smallest normal
+        (abs / min_normal * scale + 0.5) as u32
+    } else {
+        let exponent = abs.log2().floor() as i32;
+        let mantissa = ((abs / 2f32.powi(exponent) - 1.0) * scale + 0.5) as u32;
+        let biased = exponent + 15;
+        if biased >= 0x1F {
+            0x1F << mantissa_bits
+        } else {
+            // a mantissa overflow carries into the exponent
+            ((biased as u32) << mantissa_bits) + mantissa
+        }
+    };
+    sign | bits.min(0x1F << mantissa_bits)
+}
+
+/// Convert a half float into a single precision float.
+pub fn decode_half(raw: u16) -> f32 {
+    decode_float(raw as u32, 10, true)
+}
+
+/// Convert a single precision float into a half float, rounding to
+/// the nearest value and saturating to infinity.
+pub fn encode_half(value: f32) -> u16 {
+    encode_float(value, 10, true) as u16
+}
+
+fn unpack(raw: u32, bits: u8, channel: ChannelType) -> f32 {
+    match channel {
+        ChannelType::Int => sign_extend(raw, bits) as f32,
+        ChannelType::Uint => raw as f32,
+        ChannelType::Inorm => {
+            let max = get_mask(bits - 1) as f32;
+            (sign_extend(raw, bits) as f32 / max).max(-1.0)
+        },
+        ChannelType::Unorm | ChannelType::Srgb => raw as f32 / get_mask(bits) as f32,
+        ChannelType::Float => match bits {
+            32 => unsafe { mem::transmute::<u32, f32>(raw) },
+            16 => decode_float(raw, 10, true),
+            11 => decode_float(raw, 6, false),
+            10 => decode_float(raw, 5, false),
+            _ => {
+                error!("Unsupported {}-bit float channel", bits);
+                0.0
+            },
+        },
+    }
+}
+
+fn pack(value: f32, bits: u8, channel: ChannelType) -> u32 {
+    let mask = get_mask(bits);
+    match channel {
+        ChannelType::Int => {
+            let max = get_mask(bits - 1) as f64;
+            let v = (value as f64).round().max(-max - 1.0).min(max);
+            (v as i32 as u32) & (mask as u32)
+        },
+        ChannelType::Uint => (value as f64).round().max(0.0).min(mask as f64) as u32,
+        ChannelType::Inorm => {
+            let max = get_mask(bits - 1) as f32;
+            let v = (value.max(-1.0).min(1.0) * max).round();
+            (v as i32 as u32) & (mask as u32)
+        },
+        ChannelType::Unorm | ChannelType::Srgb =>
+            (value.max(0.0).min(1.0) * mask as f32 + 0.5) as u32,
+        ChannelType::Float => match bits {
+            32 => unsafe { mem::transmute::<f32, u32>(value) },
+            16 => encode_float(value, 10, true),
+            11 => encode_float(value, 6, false),
+            10 => encode_float(value, 5, false),
+            _ => {
+                error!("Unsupported {}-bit float channel", bits);
+                0
+            },
+        },
+    }
+}
+
+/// Convert an sRGB encoded value into the linear space.
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert a linear value into the sRGB encoding.
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Decode a texel into RGBA components. Missing components are filled
+/// with (0, 0, 0, 1), sRGB values are returned in the linear space and
+/// integer channels are converted to floats without normalization.
+pub fn decode(format: Format, texel: &[u8]) -> [f32; 4] {
+    let Format(surface, channel) = format;
+    let mut out = [0.0, 0.0, 0.0, 1.0];
+    for (value, ch) in out.iter_mut().zip(get_channels(surface).iter()) {
+        if let Some((offset, bits)) = *ch {
+            *value = unpack(get_bits(texel, offset, bits), bits, channel);
+        }
+    }
+    if channel == ChannelType::Srgb {
+        for c in out[..3].iter_mut() {
+            *c = srgb_to_linear(*c);
+        }
+    }
+    out
+}
+
+/// Encode RGBA components into a texel, the inverse of `decode`.
+/// The bits not covered by the channels of the format are left intact.
+pub fn encode(format: Format, value: [f32; 4], texel: &mut [u8]) {
+    let Format(surface, channel) = format;
+    let mut value = value;
+    if channel == ChannelType::Srgb {
+        for c in value[..3].iter_mut() {
+            *c = linear_to_srgb(*c);
+        }
+    }
+    for (&v, ch) in value.iter().zip(get_channels(surface).iter()) {
+        if let Some((offset, bits)) = *ch {
+            set_bits(texel, offset, bits, pack(v, bits, channel));
+        }
+    }
+}
+
+fn get_texel_size(format: Format) -> Result<usize, Error> {
+    if format.0.is_compressed() {
+        Err(Error::Compressed(format))
+    } else {
+        Ok(format.0.get_total_bits() as usize / 8)
+    }
+}
+
+/// Convert the texels of `src`, stored in `src_format`, into `dst`
+/// stored in `dst_format`. Both slices must hold the same number of texels.
+pub fn convert_into(src: &[u8], src_format: Format, dst: &mut [u8], dst_format: Format)
+                    -> Result<(), Error> {
+    let src_size = try!(get_texel_size(src_format));
+    let dst_size = try!(get_texel_size(dst_format));
+    if src.len() % src_size != 0 {
+        return Err(Error::Size(src.len()))
+    }
+    if dst.len() % dst_size != 0 {
+        return Err(Error::Size(dst.len()))
+    }
+    let (src_count, dst_count) = (src.len() / src_size, dst.len() / dst_size);
+    if src_count != dst_count {
+        return Err(Error::TexelCountMismatch {
+            src: src_count,
+            dst: dst_count,
+        })
+    }
+    if src_format == dst_format {
+        dst.copy_from_slice(src);
+        return Ok(())
+    }
+    for (s, d) in src.chunks(src_size).zip(dst.chunks_mut(dst_size)) {
+        encode(dst_format, decode(src_format, s), d);
+    }
+    Ok(())
+}
+
+/// Convert the texels of `src` from `src_format` into a new vector
+/// of `dst_format` texels.
+pub fn convert(src: &[u8], src_format: Format, dst_format: Format) -> Result<Vec<u8>, Error> {
+    let src_size = try!(get_texel_size(src_format));
+    let dst_size = try!(get_texel_size(dst_format));
+    let mut dst = vec![0; src.len() / src_size * dst_size];
+    try!(convert_into(src, src_format, &mut dst, dst_format));
+    Ok(dst)
+}
+
+#[cfg(test)]
+mod tests {
+    use format::{ChannelType, Format, SurfaceType};
+    use super::{convert, decode, decode_half, encode, encode_half, Error};
+
+    #[test]
+    fn round_trip() {
+        let formats = [
+            Format(SurfaceType::R8_G8_B8_A8, ChannelType::Unorm),
+            Format(SurfaceType::R8_G8_B8_A8, ChannelType::Srgb),
+            Format(SurfaceType::B8_G8_R8_A8, ChannelType::Unorm),
+            Format(SurfaceType::R5_G6_B5, ChannelType::Unorm),
+            Format(SurfaceType::R16_G16_B16_A16, ChannelType::Float),
+            Format(SurfaceType::R11_G11_B10, ChannelType::Float),
+            Format(SurfaceType::R32_G32_B32_A32, ChannelType::Float),
+        ];
+        for &format in formats.iter() {
+            let mut texel = [0u8; 16];
+            encode(format, [0.0, 1.0, 0.5, 1.0], &mut texel);
+            let value = decode(format, &texel);
+            for (a, b) in value.iter().zip([0.0, 1.0, 0.5, 1.0].iter()) {
+                assert!((a - b).abs() < 0.02, "{:?}: {:?}", format, value);
+            }
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let mut texel = [0u8; 4];
+        encode(Format(SurfaceType::B8_G8_R8_A8, ChannelType::Unorm), [1.0, 0.0, 0.0, 1.0], &mut texel);
+        assert_eq!(texel, [0, 0, 0xFF, 0xFF]);
+        let mut texel = [0u8; 2];
+        encode(Format(SurfaceType::R16, ChannelType::Float), [1.0, 0.0, 0.0, 0.0], &mut texel);
+        assert_eq!(texel, [0x00, 0x3C]);
+    }
+
+    #[test]
+    fn convert_formats() {
+        let srgb = Format(SurfaceType::R8_G8_B8_A8, ChannelType::Srgb);
+        let linear = Format(SurfaceType::R32_G32_B32_A32, ChannelType::Float);
+        let data = convert(&[0xFF, 0, 0xFF, 0x80], srgb, linear).unwrap();
+        let value = decode(linear, &data);
+        for (a, b) in value.iter().zip([1.0, 0.0, 1.0, 0.502].iter()) {
+            assert!((a - b).abs() < 0.001, "{:?}", value);
+        }
+        assert_eq!(convert(&data, linear, srgb), Ok(vec![0xFF, 0, 0xFF, 0x80]));
+
+        // red 1.0 and green 2.0, with blue 0.5 in the top bits
+        let packed = [0xC0, 0x03, 0x20, 0x70];
+        let value = decode(Format(SurfaceType::R11_G11_B10, ChannelType::Float), &packed);
+        assert_eq!(value, [1.0, 2.0, 0.5, 1.0]);
+
+        let rgb565 = Format(SurfaceType::R5_G6_B5, ChannelType::Unorm);
+        let rgba = Format(SurfaceType::R8_G8_B8_A8, ChannelType::Unorm);
+        assert_eq!(convert(&[0xFF, 0, 0xFF, 0xFF], rgba, rgb565), Ok(vec![0x1F, 0xF8]));
+
+        let bc1 = Format(SurfaceType::BC1_R8_G8_B8_A8, ChannelType::Unorm);
+        assert_eq!(convert(&[0; 8], bc1, rgba), Err(Error::Compressed(bc1)));
+        assert_eq!(convert(&[0; 3], rgb565, rgba), Err(Error::Size(3)));
+    }
+
+    #[test]
+    fn half_floats() {
+        assert_eq!(encode_half(1.0), 0x3C00);
+        assert_eq!(encode_half(-2.0), 0xC000);
+        assert_eq!(encode_half(1.0e6), 0x7C00);
+        assert_eq!(decode_half(0x3555), 0.333251953125);
+        assert_eq!(decode_half(0x0001), 2f32.powi(-24));
+    }
+}
@@ -14,14 +14,14 @@
 
 //! Universal format specification.
 //! Applicable to textures, views, and vertex buffers.
+//!
+//! The block-compressed surface types store blocks of texels, described
+//! by `SurfaceType::get_block_dimensions`. Their `DataType` is a single block.
 
-//TODO:
-//  DXT 1-5, BC7
-//  ETC2_RGB, // Use the EXT2 algorithm on 3 components.
-//  ETC2_SRGB, // Use the EXT2 algorithm on 4 components (RGBA) in the sRGB color space.
-//  ETC2_EAC_RGBA8, // Use the EXT2 EAC algorithm on 4 components.
 use memory::Pod;
 
+pub mod convert;
+
 macro_rules! impl_channel_type {
     { $($name:ident = $shader_type:ident [ $($imp_trait:ident),* ] ,)* } => {
         /// Type of a surface channel. This is how we interpret the
@@ -72,6 +72,7 @@ macro_rules! impl_formats {
         }
         impl SurfaceType {
             /// Return the total number of bits for this format.
+            /// For the compressed formats, this is the size of a block.
             pub fn get_total_bits(&self) -> u8 {
                 use std::mem::size_of;
                 match *self {
@@ -148,6 +149,66 @@ impl_formats! {
     D24_S8          : Vec1<Unorm, Uint> = u32 {8} [TextureSurface, DepthSurface, StencilSurface],
     D32             : Vec1<Float> = f32 {0} [TextureSurface, DepthSurface],
     //D32_S8          : Vec1<Unorm, Float, Uint> = (f32, u32) {32} [TextureSurface, DepthSurface, StencilSurface],
+    BC1_R8_G8_B8_A8 : Vec4<Unorm, Srgb> = [u8; 8] {1} [TextureSurface],
+    BC2_R8_G8_B8_A8 : Vec4<Unorm, Srgb> = [u8; 16] {4} [TextureSurface],
+    BC3_R8_G8_B8_A8 : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    BC4_R8          : Vec1<Inorm, Unorm> = [u8; 8] {0} [TextureSurface],
+    BC5_R8_G8       : Vec2<Inorm, Unorm> = [u8; 16] {0} [TextureSurface],
+    BC6H_R16_G16_B16_UF : Vec3<Float> = [u8; 16] {0} [TextureSurface],
+    BC6H_R16_G16_B16_SF : Vec3<Float> = [u8; 16] {0} [TextureSurface],
+    BC7_R8_G8_B8_A8 : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ETC2_R8_G8_B8   : Vec3<Unorm, Srgb> = [u8; 8] {0} [TextureSurface],
+    ETC2_R8_G8_B8_A1 : Vec4<Unorm, Srgb> = [u8; 8] {1} [TextureSurface],
+    ETC2_R8_G8_B8_A8 : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    EAC_R11         : Vec1<Inorm, Unorm> = [u8; 8] {0} [TextureSurface],
+    EAC_R11_G11     : Vec2<Inorm, Unorm> = [u8; 16] {0} [TextureSurface],
+    ASTC_4x4        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_5x4        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_5x5        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_6x5        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_6x6        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_8x5        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_8x6        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_8x8        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_10x5       : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_10x6       : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_10x8       : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_10x10      : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_12x10      : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_12x12      : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+}
+
+impl SurfaceType {
+    /// Return the width and height of the blocks of texels,
+    /// `(1, 1)` for the uncompressed formats.
+    pub fn get_block_dimensions(&self) -> (u8, u8) {
+        use self::SurfaceType::*;
+        match *self {
+            BC1_R8_G8_B8_A8 | BC2_R8_G8_B8_A8 | BC3_R8_G8_B8_A8 | BC4_R8 | BC5_R8_G8 |
+            BC6H_R16_G16_B16_UF | BC6H_R16_G16_B16_SF | BC7_R8_G8_B8_A8 |
+            ETC2_R8_G8_B8 | ETC2_R8_G8_B8_A1 | ETC2_R8_G8_B8_A8 | EAC_R11 | EAC_R11_G11 |
+            ASTC_4x4 => (4, 4),
+            ASTC_5x4 => (5, 4),
+            ASTC_5x5 => (5, 5),
+            ASTC_6x5 => (6, 5),
+            ASTC_6x6 => (6, 6),
+            ASTC_8x5 => (8, 5),
+            ASTC_8x6 => (8, 6),
+            ASTC_8x8 => (8, 8),
+            ASTC_10x5 => (10, 5),
+            ASTC_10x6 => (10, 6),
+            ASTC_10x8 => (10, 8),
+            ASTC_10x10 => (10, 10),
+            ASTC_12x10 => (12, 10),
+            ASTC_12x12 => (12, 12),
+            _ => (1, 1),
+        }
+    }
+
+    /// Check if this is a block-compressed format.
+    pub fn is_compressed(&self) -> bool {
+        self.get_block_dimensions() != (1, 1)
+    }
 }
 
 
@@ -19,7 +19,7 @@
 use std::{ops, cmp, hash};
 use std::marker::PhantomData;
 use std::sync::Arc;
-use {buffer, shade, texture, Resources};
+use {buffer, query, shade, texture, Resources};
 use memory::Typed;
 
 /// Untyped buffer handle
@@ -31,6 +31,13 @@ impl<R: Resources> ops::Deref for RawBuffer<R> {
     fn deref(&self) -> &Self::Target { &self.0 }
 }
 
+impl<R: Resources> RawBuffer<R> {
+    #[doc(hidden)]
+    pub fn get_ref_count(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+}
+
 /// Type-safe buffer handle
 #[derive(Clone, Debug)]
 pub struct Buffer<R: Resources, T>(RawBuffer<R>, PhantomData<T>);
@@ -83,6 +90,11 @@ impl<R: Resources> ops::Deref for Program<R> {
 #[derive(Clone, Debug, PartialEq)]
 pub struct RawPipelineState<R: Resources>(Arc<R::PipelineStateObject>, Program<R>);
 
+impl<R: Resources> RawPipelineState<R> {
+    #[doc(hidden)]
+    pub fn resource(&self) -> &R::PipelineStateObject { &self.0 }
+}
+
 /// Raw texture handle
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct RawTexture<R: Resources>(Arc<texture::Raw<R>>);
@@ -92,6 +104,13 @@ impl<R: Resources> ops::Deref for RawTexture<R> {
     fn deref(&self) -> &Self::Target { &self.0 }
 }
 
+impl<R: Resources> RawTexture<R> {
+    #[doc(hidden)]
+    pub fn get_ref_count(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+}
+
 /// Typed texture object
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 // TODO: manual Eq & Hash impl because PhantomData
@@ -231,6 +250,15 @@ impl<R: Resources> Sampler<R> {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Fence<R: Resources>(Arc<R::Fence>);
 
+/// Query Handle
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Query<R: Resources>(Arc<query::Raw<R>>);
+
+impl<R: Resources> ops::Deref for Query<R> {
+    type Target = query::Raw<R>;
+    fn deref(&self) -> &Self::Target { &self.0 }
+}
+
 /// Stores reference-counted resources used in a command buffer.
 /// Seals actual resource names behind the interface, automatically
 /// referencing them both by the Factory on resource creation
@@ -248,6 +276,17 @@ pub struct Manager<R: Resources> {
     dsvs:          Vec<Arc<R::DepthStencilView>>,
     samplers:      Vec<Arc<R::Sampler>>,
     fences:        Vec<Arc<R::Fence>>,
+    queries:       Vec<Arc<query::Raw<R>>>,
+    view_textures: Vec<ViewTexture<R>>,
+}
+
+/// Association of a referenced view with the texture it was made from.
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+pub enum ViewTexture<R: Resources> {
+    ShaderResource(R::ShaderResourceView, R::Texture),
+    RenderTarget(R::RenderTargetView, R::Texture),
+    DepthStencil(R::DepthStencilView, R::Texture),
 }
 
 /// A service trait to be used by the device implementation
@@ -269,6 +308,7 @@ pub trait Producer<R: Resources> {
     fn make_dsv(&mut self, R::DepthStencilView, &RawTexture<R>, texture::Dimensions) -> RawDepthStencilView<R>;
     fn make_sampler(&mut self, R::Sampler, texture::SamplerInfo) -> Sampler<R>;
     fn make_fence(&mut self, name: R::Fence) -> Fence<R>;
+    fn make_query(&mut self, R::Query, query::QueryType) -> Query<R>;
 
     /// Walk through all the handles, keep ones that are reference elsewhere
     /// and call the provided delete function (resource-specific) for others
@@ -284,7 +324,8 @@ pub trait Producer<R: Resources> {
         I: Fn(&mut T, &R::DepthStencilView),
         J: Fn(&mut T, &R::Sampler),
         K: Fn(&mut T, &R::Fence),
-    >(&mut self, &mut T, A, B, C, D, E, F, G, H, I, J, K);
+        L: Fn(&mut T, &query::Raw<R>),
+    >(&mut self, &mut T, A, B, C, D, E, F, G, H, I, J, K, L);
 }
 
 impl<R: Resources> Producer<R> for Manager<R> {
@@ -369,6 +410,12 @@ impl<R: Resources> Producer<R> for Manager<R> {
         Fence(r)
     }
 
+    fn make_query(&mut self, res: R::Query, ty: query::QueryType) -> Query<R> {
+        let r = Arc::new(query::Raw::new(res, ty));
+        self.queries.push(r.clone());
+        Query(r)
+    }
+
     fn clean_with<T,
         A: Fn(&mut T, &buffer::Raw<R>),
         B: Fn(&mut T, &R::Shader),
@@ -381,7 +428,8 @@ impl<R: Resources> Producer<R> for Manager<R> {
         I: Fn(&mut T, &R::DepthStencilView),
         J: Fn(&mut T, &R::Sampler),
         K: Fn(&mut T, &R::Fence),
-    >(&mut self, param: &mut T, fa: A, fb: B, fc: C, fd: D, fe: E, ff: F, fg: G, fh: H, fi: I, fj: J, fk: K) {
+        L: Fn(&mut T, &query::Raw<R>),
+    >(&mut self, param: &mut T, fa: A, fb: B, fc: C, fd: D, fe: E, ff: F, fg: G, fh: H, fi: I, fj: J, fk: K, fl: L) {
         fn clean_vec<X, Param, Fun>(param: &mut Param, vector: &mut Vec<Arc<X>>, fun: Fun)
             where Fun: Fn(&mut Param, &X)
         {
@@ -410,6 +458,7 @@ impl<R: Resources> Producer<R> for Manager<R> {
         clean_vec(param, &mut self.dsvs,          fi);
         clean_vec(param, &mut self.samplers,      fj);
         clean_vec(param, &mut self.fences,        fk);
+        clean_vec(param, &mut self.queries,       fl);
     }
 }
 
@@ -428,6 +477,8 @@ impl<R: Resources> Manager<R> {
             dsvs: Vec::new(),
             samplers: Vec::new(),
             fences: Vec::new(),
+            queries: Vec::new(),
+            view_textures: Vec::new(),
         }
     }
     /// Clear all references
@@ -443,6 +494,8 @@ impl<R: Resources> Manager<R> {
         self.dsvs.clear();
         self.samplers.clear();
         self.fences.clear();
+        self.queries.clear();
+        self.view_textures.clear();
     }
     /// Extend with all references of another handle manager
     pub fn extend(&mut self, other: &Manager<R>) {
@@ -457,6 +510,8 @@ impl<R: Resources> Manager<R> {
         self.dsvs     .extend(other.dsvs     .iter().map(|h| h.clone()));
         self.samplers .extend(other.samplers .iter().map(|h| h.clone()));
         self.fences   .extend(other.fences   .iter().map(|h| h.clone()));
+        self.queries  .extend(other.queries  .iter().map(|h| h.clone()));
+        self.view_textures.extend(other.view_textures.iter().cloned());
     }
     /// Count the total number of referenced resources
     pub fn count(&self) -> usize {
@@ -470,8 +525,26 @@ impl<R: Resources> Manager<R> {
         self.rtvs.len() +
         self.dsvs.len() +
         self.samplers.len() +
-        self.fences.len()
-    }
+        self.fences.len() +
+        self.queries.len()
+    }
+    /// Get the referenced buffers
+    #[doc(hidden)]
+    pub fn get_buffers(&self) -> &[Arc<buffer::Raw<R>>] {
+        &self.buffers
+    }
+    /// Get the textures of the referenced views, only tracked
+    /// with the `validate` feature
+    #[doc(hidden)]
+    pub fn get_view_textures(&self) -> &[ViewTexture<R>] {
+        &self.view_textures
+    }
+    #[cfg(feature = "validate")]
+    fn ref_view_texture(&mut self, vt: ViewTexture<R>) {
+        self.view_textures.push(vt);
+    }
+    #[cfg(not(feature = "validate"))]
+    fn ref_view_texture(&mut self, _: ViewTexture<R>) {}
     /// Reference a buffer
     pub fn ref_buffer<'a>(&mut self, handle: &'a RawBuffer<R>) -> &'a R::Buffer {
         self.buffers.push(handle.0.clone());
@@ -501,6 +574,9 @@ impl<R: Resources> Manager<R> {
     /// Reference a shader resource view
     pub fn ref_srv<'a>(&mut self, handle: &'a RawShaderResourceView<R>) -> &'a R::ShaderResourceView {
         self.srvs.push(handle.0.clone());
+        if let ViewSource::Texture(ref tex) = handle.1 {
+            self.ref_view_texture(ViewTexture::ShaderResource(*handle.0, tex.resource().clone()));
+        }
         &handle.0
     }
     /// Reference an unordered access view
@@ -512,12 +588,14 @@ impl<R: Resources> Manager<R> {
     pub fn ref_rtv<'a>(&mut self, handle: &'a RawRenderTargetView<R>) -> &'a R::RenderTargetView {
         self.rtvs.push(handle.0.clone());
         self.textures.push((handle.1).0.clone());
+        self.ref_view_texture(ViewTexture::RenderTarget(*handle.0, handle.1.resource().clone()));
         &handle.0
     }
     /// Reference a DSV
     pub fn ref_dsv<'a>(&mut self, handle: &'a RawDepthStencilView<R>) -> &'a R::DepthStencilView {
         self.dsvs.push(handle.0.clone());
         self.textures.push((handle.1).0.clone());
+        self.ref_view_texture(ViewTexture::DepthStencil((*handle.0).clone(), handle.1.resource().clone()));
         &handle.0
     }
     /// Reference a sampler
@@ -530,4 +608,9 @@ impl<R: Resources> Manager<R> {
         self.fences.push(fence.0.clone());
         &fence.0
     }
+    /// Reference a query
+    pub fn ref_query<'a>(&mut self, query: &'a Query<R>) -> &'a R::Query {
+        self.queries.push(query.0.clone());
+        query.resource()
+    }
 }
@@ -40,9 +40,13 @@ pub mod format;
 pub mod handle;
 pub mod mapping;
 pub mod memory;
+pub mod mipmap;
 pub mod pso;
+pub mod query;
 pub mod shade;
 pub mod texture;
+#[cfg(feature = "validate")]
+pub mod validate;
 
 /// Compile-time maximum number of vertex attributes.
 pub const MAX_VERTEX_ATTRIBUTES: usize = 16;
@@ -96,7 +100,7 @@ macro_rules! define_shaders {
     )+}
 }
 
-define_shaders!(VertexShader, HullShader, DomainShader, GeometryShader, PixelShader);
+define_shaders!(VertexShader, HullShader, DomainShader, GeometryShader, PixelShader, ComputeShader);
 
 /// A complete set of shaders to link a program.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -107,7 +111,8 @@ pub enum ShaderSet<R: Resources> {
     Geometry(VertexShader<R>, GeometryShader<R>, PixelShader<R>),
     /// Tessellated TODO: Tessellated, TessellatedGeometry, TransformFeedback
     Tessellated(VertexShader<R>, HullShader<R>, DomainShader<R>, PixelShader<R>),
-
+    /// Compute program: Cs
+    Compute(ComputeShader<R>),
 }
 
 impl<R: Resources> ShaderSet<R> {
@@ -117,6 +122,7 @@ impl<R: Resources> ShaderSet<R> {
             &ShaderSet::Simple(..) => shade::VERTEX | shade::PIXEL,
             &ShaderSet::Geometry(..) => shade::VERTEX | shade::GEOMETRY | shade::PIXEL,
             &ShaderSet::Tessellated(..) => shade::VERTEX | shade::HULL | shade::DOMAIN | shade::PIXEL,
+            &ShaderSet::Compute(..) => shade::COMPUTE,
         }
     }
 }
@@ -130,6 +136,7 @@ pub struct Capabilities {
     pub max_index_count: usize,
     pub max_texture_size: usize,
     pub max_patch_size: usize,
+    pub constant_buffer_alignment: usize,
 
     pub instance_base_supported: bool,
     pub instance_call_supported: bool,
@@ -137,9 +144,18 @@ pub struct Capabilities {
     pub vertex_base_supported: bool,
     pub srgb_color_supported: bool,
     pub constant_buffer_supported: bool,
+    pub constant_buffer_range_supported: bool,
     pub unordered_access_view_supported: bool,
     pub separate_blending_slots_supported: bool,
     pub copy_buffer_supported: bool,
+    pub copy_texture_supported: bool,
+    pub blit_texture_supported: bool,
+    pub resolve_texture_supported: bool,
+    pub compute_supported: bool,
+    pub draw_indirect_supported: bool,
+    pub bc_compression_supported: bool,
+    pub etc2_compression_supported: bool,
+    pub astc_compression_supported: bool,
 }
 
 /// Describes what geometric primitives are created from vertex data.
@@ -209,6 +225,7 @@ pub trait Resources:          Clone + Hash + Debug + Eq + PartialEq + Any {
     type DepthStencilView:    Clone + Hash + Debug + Eq + PartialEq + Any + Send + Sync;
     type Sampler:             Clone + Hash + Debug + Eq + PartialEq + Any + Send + Sync + Copy;
     type Fence:               Clone + Hash + Debug + Eq + PartialEq + Any + Send + Sync;
+    type Query:               Clone + Hash + Debug + Eq + PartialEq + Any + Send + Sync;
     type Mapping:             Hash + Debug + Eq + PartialEq + Any + Send + Sync + mapping::Gate<Self>;
 }
 
@@ -270,6 +287,13 @@ pub trait Device: Sized {
     /// Stalls the current thread until the fence is satisfied
     fn wait_fence(&mut self, &handle::Fence<Self::Resources>);
 
+    /// Checks if the fence is satisfied, without stalling the current thread.
+    /// Backends that can not query a fence wait for it instead.
+    fn is_fence_signaled(&mut self, fence: &handle::Fence<Self::Resources>) -> bool {
+        self.wait_fence(fence);
+        true
+    }
+
     /// Cleanup unused resources. This should be called between frames. 
     fn cleanup(&mut self);
 }
@@ -42,6 +42,9 @@ pub enum Error {
     InvalidAccess(memory::Access, memory::Usage),
     /// The requested mapping access overlaps with another.
     AccessOverlap,
+    /// The requested region is not one of the `num_regions` regions, given as
+    /// `(region, num_regions)`.
+    InvalidRegion(usize, usize),
 }
 
 impl fmt::Display for Error {
@@ -51,7 +54,9 @@ impl fmt::Display for Error {
             InvalidAccess(ref access, ref usage) => {
                 write!(f, "{}: access = {:?}, usage = {:?}", self.description(), access, usage)
             }
-            AccessOverlap => write!(f, "{}", self.description())
+            AccessOverlap => write!(f, "{}", self.description()),
+            InvalidRegion(region, num_regions) =>
+                write!(f, "{}: {} of {}", self.description(), region, num_regions),
         }
     }
 }
@@ -61,7 +66,8 @@ impl StdError for Error {
         use self::Error::*;
         match *self {
             InvalidAccess(..) => "The requested mapping access did not match the expected usage",
-            AccessOverlap => "The requested mapping access overlaps with another"
+            AccessOverlap => "The requested mapping access overlaps with another",
+            InvalidRegion(..) => "The requested mapping region is out of range",
         }
     }
 }
@@ -209,12 +215,40 @@ impl<'a, R: Resources, T: 'a + Copy> DerefMut for Writer<'a, R, T> {
     fn deref_mut(&mut self) -> &mut [T] { self.slice }
 }
 
+impl<'a, R: Resources, T: 'a + Copy> Writer<'a, R, T> {
+    /// Restrict the writer to a region of the buffer.
+    #[doc(hidden)]
+    pub fn into_region(self, region: usize, num_regions: usize) -> Result<Writer<'a, R, T>, Error> {
+        let (start, end) = try!(get_region(self.slice.len(), region, num_regions));
+        let Writer { slice, mapping } = self;
+        Ok(Writer {
+            slice: &mut slice[start .. end],
+            mapping: mapping,
+        })
+    }
+}
+
+/// Get the range of elements of a region, when a buffer of `len` elements
+/// is split into `num_regions` regions of the same size. The elements left
+/// after the last whole region are not part of any region.
+pub fn get_region(len: usize, region: usize, num_regions: usize) -> Result<(usize, usize), Error> {
+    if region >= num_regions {
+        return Err(Error::InvalidRegion(region, num_regions))
+    }
+    let size = len / num_regions;
+    Ok((region * size, (region + 1) * size))
+}
+
 #[derive(Debug, PartialEq, Eq, Hash)]
 #[doc(hidden)]
 /// A service struct that can be used by backends to track the mapping status
 pub struct Status<R: Resources> {
     cpu_wrote: bool,
     gpu_access: Option<handle::Fence<R>>,
+    /// Fences of the GPU accesses to each region, for the region writes
+    region_access: Vec<Option<handle::Fence<R>>>,
+    /// The region the GPU accesses are attributed to
+    current_region: Option<usize>,
 }
 
 #[doc(hidden)]
@@ -223,24 +257,59 @@ impl<R: Resources> Status<R> {
         Status {
             cpu_wrote: false,
             gpu_access: None,
+            region_access: Vec::new(),
+            current_region: None,
         }
     }
 
-    pub fn cpu_access<F>(&mut self, wait_fence: F)
-        where F: FnOnce(handle::Fence<R>)
+    pub fn cpu_access<F>(&mut self, mut wait_fence: F)
+        where F: FnMut(handle::Fence<R>)
     {
-        self.gpu_access.take().map(wait_fence);
+        // the whole buffer is accessed, so every region has to be finished
+        for fence in self.region_access.drain(..) {
+            if let Some(fence) = fence {
+                wait_fence(fence);
+            }
+        }
+        self.current_region = None;
+        if let Some(fence) = self.gpu_access.take() {
+            wait_fence(fence);
+        }
     }
 
     pub fn cpu_write_access<F>(&mut self, wait_fence: F)
-        where F: FnOnce(handle::Fence<R>)
+        where F: FnMut(handle::Fence<R>)
     {
         self.cpu_access(wait_fence);
         self.cpu_wrote = true;
     }
 
+    /// Write to one of the `num_regions` regions of the buffer, only waiting
+    /// for the GPU accesses to this region. The following GPU accesses are
+    /// assumed to only read this region, until another one is written.
+    pub fn cpu_write_region_access<F>(&mut self, region: usize, num_regions: usize, mut wait_fence: F)
+        where F: FnMut(handle::Fence<R>)
+    {
+        if self.region_access.len() != num_regions {
+            // the regions have changed, so the previous accesses can be anywhere
+            self.cpu_access(&mut wait_fence);
+            self.region_access = (0 .. num_regions).map(|_| None).collect();
+        }
+        if let Some(fence) = self.gpu_access.take() {
+            wait_fence(fence);
+        }
+        if let Some(fence) = self.region_access[region].take() {
+            wait_fence(fence);
+        }
+        self.current_region = Some(region);
+        self.cpu_wrote = true;
+    }
+
     pub fn gpu_access(&mut self, fence: handle::Fence<R>) {
-        self.gpu_access = Some(fence);
+        match self.current_region {
+            Some(region) => self.region_access[region] = Some(fence),
+            None => self.gpu_access = Some(fence),
+        }
     }
 
     pub fn ensure_flushed<F>(&mut self, flush: F)
@@ -0,0 +1,196 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! CPU generation of mipmap chains, for the devices lacking
+//! `command::Buffer::generate_mipmap` support, or when the levels
+//! need to be known before the texture is created.
+//!
+//! The texels are decoded with `format::convert`, so the levels of `Srgb`
+//! textures are averaged in the linear space. Each slice of an array and
+//! each face of a cube is reduced on its own, while the depth of a volume
+//! texture is reduced along with its width and height.
+
+use std::cmp::{max, min};
+use std::f32::consts::PI;
+use format::Format;
+use format::convert::{decode, encode};
+use texture::{AaMode, CreationError, Kind, MipmapFilter};
+
+/// Radius of the Kaiser filter, in texels of the reduced level.
+const KAISER_RADIUS: f32 = 3.0;
+/// Shape parameter of the Kaiser window.
+const KAISER_ALPHA: f32 = 4.0;
+
+type Texel = [f32; 4];
+
+/// Source texels, with their weights, contributing to each texel
+/// of the reduced axis.
+type Weights = Vec<Vec<(usize, f32)>>;
+
+fn get_box_weights(src: usize, dst: usize) -> Weights {
+    let scale = src as f32 / dst as f32;
+    (0 .. dst).map(|x| {
+        let (start, end) = (x as f32 * scale, (x + 1) as f32 * scale);
+        (start.floor() as usize .. min(end.ceil() as usize, src))
+            .map(|i| (i, ((i + 1) as f32).min(end) - (i as f32).max(start)))
+            .filter(|&(_, w)| w > 0.0)
+            .collect()
+    }).collect()
+}
+
+/// Modified Bessel function of the first kind, of order zero.
+fn bessel_i0(x: f32) -> f32 {
+    let q = x * x / 4.0;
+    let (mut sum, mut term) = (1.0, 1.0);
+    for k in 1 .. 32 {
+        term *= q / (k * k) as f32;
+        sum += term;
+        if term < sum * 1e-7 {
+            break
+        }
+    }
+    sum
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+fn get_kaiser_weights(src: usize, dst: usize) -> Weights {
+    let scale = src as f32 / dst as f32;
+    let norm = bessel_i0(KAISER_ALPHA);
+    (0 .. dst).map(|x| {
+        let center = (x as f32 + 0.5) * scale;
+        let first = (center - KAISER_RADIUS * scale).floor() as isize;
+        let last = (center + KAISER_RADIUS * scale).ceil() as isize;
+        (first .. last).filter_map(|i| {
+            let t = (i as f32 + 0.5 - center) / scale;
+            let r = t / KAISER_RADIUS;
+            if r.abs() >= 1.0 {
+                return None
+            }
+            let window = bessel_i0(KAISER_ALPHA * (1.0 - r * r).sqrt()) / norm;
+            // texels outside of the image are clamped to its edge
+            let index = max(0, min(i, src as isize - 1)) as usize;
+            Some((index, sinc(t) * window))
+        }).collect()
+    }).collect()
+}
+
+fn get_weights(filter: MipmapFilter, src: usize, dst: usize) -> Weights {
+    let mut weights: Weights = if src == dst {
+        (0 .. dst).map(|x| vec![(x, 1.0)]).collect()
+    } else {
+        match filter {
+            MipmapFilter::Box => get_box_weights(src, dst),
+            MipmapFilter::Kaiser => get_kaiser_weights(src, dst),
+        }
+    };
+    for texel_weights in weights.iter_mut() {
+        let sum = texel_weights.iter().map(|&(_, w)| w).sum::<f32>();
+        for weight in texel_weights.iter_mut() {
+            weight.1 /= sum;
+        }
+    }
+    weights
+}
+
+/// Reduce an image of the given size along one of its axes.
+fn reduce(src: &[Texel], size: [usize; 3], axis: usize, weights: &Weights) -> Vec<Texel> {
+    let mut out_size = size;
+    out_size[axis] = weights.len();
+    let stride = [1, size[0], size[0] * size[1]][axis];
+    let mut out = Vec::with_capacity(out_size[0] * out_size[1] * out_size[2]);
+    for z in 0 .. out_size[2] {
+        for y in 0 .. out_size[1] {
+            for x in 0 .. out_size[0] {
+                let mut pos = [x, y, z];
+                let texel_weights = &weights[pos[axis]];
+                pos[axis] = 0;
+                let base = pos[0] + size[0] * (pos[1] + size[1] * pos[2]);
+                let mut texel = [0.0; 4];
+                for &(i, w) in texel_weights.iter() {
+                    let s = &src[base + i * stride];
+                    for c in 0 .. 4 {
+                        texel[c] += s[c] * w;
+                    }
+                }
+                out.push(texel);
+            }
+        }
+    }
+    out
+}
+
+/// Generate the full mipmap chain of a texture from its top level.
+///
+/// `data` holds the top level of each array slice and cube face, in the
+/// order expected by `Factory::create_texture_raw`. The result holds all
+/// the levels of each of them, starting with a copy of the top one.
+pub fn generate(kind: Kind, format: Format, filter: MipmapFilter, data: &[&[u8]])
+                -> Result<Vec<Vec<u8>>, CreationError> {
+    let Format(surface, channel) = format;
+    if surface.is_compressed() {
+        return Err(CreationError::Format(surface, Some(channel)))
+    }
+    let (w, h, d, aa) = kind.get_dimensions();
+    if aa != AaMode::Single {
+        return Err(CreationError::Samples(aa))
+    }
+    let num_faces = if kind.is_cube() { 6 } else { 1 };
+    let num_images = kind.get_num_slices().unwrap_or(1) as usize * num_faces;
+    if data.len() != num_images {
+        error!("Mipmap generation expects {} top level images, given {} instead",
+            num_images, data.len());
+        return Err(CreationError::Data(data.len()))
+    }
+    let depth = match kind {
+        Kind::D3(..) => d,
+        _ => 1,
+    };
+    let top = [max(w, 1) as usize, max(h, 1) as usize, max(depth, 1) as usize];
+    let texel_size = surface.get_total_bits() as usize / 8;
+    let num_levels = kind.get_num_levels();
+
+    let mut chain = Vec::with_capacity(num_images * num_levels as usize);
+    for chunk in data.iter() {
+        if chunk.len() != top[0] * top[1] * top[2] * texel_size {
+            return Err(CreationError::Data(chunk.len()))
+        }
+        chain.push(chunk.to_vec());
+        // each level is reduced from the unquantized previous one
+        let mut texels: Vec<Texel> = chunk.chunks(texel_size)
+                                          .map(|t| decode(format, t))
+                                          .collect();
+        let mut size = top;
+        for _ in 1 .. num_levels {
+            for axis in 0 .. 3 {
+                let reduced = max(1, size[axis] / 2);
+                let weights = get_weights(filter, size[axis], reduced);
+                texels = reduce(&texels, size, axis, &weights);
+                size[axis] = reduced;
+            }
+            let mut level = vec![0; texels.len() * texel_size];
+            for (texel, out) in texels.iter().zip(level.chunks_mut(texel_size)) {
+                encode(format, *texel, out);
+            }
+            chain.push(level);
+        }
+    }
+    Ok(chain)
+}
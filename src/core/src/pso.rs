@@ -221,9 +221,14 @@ impl<R: Resources> VertexBufferSet<R> {
     }
 }
 
+/// A range of a buffer: the offset and the size in bytes.
+pub type BufferRange = (usize, usize);
+
 /// A constant buffer run-time parameter for PSO.
+/// The range is only bound when it's given, otherwise the whole buffer is.
 #[derive(Copy, Clone, Debug)]
-pub struct ConstantBufferParam<R: Resources>(pub R::Buffer, pub Usage, pub ConstantBufferSlot);
+pub struct ConstantBufferParam<R: Resources>(pub R::Buffer, pub Usage, pub ConstantBufferSlot,
+                                             pub Option<BufferRange>);
 
 /// A shader resource view (SRV) run-time parameter for PSO.
 #[derive(Copy, Clone, Debug)]
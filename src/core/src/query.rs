@@ -0,0 +1,110 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! GPU queries
+
+use std::error::Error;
+use std::{fmt, cmp, hash};
+use std::sync::atomic::{AtomicBool, Ordering};
+use Resources;
+
+/// Type of the information gathered by a query.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[repr(u8)]
+pub enum QueryType {
+    /// Number of samples that passed the depth and stencil tests
+    Occlusion,
+    /// Whether any sample passed the depth and stencil tests
+    AnySamplesPassed,
+    /// GPU time at which all the preceding commands completed, in nanoseconds
+    Timestamp,
+}
+
+/// Untyped query
+#[derive(Debug)]
+pub struct Raw<R: Resources> {
+    resource: R::Query,
+    ty: QueryType,
+    submitted: AtomicBool,
+}
+
+impl<R: Resources> Raw<R> {
+    #[doc(hidden)]
+    pub fn new(resource: R::Query, ty: QueryType) -> Self {
+        Raw {
+            resource: resource,
+            ty: ty,
+            submitted: AtomicBool::new(false),
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn resource(&self) -> &R::Query { &self.resource }
+
+    /// Get the query type
+    pub fn get_type(&self) -> QueryType { self.ty }
+
+    /// Has the query been submitted to the device, so that its result
+    /// may eventually become available ?
+    pub fn is_submitted(&self) -> bool {
+        self.submitted.load(Ordering::Acquire)
+    }
+
+    #[doc(hidden)]
+    pub fn mark_submitted(&self) {
+        self.submitted.store(true, Ordering::Release);
+    }
+
+    #[doc(hidden)]
+    pub fn mark_encoded(&self) {
+        self.submitted.store(false, Ordering::Release);
+    }
+}
+
+impl<R: Resources + cmp::PartialEq> cmp::PartialEq for Raw<R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.resource().eq(other.resource())
+    }
+}
+
+impl<R: Resources + cmp::Eq> cmp::Eq for Raw<R> {}
+
+impl<R: Resources + hash::Hash> hash::Hash for Raw<R> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.resource().hash(state);
+    }
+}
+
+/// Error creating a query.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CreationError {
+    /// The query type is not supported by the device.
+    UnsupportedType(QueryType),
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CreationError::UnsupportedType(ty) => write!(f, "{}: {:?}", self.description(), ty),
+        }
+    }
+}
+
+impl Error for CreationError {
+    fn description(&self) -> &str {
+        match *self {
+            CreationError::UnsupportedType(_) => "Query type is not supported",
+        }
+    }
+}
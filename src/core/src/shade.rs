@@ -115,9 +115,10 @@ pub enum Stage {
     Domain,
     Geometry,
     Pixel,
+    Compute,
 }
 
-/// A constant static array of all shader stages.
+/// A constant static array of all graphics shader stages.
 pub const STAGES: [Stage; 5] = [Stage::Vertex, Stage::Hull, Stage::Domain, Stage::Geometry, Stage::Pixel];
 
 // Describing program data
@@ -266,6 +267,8 @@ bitflags!(
         const HULL    = 0x8,
         /// Used by the pixel shader
         const DOMAIN    = 0x16,
+        /// Used by the compute shader
+        const COMPUTE  = 0x20,
 
     }
 );
@@ -278,6 +281,7 @@ impl From<Stage> for Usage {
             Stage::Pixel => PIXEL,
             Stage::Hull => HULL,
             Stage::Domain => DOMAIN,
+            Stage::Compute => COMPUTE,
         }
     }
 }
@@ -233,6 +233,26 @@ pub const CUBE_FACES: [CubeFace; 6] = [
     CubeFace::PosZ, CubeFace::NegZ,
 ];
 
+/// Filter used to reduce a mipmap level into the next one
+/// when generating mipmaps on the CPU.
+#[derive(Eq, Ord, PartialEq, PartialOrd, Hash, Copy, Clone, Debug)]
+pub enum MipmapFilter {
+    /// Average the texels covered by each texel of the next level.
+    Box,
+    /// Kaiser-windowed sinc, keeping the levels sharper than `Box`.
+    Kaiser,
+}
+
+/// Specifies how the mipmap levels of an immutable texture are obtained.
+#[derive(Eq, Ord, PartialEq, PartialOrd, Hash, Copy, Clone, Debug)]
+pub enum Mipmap {
+    /// All the levels are provided along with the data.
+    Provided,
+    /// Only the top level is provided, and the full chain is generated
+    /// on the CPU with the given filter. See `mipmap::generate`.
+    Generated(MipmapFilter),
+}
+
 /// Specifies the kind of a texture storage to be allocated.
 #[derive(Eq, Ord, PartialEq, PartialOrd, Hash, Copy, Clone, Debug)]
 pub enum Kind {
@@ -280,9 +300,12 @@ impl Kind {
     pub fn get_num_levels(&self) -> Level {
         use std::cmp::max;
         let (w, h, d, aa) = self.get_dimensions();
-        let dominant = max(max(w, h), d);
+        let dominant = match *self {
+            Kind::D3(..) => max(max(w, h), d),
+            _ => max(w, h), // layers are not affected by mipmapping
+        };
         if aa == AaMode::Single {
-            (1..).find(|level| dominant>>level <= 1).unwrap()
+            (1..).find(|level| dominant>>level == 0).unwrap()
         }else {
             1 // anti-aliased textures can't have mipmaps
         }
@@ -338,6 +361,17 @@ impl<F> ImageInfoCommon<F> {
         max(1, self.depth) as usize
     }
 
+    /// Get the number of blocks of texels of the given surface type covering the image.
+    /// This is the texel count for the uncompressed formats.
+    pub fn get_block_count(&self, surface: format::SurfaceType) -> usize {
+        use std::cmp::max;
+        let (bw, bh) = surface.get_block_dimensions();
+        let blocks = |size: Size, block: u8| (max(1, size) as usize + block as usize - 1) / block as usize;
+        blocks(self.width, bw) *
+        blocks(self.height, bh) *
+        max(1, self.depth) as usize
+    }
+
     /// Convert into a differently typed format.
     pub fn convert<T>(&self, new_format: T) -> ImageInfoCommon<T> {
         ImageInfoCommon {
@@ -364,8 +398,19 @@ impl<F> ImageInfoCommon<F> {
 impl RawImageInfo {
     /// Get the total number of bytes.
     pub fn get_byte_count(&self) -> usize {
-        let texel_bytes = self.format.0.get_total_bits() / 8;
-        self.get_texel_count() * (texel_bytes as usize)
+        let block_bytes = self.format.0.get_total_bits() as usize / 8;
+        self.get_block_count(self.format.0) * block_bytes
+    }
+
+    /// Check if the region is made of whole blocks of a compressed format.
+    /// The blocks at the right and bottom edges of the mipmap level of the
+    /// given dimensions may be partially covered by the level.
+    pub fn is_block_aligned(&self, (w, h, _, _): Dimensions) -> bool {
+        let (bw, bh) = self.format.0.get_block_dimensions();
+        let (bw, bh) = (bw as Size, bh as Size);
+        let (x_end, y_end) = (self.xoffset + self.width, self.yoffset + self.height);
+        self.xoffset % bw == 0 && (x_end % bw == 0 || x_end == w) &&
+        self.yoffset % bh == 0 && (y_end % bh == 0 || y_end == h)
     }
 }
 
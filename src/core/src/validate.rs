@@ -0,0 +1,575 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation layer, checking the command stream before it reaches the backend.
+//!
+//! Wrap the device into a `ValidatingDevice` and the command buffers into
+//! `ValidatingCommandBuffer`s. The commands are recorded on the side and checked
+//! upon submission, when the information about the used resources is known.
+//! The errors are logged together with the offending command, and optionally
+//! turned into a panic. Validation is not free, so it's best reserved for
+//! the debug builds: it is only compiled with the `validate` feature, which
+//! also makes the handle managers track the textures behind the views.
+
+use std::collections::HashMap;
+use std::cmp::max;
+use {Capabilities, Device, IndexType, Resources, SubmissionResult, VertexCount};
+use {MAX_RESOURCE_VIEWS, ResourceViewSlot};
+use {buffer, handle, pso, shade, state, target, texture};
+use command::{self, AccessInfo};
+use format::Format;
+use handle::ViewTexture;
+
+
+/// A command as seen by the validation layer. Only the parts
+/// relevant to the validation are recorded.
+#[allow(missing_docs)]
+#[derive(Clone, Debug)]
+pub enum Command<R: Resources> {
+    BindPipelineState,
+    BindConstantBuffers(Vec<pso::ConstantBufferParam<R>>),
+    BindResourceViews(Vec<pso::ResourceViewParam<R>>),
+    BindPixelTargets(pso::PixelTargetSet<R>),
+    BindIndex(R::Buffer, IndexType),
+    CopyBuffer(R::Buffer, R::Buffer, usize, usize, usize),
+    CopyBufferToTexture(R::Buffer, usize, texture::Kind, texture::RawImageInfo),
+    CopyTextureToBuffer(texture::Kind, texture::RawImageInfo, R::Buffer, usize),
+    CopyTextureToTexture(texture::Kind, texture::RawImageInfo, texture::Kind, texture::RawImageInfo),
+    BlitTexture(texture::Kind, texture::RawImageInfo, texture::Kind, texture::RawImageInfo),
+    UpdateBuffer(R::Buffer, usize, usize),
+    UpdateTexture(texture::Kind, usize, texture::RawImageInfo),
+    Draw(VertexCount, VertexCount, Option<command::InstanceParams>),
+    DrawIndexed(VertexCount, VertexCount, VertexCount, Option<command::InstanceParams>),
+    DrawIndirect(R::Buffer, usize, usize, bool),
+}
+
+/// Command buffer wrapper, recording the commands for the `ValidatingDevice`
+/// to check before passing them to the wrapped command buffer.
+pub struct ValidatingCommandBuffer<R: Resources, C> {
+    inner: C,
+    commands: Vec<Command<R>>,
+}
+
+impl<R: Resources, C: command::Buffer<R>> ValidatingCommandBuffer<R, C> {
+    /// Wrap a command buffer of the backend.
+    pub fn new(inner: C) -> ValidatingCommandBuffer<R, C> {
+        ValidatingCommandBuffer {
+            inner: inner,
+            commands: Vec::new(),
+        }
+    }
+
+    /// Get the wrapped command buffer.
+    pub fn get_inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<R: Resources, C: command::Buffer<R>> command::Buffer<R> for ValidatingCommandBuffer<R, C> {
+    fn reset(&mut self) {
+        self.commands.clear();
+        self.inner.reset();
+    }
+
+    fn bind_pipeline_state(&mut self, pso: R::PipelineStateObject) {
+        self.commands.push(Command::BindPipelineState);
+        self.inner.bind_pipeline_state(pso);
+    }
+
+    fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<R>) {
+        self.inner.bind_vertex_buffers(vbs);
+    }
+
+    fn bind_constant_buffers(&mut self, cbs: &[pso::ConstantBufferParam<R>]) {
+        if cbs.iter().any(|param| param.3.is_some()) {
+            self.commands.push(Command::BindConstantBuffers(cbs.to_vec()));
+        }
+        self.inner.bind_constant_buffers(cbs);
+    }
+
+    fn bind_global_constant(&mut self, loc: shade::Location, value: shade::UniformValue) {
+        self.inner.bind_global_constant(loc, value);
+    }
+
+    fn bind_resource_views(&mut self, srvs: &[pso::ResourceViewParam<R>]) {
+        self.commands.push(Command::BindResourceViews(srvs.to_vec()));
+        self.inner.bind_resource_views(srvs);
+    }
+
+    fn bind_unordered_views(&mut self, uavs: &[pso::UnorderedViewParam<R>]) {
+        self.inner.bind_unordered_views(uavs);
+    }
+
+    fn bind_samplers(&mut self, ss: &[pso::SamplerParam<R>]) {
+        self.inner.bind_samplers(ss);
+    }
+
+    fn bind_pixel_targets(&mut self, pts: pso::PixelTargetSet<R>) {
+        self.commands.push(Command::BindPixelTargets(pts));
+        self.inner.bind_pixel_targets(pts);
+    }
+
+    fn bind_index(&mut self, buf: R::Buffer, itype: IndexType) {
+        self.commands.push(Command::BindIndex(buf, itype));
+        self.inner.bind_index(buf, itype);
+    }
+
+    fn set_scissor(&mut self, rect: target::Rect) {
+        self.inner.set_scissor(rect);
+    }
+
+    fn set_ref_values(&mut self, rv: state::RefValues) {
+        self.inner.set_ref_values(rv);
+    }
+
+    fn copy_buffer(&mut self, src: R::Buffer, dst: R::Buffer,
+                   src_offset_bytes: usize, dst_offset_bytes: usize,
+                   size_bytes: usize) {
+        self.commands.push(Command::CopyBuffer(src, dst, src_offset_bytes,
+                                               dst_offset_bytes, size_bytes));
+        self.inner.copy_buffer(src, dst, src_offset_bytes, dst_offset_bytes, size_bytes);
+    }
+
+    fn copy_buffer_to_texture(&mut self,
+                              src: R::Buffer, src_offset_bytes: usize,
+                              dst: R::Texture, kind: texture::Kind,
+                              face: Option<texture::CubeFace>, img: texture::RawImageInfo) {
+        self.commands.push(Command::CopyBufferToTexture(src, src_offset_bytes, kind, img));
+        self.inner.copy_buffer_to_texture(src, src_offset_bytes, dst, kind, face, img);
+    }
+
+    fn copy_texture_to_buffer(&mut self,
+                              src: R::Texture, kind: texture::Kind,
+                              face: Option<texture::CubeFace>, img: texture::RawImageInfo,
+                              dst: R::Buffer, dst_offset_bytes: usize) {
+        self.commands.push(Command::CopyTextureToBuffer(kind, img, dst, dst_offset_bytes));
+        self.inner.copy_texture_to_buffer(src, kind, face, img, dst, dst_offset_bytes);
+    }
+
+    fn copy_texture_to_texture(&mut self,
+                               src: R::Texture, src_kind: texture::Kind,
+                               src_face: Option<texture::CubeFace>, src_img: texture::RawImageInfo,
+                               dst: R::Texture, dst_kind: texture::Kind,
+                               dst_face: Option<texture::CubeFace>, dst_img: texture::RawImageInfo) {
+        self.commands.push(Command::CopyTextureToTexture(src_kind, src_img, dst_kind, dst_img));
+        self.inner.copy_texture_to_texture(src, src_kind, src_face, src_img,
+                                           dst, dst_kind, dst_face, dst_img);
+    }
+
+    fn blit_texture(&mut self,
+                    src: R::Texture, src_kind: texture::Kind,
+                    src_face: Option<texture::CubeFace>, src_img: texture::RawImageInfo,
+                    dst: R::Texture, dst_kind: texture::Kind,
+                    dst_face: Option<texture::CubeFace>, dst_img: texture::RawImageInfo,
+                    mirror: target::Mirror, filter: command::BlitFilter) {
+        self.commands.push(Command::BlitTexture(src_kind, src_img, dst_kind, dst_img));
+        self.inner.blit_texture(src, src_kind, src_face, src_img,
+                                dst, dst_kind, dst_face, dst_img,
+                                mirror, filter);
+    }
+
+    fn resolve_texture(&mut self, src: R::Texture, src_kind: texture::Kind,
+                       dst: R::Texture, dst_kind: texture::Kind, format: Format) {
+        self.inner.resolve_texture(src, src_kind, dst, dst_kind, format);
+    }
+
+    fn update_buffer(&mut self, buf: R::Buffer, data: &[u8], offset_bytes: usize) {
+        self.commands.push(Command::UpdateBuffer(buf, data.len(), offset_bytes));
+        self.inner.update_buffer(buf, data, offset_bytes);
+    }
+
+    fn update_texture(&mut self, tex: R::Texture, kind: texture::Kind, face: Option<texture::CubeFace>,
+                      data: &[u8], img: texture::RawImageInfo) {
+        self.commands.push(Command::UpdateTexture(kind, data.len(), img));
+        self.inner.update_texture(tex, kind, face, data, img);
+    }
+
+    fn generate_mipmap(&mut self, srv: R::ShaderResourceView) {
+        self.inner.generate_mipmap(srv);
+    }
+
+    fn clear_color(&mut self, target: R::RenderTargetView, value: command::ClearColor) {
+        self.inner.clear_color(target, value);
+    }
+
+    fn clear_depth_stencil(&mut self, target: R::DepthStencilView, depth: Option<target::Depth>,
+                           stencil: Option<target::Stencil>) {
+        self.inner.clear_depth_stencil(target, depth, stencil);
+    }
+
+    fn call_draw(&mut self, start: VertexCount, count: VertexCount,
+                 instances: Option<command::InstanceParams>) {
+        self.commands.push(Command::Draw(start, count, instances));
+        self.inner.call_draw(start, count, instances);
+    }
+
+    fn call_draw_indexed(&mut self, start: VertexCount, count: VertexCount,
+                         base: VertexCount, instances: Option<command::InstanceParams>) {
+        self.commands.push(Command::DrawIndexed(start, count, base, instances));
+        self.inner.call_draw_indexed(start, count, base, instances);
+    }
+
+    fn call_draw_indirect(&mut self, buf: R::Buffer, offset_bytes: usize, count: usize) {
+        self.commands.push(Command::DrawIndirect(buf, offset_bytes, count, false));
+        self.inner.call_draw_indirect(buf, offset_bytes, count);
+    }
+
+    fn call_draw_indexed_indirect(&mut self, buf: R::Buffer, offset_bytes: usize, count: usize) {
+        self.commands.push(Command::DrawIndirect(buf, offset_bytes, count, true));
+        self.inner.call_draw_indexed_indirect(buf, offset_bytes, count);
+    }
+
+    fn call_dispatch(&mut self, count: [u32; 3]) {
+        self.inner.call_dispatch(count);
+    }
+
+    fn begin_query(&mut self, query: R::Query) {
+        self.inner.begin_query(query);
+    }
+
+    fn end_query(&mut self, query: R::Query) {
+        self.inner.end_query(query);
+    }
+
+    fn write_timestamp(&mut self, query: R::Query) {
+        self.inner.write_timestamp(query);
+    }
+
+    fn begin_conditional(&mut self, query: R::Query) {
+        self.inner.begin_conditional(query);
+    }
+
+    fn end_conditional(&mut self) {
+        self.inner.end_conditional();
+    }
+}
+
+/// Get the size of a texture mipmap level, in texels.
+fn get_level_size(kind: texture::Kind, level: texture::Level) -> [texture::Size; 3] {
+    let (w, h, d, _) = kind.get_dimensions();
+    let d = match kind {
+        texture::Kind::D3(..) => d >> level,
+        _ => d, // layers are not affected by mipmapping
+    };
+    [max(1, w >> level), max(1, h >> level), max(1, d)]
+}
+
+fn is_region_inside(kind: texture::Kind, img: &texture::RawImageInfo) -> bool {
+    let size = get_level_size(kind, img.mipmap);
+    // an overflowing end is outside of any texture
+    let fits = |offset: texture::Size, len: texture::Size, size: texture::Size|
+        offset.checked_add(len).map_or(false, |end| end <= size);
+    fits(img.xoffset, img.width, size[0]) &&
+    fits(img.yoffset, img.height, size[1]) &&
+    fits(img.zoffset, img.depth, size[2])
+}
+
+/// Currently bound state, as far as the validation is concerned.
+struct State<R: Resources> {
+    pipeline: bool,
+    resource_views: [Option<R::ShaderResourceView>; MAX_RESOURCE_VIEWS],
+    pixel_targets: Option<pso::PixelTargetSet<R>>,
+    index: Option<(R::Buffer, IndexType)>,
+}
+
+/// Device wrapper, validating the commands of the `ValidatingCommandBuffer`s
+/// before submitting them to the wrapped device.
+pub struct ValidatingDevice<D: Device> {
+    inner: D,
+    panic: bool,
+    buffers: HashMap<<D::Resources as Resources>::Buffer, buffer::Info>,
+    srv_textures: HashMap<<D::Resources as Resources>::ShaderResourceView,
+                          <D::Resources as Resources>::Texture>,
+    rtv_textures: HashMap<<D::Resources as Resources>::RenderTargetView,
+                          <D::Resources as Resources>::Texture>,
+    dsv_textures: HashMap<<D::Resources as Resources>::DepthStencilView,
+                          <D::Resources as Resources>::Texture>,
+    num_errors: usize,
+}
+
+impl<D: Device> ValidatingDevice<D> {
+    /// Wrap a device of the backend. The errors are logged,
+    /// and if `panic` is set, the first one panics.
+    pub fn new(inner: D, panic: bool) -> ValidatingDevice<D> {
+        ValidatingDevice {
+            inner: inner,
+            panic: panic,
+            buffers: HashMap::new(),
+            srv_textures: HashMap::new(),
+            rtv_textures: HashMap::new(),
+            dsv_textures: HashMap::new(),
+            num_errors: 0,
+        }
+    }
+
+    /// Get the wrapped device.
+    pub fn get_inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Get the wrapped device, mutably.
+    pub fn get_inner_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Get the number of errors found so far.
+    pub fn get_num_errors(&self) -> usize {
+        self.num_errors
+    }
+
+    fn report(&mut self, command: &Command<D::Resources>, message: &str) {
+        self.num_errors += 1;
+        error!("{} in {:?}", message, command);
+        if self.panic {
+            panic!("Validation failed: {} in {:?}", message, command);
+        }
+    }
+
+    fn check_buffer_range(&mut self, command: &Command<D::Resources>,
+                          buffer: &<D::Resources as Resources>::Buffer,
+                          offset: usize, size: usize) {
+        let buf_size = match self.buffers.get(buffer) {
+            Some(info) => info.size,
+            None => return,
+        };
+        let message = match offset.checked_add(size) {
+            Some(end) if end <= buf_size => return,
+            Some(end) => format!("Range {}..{} exceeds the buffer size {}",
+                                 offset, end, buf_size),
+            None => format!("Range of {} bytes at {} overflows the buffer size {}",
+                            size, offset, buf_size),
+        };
+        self.report(command, &message);
+    }
+
+    fn check_constant_range(&mut self, command: &Command<D::Resources>,
+                            buffer: &<D::Resources as Resources>::Buffer,
+                            offset: usize, size: usize) {
+        let caps = *self.inner.get_capabilities();
+        if !caps.constant_buffer_range_supported {
+            self.report(command, "Constant buffer ranges are not supported");
+        } else if caps.constant_buffer_alignment != 0 && offset % caps.constant_buffer_alignment != 0 {
+            let message = format!("Constant buffer offset {} is not aligned to {}",
+                                  offset, caps.constant_buffer_alignment);
+            self.report(command, &message);
+        }
+        self.check_buffer_range(command, buffer, offset, size);
+    }
+
+    fn check_texture_region(&mut self, command: &Command<D::Resources>,
+                            kind: texture::Kind, img: &texture::RawImageInfo) {
+        if !is_region_inside(kind, img) {
+            let message = format!("Region is outside of the texture level {} of {:?}",
+                                  img.mipmap, kind);
+            self.report(command, &message);
+            // the alignment of the region end is meaningless if it overflows
+            return;
+        }
+        if !img.is_block_aligned(kind.get_level_dimensions(img.mipmap)) {
+            let message = format!("Region is not aligned to the blocks of {:?}", img.format);
+            self.report(command, &message);
+        }
+    }
+
+    fn check_draw(&mut self, command: &Command<D::Resources>, state: &State<D::Resources>,
+                  instances: Option<command::InstanceParams>) {
+        let caps = *self.inner.get_capabilities();
+        if !state.pipeline {
+            self.report(command, "No pipeline state is bound");
+        }
+        let targets = match state.pixel_targets {
+            Some(ref pts) if pts.colors.iter().any(|c| c.is_some()) ||
+                             pts.depth.is_some() || pts.stencil.is_some() => *pts,
+            _ => {
+                self.report(command, "No pixel targets are bound");
+                pso::PixelTargetSet::new()
+            },
+        };
+        if instances.is_some() && !caps.instance_call_supported {
+            self.report(command, "Instanced draws are not supported");
+        }
+        // a texture can't be read from and rendered into at the same time
+        let mut target_textures = Vec::new();
+        for color in targets.colors.iter() {
+            if let Some(tex) = color.as_ref().and_then(|v| self.rtv_textures.get(v)) {
+                target_textures.push(tex.clone());
+            }
+        }
+        for ds in [targets.depth, targets.stencil].iter() {
+            if let Some(tex) = ds.as_ref().and_then(|v| self.dsv_textures.get(v)) {
+                target_textures.push(tex.clone());
+            }
+        }
+        let mut conflicts = Vec::new();
+        for (slot, view) in state.resource_views.iter().enumerate() {
+            if let Some(tex) = view.as_ref().and_then(|v| self.srv_textures.get(v)) {
+                if target_textures.contains(tex) {
+                    conflicts.push(slot);
+                }
+            }
+        }
+        for slot in conflicts {
+            let message = format!("Shader resource at slot {} is also bound as a pixel target", slot);
+            self.report(command, &message);
+        }
+    }
+
+    fn validate(&mut self, commands: &[Command<D::Resources>]) {
+        let mut state = State {
+            pipeline: false,
+            resource_views: [None; MAX_RESOURCE_VIEWS],
+            pixel_targets: None,
+            index: None,
+        };
+        for command in commands.iter() {
+            match *command {
+                Command::BindPipelineState => {
+                    state.pipeline = true;
+                    // the views of the previous pipeline are no longer used
+                    state.resource_views = [None; MAX_RESOURCE_VIEWS];
+                },
+                Command::BindConstantBuffers(ref cbs) => {
+                    for param in cbs.iter() {
+                        if let Some((offset, size)) = param.3 {
+                            self.check_constant_range(command, &param.0, offset, size);
+                        }
+                    }
+                },
+                Command::BindResourceViews(ref srvs) => {
+                    for param in srvs.iter() {
+                        let slot: ResourceViewSlot = param.2;
+                        state.resource_views[slot as usize] = Some(param.0);
+                    }
+                },
+                Command::BindPixelTargets(pts) => {
+                    state.pixel_targets = Some(pts);
+                },
+                Command::BindIndex(buf, itype) => {
+                    state.index = Some((buf, itype));
+                },
+                Command::CopyBuffer(src, dst, src_offset, dst_offset, size) => {
+                    self.check_buffer_range(command, &src, src_offset, size);
+                    self.check_buffer_range(command, &dst, dst_offset, size);
+                },
+                Command::CopyBufferToTexture(src, src_offset, kind, ref img) => {
+                    self.check_buffer_range(command, &src, src_offset, img.get_byte_count());
+                    self.check_texture_region(command, kind, img);
+                },
+                Command::CopyTextureToBuffer(kind, ref img, dst, dst_offset) => {
+                    self.check_texture_region(command, kind, img);
+                    self.check_buffer_range(command, &dst, dst_offset, img.get_byte_count());
+                },
+                Command::CopyTextureToTexture(src_kind, ref src_img, dst_kind, ref dst_img) |
+                Command::BlitTexture(src_kind, ref src_img, dst_kind, ref dst_img) => {
+                    self.check_texture_region(command, src_kind, src_img);
+                    self.check_texture_region(command, dst_kind, dst_img);
+                },
+                Command::UpdateBuffer(buf, size, offset) => {
+                    self.check_buffer_range(command, &buf, offset, size);
+                },
+                Command::UpdateTexture(kind, _, ref img) => {
+                    self.check_texture_region(command, kind, img);
+                },
+                Command::Draw(_, _, instances) => {
+                    self.check_draw(command, &state, instances);
+                },
+                Command::DrawIndexed(start, count, _, instances) => {
+                    self.check_draw(command, &state, instances);
+                    match state.index {
+                        Some((buf, itype)) => {
+                            let stride = match itype {
+                                IndexType::U16 => 2,
+                                IndexType::U32 => 4,
+                            };
+                            let offset = start as usize * stride;
+                            self.check_buffer_range(command, &buf, offset, count as usize * stride);
+                        },
+                        None => self.report(command, "No index buffer is bound"),
+                    }
+                },
+                Command::DrawIndirect(buf, offset, count, indexed) => {
+                    self.check_draw(command, &state, None);
+                    let stride = if indexed {
+                        ::std::mem::size_of::<command::DrawIndexedIndirectArgs>()
+                    } else {
+                        ::std::mem::size_of::<command::DrawIndirectArgs>()
+                    };
+                    self.check_buffer_range(command, &buf, offset, count * stride);
+                },
+            }
+        }
+    }
+}
+
+impl<D: Device> Device for ValidatingDevice<D> {
+    type Resources = D::Resources;
+    type CommandBuffer = ValidatingCommandBuffer<D::Resources, D::CommandBuffer>;
+
+    fn get_capabilities(&self) -> &Capabilities {
+        self.inner.get_capabilities()
+    }
+
+    fn pin_submitted_resources(&mut self, man: &handle::Manager<D::Resources>) {
+        self.buffers.clear();
+        for buf in man.get_buffers().iter() {
+            self.buffers.insert(*buf.resource(), buf.get_info().clone());
+        }
+        self.srv_textures.clear();
+        self.rtv_textures.clear();
+        self.dsv_textures.clear();
+        for vt in man.get_view_textures().iter() {
+            match *vt {
+                ViewTexture::ShaderResource(view, ref tex) => {
+                    self.srv_textures.insert(view, tex.clone());
+                },
+                ViewTexture::RenderTarget(view, ref tex) => {
+                    self.rtv_textures.insert(view, tex.clone());
+                },
+                ViewTexture::DepthStencil(ref view, ref tex) => {
+                    self.dsv_textures.insert(view.clone(), tex.clone());
+                },
+            }
+        }
+        self.inner.pin_submitted_resources(man);
+    }
+
+    fn submit(&mut self,
+              cb: &mut Self::CommandBuffer,
+              access: &AccessInfo<D::Resources>)
+              -> SubmissionResult<()> {
+        self.validate(&cb.commands);
+        self.inner.submit(&mut cb.inner, access)
+    }
+
+    fn fenced_submit(&mut self,
+                     cb: &mut Self::CommandBuffer,
+                     access: &AccessInfo<D::Resources>,
+                     after: Option<handle::Fence<D::Resources>>)
+                     -> SubmissionResult<handle::Fence<D::Resources>> {
+        self.validate(&cb.commands);
+        self.inner.fenced_submit(&mut cb.inner, access, after)
+    }
+
+    fn wait_fence(&mut self, fence: &handle::Fence<D::Resources>) {
+        self.inner.wait_fence(fence);
+    }
+
+    fn is_fence_signaled(&mut self, fence: &handle::Fence<D::Resources>) -> bool {
+        self.inner.is_fence_signaled(fence)
+    }
+
+    fn cleanup(&mut self) {
+        self.inner.cleanup();
+    }
+}
@@ -17,6 +17,8 @@ extern crate log;
 extern crate env_logger;
 extern crate winit;
 extern crate glutin;
+#[cfg(feature = "png")]
+extern crate image;
 extern crate gfx;
 extern crate gfx_device_gl;
 extern crate gfx_window_glutin;
@@ -37,6 +39,8 @@ extern crate gfx_device_vulkan;
 #[cfg(feature = "vulkan")]
 extern crate gfx_window_vulkan;
 
+use std::path::{Path, PathBuf};
+
 pub mod shade;
 
 #[cfg(not(feature = "vulkan"))]
@@ -55,6 +59,284 @@ pub struct WindowTargets<R: gfx::Resources> {
     pub aspect_ratio: f32,
 }
 
+/// Off-screen multisampled color target, resolved into
+/// the window color target at the end of each frame.
+struct MsaaTarget<R: gfx::Resources> {
+    color: gfx::handle::RenderTargetView<R, ColorFormat>,
+    main_color: gfx::handle::RenderTargetView<R, ColorFormat>,
+}
+
+fn create_msaa_targets<R, F>(factory: &mut F, targets: WindowTargets<R>,
+                             samples: gfx::texture::NumSamples)
+                             -> (WindowTargets<R>, Option<MsaaTarget<R>>)
+    where R: gfx::Resources, F: gfx::Factory<R>
+{
+    use gfx::format::{ChannelTyped, Formatted};
+    use gfx::texture::{AaMode, Kind};
+    use gfx::memory::Usage;
+    use gfx::traits::Factory;
+
+    if samples <= 1 {
+        return (targets, None)
+    }
+    if !factory.get_capabilities().resolve_texture_supported {
+        warn!("Multisampled texture resolves are not supported, rendering without MSAA");
+        return (targets, None)
+    }
+    let (width, height, _, _) = targets.color.get_dimensions();
+    let kind = Kind::D2(width, height, AaMode::Multi(samples));
+    let color_tex = factory.create_texture::<<ColorFormat as Formatted>::Surface>(
+        kind, 1, gfx::RENDER_TARGET | gfx::TRANSFER_SRC, Usage::Data,
+        Some(<<ColorFormat as Formatted>::Channel as ChannelTyped>::get_channel_type())
+    ).unwrap();
+    let color = factory.view_texture_as_render_target(&color_tex, 0, None).unwrap();
+    let depth_tex = factory.create_texture::<<DepthFormat as Formatted>::Surface>(
+        kind, 1, gfx::DEPTH_STENCIL, Usage::Data,
+        Some(<<DepthFormat as Formatted>::Channel as ChannelTyped>::get_channel_type())
+    ).unwrap();
+    let depth = factory.view_texture_as_depth_stencil_trivial(&depth_tex).unwrap();
+    let msaa = MsaaTarget {
+        color: color.clone(),
+        main_color: targets.color,
+    };
+    (WindowTargets {
+        color: color,
+        depth: depth,
+        aspect_ratio: targets.aspect_ratio,
+    }, Some(msaa))
+}
+
+type ColorSurface = <ColorFormat as gfx::format::Formatted>::Surface;
+
+/// Create off-screen color and depth targets, for the applications
+/// running without a window. The color texture can be read back.
+fn create_offscreen_targets<R, F>(factory: &mut F, width: gfx::texture::Size,
+                                  height: gfx::texture::Size)
+                                  -> (gfx::handle::Texture<R, ColorSurface>, WindowTargets<R>)
+    where R: gfx::Resources, F: gfx::Factory<R>
+{
+    use gfx::format::{ChannelTyped, Formatted};
+    use gfx::texture::{AaMode, Kind};
+    use gfx::memory::Usage;
+    use gfx::traits::Factory;
+
+    let kind = Kind::D2(width, height, AaMode::Single);
+    let color_tex = factory.create_texture::<<ColorFormat as Formatted>::Surface>(
+        kind, 1, gfx::RENDER_TARGET | gfx::TRANSFER_SRC, Usage::Data,
+        Some(<<ColorFormat as Formatted>::Channel as ChannelTyped>::get_channel_type())
+    ).unwrap();
+    let color = factory.view_texture_as_render_target(&color_tex, 0, None).unwrap();
+    let depth = factory.create_depth_stencil_view_only(width, height).unwrap();
+    (color_tex, WindowTargets {
+        color: color,
+        depth: depth,
+        aspect_ratio: width as f32 / height as f32,
+    })
+}
+
+/// A frame read back from a color target, as RGBA8 texels
+/// in rows ordered from top to bottom.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    /// Save the frame as a PNG image.
+    #[cfg(feature = "png")]
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        image::save_buffer(path, &self.data, self.width, self.height, image::ColorType::RGBA(8))
+    }
+
+    /// Save the frame as a PNG image, which needs the `png` feature.
+    #[cfg(not(feature = "png"))]
+    pub fn save_png<P: AsRef<Path>>(&self, _path: P) -> std::io::Result<()> {
+        Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                "gfx_app is built without the `png` feature"))
+    }
+}
+
+/// Read a color texture back into a frame. GL targets are stored
+/// from the bottom row up, so they need to be `flipped`.
+fn read_frame<R, C, F, D>(factory: &mut F, device: &mut D,
+                          texture: &gfx::handle::Texture<R, ColorSurface>, flipped: bool) -> Frame
+    where R: gfx::Resources,
+          C: gfx::CommandBuffer<R>,
+          F: Factory<R, CommandBuffer = C>,
+          D: gfx::Device<Resources = R, CommandBuffer = C>
+{
+    use gfx::format::{Formatted, Rgba8};
+    use gfx::format::convert::convert;
+
+    let info = *texture.get_info();
+    let mut encoder = factory.create_encoder();
+    let readback = encoder.read_texture::<_, _, ColorFormat>(factory, texture, None,
+                                                             info.to_image_info(0)).unwrap();
+    encoder.flush(device);
+    // the encoder is flushed, so the data is there once it can be mapped
+    let texels = readback.wait(device, factory).unwrap().unwrap();
+    let mut data = convert(gfx::memory::cast_slice(&texels[..]), ColorFormat::get_format(),
+                           Rgba8::get_format()).unwrap();
+    let (width, height, _, _) = info.kind.get_dimensions();
+    if flipped {
+        let row = width as usize * 4;
+        let rows: Vec<&[u8]> = data.chunks(row).rev().collect();
+        data = rows.concat();
+    }
+    Frame {
+        width: width as u32,
+        height: height as u32,
+        data: data,
+    }
+}
+
+/// Get the texture behind a color target, to read it back.
+fn get_color_texture<R: gfx::Resources>(view: &gfx::handle::RenderTargetView<R, ColorFormat>)
+                                        -> gfx::handle::Texture<R, ColorSurface> {
+    use gfx::memory::Typed;
+    Typed::new(view.raw().get_texture().clone())
+}
+
+/// Key saving the next frame as a screenshot.
+pub const SCREENSHOT_KEY: winit::VirtualKeyCode = winit::VirtualKeyCode::F12;
+/// Key starting and stopping the recording of every frame.
+pub const RECORD_KEY: winit::VirtualKeyCode = winit::VirtualKeyCode::F11;
+
+/// Capture of the rendered frames into PNG images, controlled by
+/// `SCREENSHOT_KEY`, `RECORD_KEY` and `Application::capture`.
+/// The images are only saved with the `png` feature, and only on OpenGL,
+/// the other backends can't read the textures back yet.
+///
+/// The main color target is read back after `render`, which stalls
+/// the GPU, so the frame rate drops while recording.
+pub struct Capture {
+    directory: PathBuf,
+    screenshot: Option<PathBuf>,
+    /// Directory of the recording, and the number of the next frame.
+    recording: Option<(PathBuf, usize)>,
+}
+
+/// Find the first path produced by `make_path` that isn't taken yet,
+/// so that the captures of previous runs are not overwritten.
+fn get_free_path<F: Fn(usize) -> PathBuf>(make_path: F) -> PathBuf {
+    (0 ..).map(make_path).find(|path| !path.exists()).unwrap()
+}
+
+impl Capture {
+    /// Create a capture saving the images into `directory`.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Capture {
+        Capture {
+            directory: directory.into(),
+            screenshot: None,
+            recording: None,
+        }
+    }
+
+    /// Create a capture saving the images into `GFX_APP_CAPTURE_DIR`,
+    /// or the current directory if it is not set.
+    pub fn from_env() -> Capture {
+        let directory = std::env::var("GFX_APP_CAPTURE_DIR").unwrap_or_else(|_| ".".to_string());
+        Capture::new(directory)
+    }
+
+    /// Save the next frame as `screenshot-<n>.png` in the capture directory.
+    pub fn screenshot(&mut self) {
+        let path = get_free_path(|i| self.directory.join(format!("screenshot-{}.png", i)));
+        self.screenshot_to(path);
+    }
+
+    /// Save the next frame at the given path.
+    pub fn screenshot_to<P: Into<PathBuf>>(&mut self, path: P) {
+        self.screenshot = Some(path.into());
+    }
+
+    /// Save every frame into a new `recording-<n>` directory
+    /// inside the capture directory, until `stop_recording`.
+    pub fn start_recording(&mut self) {
+        let path = get_free_path(|i| self.directory.join(format!("recording-{}", i)));
+        self.start_recording_to(path);
+    }
+
+    /// Save every frame into `directory` as `00000.png`, `00001.png` and so on,
+    /// until `stop_recording`.
+    pub fn start_recording_to<P: Into<PathBuf>>(&mut self, directory: P) {
+        self.recording = Some((directory.into(), 0));
+    }
+
+    /// Stop the recording, if any.
+    pub fn stop_recording(&mut self) {
+        if let Some((directory, num_frames)) = self.recording.take() {
+            info!("Recorded {} frames into {:?}", num_frames, directory);
+        }
+    }
+
+    /// Check if every frame is being saved.
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Handle a key press, ignoring the keys not bound to the capture.
+    fn on_key(&mut self, key: winit::VirtualKeyCode) {
+        if key == SCREENSHOT_KEY {
+            self.screenshot();
+        } else if key == RECORD_KEY {
+            if self.is_recording() {
+                self.stop_recording();
+            } else {
+                self.start_recording();
+            }
+        }
+    }
+
+    /// Take the paths the current frame has to be saved at,
+    /// advancing the recording to the next frame.
+    pub fn take_paths(&mut self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.screenshot.take().into_iter().collect();
+        if let Some((ref directory, ref mut num_frames)) = self.recording {
+            paths.push(directory.join(format!("{:05}.png", num_frames)));
+            *num_frames += 1;
+        }
+        paths
+    }
+
+    /// Read back the main color texture, if the current frame is captured.
+    fn save<R, C, F, D>(&mut self, factory: &mut F, device: &mut D,
+                        texture: &gfx::handle::Texture<R, ColorSurface>, flipped: bool)
+        where R: gfx::Resources,
+              C: gfx::CommandBuffer<R>,
+              F: Factory<R, CommandBuffer = C>,
+              D: gfx::Device<Resources = R, CommandBuffer = C>
+    {
+        let paths = self.take_paths();
+        if paths.is_empty() {
+            return
+        }
+        let frame = read_frame(factory, device, texture, flipped);
+        for path in paths {
+            if let Some(parent) = path.parent() {
+                if let Err(e) = std::fs::create_dir_all(parent) {
+                    error!("Unable to create the capture directory {:?}: {}", parent, e);
+                    continue
+                }
+            }
+            match frame.save_png(&path) {
+                Ok(()) => debug!("Captured the frame into {:?}", path),
+                Err(e) => error!("Unable to save the frame into {:?}: {}", path, e),
+            }
+        }
+    }
+
+    /// Drop the current frame captures, for the backends which can't
+    /// read the textures back.
+    fn skip(&mut self, backend: &str) {
+        let paths = self.take_paths();
+        if !paths.is_empty() {
+            warn!("Capturing the frames is not supported on {}, skipping {:?}", backend, paths);
+        }
+    }
+}
+
 pub enum Backend {
     OpenGL2,
     Direct3D11 { pix_mode: bool },
@@ -92,54 +374,138 @@ pub trait Factory<R: gfx::Resources>: gfx::Factory<R> {
     fn create_encoder(&mut self) -> gfx::Encoder<R, Self::CommandBuffer>;
 }
 
+/// Wrap a command buffer into an encoder that checks the commands
+/// against the capabilities of the factory's device.
+fn new_encoder<R, C, F>(factory: &F, combuf: C) -> gfx::Encoder<R, C> where
+    R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Factory<R>
+{
+    let mut encoder: gfx::Encoder<R, C> = combuf.into();
+    encoder.set_capabilities(Some(*factory.get_capabilities()));
+    encoder
+}
+
 pub trait ApplicationBase<R: gfx::Resources, C: gfx::CommandBuffer<R>> {
     fn new<F>(&mut F, shade::Backend, WindowTargets<R>) -> Self where F: Factory<R, CommandBuffer = C>;
     fn render<D>(&mut self, &mut D) where D: gfx::Device<Resources = R, CommandBuffer = C>;
     fn get_exit_key() -> Option<winit::VirtualKeyCode>;
     fn on(&mut self, winit::Event);
     fn on_resize<F>(&mut self, &mut F, WindowTargets<R>) where F: Factory<R, CommandBuffer = C>;
+    /// Control the capture of the frame which was just rendered.
+    fn capture(&mut self, _capture: &mut Capture) {}
 }
 
 
 impl Factory<gfx_device_gl::Resources> for gfx_device_gl::Factory {
     type CommandBuffer = gfx_device_gl::CommandBuffer;
     fn create_encoder(&mut self) -> gfx::Encoder<gfx_device_gl::Resources, Self::CommandBuffer> {
-        self.create_command_buffer().into()
+        let combuf = self.create_command_buffer();
+        new_encoder(self, combuf)
     }
 }
 
+const GL_VERSION: glutin::GlRequest = glutin::GlRequest::GlThenGles {
+    opengl_version: (3, 2), // TODO: try more versions
+    opengles_version: (2, 0),
+};
+
+fn get_gl_backend(device: &gfx_device_gl::Device) -> shade::Backend {
+    let shade_lang = device.get_info().shading_language;
+    if shade_lang.is_embedded {
+        shade::Backend::GlslEs(shade_lang)
+    } else {
+        shade::Backend::Glsl(shade_lang)
+    }
+}
+
+/// Settings of a headless run, see `launch_gl3_headless`.
+pub struct HeadlessSettings {
+    pub width: gfx::texture::Size,
+    pub height: gfx::texture::Size,
+    pub num_frames: usize,
+    /// Path to save the last frame to, as a PNG image (needs the `png` feature).
+    pub output: Option<PathBuf>,
+}
+
+impl HeadlessSettings {
+    /// Read the settings from the environment, if `GFX_APP_HEADLESS_FRAMES`
+    /// is set. The size is given by `GFX_APP_HEADLESS_SIZE` as `<width>x<height>`,
+    /// 800x600 by default, and the output path by `GFX_APP_HEADLESS_OUTPUT`.
+    /// Invalid values are warned about and replaced by the defaults, a single frame
+    /// for `GFX_APP_HEADLESS_FRAMES`.
+    pub fn from_env() -> Option<HeadlessSettings> {
+        use std::env::var;
+
+        let num_frames = match var("GFX_APP_HEADLESS_FRAMES") {
+            Ok(frames) => match frames.parse() {
+                Ok(num) => num,
+                Err(_) => {
+                    warn!("Invalid GFX_APP_HEADLESS_FRAMES: {}, rendering a single frame", frames);
+                    1
+                },
+            },
+            Err(_) => return None,
+        };
+        let (width, height) = match var("GFX_APP_HEADLESS_SIZE") {
+            Ok(size) => {
+                let dims: Vec<gfx::texture::Size> = size.split('x').filter_map(|d| d.parse().ok())
+                                                        .collect();
+                if dims.len() == 2 {
+                    (dims[0], dims[1])
+                } else {
+                    warn!("Invalid GFX_APP_HEADLESS_SIZE: {}, using 800x600", size);
+                    (800, 600)
+                }
+            },
+            Err(_) => (800, 600),
+        };
+        Some(HeadlessSettings {
+            width: width,
+            height: height,
+            num_frames: num_frames,
+            output: var("GFX_APP_HEADLESS_OUTPUT").ok().map(PathBuf::from),
+        })
+    }
+}
+
+/// Run the application in a window, or headless if
+/// `HeadlessSettings::from_env` finds the settings for it.
 pub fn launch_gl3<A>(wb: winit::WindowBuilder) where
 A: Sized + ApplicationBase<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>
 {
     use gfx::traits::Device;
 
     env_logger::init().unwrap();
-    let gl_version = glutin::GlRequest::GlThenGles {
-        opengl_version: (3, 2), // TODO: try more versions
-        opengles_version: (2, 0),
-    };
+    if let Some(settings) = HeadlessSettings::from_env() {
+        let frame = launch_gl3_headless::<A>(settings.width, settings.height, settings.num_frames);
+        if let Some(path) = settings.output {
+            if let Err(e) = frame.save_png(&path) {
+                error!("Unable to save the frame into {:?}: {}", path, e);
+            }
+        }
+        return
+    }
     let builder = glutin::WindowBuilder::from_winit_builder(wb)
-                                        .with_gl(gl_version)
+                                        .with_gl(GL_VERSION)
                                         .with_vsync();
     let (window, mut device, mut factory, main_color, main_depth) =
         gfx_window_glutin::init::<ColorFormat, DepthFormat>(builder);
     let (mut cur_width, mut cur_height) = window.get_inner_size_points().unwrap();
-    let shade_lang = device.get_info().shading_language;
 
-    let backend = if shade_lang.is_embedded {
-        shade::Backend::GlslEs(shade_lang)
-    } else {
-        shade::Backend::Glsl(shade_lang)
-    }; 
+    let backend = get_gl_backend(&device);
+    let mut main_color_tex = get_color_texture(&main_color);
     let mut app = A::new(&mut factory, backend, WindowTargets {
         color: main_color,
         depth: main_depth,
         aspect_ratio: cur_width as f32 / cur_height as f32,
     });
 
+    let mut capture = Capture::from_env();
     let mut harness = Harness::new();
     loop {
         for event in window.poll_events() {
+            if let winit::Event::KeyboardInput(winit::ElementState::Pressed, _, Some(key)) = event {
+                capture.on_key(key);
+            }
             match event {
                 winit::Event::Closed => return,
                 winit::Event::KeyboardInput(winit::ElementState::Pressed, _, key) if key == A::get_exit_key() => return,
@@ -147,6 +513,7 @@ A: Sized + ApplicationBase<gfx_device_gl::Resources, gfx_device_gl::CommandBuffe
                     cur_width = width;
                     cur_height = height;
                     let (new_color, new_depth) = gfx_window_glutin::new_views(&window);
+                    main_color_tex = get_color_texture(&new_color);
                     app.on_resize(&mut factory, WindowTargets {
                         color: new_color,
                         depth: new_depth,
@@ -158,12 +525,47 @@ A: Sized + ApplicationBase<gfx_device_gl::Resources, gfx_device_gl::CommandBuffe
         }
         // draw a frame
         app.render(&mut device);
+        // the back buffer is undefined after the swap
+        app.capture(&mut capture);
+        capture.save(&mut factory, &mut device, &main_color_tex, true);
         window.swap_buffers().unwrap();
         device.cleanup();
         harness.bump();
     }
 }
 
+/// Run the application without a window, rendering `num_frames` frames into
+/// off-screen targets of the given size, and return the last one.
+/// The GL context is headless (OSMesa on Linux), so a software driver
+/// like llvmpipe works without a display.
+pub fn launch_gl3_headless<A>(width: gfx::texture::Size, height: gfx::texture::Size,
+                              num_frames: usize) -> Frame where
+A: Sized + ApplicationBase<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>
+{
+    use gfx::traits::Device;
+
+    // the logger is already set up when called from `launch_gl3`
+    let _ = env_logger::init();
+    let builder = glutin::HeadlessRendererBuilder::new(width as u32, height as u32)
+                                                  .with_gl(GL_VERSION);
+    let (_context, mut device, mut factory) = gfx_window_glutin::init_headless(builder)
+                                                                .unwrap();
+    let backend = get_gl_backend(&device);
+    let (color_tex, targets) = create_offscreen_targets(&mut factory, width, height);
+    let mut app = A::new(&mut factory, backend, targets);
+
+    let mut capture = Capture::from_env();
+    let mut harness = Harness::new();
+    for _ in 0 .. num_frames {
+        app.render(&mut device);
+        app.capture(&mut capture);
+        capture.save(&mut factory, &mut device, &color_tex, true);
+        device.cleanup();
+        harness.bump();
+    }
+    read_frame(&mut factory, &mut device, &color_tex, true)
+}
+
 
 #[cfg(target_os = "windows")]
 pub type D3D11CommandBuffer = gfx_device_dx11::CommandBuffer<gfx_device_dx11::DeferredContext>;
@@ -174,7 +576,8 @@ pub type D3D11CommandBufferFake = gfx_device_dx11::CommandBuffer<gfx_device_dx11
 impl Factory<gfx_device_dx11::Resources> for gfx_device_dx11::Factory {
     type CommandBuffer = D3D11CommandBuffer;
     fn create_encoder(&mut self) -> gfx::Encoder<gfx_device_dx11::Resources, Self::CommandBuffer> {
-        self.create_command_buffer_native().into()
+        let combuf = self.create_command_buffer_native();
+        new_encoder(self, combuf)
     }
 }
 
@@ -198,10 +601,14 @@ A: Sized + ApplicationBase<gfx_device_dx11::Resources, D3D11CommandBuffer>
     });
     let mut device = gfx_device_dx11::Deferred::from(device);
 
+    let mut capture = Capture::from_env();
     let mut harness = Harness::new();
     loop {
         let mut new_size = None;
         for event in window.poll_events() {
+            if let winit::Event::KeyboardInput(winit::ElementState::Pressed, _, Some(key)) = event {
+                capture.on_key(key);
+            }
             match event {
                 winit::Event::Closed => return,
                 winit::Event::KeyboardInput(winit::ElementState::Pressed, _, key) if key == A::get_exit_key() => return,
@@ -232,6 +639,8 @@ A: Sized + ApplicationBase<gfx_device_dx11::Resources, D3D11CommandBuffer>
             continue;
         }
         app.render(&mut device);
+        app.capture(&mut capture);
+        capture.skip("Direct3D 11");
         window.swap_buffers(1);
         device.cleanup();
         harness.bump();
@@ -243,7 +652,8 @@ A: Sized + ApplicationBase<gfx_device_dx11::Resources, D3D11CommandBuffer>
 impl Factory<gfx_device_metal::Resources> for gfx_device_metal::Factory {
     type CommandBuffer = gfx_device_metal::CommandBuffer;
     fn create_encoder(&mut self) -> gfx::Encoder<gfx_device_metal::Resources, Self::CommandBuffer> {
-        self.create_command_buffer().into()
+        let combuf = self.create_command_buffer();
+        new_encoder(self, combuf)
     }
 }
 
@@ -267,9 +677,13 @@ A: Sized + ApplicationBase<gfx_device_metal::Resources, gfx_device_metal::Comman
         aspect_ratio: width as f32 / height as f32
     });
 
+    let mut capture = Capture::from_env();
     let mut harness = Harness::new();
     loop {
         for event in window.poll_events() {
+            if let winit::Event::KeyboardInput(winit::ElementState::Pressed, _, Some(key)) = event {
+                capture.on_key(key);
+            }
             match event {
                 winit::Event::Closed => return,
                 winit::Event::KeyboardInput(winit::ElementState::Pressed, _, key) if key == A::get_exit_key() => return,
@@ -280,6 +694,8 @@ A: Sized + ApplicationBase<gfx_device_metal::Resources, gfx_device_metal::Comman
             }
         }
         app.render(&mut device);
+        app.capture(&mut capture);
+        capture.skip("Metal");
         window.swap_buffers().unwrap();
         device.cleanup();
         harness.bump();
@@ -291,7 +707,8 @@ A: Sized + ApplicationBase<gfx_device_metal::Resources, gfx_device_metal::Comman
 impl Factory<gfx_device_vulkan::Resources> for gfx_device_vulkan::Factory {
     type CommandBuffer = gfx_device_vulkan::CommandBuffer;
     fn create_encoder(&mut self) -> gfx::Encoder<gfx_device_vulkan::Resources, Self::CommandBuffer> {
-        self.create_command_buffer().into()
+        let combuf = self.create_command_buffer();
+        new_encoder(self, combuf)
     }
 }
 
@@ -314,9 +731,13 @@ A: Sized + ApplicationBase<gfx_device_vulkan::Resources, gfx_device_vulkan::Comm
         aspect_ratio: width as f32 / height as f32, //TODO
     });
 
+    let mut capture = Capture::from_env();
     let mut harness = Harness::new();
     loop {
         for event in win.get_window().poll_events() {
+            if let winit::Event::KeyboardInput(winit::ElementState::Pressed, _, Some(key)) = event {
+                capture.on_key(key);
+            }
             match event {
                 winit::Event::Closed => return,
                 winit::Event::KeyboardInput(winit::ElementState::Pressed, _, key) if key == A::get_exit_key() => return,
@@ -328,6 +749,8 @@ A: Sized + ApplicationBase<gfx_device_vulkan::Resources, gfx_device_vulkan::Comm
         }
         let mut frame = win.start_frame();
         app.render(frame.get_queue());
+        app.capture(&mut capture);
+        capture.skip("Vulkan");
         frame.get_queue().cleanup();
         harness.bump();
     }
@@ -350,12 +773,26 @@ pub trait Application<R: gfx::Resources>: Sized {
     fn get_exit_key() -> Option<winit::VirtualKeyCode> {
         Some(winit::VirtualKeyCode::Escape)
     }
+    /// Number of samples of the window targets. With more than one sample,
+    /// the application renders into multisampled targets, which are resolved
+    /// into the window at the end of each frame.
+    fn get_num_samples() -> gfx::texture::NumSamples {
+        1
+    }
     fn on_resize(&mut self, WindowTargets<R>) {}
     fn on_resize_ext<F: gfx::Factory<R>>(&mut self, _factory: &mut F, targets: WindowTargets<R>) {
         self.on_resize(targets);
     }
     fn on(&mut self, _event: winit::Event) {}
-
+    /// Control the capture of the frame which was just rendered, e.g. to
+    /// record a fixed range of frames.
+    fn capture(&mut self, _capture: &mut Capture) {}
+
+    /// Run without a window for `num_frames` frames, see `launch_gl3_headless`.
+    fn launch_headless(width: gfx::texture::Size, height: gfx::texture::Size, num_frames: usize)
+                       -> Frame where Self: Application<gfx_device_gl::Resources> {
+        launch_gl3_headless::<Wrap<_, _, Self>>(width, height, num_frames)
+    }
     fn launch_simple(name: &str) where Self: Application<DefaultResources> {
         let wb = winit::WindowBuilder::new().with_title(name);
         <Self as Application<DefaultResources>>::launch_default(wb)
@@ -380,6 +817,7 @@ pub trait Application<R: gfx::Resources>: Sized {
 
 pub struct Wrap<R: gfx::Resources, C, A> {
     encoder: gfx::Encoder<R, C>,
+    msaa: Option<MsaaTarget<R>>,
     app: A,
 }
 
@@ -391,9 +829,11 @@ impl<R, C, A> ApplicationBase<R, C> for Wrap<R, C, A>
     fn new<F>(factory: &mut F, backend: shade::Backend, window_targets: WindowTargets<R>) -> Self
         where F: Factory<R, CommandBuffer = C>
     {
+        let (targets, msaa) = create_msaa_targets(factory, window_targets, A::get_num_samples());
         Wrap {
             encoder: factory.create_encoder(),
-            app: A::new(factory, backend, window_targets),
+            msaa: msaa,
+            app: A::new(factory, backend, targets),
         }
     }
 
@@ -401,6 +841,11 @@ impl<R, C, A> ApplicationBase<R, C> for Wrap<R, C, A>
         where D: gfx::Device<Resources = R, CommandBuffer = C>
     {
         self.app.render(&mut self.encoder);
+        if let Some(ref msaa) = self.msaa {
+            if let Err(e) = self.encoder.resolve(&msaa.color, &msaa.main_color) {
+                error!("Failed to resolve the multisampled frame: {:?}", e);
+            }
+        }
         self.encoder.flush(device);
     }
 
@@ -412,9 +857,15 @@ impl<R, C, A> ApplicationBase<R, C> for Wrap<R, C, A>
         self.app.on(event)
     }
 
+    fn capture(&mut self, capture: &mut Capture) {
+        self.app.capture(capture)
+    }
+
     fn on_resize<F>(&mut self, factory: &mut F, window_targets: WindowTargets<R>)
         where F: Factory<R, CommandBuffer = C>
     {
-        self.app.on_resize_ext(factory, window_targets);
+        let (targets, msaa) = create_msaa_targets(factory, window_targets, A::get_num_samples());
+        self.msaa = msaa;
+        self.app.on_resize_ext(factory, targets);
     }
 }
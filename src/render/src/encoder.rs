@@ -16,16 +16,19 @@
 
 #![deny(missing_docs)]
 
-use draw_state::target::{Depth, Stencil};
+use draw_state::target::{Depth, Mirror, Stencil};
 use std::error::Error;
 use std::any::Any;
 use std::{fmt, mem};
+use std::sync::{Arc, Mutex};
 
-use core::{Device, SubmissionResult, IndexType, Resources, VertexCount};
-use core::{command, format, handle, texture};
+use core::{Capabilities, Device, SubmissionResult, IndexType, Resources, VertexCount};
+use core::{buffer, command, format, handle, mapping, texture};
+use core::factory::Factory;
 use core::memory::{self, cast_slice, Typed, Pod, Usage};
 use slice;
 use pso;
+use profiler::{self, Profiler};
 
 /// An error occuring in memory copies.
 #[allow(missing_docs)]
@@ -44,13 +47,24 @@ pub enum CopyError<S, D> {
         dst_offset: usize,
         size: usize,
     },
+    SizeMismatch {
+        src: S,
+        dst: D,
+    },
+    IncompatibleFormats,
+    InvalidFilter,
+    SrcNotMultisampled,
+    DstMultisampled,
     NoSrcBindFlag,
     NoDstBindFlag,
+    UnalignedRegion,
+    Unsupported,
 }
 
 pub type CopyBufferResult = Result<(), CopyError<usize, usize>>;
 pub type CopyBufferTextureResult = Result<(), CopyError<usize, [texture::Size; 3]>>;
 pub type CopyTextureBufferResult = Result<(), CopyError<[texture::Size; 3], usize>>;
+pub type CopyTextureResult = Result<(), CopyError<[texture::Size; 3], [texture::Size; 3]>>;
 
 impl<S, D> fmt::Display for CopyError<S, D>
     where S: fmt::Debug + fmt::Display, D: fmt::Debug + fmt::Display
@@ -67,6 +81,8 @@ impl<S, D> fmt::Display for CopyError<S, D>
                        self.description(),
                        src_offset, src_offset + size,
                        dst_offset, dst_offset + size),
+            SizeMismatch { ref src, ref dst } =>
+                write!(f, "{}: {:?} to {:?}", self.description(), src, dst),
             _ => write!(f, "{}", self.description())
         }
     }
@@ -81,12 +97,84 @@ impl<S, D> Error for CopyError<S, D>
             OutOfSrcBounds {..} => "Copy source is out of bounds",
             OutOfDstBounds {..} => "Copy destination is out of bounds",
             Overlap {..} => "Copy source and destination are overlapping",
+            SizeMismatch {..} => "Copy source and destination have different sizes",
+            IncompatibleFormats => "Copy source and destination formats are not compatible",
+            InvalidFilter => "Depth and stencil blits only support nearest filtering",
+            SrcNotMultisampled => "Resolve source is not multisampled",
+            DstMultisampled => "Resolve destination is multisampled",
             NoSrcBindFlag => "Copy source is missing `TRANSFER_SRC`",
             NoDstBindFlag => "Copy destination is missing `TRANSFER_DST`",
+            UnalignedRegion => "Copy region is not aligned to the compressed blocks",
+            Unsupported => "Copy is not supported by the device",
         }
     }
 }
 
+/// Get the size of a texture mipmap level, or `None` if there is no such level.
+fn get_level_size(info: &texture::Info, level: texture::Level) -> Option<[texture::Size; 3]> {
+    use std::cmp::max;
+    if level >= info.levels {
+        return None
+    }
+    let map = |val: texture::Size| if val == 0 { 0 } else { max(1, val >> level) };
+    let (w, h, d, _) = info.kind.get_dimensions();
+    let d = match info.kind {
+        texture::Kind::D3(..) => map(d),
+        _ => d, // layers are not affected by mipmapping
+    };
+    Some([map(w), map(h), d])
+}
+
+/// Check that an image region fits inside its texture, returning
+/// the size of the mipmap level otherwise.
+fn check_texture_region(info: &texture::Info, img: &texture::RawImageInfo)
+                        -> Result<(), [texture::Size; 3]> {
+    match get_level_size(info, img.mipmap) {
+        Some(size) if img.xoffset + img.width <= size[0] &&
+                      img.yoffset + img.height <= size[1] &&
+                      img.zoffset + img.depth <= size[2] => Ok(()),
+        Some(size) => Err(size),
+        None => Err([0; 3]),
+    }
+}
+
+/// Check that an image region starts and ends on the compressed block
+/// boundaries of its texture, or at the edges of the mipmap level.
+fn is_block_aligned(info: &texture::Info, img: &texture::RawImageInfo) -> bool {
+    img.is_block_aligned(info.kind.get_level_dimensions(img.mipmap))
+}
+
+fn get_copy_end(img: &texture::RawImageInfo) -> [texture::Size; 3] {
+    [img.xoffset + img.width, img.yoffset + img.height, img.zoffset + img.depth]
+}
+
+fn is_depth_stencil(surface: format::SurfaceType) -> bool {
+    use core::format::SurfaceType as S;
+    match surface {
+        S::D16 | S::D24 | S::D24_S8 | S::D32 => true,
+        _ => false,
+    }
+}
+
+/// A texture, or a view of it, taking part in a resolve.
+/// The format `T` tells how to interpret the texels.
+pub trait ResolveView<R: Resources, T> {
+    /// Get the underlying texture.
+    fn get_texture(&self) -> &handle::RawTexture<R>;
+}
+
+impl<R: Resources, T: format::Formatted> ResolveView<R, T> for handle::Texture<R, T::Surface> {
+    fn get_texture(&self) -> &handle::RawTexture<R> {
+        self.raw()
+    }
+}
+
+impl<R: Resources, T: format::RenderFormat> ResolveView<R, T> for handle::RenderTargetView<R, T> {
+    fn get_texture(&self) -> &handle::RawTexture<R> {
+        self.raw().get_texture()
+    }
+}
+
 /// An error occuring in buffer/texture updates.
 #[allow(missing_docs)]
 #[derive(Clone, Debug, PartialEq)]
@@ -100,6 +188,7 @@ pub enum UpdateError<T> {
         slice: usize,
     },
     InvalidUsage(Usage),
+    UnalignedRegion,
 }
 
 fn check_update_usage<T>(usage: Usage) -> Result<(), UpdateError<T>> {
@@ -119,6 +208,8 @@ impl<T: Any + fmt::Debug + fmt::Display> fmt::Display for UpdateError<T> {
                 write!(f, "{}: expected {}, found {}", self.description(), target, slice),
             UpdateError::InvalidUsage(usage) =>
                 write!(f, "{}: {:?}", self.description(), usage),
+            UpdateError::UnalignedRegion =>
+                write!(f, "{}", self.description()),
         }
     }
 }
@@ -129,8 +220,215 @@ impl<T: Any + fmt::Debug + fmt::Display> Error for UpdateError<T> {
             UpdateError::OutOfBounds {..} => "Write to data is out of bounds",
             UpdateError::UnitCountMismatch {..} => "Unit count mismatch",
             UpdateError::InvalidUsage(_) => "This memory usage does not allow updates",
+            UpdateError::UnalignedRegion => "Update region is not aligned to the compressed blocks",
+        }
+    }
+}
+
+/// An error occuring in indirect draw calls.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawIndirectError {
+    OutOfBounds {
+        size: usize,
+        draw_end: usize,
+    },
+    NoIndexBuffer,
+    Unsupported,
+}
+
+impl fmt::Display for DrawIndirectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DrawIndirectError::OutOfBounds { ref size, ref draw_end } =>
+                write!(f, "{}: {} / {}", self.description(), draw_end, size),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for DrawIndirectError {
+    fn description(&self) -> &str {
+        match *self {
+            DrawIndirectError::OutOfBounds {..} => "Draw arguments are out of the buffer bounds",
+            DrawIndirectError::NoIndexBuffer => "Indexed indirect draws require an index buffer",
+            DrawIndirectError::Unsupported => "Indirect draws are not supported by the device",
+        }
+    }
+}
+
+/// An error occuring in compute dispatches.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DispatchError {
+    /// Compute shaders are not supported by the device.
+    Unsupported,
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+impl Error for DispatchError {
+    fn description(&self) -> &str {
+        match *self {
+            DispatchError::Unsupported => "Compute dispatches are not supported by the device",
+        }
+    }
+}
+
+/// Counters of the state commands considered by the `Encoder` draw calls.
+/// The bindings that didn't change since the previous draw call are skipped.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StateStats {
+    /// Number of state commands sent to the command buffer.
+    pub emitted: usize,
+    /// Number of state commands skipped as redundant.
+    pub skipped: usize,
+}
+
+/// Register the decision to emit a state command, returning it.
+fn count_state(stats: &mut StateStats, emit: bool) -> bool {
+    if emit {
+        stats.emitted += 1;
+    } else {
+        stats.skipped += 1;
+    }
+    emit
+}
+
+/// Compare the parameter lists of two draw calls, given the element comparison.
+fn same_params<T, F>(a: &[T], b: &[T], eq: F) -> bool where F: Fn(&T, &T) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| eq(x, y))
+}
+
+/// An error occuring when requesting a readback, or reading its data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReadbackError {
+    /// Failed to create the staging buffer.
+    Create(buffer::CreationError),
+    /// Failed to copy the buffer.
+    CopyBuffer(CopyError<usize, usize>),
+    /// Failed to copy the texture.
+    CopyTexture(CopyError<[texture::Size; 3], usize>),
+    /// Failed to map the staging buffer to read the data.
+    Map(mapping::Error),
+}
+
+impl fmt::Display for ReadbackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReadbackError::Create(ref e) => write!(f, "{}: {}", self.description(), e),
+            ReadbackError::CopyBuffer(ref e) => write!(f, "{}: {}", self.description(), e),
+            ReadbackError::CopyTexture(ref e) => write!(f, "{}: {}", self.description(), e),
+            ReadbackError::Map(ref e) => write!(f, "{}: {}", self.description(), e),
+        }
+    }
+}
+
+impl Error for ReadbackError {
+    fn description(&self) -> &str {
+        match *self {
+            ReadbackError::Create(_) => "Failed to create the readback buffer",
+            ReadbackError::CopyBuffer(_) => "Failed to copy the buffer for readback",
+            ReadbackError::CopyTexture(_) => "Failed to copy the texture for readback",
+            ReadbackError::Map(_) => "Failed to map the readback buffer",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            ReadbackError::Create(ref e) => Some(e),
+            ReadbackError::CopyBuffer(ref e) => Some(e),
+            ReadbackError::CopyTexture(ref e) => Some(e),
+            ReadbackError::Map(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<buffer::CreationError> for ReadbackError {
+    fn from(e: buffer::CreationError) -> Self {
+        ReadbackError::Create(e)
+    }
+}
+
+impl From<CopyError<usize, usize>> for ReadbackError {
+    fn from(e: CopyError<usize, usize>) -> Self {
+        ReadbackError::CopyBuffer(e)
+    }
+}
+
+impl From<CopyError<[texture::Size; 3], usize>> for ReadbackError {
+    fn from(e: CopyError<[texture::Size; 3], usize>) -> Self {
+        ReadbackError::CopyTexture(e)
+    }
+}
+
+impl From<mapping::Error> for ReadbackError {
+    fn from(e: mapping::Error) -> Self {
+        ReadbackError::Map(e)
+    }
+}
+
+/// The fence of the submission a readback belongs to, once it is flushed.
+type FenceSlot<R> = Arc<Mutex<Option<handle::Fence<R>>>>;
+
+/// Data being copied from the GPU, see `Encoder::read_buffer` and `Encoder::read_texture`.
+///
+/// The readback becomes ready when the GPU has executed the submission
+/// of the encoder that requested it.
+pub struct Readback<R: Resources, T> {
+    buffer: handle::Buffer<R, T>,
+    fence: FenceSlot<R>,
+}
+
+impl<R: Resources, T: Copy> Readback<R, T> {
+    /// Get the staging buffer the data is copied to.
+    pub fn get_buffer(&self) -> &handle::Buffer<R, T> {
+        &self.buffer
+    }
+
+    /// Check if the commands of the readback have been submitted.
+    pub fn is_submitted(&self) -> bool {
+        self.fence.lock().unwrap().is_some()
+    }
+
+    /// Get the data if the GPU has finished copying it, without blocking.
+    /// Fails if the staging buffer can not be mapped, for example because
+    /// it is still mapped through `get_buffer`.
+    pub fn try_get<D, F>(&self, device: &mut D, factory: &mut F)
+                         -> Result<Option<Vec<T>>, ReadbackError>
+        where D: Device<Resources=R>, F: Factory<R>
+    {
+        let fence = self.fence.lock().unwrap().clone();
+        match fence {
+            Some(ref fence) if device.is_fence_signaled(fence) => self.read(factory).map(Some),
+            _ => Ok(None),
         }
     }
+
+    /// Wait for the GPU to finish copying the data and get it.
+    /// Returns `None` if the encoder has not been flushed since the readback was requested.
+    /// Fails like `try_get`.
+    pub fn wait<D, F>(&self, device: &mut D, factory: &mut F)
+                      -> Result<Option<Vec<T>>, ReadbackError>
+        where D: Device<Resources=R>, F: Factory<R>
+    {
+        let fence = self.fence.lock().unwrap().clone();
+        match fence {
+            Some(fence) => {
+                device.wait_fence(&fence);
+                self.read(factory).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn read<F: Factory<R>>(&self, factory: &mut F) -> Result<Vec<T>, ReadbackError> {
+        let reader = try!(factory.read_mapping(&self.buffer));
+        Ok(reader.to_vec())
+    }
 }
 
 /// Graphics Command Encoder
@@ -152,6 +450,15 @@ pub struct Encoder<R: Resources, C> {
     raw_pso_data: pso::RawDataSet<R>,
     access_info: command::AccessInfo<R>,
     handles: handle::Manager<R>,
+    profiler: Option<Profiler<R>>,
+    /// Device capabilities to check the commands against, if known
+    capabilities: Option<Capabilities>,
+    /// Pipeline state bound by the last draw, if the bindings are still valid
+    last_pso: Option<R::PipelineStateObject>,
+    last_pso_data: pso::RawDataSet<R>,
+    state_stats: StateStats,
+    /// Readbacks waiting for the next submission
+    readbacks: Vec<FenceSlot<R>>,
 }
 
 impl<R: Resources, C> From<C> for Encoder<R, C> {
@@ -161,6 +468,12 @@ impl<R: Resources, C> From<C> for Encoder<R, C> {
             raw_pso_data: pso::RawDataSet::new(),
             access_info: command::AccessInfo::new(),
             handles: handle::Manager::new(),
+            profiler: None,
+            capabilities: None,
+            last_pso: None,
+            last_pso_data: pso::RawDataSet::new(),
+            state_stats: StateStats::default(),
+            readbacks: Vec::new(),
         }
     }
 }
@@ -185,14 +498,46 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
         where D: Device<Resources=R, CommandBuffer=C>
     {
         device.pin_submitted_resources(&self.handles);
-        device.submit(&mut self.command_buffer, &self.access_info)
+        if self.readbacks.is_empty() {
+            return device.submit(&mut self.command_buffer, &self.access_info)
+        }
+        let fence = try!(device.fenced_submit(&mut self.command_buffer, &self.access_info, None));
+        for slot in self.readbacks.drain(..) {
+            *slot.lock().unwrap() = Some(fence.clone());
+        }
+        Ok(())
     }
 
     /// Resets the encoded commands.
     pub fn reset(&mut self) {
+        self.invalidate_state();
         self.command_buffer.reset();
         self.access_info.clear();
         self.handles.clear();
+        self.readbacks.clear();
+    }
+
+    /// Get the counters of the emitted and skipped state commands.
+    pub fn get_state_stats(&self) -> StateStats {
+        self.state_stats
+    }
+
+    /// Reset the counters of the state commands.
+    pub fn reset_state_stats(&mut self) {
+        self.state_stats = StateStats::default();
+    }
+
+    /// Forget the bindings of the last draw call, so that the next one
+    /// binds everything. Copies, clears and the like may change the state
+    /// behind the scenes, depending on the backend. Buffer updates do not.
+    fn invalidate_state(&mut self) {
+        self.last_pso = None;
+    }
+
+    /// Get the command buffer holding the encoded commands,
+    /// for example to inspect them in the tests.
+    pub fn command_buffer(&self) -> &C {
+        &self.command_buffer
     }
 
     /// Copy part of a buffer to another
@@ -235,6 +580,8 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
         self.access_info.buffer_read(src.raw());
         self.access_info.buffer_write(dst.raw());
 
+        self.invalidate_state();
+
         self.command_buffer.copy_buffer(
             self.handles.ref_buffer(src.raw()).clone(),
             self.handles.ref_buffer(dst.raw()).clone(),
@@ -274,9 +621,14 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
                            info.zoffset + info.depth]
             });
         }
+        if !is_block_aligned(dst.get_info(), &info) {
+            return Err(CopyError::UnalignedRegion);
+        }
 
         self.access_info.buffer_read(src);
 
+        self.invalidate_state();
+
         self.command_buffer.copy_buffer_to_texture(
             self.handles.ref_buffer(src).clone(), src_offset_bytes,
             self.handles.ref_texture(dst).clone(), dst.get_info().kind,
@@ -317,9 +669,14 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
                            info.zoffset + info.depth]
             });
         }
+        if !is_block_aligned(src.get_info(), &info) {
+            return Err(CopyError::UnalignedRegion);
+        }
 
         self.access_info.buffer_write(dst);
 
+        self.invalidate_state();
+
         self.command_buffer.copy_texture_to_buffer(
             self.handles.ref_texture(src).clone(), src.get_info().kind,
             face, info,
@@ -327,6 +684,191 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
         Ok(())
     }
 
+    /// Copy a region of a texture to another texture, or another part of the same texture.
+    /// The regions must have the same size, and the formats the same texel size.
+    pub fn copy_texture_raw(
+        &mut self, src: &handle::RawTexture<R>, src_face: Option<texture::CubeFace>,
+        src_info: texture::RawImageInfo,
+        dst: &handle::RawTexture<R>, dst_face: Option<texture::CubeFace>,
+        dst_info: texture::RawImageInfo)
+        -> CopyTextureResult
+    {
+        if !self.is_supported(|caps| caps.copy_texture_supported) {
+            return Err(CopyError::Unsupported);
+        }
+        if !src.get_info().bind.contains(memory::TRANSFER_SRC) {
+            return Err(CopyError::NoSrcBindFlag);
+        }
+        if !dst.get_info().bind.contains(memory::TRANSFER_DST) {
+            return Err(CopyError::NoDstBindFlag);
+        }
+        if src.get_info().format.get_total_bits() != dst.get_info().format.get_total_bits() ||
+           is_depth_stencil(src.get_info().format) != is_depth_stencil(dst.get_info().format)
+        {
+            return Err(CopyError::IncompatibleFormats);
+        }
+        let (src_size, dst_size) = ([src_info.width, src_info.height, src_info.depth],
+                                    [dst_info.width, dst_info.height, dst_info.depth]);
+        if src_size != dst_size {
+            return Err(CopyError::SizeMismatch {
+                src: src_size,
+                dst: dst_size,
+            });
+        }
+        if let Err(size) = check_texture_region(src.get_info(), &src_info) {
+            return Err(CopyError::OutOfSrcBounds {
+                size: size,
+                copy_end: get_copy_end(&src_info),
+            });
+        }
+        if let Err(size) = check_texture_region(dst.get_info(), &dst_info) {
+            return Err(CopyError::OutOfDstBounds {
+                size: size,
+                copy_end: get_copy_end(&dst_info),
+            });
+        }
+        if !is_block_aligned(src.get_info(), &src_info) ||
+           !is_block_aligned(dst.get_info(), &dst_info) {
+            return Err(CopyError::UnalignedRegion);
+        }
+
+        self.invalidate_state();
+
+        self.command_buffer.copy_texture_to_texture(
+            self.handles.ref_texture(src).clone(), src.get_info().kind, src_face, src_info,
+            self.handles.ref_texture(dst).clone(), dst.get_info().kind, dst_face, dst_info);
+        Ok(())
+    }
+
+    /// Copy a region of a texture to another texture of the same format.
+    pub fn copy_texture<T: format::Formatted>(
+        &mut self, src: &handle::Texture<R, T::Surface>, src_face: Option<texture::CubeFace>,
+        src_img: texture::NewImageInfo,
+        dst: &handle::Texture<R, T::Surface>, dst_face: Option<texture::CubeFace>,
+        dst_img: texture::NewImageInfo)
+        -> CopyTextureResult
+    {
+        self.copy_texture_raw(src.raw(), src_face, src_img.convert(T::get_format()),
+                              dst.raw(), dst_face, dst_img.convert(T::get_format()))
+    }
+
+    /// Copy a region of a texture to a region of another texture, scaling it
+    /// with the given filter. The source is flipped along the `mirror` axes.
+    pub fn blit_raw(
+        &mut self, src: &handle::RawTexture<R>, src_face: Option<texture::CubeFace>,
+        src_info: texture::RawImageInfo,
+        dst: &handle::RawTexture<R>, dst_face: Option<texture::CubeFace>,
+        dst_info: texture::RawImageInfo,
+        mirror: Mirror, filter: command::BlitFilter)
+        -> CopyTextureResult
+    {
+        if !self.is_supported(|caps| caps.blit_texture_supported) {
+            return Err(CopyError::Unsupported);
+        }
+        if !src.get_info().bind.contains(memory::TRANSFER_SRC) {
+            return Err(CopyError::NoSrcBindFlag);
+        }
+        if !dst.get_info().bind.contains(memory::TRANSFER_DST) {
+            return Err(CopyError::NoDstBindFlag);
+        }
+        let (src_format, dst_format) = (src.get_info().format, dst.get_info().format);
+        if is_depth_stencil(src_format) || is_depth_stencil(dst_format) {
+            if src_format != dst_format {
+                return Err(CopyError::IncompatibleFormats);
+            }
+            if filter != command::BlitFilter::Nearest {
+                return Err(CopyError::InvalidFilter);
+            }
+        }
+        if let Err(size) = check_texture_region(src.get_info(), &src_info) {
+            return Err(CopyError::OutOfSrcBounds {
+                size: size,
+                copy_end: get_copy_end(&src_info),
+            });
+        }
+        if let Err(size) = check_texture_region(dst.get_info(), &dst_info) {
+            return Err(CopyError::OutOfDstBounds {
+                size: size,
+                copy_end: get_copy_end(&dst_info),
+            });
+        }
+
+        self.invalidate_state();
+
+        self.command_buffer.blit_texture(
+            self.handles.ref_texture(src).clone(), src.get_info().kind, src_face, src_info,
+            self.handles.ref_texture(dst).clone(), dst.get_info().kind, dst_face, dst_info,
+            mirror, filter);
+        Ok(())
+    }
+
+    /// Copy a region of a texture to a region of another texture, scaling it
+    /// with the given filter. The formats are given as `blit::<S, D>(..)`.
+    pub fn blit<S: format::Formatted, D: format::Formatted>(
+        &mut self, src: &handle::Texture<R, S::Surface>, src_face: Option<texture::CubeFace>,
+        src_img: texture::NewImageInfo,
+        dst: &handle::Texture<R, D::Surface>, dst_face: Option<texture::CubeFace>,
+        dst_img: texture::NewImageInfo,
+        mirror: Mirror, filter: command::BlitFilter)
+        -> CopyTextureResult
+    {
+        self.blit_raw(src.raw(), src_face, src_img.convert(S::get_format()),
+                      dst.raw(), dst_face, dst_img.convert(D::get_format()),
+                      mirror, filter)
+    }
+
+    /// Resolve a multisampled texture into a single-sampled texture
+    /// of the same format and size.
+    pub fn resolve_raw(&mut self, src: &handle::RawTexture<R>, dst: &handle::RawTexture<R>,
+                       format: format::Format) -> CopyTextureResult
+    {
+        if !self.is_supported(|caps| caps.resolve_texture_supported) {
+            return Err(CopyError::Unsupported);
+        }
+        let (src_info, dst_info) = (src.get_info(), dst.get_info());
+        if !src_info.bind.contains(memory::TRANSFER_SRC) {
+            return Err(CopyError::NoSrcBindFlag);
+        }
+        if !dst_info.bind.contains(memory::TRANSFER_DST) {
+            return Err(CopyError::NoDstBindFlag);
+        }
+        let (sw, sh, sd, src_aa) = src_info.kind.get_dimensions();
+        let (dw, dh, dd, dst_aa) = dst_info.kind.get_dimensions();
+        if !src_aa.needs_resolve() {
+            return Err(CopyError::SrcNotMultisampled);
+        }
+        if dst_aa.needs_resolve() {
+            return Err(CopyError::DstMultisampled);
+        }
+        if src_info.format != format.0 || dst_info.format != format.0 {
+            return Err(CopyError::IncompatibleFormats);
+        }
+        if (sw, sh) != (dw, dh) {
+            return Err(CopyError::SizeMismatch {
+                src: [sw, sh, sd],
+                dst: [dw, dh, dd],
+            });
+        }
+
+        self.invalidate_state();
+
+        self.command_buffer.resolve_texture(
+            self.handles.ref_texture(src).clone(), src_info.kind,
+            self.handles.ref_texture(dst).clone(), dst_info.kind,
+            format);
+        Ok(())
+    }
+
+    /// Resolve a multisampled texture or render target into a single-sampled one,
+    /// so that it can be sampled or presented.
+    pub fn resolve<T, S, D>(&mut self, src: &S, dst: &D) -> CopyTextureResult where
+        T: format::Formatted,
+        S: ResolveView<R, T>,
+        D: ResolveView<R, T>,
+    {
+        self.resolve_raw(src.get_texture(), dst.get_texture(), T::get_format())
+    }
+
     /// Update a buffer with a slice of data.
     pub fn update_buffer<T: Pod>(&mut self, buf: &handle::Buffer<R, T>,
                          data: &[T], offset_elements: usize)
@@ -364,6 +906,46 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
             self.handles.ref_buffer(buf.raw()).clone(), slice, 0);
     }
 
+    fn add_readback<T>(&mut self, buffer: handle::Buffer<R, T>) -> Readback<R, T> {
+        let fence = Arc::new(Mutex::new(None));
+        self.readbacks.push(fence.clone());
+        Readback {
+            buffer: buffer,
+            fence: fence,
+        }
+    }
+
+    /// Read the contents of a buffer back. The data is copied to a
+    /// `Usage::Download` staging buffer, and is available from the returned
+    /// `Readback` once the encoder is flushed and the GPU has executed it.
+    pub fn read_buffer<F, T>(&mut self, factory: &mut F, buf: &handle::Buffer<R, T>)
+                             -> Result<Readback<R, T>, ReadbackError>
+        where F: Factory<R>, T: Pod
+    {
+        let len = buf.len();
+        let staging = try!(factory.create_buffer::<T>(len, buffer::Role::Staging,
+                                                      Usage::Download, memory::TRANSFER_DST));
+        try!(self.copy_buffer(buf, &staging, 0, 0, len));
+        Ok(self.add_readback(staging))
+    }
+
+    /// Read a region of a texture back, see `read_buffer`.
+    pub fn read_texture<F, S, T>(&mut self, factory: &mut F, tex: &handle::Texture<R, T::Surface>,
+                                 face: Option<texture::CubeFace>, img: texture::NewImageInfo)
+                                 -> Result<Readback<R, S::DataType>, ReadbackError>
+    where
+        F: Factory<R>,
+        S: format::SurfaceTyped,
+        S::DataType: Copy,
+        T: format::Formatted<Surface = S>,
+    {
+        let staging = try!(factory.create_buffer::<S::DataType>(
+            img.get_texel_count(), buffer::Role::Staging, Usage::Download, memory::TRANSFER_DST));
+        try!(self.copy_texture_to_buffer_raw(tex.raw(), face, img.convert(T::get_format()),
+                                             staging.raw(), 0));
+        Ok(self.add_readback(staging))
+    }
+
     /// Update the contents of a texture.
     pub fn update_texture<S, T>(&mut self, tex: &handle::Texture<R, T::Surface>,
                           face: Option<texture::CubeFace>,
@@ -377,7 +959,8 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
         if data.is_empty() { return Ok(()); }
         try!(check_update_usage(tex.raw().get_info().usage));
 
-        let target_count = img.get_texel_count();
+        // compressed surfaces take one data unit per block
+        let target_count = img.get_block_count(S::get_surface_type());
         if target_count != data.len() {
             return Err(UpdateError::UnitCountMismatch {
                 target: target_count,
@@ -397,11 +980,16 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
                 source: [w, h, d],
             })
         }
+        let raw_img = img.convert(T::get_format());
+        if !is_block_aligned(tex.get_info(), &raw_img) {
+            return Err(UpdateError::UnalignedRegion)
+        }
+
+        self.invalidate_state();
 
         self.command_buffer.update_texture(
             self.handles.ref_texture(tex.raw()).clone(),
-            tex.get_info().kind, face, cast_slice(data),
-            img.convert(T::get_format()));
+            tex.get_info().kind, face, cast_slice(data), raw_img);
         Ok(())
     }
 
@@ -429,12 +1017,14 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
                  view: &handle::RenderTargetView<R, T>, value: T::View)
     where T::View: Into<command::ClearColor> {
         let target = self.handles.ref_rtv(view.raw()).clone();
+        self.invalidate_state();
         self.command_buffer.clear_color(target, value.into())
     }
     /// Clear a depth view with a specified value.
     pub fn clear_depth<T: format::DepthFormat>(&mut self,
                        view: &handle::DepthStencilView<R, T>, depth: Depth) {
         let target = self.handles.ref_dsv(view.raw()).clone();
+        self.invalidate_state();
         self.command_buffer.clear_depth_stencil(target, Some(depth), None)
     }
 
@@ -442,30 +1032,245 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
     pub fn clear_stencil<T: format::StencilFormat>(&mut self,
                          view: &handle::DepthStencilView<R, T>, stencil: Stencil) {
         let target = self.handles.ref_dsv(view.raw()).clone();
+        self.invalidate_state();
         self.command_buffer.clear_depth_stencil(target, None, Some(stencil))
     }
 
+    /// Starts gathering information into the query, until `end_query` is called.
+    pub fn begin_query(&mut self, query: &handle::Query<R>) {
+        let q = self.handles.ref_query(query).clone();
+        self.command_buffer.begin_query(q);
+    }
+
+    /// Stops gathering information into the query. The result can then be
+    /// polled with `Factory::get_query_result`, once the encoder is flushed.
+    pub fn end_query(&mut self, query: &handle::Query<R>) {
+        self.access_info.query_write(query);
+        let q = self.handles.ref_query(query).clone();
+        self.command_buffer.end_query(q);
+    }
+
+    /// Starts skipping the draw calls on the GPU if no sample passed during the query.
+    pub fn begin_conditional(&mut self, query: &handle::Query<R>) {
+        let q = self.handles.ref_query(query).clone();
+        self.command_buffer.begin_conditional(q);
+    }
+
+    /// Stops the conditional rendering started by `begin_conditional`.
+    pub fn end_conditional(&mut self) {
+        self.command_buffer.end_conditional();
+    }
+
+    /// Writes the GPU time into a timestamp query, once all the previous commands are done.
+    pub fn write_timestamp(&mut self, query: &handle::Query<R>) {
+        self.access_info.query_write(query);
+        let q = self.handles.ref_query(query).clone();
+        self.command_buffer.write_timestamp(q);
+    }
+
+    /// Sets the capabilities of the device this encoder records for. Commands
+    /// that the device does not support then return an error instead of being
+    /// recorded. Without capabilities, every command is assumed to be supported.
+    pub fn set_capabilities(&mut self, capabilities: Option<Capabilities>) {
+        self.capabilities = capabilities;
+    }
+
+    /// Get the device capabilities, if they were set.
+    pub fn capabilities(&self) -> Option<&Capabilities> {
+        self.capabilities.as_ref()
+    }
+
+    /// Check a device capability, assuming it is there if the capabilities are unknown.
+    fn is_supported<F: FnOnce(&Capabilities) -> bool>(&self, check: F) -> bool {
+        self.capabilities.as_ref().map_or(true, check)
+    }
+
+    /// Attaches a profiler to this encoder, which is then used by `profile_scope`.
+    pub fn set_profiler(&mut self, profiler: Option<Profiler<R>>) {
+        self.profiler = profiler;
+    }
+
+    /// Get the attached profiler.
+    pub fn profiler(&self) -> Option<&Profiler<R>> {
+        self.profiler.as_ref()
+    }
+
+    /// Get the attached profiler, mutably.
+    pub fn profiler_mut(&mut self) -> Option<&mut Profiler<R>> {
+        self.profiler.as_mut()
+    }
+
+    /// Starts a named profiling scope, timed on the GPU by the attached profiler.
+    /// Does nothing if there is no profiler.
+    pub fn begin_profile_scope(&mut self, name: &str) {
+        let query = self.profiler.as_mut().and_then(|p| p.begin_scope(name));
+        if let Some(query) = query {
+            self.write_timestamp(&query);
+        }
+    }
+
+    /// Ends the innermost profiling scope.
+    pub fn end_profile_scope(&mut self) {
+        let query = self.profiler.as_mut().and_then(|p| p.end_scope());
+        if let Some(query) = query {
+            self.write_timestamp(&query);
+        }
+    }
+
+    /// Starts a named profiling scope that ends when the returned guard is dropped.
+    /// The guard dereferences to the encoder.
+    pub fn profile_scope<'a>(&'a mut self, name: &str) -> profiler::Scope<'a, R, C> {
+        self.begin_profile_scope(name);
+        profiler::Scope::new(self)
+    }
+
+    fn bind_pipeline_data<D: pso::PipelineData<R>>(&mut self,
+                          pipeline: &pso::PipelineState<R, D::Meta>, user_data: &D)
+    {
+        let (pso, _) = self.handles.ref_pso(pipeline.get_handle());
+        self.raw_pso_data.clear();
+        user_data.bake_to(&mut self.raw_pso_data, pipeline.get_meta(), &mut self.handles, &mut self.access_info);
+        {
+            let data = &self.raw_pso_data;
+            let last = &self.last_pso_data;
+            let stats = &mut self.state_stats;
+            let cb = &mut self.command_buffer;
+            // a new pipeline state may reset any of the bindings in the backend
+            let all = self.last_pso.as_ref() != Some(pso);
+            let targets = all || data.pixel_targets != last.pixel_targets;
+            if count_state(stats, targets) {
+                cb.bind_pixel_targets(data.pixel_targets.clone());
+            }
+            if count_state(stats, all) {
+                cb.bind_pipeline_state(pso.clone());
+            }
+            if count_state(stats, all || data.vertex_buffers.0 != last.vertex_buffers.0) {
+                cb.bind_vertex_buffers(data.vertex_buffers.clone());
+            }
+            if count_state(stats, all || data.ref_values != last.ref_values) {
+                cb.set_ref_values(data.ref_values);
+            }
+            if count_state(stats, all || data.scissor != last.scissor) {
+                cb.set_scissor(data.scissor);
+            }
+            if count_state(stats, all || !same_params(&data.constant_buffers, &last.constant_buffers,
+                                                      |a, b| a.0 == b.0 && a.1 == b.1 && a.2 == b.2 && a.3 == b.3)) {
+                cb.bind_constant_buffers(&data.constant_buffers);
+            }
+            // uniform values can't be compared, so they are always set
+            for &(location, value) in &data.global_constants {
+                stats.emitted += 1;
+                cb.bind_global_constant(location, value);
+            }
+            //Note: it's important to bind RTV, DSV, and UAV before SRV,
+            // so the views are bound again when the targets change
+            if count_state(stats, targets || !same_params(&data.unordered_views, &last.unordered_views,
+                                                          |a, b| a.0 == b.0 && a.1 == b.1 && a.2 == b.2)) {
+                cb.bind_unordered_views(&data.unordered_views);
+            }
+            let views = targets || !same_params(&data.resource_views, &last.resource_views,
+                                                |a, b| a.0 == b.0 && a.1 == b.1 && a.2 == b.2);
+            if count_state(stats, views) {
+                cb.bind_resource_views(&data.resource_views);
+            }
+            if count_state(stats, views || !same_params(&data.samplers, &last.samplers,
+                                                        |a, b| a.0 == b.0 && a.1 == b.1 && a.2 == b.2)) {
+                cb.bind_samplers(&data.samplers);
+            }
+        }
+        mem::swap(&mut self.raw_pso_data, &mut self.last_pso_data);
+        self.last_pso = Some(pso.clone());
+    }
+
     /// Draws a `slice::Slice` using a pipeline state object, and its matching `Data` structure.
     pub fn draw<D: pso::PipelineData<R>>(&mut self, slice: &slice::Slice<R>,
                 pipeline: &pso::PipelineState<R, D::Meta>, user_data: &D)
     {
+        self.bind_pipeline_data(pipeline, user_data);
+        self.draw_slice(slice, slice.instances);
+    }
+
+    /// Draws `count` primitive batches, whose `DrawIndirectArgs` are read from the `args` buffer
+    /// starting at the element `offset`, using a pipeline state object and its matching `Data`
+    /// structure. The arguments may be written by the GPU, e.g. by a culling pass.
+    /// Fails if the records are out of the buffer bounds, or indirect draws are not supported.
+    pub fn draw_indirect<D: pso::PipelineData<R>>(&mut self,
+                         args: &handle::Buffer<R, command::DrawIndirectArgs>,
+                         offset: usize, count: usize,
+                         pipeline: &pso::PipelineState<R, D::Meta>, user_data: &D)
+                         -> Result<(), DrawIndirectError>
+    {
+        try!(self.check_draw_indirect(args.len(), offset, count));
+        self.bind_pipeline_data(pipeline, user_data);
+        self.access_info.buffer_read(args.raw());
+        let buf = self.handles.ref_buffer(args.raw()).clone();
+        let offset_bytes = offset * mem::size_of::<command::DrawIndirectArgs>();
+        self.command_buffer.call_draw_indirect(buf, offset_bytes, count);
+        Ok(())
+    }
+
+    /// Draws `count` indexed primitive batches, whose `DrawIndexedIndirectArgs` are read from the
+    /// `args` buffer starting at the element `offset`, using the supplied index buffer, a pipeline
+    /// state object and its matching `Data` structure.
+    pub fn draw_indexed_indirect<D: pso::PipelineData<R>>(&mut self, index: &slice::IndexBuffer<R>,
+                                 args: &handle::Buffer<R, command::DrawIndexedIndirectArgs>,
+                                 offset: usize, count: usize,
+                                 pipeline: &pso::PipelineState<R, D::Meta>, user_data: &D)
+                                 -> Result<(), DrawIndirectError>
+    {
+        try!(self.check_draw_indirect(args.len(), offset, count));
+        let (raw_index, ty) = match *index {
+            slice::IndexBuffer::Auto => return Err(DrawIndirectError::NoIndexBuffer),
+            slice::IndexBuffer::Index16(ref buf) => (buf.raw(), IndexType::U16),
+            slice::IndexBuffer::Index32(ref buf) => (buf.raw(), IndexType::U32),
+        };
+        self.bind_pipeline_data(pipeline, user_data);
+        self.access_info.buffer_read(raw_index);
+        self.access_info.buffer_read(args.raw());
+        self.command_buffer.bind_index(self.handles.ref_buffer(raw_index).clone(), ty);
+        let buf = self.handles.ref_buffer(args.raw()).clone();
+        let offset_bytes = offset * mem::size_of::<command::DrawIndexedIndirectArgs>();
+        self.command_buffer.call_draw_indexed_indirect(buf, offset_bytes, count);
+        Ok(())
+    }
+
+    fn check_draw_indirect(&self, size: usize, offset: usize, count: usize)
+                           -> Result<(), DrawIndirectError> {
+        if !self.is_supported(|caps| caps.draw_indirect_supported) {
+            return Err(DrawIndirectError::Unsupported);
+        }
+        // an overflowing end is out of bounds of any buffer
+        let draw_end = offset.checked_add(count).unwrap_or(usize::max_value());
+        if draw_end > size {
+            return Err(DrawIndirectError::OutOfBounds {
+                size: size,
+                draw_end: draw_end,
+            });
+        }
+        Ok(())
+    }
+
+    /// Dispatches `groups` work groups of a compute pipeline state object, using its matching
+    /// `Data` structure. Fails if compute shaders are not supported.
+    pub fn dispatch<D: pso::PipelineData<R>>(&mut self, pipeline: &pso::PipelineState<R, D::Meta>,
+                    user_data: &D, groups: [u32; 3]) -> Result<(), DispatchError>
+    {
+        if !self.is_supported(|caps| caps.compute_supported) {
+            return Err(DispatchError::Unsupported);
+        }
         let (pso, _) = self.handles.ref_pso(pipeline.get_handle());
-        //TODO: make `raw_data` a member to this struct, to re-use the heap allocation
+        self.invalidate_state();
         self.raw_pso_data.clear();
         user_data.bake_to(&mut self.raw_pso_data, pipeline.get_meta(), &mut self.handles, &mut self.access_info);
-        self.command_buffer.bind_pixel_targets(self.raw_pso_data.pixel_targets.clone());
         self.command_buffer.bind_pipeline_state(pso.clone());
-        self.command_buffer.bind_vertex_buffers(self.raw_pso_data.vertex_buffers.clone());
-        self.command_buffer.set_ref_values(self.raw_pso_data.ref_values);
-        self.command_buffer.set_scissor(self.raw_pso_data.scissor);
         self.command_buffer.bind_constant_buffers(&self.raw_pso_data.constant_buffers);
         for &(location, value) in &self.raw_pso_data.global_constants {
             self.command_buffer.bind_global_constant(location, value);
         }
         self.command_buffer.bind_unordered_views(&self.raw_pso_data.unordered_views);
-        //Note: it's important to bind RTV, DSV, and UAV before SRV
         self.command_buffer.bind_resource_views(&self.raw_pso_data.resource_views);
         self.command_buffer.bind_samplers(&self.raw_pso_data.samplers);
-        self.draw_slice(slice, slice.instances);
+        self.command_buffer.call_dispatch(groups);
+        Ok(())
     }
 }
@@ -175,6 +175,17 @@ pub trait FactoryExt<R: Resources>: Factory<R> {
                            memory::TRANSFER_DST)
     }
 
+    /// Creates an indirect arguments buffer for `num` draw calls, described by `T`, which is
+    /// either `DrawIndirectArgs` or `DrawIndexedIndirectArgs`.
+    fn create_indirect_buffer<T>(&mut self, num: usize, bind: Bind)
+                                 -> Result<handle::Buffer<R, T>, buffer::CreationError>
+    {
+        self.create_buffer(num,
+                           buffer::Role::Indirect,
+                           memory::Usage::Data,
+                           bind)
+    }
+
     /// Creates a `ShaderSet` from the supplied vertex and pixel shader source code.
     fn create_shader_set(&mut self, vs_code: &[u8], ps_code: &[u8])
                          -> Result<ShaderSet<R>, ProgramError> {
@@ -263,6 +274,20 @@ pub trait FactoryExt<R: Resources>: Factory<R> {
                                    init)
     }
 
+    /// Creates a strongly typed compute `PipelineState` from its `Init` structure and the
+    /// source of a compute shader. The result is meant to be used with `Encoder::dispatch`.
+    fn create_pipeline_compute<I: pso::PipelineInit>(&mut self, cs: &[u8], init: I)
+                               -> Result<pso::PipelineState<R, I::Meta>, PipelineStateError<String>>
+    {
+        let cs = match self.create_shader_compute(cs) {
+            Ok(s) => s,
+            Err(e) => return Err(ProgramError::Compute(e).into()),
+        };
+        let set = ShaderSet::Compute(cs);
+        self.create_pipeline_state(&set, Primitive::PointList, state::Rasterizer::new_fill(),
+                                   init)
+    }
+
     /// Create a linear sampler with clamping to border.
     fn create_sampler_linear(&mut self) -> handle::Sampler<R> {
         self.create_sampler(texture::SamplerInfo::new(
@@ -0,0 +1,422 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Frame graph with transient render targets.
+//!
+//! A `Graph` is built every frame out of passes, each declaring the
+//! textures it reads and writes. Executing the graph:
+//!
+//! - orders the passes so that each texture is written before it is read,
+//! - culls the passes whose results are never used. Passes that don't write
+//!   any texture of the graph are assumed to have side effects (like drawing
+//!   to the screen) and are always executed,
+//! - allocates the textures from a `ResourcePool`, re-using the same memory
+//!   for textures whose lifetimes don't overlap. The pool keeps the textures
+//!   and their views between the frames, until they are unused for too long.
+//!
+//! The textures only live during the execution, and a pass may only access the
+//! textures it declared, since the others may be aliased.
+
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use core::{Resources, format, handle, texture};
+use core::command::Buffer as CommandBuffer;
+use core::factory::{CombinedError, Factory};
+use core::memory::{self, Typed};
+use encoder::Encoder;
+use pool::ResourcePool;
+
+/// An error occuring in the graph execution.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GraphError {
+    /// The passes depend on each other in a cycle, including the named pass.
+    Cycle(String),
+    /// The named texture is read, but no pass writes it.
+    Unwritten(String),
+    /// Failed to create a transient texture or its views.
+    Resource(CombinedError),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GraphError::Cycle(ref name) => write!(f, "{}: {}", self.description(), name),
+            GraphError::Unwritten(ref name) => write!(f, "{}: {}", self.description(), name),
+            GraphError::Resource(ref e) => write!(f, "{}: {}", self.description(), e),
+        }
+    }
+}
+
+impl Error for GraphError {
+    fn description(&self) -> &str {
+        match *self {
+            GraphError::Cycle(_) => "The passes have a cyclic dependency",
+            GraphError::Unwritten(_) => "The texture is read but never written",
+            GraphError::Resource(_) => "Failed to create a transient texture",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            GraphError::Resource(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<CombinedError> for GraphError {
+    fn from(e: CombinedError) -> GraphError {
+        GraphError::Resource(e)
+    }
+}
+
+/// A texture of the graph, interpreted with the format `T`.
+pub struct TextureId<T>(usize, PhantomData<T>);
+
+impl<T> Clone for TextureId<T> {
+    fn clone(&self) -> Self {
+        TextureId(self.0, PhantomData)
+    }
+}
+
+impl<T> Copy for TextureId<T> {}
+
+/// The storage requirements of a transient texture.
+type Key = (texture::Info, format::ChannelType);
+
+/// A transient texture with its views.
+#[derive(Clone, Debug)]
+struct Transient<R: Resources> {
+    texture: handle::RawTexture<R>,
+    rtv: Option<handle::RawRenderTargetView<R>>,
+    dsv: Option<handle::RawDepthStencilView<R>>,
+    srv: Option<handle::RawShaderResourceView<R>>,
+}
+
+impl<R: Resources> Transient<R> {
+    fn new<F: Factory<R>>(pool: &mut ResourcePool<R>, factory: &mut F, key: &Key)
+                          -> Result<Transient<R>, CombinedError> {
+        let (info, channel) = *key;
+        if info.levels == 0 {
+            return Err(CombinedError::Texture(texture::CreationError::Size(0)))
+        }
+        let texture = try!(pool.get_texture_raw(factory, info, Some(channel)));
+        let rtv = if info.bind.contains(memory::RENDER_TARGET) {
+            let desc = texture::RenderDesc {
+                channel: channel,
+                level: 0,
+                layer: None,
+            };
+            Some(try!(pool.view_texture_as_render_target_raw(factory, &texture, desc)))
+        } else {
+            None
+        };
+        let dsv = if info.bind.contains(memory::DEPTH_STENCIL) {
+            let desc = texture::DepthStencilDesc {
+                level: 0,
+                layer: None,
+                flags: texture::DepthStencilFlags::empty(),
+            };
+            Some(try!(pool.view_texture_as_depth_stencil_raw(factory, &texture, desc)))
+        } else {
+            None
+        };
+        let srv = if info.bind.contains(memory::SHADER_RESOURCE) {
+            let desc = texture::ResourceDesc {
+                channel: channel,
+                layer: None,
+                min: 0,
+                max: info.levels - 1,
+                swizzle: format::Swizzle::new(),
+            };
+            Some(try!(pool.view_texture_as_shader_resource_raw(factory, &texture, desc)))
+        } else {
+            None
+        };
+        Ok(Transient {
+            texture: texture,
+            rtv: rtv,
+            dsv: dsv,
+            srv: srv,
+        })
+    }
+}
+
+/// The textures accessible to a pass during its execution.
+///
+/// The getters panic if the texture is not declared by the pass, or
+/// is not declared with the access the requested view needs.
+pub struct Targets<'a, R: Resources> {
+    names: &'a [String],
+    transients: Vec<Option<Transient<R>>>,
+}
+
+impl<'a, R: Resources> Targets<'a, R> {
+    fn get<T>(&self, id: TextureId<T>) -> &Transient<R> {
+        match self.transients.get(id.0) {
+            Some(&Some(ref transient)) => transient,
+            _ => panic!("Texture {} is not declared by the pass", self.names[id.0]),
+        }
+    }
+
+    /// Get the raw texture. Panics if the pass does not declare it.
+    pub fn get_texture<T>(&self, id: TextureId<T>) -> &handle::RawTexture<R> {
+        &self.get(id).texture
+    }
+
+    /// Get the render target view of a texture written by the pass.
+    /// Panics if the pass does not write it.
+    pub fn get_render_target<T: format::RenderFormat>(&self, id: TextureId<T>)
+                             -> handle::RenderTargetView<R, T> {
+        match self.get(id).rtv {
+            Some(ref rtv) => Typed::new(rtv.clone()),
+            None => panic!("Texture {} is not written by the pass", self.names[id.0]),
+        }
+    }
+
+    /// Get the depth stencil view of a texture written by the pass.
+    /// Panics if the pass does not write it.
+    pub fn get_depth_stencil<T: format::DepthFormat>(&self, id: TextureId<T>)
+                             -> handle::DepthStencilView<R, T> {
+        match self.get(id).dsv {
+            Some(ref dsv) => Typed::new(dsv.clone()),
+            None => panic!("Texture {} is not written by the pass", self.names[id.0]),
+        }
+    }
+
+    /// Get the shader resource view of a texture read by the pass.
+    /// Panics if the pass does not read it.
+    pub fn get_shader_resource<T: format::TextureFormat>(&self, id: TextureId<T>)
+                               -> handle::ShaderResourceView<R, T::View> {
+        match self.get(id).srv {
+            Some(ref srv) => Typed::new(srv.clone()),
+            None => panic!("Texture {} is not read by the pass", self.names[id.0]),
+        }
+    }
+}
+
+/// The function executing a pass.
+pub type PassFn<'a, R, C> = Box<FnMut(&mut Encoder<R, C>, &Targets<R>) + 'a>;
+
+struct Pass<'a, R: 'a + Resources, C: 'a> {
+    name: String,
+    reads: Vec<usize>,
+    writes: Vec<usize>,
+    function: PassFn<'a, R, C>,
+}
+
+/// Declares the textures used by a pass.
+pub struct PassBuilder<'b, 'a: 'b, R: 'a + Resources, C: 'a> {
+    pass: &'b mut Pass<'a, R, C>,
+}
+
+impl<'b, 'a, R: Resources, C> PassBuilder<'b, 'a, R, C> {
+    /// Declare a texture read by the pass.
+    pub fn read<T>(self, id: TextureId<T>) -> Self {
+        self.pass.reads.push(id.0);
+        self
+    }
+
+    /// Declare a texture written by the pass.
+    pub fn write<T>(self, id: TextureId<T>) -> Self {
+        self.pass.writes.push(id.0);
+        self
+    }
+}
+
+fn is_depth_stencil(surface: format::SurfaceType) -> bool {
+    use core::format::SurfaceType as S;
+    match surface {
+        S::D16 | S::D24 | S::D24_S8 | S::D32 => true,
+        _ => false,
+    }
+}
+
+/// A frame graph.
+pub struct Graph<'a, R: 'a + Resources, C: 'a> {
+    names: Vec<String>,
+    keys: Vec<Key>,
+    passes: Vec<Pass<'a, R, C>>,
+}
+
+impl<'a, R: Resources, C: CommandBuffer<R>> Graph<'a, R, C> {
+    /// Create a new empty graph.
+    pub fn new() -> Graph<'a, R, C> {
+        Graph {
+            names: Vec::new(),
+            keys: Vec::new(),
+            passes: Vec::new(),
+        }
+    }
+
+    /// Declare a transient texture, described by `info` and the format `T`.
+    /// The surface type of `info` is overridden by the one of `T`, and the
+    /// bind flags are extended according to the way the passes use it.
+    pub fn create_texture<T: format::Formatted>(&mut self, name: &str, info: texture::Info)
+                          -> TextureId<T> {
+        let format = T::get_format();
+        let info = texture::Info {
+            format: format.0,
+            .. info
+        };
+        self.names.push(name.to_string());
+        self.keys.push((info, format.1));
+        TextureId(self.keys.len() - 1, PhantomData)
+    }
+
+    /// Add a pass executed by `function`. The returned builder declares
+    /// the textures it uses.
+    pub fn add_pass<'b, F>(&'b mut self, name: &str, function: F) -> PassBuilder<'b, 'a, R, C>
+        where F: FnMut(&mut Encoder<R, C>, &Targets<R>) + 'a
+    {
+        self.passes.push(Pass {
+            name: name.to_string(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+            function: Box::new(function),
+        });
+        PassBuilder {
+            pass: self.passes.last_mut().unwrap(),
+        }
+    }
+
+    /// Get the order in which the passes are executed, culling
+    /// the passes whose results are not used.
+    fn schedule(&self) -> Result<Vec<usize>, GraphError> {
+        let num = self.passes.len();
+        let mut writers = vec![Vec::new(); self.keys.len()];
+        for (i, pass) in self.passes.iter().enumerate() {
+            for &t in &pass.writes {
+                writers[t].push(i);
+            }
+        }
+        // dependencies of each pass
+        let mut deps = vec![Vec::new(); num];
+        for (i, pass) in self.passes.iter().enumerate() {
+            for &t in &pass.reads {
+                if writers[t].is_empty() {
+                    return Err(GraphError::Unwritten(self.names[t].clone()))
+                }
+                deps[i].extend(writers[t].iter().cloned().filter(|&w| w != i));
+            }
+            // multiple writers of a texture keep their declaration order
+            for &t in &pass.writes {
+                deps[i].extend(writers[t].iter().cloned().filter(|&w| w < i));
+            }
+        }
+        // cull the passes that don't contribute to any pass with side effects
+        let mut live = vec![false; num];
+        let mut stack: Vec<usize> = (0 .. num).filter(|&i| self.passes[i].writes.is_empty()).collect();
+        while let Some(i) = stack.pop() {
+            if !live[i] {
+                live[i] = true;
+                stack.extend(deps[i].iter().cloned());
+            }
+        }
+        // order the live passes, preferring the declaration order
+        let mut order = Vec::new();
+        let mut done = vec![false; num];
+        while order.len() < live.iter().filter(|&&l| l).count() {
+            let next = (0 .. num).find(|&i| live[i] && !done[i] && deps[i].iter().all(|&d| done[d]));
+            match next {
+                Some(i) => {
+                    done[i] = true;
+                    order.push(i);
+                },
+                None => {
+                    let i = (0 .. num).find(|&i| live[i] && !done[i]).unwrap();
+                    return Err(GraphError::Cycle(self.passes[i].name.clone()))
+                },
+            }
+        }
+        Ok(order)
+    }
+
+    /// Execute the graph, allocating the textures from the `pool`.
+    /// They return to the pool once the commands using them are done.
+    pub fn execute<F>(mut self, pool: &mut ResourcePool<R>, factory: &mut F,
+                      encoder: &mut Encoder<R, C>) -> Result<(), GraphError>
+        where F: Factory<R>
+    {
+        let order = try!(self.schedule());
+        // find the lifetimes and the bind flags of the textures
+        let mut lifetimes: Vec<Option<(usize, usize)>> = vec![None; self.keys.len()];
+        for (step, &i) in order.iter().enumerate() {
+            let pass = &self.passes[i];
+            for &t in pass.reads.iter().chain(pass.writes.iter()) {
+                lifetimes[t] = Some(match lifetimes[t] {
+                    Some((first, _)) => (first, step),
+                    None => (step, step),
+                });
+            }
+            let keys = &mut self.keys;
+            for &t in &pass.reads {
+                keys[t].0.bind = keys[t].0.bind | memory::SHADER_RESOURCE;
+            }
+            for &t in &pass.writes {
+                let info = &mut keys[t].0;
+                let bind = if is_depth_stencil(info.format) {
+                    memory::DEPTH_STENCIL
+                } else {
+                    memory::RENDER_TARGET
+                };
+                info.bind = info.bind | bind;
+            }
+        }
+
+        let mut physical: Vec<Option<Transient<R>>> = vec![None; self.keys.len()];
+        // textures past their lifetime, to be aliased by the later ones
+        let mut released: Vec<(Key, Transient<R>)> = Vec::new();
+        for (step, &i) in order.iter().enumerate() {
+            for (t, lifetime) in lifetimes.iter().enumerate() {
+                if let Some((first, _)) = *lifetime {
+                    if first == step {
+                        let key = self.keys[t];
+                        physical[t] = Some(match released.iter().position(|r| r.0 == key) {
+                            Some(pos) => released.swap_remove(pos).1,
+                            None => try!(Transient::new(pool, factory, &key)),
+                        });
+                    }
+                }
+            }
+            {
+                let pass = &mut self.passes[i];
+                let mut transients = vec![None; physical.len()];
+                for &t in pass.reads.iter().chain(pass.writes.iter()) {
+                    transients[t] = physical[t].clone();
+                }
+                let targets = Targets {
+                    names: &self.names,
+                    transients: transients,
+                };
+                (pass.function)(encoder, &targets);
+            }
+            for (t, lifetime) in lifetimes.iter().enumerate() {
+                if let Some((_, last)) = *lifetime {
+                    if last == step {
+                        released.push((self.keys[t], physical[t].take().unwrap()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, R: Resources, C: CommandBuffer<R>> Default for Graph<'a, R, C> {
+    fn default() -> Self {
+        Graph::new()
+    }
+}
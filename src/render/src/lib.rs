@@ -34,27 +34,33 @@ pub use draw_state::{preset, state};
 pub use draw_state::target::*;
 
 // public re-exports
-pub use core::{Device, Resources, Primitive};
+pub use core::{Capabilities, Device, Resources, Primitive};
 pub use core::{VertexCount, InstanceCount};
-pub use core::{ShaderSet, VertexShader, HullShader, DomainShader, GeometryShader, PixelShader};
-pub use core::{buffer, format, handle, texture, mapping};
+pub use core::{ShaderSet, VertexShader, HullShader, DomainShader, GeometryShader, PixelShader, ComputeShader};
+pub use core::{buffer, format, handle, texture, mapping, mipmap, query};
+pub use core::query::QueryType;
 pub use core::factory::{Factory, ResourceViewError, TargetViewError, CombinedError};
 pub use core::memory::{self, Bind, TRANSFER_SRC, TRANSFER_DST, RENDER_TARGET,
                        DEPTH_STENCIL, SHADER_RESOURCE, UNORDERED_ACCESS};
-pub use core::command::{Buffer as CommandBuffer, InstanceParams};
+pub use core::command::{Buffer as CommandBuffer, InstanceParams, BlitFilter,
+                        DrawIndirectArgs, DrawIndexedIndirectArgs};
 pub use core::shade::{ProgramInfo, UniformValue};
 
-pub use encoder::{Encoder, UpdateError};
+pub use encoder::{CopyError, DispatchError, DrawIndirectError, Encoder, Readback, ReadbackError,
+                  ResolveView, StateStats, UpdateError};
 pub use factory::PipelineStateError;
 pub use slice::{Slice, IntoIndexBuffer, IndexBuffer};
 pub use pso::{PipelineState};
 pub use pso::buffer::{VertexBuffer, InstanceBuffer, RawVertexBuffer,
-                      ConstantBuffer, RawConstantBuffer, Global};
+                      ConstantBuffer, RawConstantBuffer, ConstantBufferRange, Global};
 pub use pso::resource::{ShaderResource, RawShaderResource, UnorderedAccess,
                         Sampler, TextureSampler};
 pub use pso::target::{DepthStencilTarget, DepthTarget, StencilTarget,
                       RenderTarget, RawRenderTarget, BlendTarget, BlendRef, Scissor};
 pub use pso::bundle::{Bundle};
+pub use queue::{RenderQueue, SortKey};
+pub use pool::ResourcePool;
+pub use ring::{DynamicRing, RingError};
 
 /// Render commands encoder
 mod encoder;
@@ -68,3 +74,15 @@ pub mod pso;
 pub mod shade;
 /// Convenience macros
 pub mod macros;
+/// GPU profiling
+pub mod profiler;
+// Sorted bundle submission
+pub mod queue;
+// Frame graph
+pub mod graph;
+// Transient resource pool
+pub mod pool;
+// Streaming constants
+pub mod ring;
+// Texture containers
+pub mod texture_loader;
@@ -0,0 +1,315 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Re-use of transient resources.
+//!
+//! A `ResourcePool` hands out the textures and buffers matching a descriptor,
+//! re-using the ones it created before when they are no longer used.
+//! A resource returns to the pool as soon as all the user handles to it,
+//! including its views and the references held by the encoders and the
+//! device for the commands in flight, are dropped.
+//! The resources that are not used for a number of frames are freed.
+//!
+//! The views of the pooled textures are cached along with them, so
+//! the views requested through the pool are only created once.
+
+use std::mem;
+use core::{Resources, buffer, format, handle, texture};
+use core::factory::{CombinedError, Factory, ResourceViewError, TargetViewError};
+use core::memory::{self, Bind, Typed, Usage};
+
+struct Entry<K, H> {
+    key: K,
+    handle: H,
+    /// Number of references held by the pool, its cached views and the factory
+    base_refs: usize,
+    last_used: usize,
+}
+
+/// The descriptor of a pooled texture.
+type TextureKey = (texture::Info, Option<format::ChannelType>);
+
+/// A handle with a reference count.
+trait Counted {
+    fn get_ref_count(&self) -> usize;
+}
+
+/// A pooled texture with the views created for it.
+struct PooledTexture<R: Resources> {
+    texture: handle::RawTexture<R>,
+    srvs: Vec<(texture::ResourceDesc, handle::RawShaderResourceView<R>)>,
+    rtvs: Vec<(texture::RenderDesc, handle::RawRenderTargetView<R>)>,
+    dsvs: Vec<(texture::DepthStencilDesc, handle::RawDepthStencilView<R>)>,
+}
+
+impl<R: Resources> PooledTexture<R> {
+    fn new(texture: handle::RawTexture<R>) -> PooledTexture<R> {
+        PooledTexture {
+            texture: texture,
+            srvs: Vec::new(),
+            rtvs: Vec::new(),
+            dsvs: Vec::new(),
+        }
+    }
+}
+
+impl<R: Resources> Counted for PooledTexture<R> {
+    fn get_ref_count(&self) -> usize {
+        self.texture.get_ref_count()
+    }
+}
+
+impl<R: Resources> Counted for handle::RawBuffer<R> {
+    fn get_ref_count(&self) -> usize {
+        handle::RawBuffer::get_ref_count(self)
+    }
+}
+
+impl<K, H: Counted> Entry<K, H> {
+    /// Create an entry for a resource whose only other
+    /// user handle is given to the user.
+    fn new(key: K, handle: H, frame: usize) -> Entry<K, H> {
+        Entry {
+            key: key,
+            base_refs: handle.get_ref_count() - 1,
+            handle: handle,
+            last_used: frame,
+        }
+    }
+
+    fn is_free(&self) -> bool {
+        self.handle.get_ref_count() == self.base_refs
+    }
+}
+
+/// Get the view of a pooled texture from the cache, creating it if needed.
+fn get_view<R, D, V, E, S, F>(entry: &mut Entry<TextureKey, PooledTexture<R>>,
+                              desc: D, select: S, create: F) -> Result<V, E>
+    where R: Resources, D: Copy + PartialEq, V: Clone,
+          S: Fn(&mut PooledTexture<R>) -> &mut Vec<(D, V)>,
+          F: FnOnce(&handle::RawTexture<R>, D) -> Result<V, E>
+{
+    if let Some(&(_, ref view)) = select(&mut entry.handle).iter().find(|v| v.0 == desc) {
+        return Ok(view.clone())
+    }
+    let count = entry.handle.get_ref_count();
+    let view = try!(create(&entry.handle.texture, desc));
+    // the cached view keeps references to the texture
+    entry.base_refs += entry.handle.get_ref_count() - count;
+    select(&mut entry.handle).push((desc, view.clone()));
+    Ok(view)
+}
+
+/// A pool of textures and buffers.
+pub struct ResourcePool<R: Resources> {
+    textures: Vec<Entry<TextureKey, PooledTexture<R>>>,
+    buffers: Vec<Entry<buffer::Info, handle::RawBuffer<R>>>,
+    frame: usize,
+    max_unused_frames: usize,
+}
+
+impl<R: Resources> ResourcePool<R> {
+    /// Create a new empty pool, freeing the resources unused
+    /// for more than `max_unused_frames` frames. With zero, the
+    /// resources not used during a frame are freed at its end.
+    pub fn new(max_unused_frames: usize) -> ResourcePool<R> {
+        ResourcePool {
+            textures: Vec::new(),
+            buffers: Vec::new(),
+            frame: 0,
+            max_unused_frames: max_unused_frames,
+        }
+    }
+
+    /// Get the number of textures in the pool, used or not.
+    pub fn num_textures(&self) -> usize {
+        self.textures.len()
+    }
+
+    /// Get the number of buffers in the pool, used or not.
+    pub fn num_buffers(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Get a texture matching the descriptor, creating it if needed.
+    pub fn get_texture_raw<F>(&mut self, factory: &mut F, info: texture::Info,
+                              channel_hint: Option<format::ChannelType>)
+                              -> Result<handle::RawTexture<R>, texture::CreationError>
+        where F: Factory<R>
+    {
+        let key = (info, channel_hint);
+        let frame = self.frame;
+        if let Some(entry) = self.textures.iter_mut().find(|e| e.key == key && e.is_free()) {
+            entry.last_used = frame;
+            return Ok(entry.handle.texture.clone())
+        }
+        let texture = try!(factory.create_texture_raw(info, channel_hint, None));
+        self.textures.push(Entry::new(key, PooledTexture::new(texture.clone()), frame));
+        Ok(texture)
+    }
+
+    fn find_texture(&mut self, texture: &handle::RawTexture<R>)
+                    -> Option<&mut Entry<TextureKey, PooledTexture<R>>> {
+        self.textures.iter_mut().find(|e| e.handle.texture == *texture)
+    }
+
+    /// View a texture as a shader resource, re-using the view created
+    /// before with the same descriptor if the texture comes from the pool.
+    pub fn view_texture_as_shader_resource_raw<F>(&mut self, factory: &mut F,
+                                                  texture: &handle::RawTexture<R>,
+                                                  desc: texture::ResourceDesc)
+                                                  -> Result<handle::RawShaderResourceView<R>,
+                                                            ResourceViewError>
+        where F: Factory<R>
+    {
+        let mut create = |tex: &handle::RawTexture<R>, desc| factory.view_texture_as_shader_resource_raw(tex, desc);
+        match self.find_texture(texture) {
+            Some(entry) => get_view(entry, desc, |t| &mut t.srvs, create),
+            None => create(texture, desc),
+        }
+    }
+
+    /// View a texture as a render target, re-using the view created
+    /// before with the same descriptor if the texture comes from the pool.
+    pub fn view_texture_as_render_target_raw<F>(&mut self, factory: &mut F,
+                                                texture: &handle::RawTexture<R>,
+                                                desc: texture::RenderDesc)
+                                                -> Result<handle::RawRenderTargetView<R>,
+                                                          TargetViewError>
+        where F: Factory<R>
+    {
+        let mut create = |tex: &handle::RawTexture<R>, desc| factory.view_texture_as_render_target_raw(tex, desc);
+        match self.find_texture(texture) {
+            Some(entry) => get_view(entry, desc, |t| &mut t.rtvs, create),
+            None => create(texture, desc),
+        }
+    }
+
+    /// View a texture as a depth stencil target, re-using the view created
+    /// before with the same descriptor if the texture comes from the pool.
+    pub fn view_texture_as_depth_stencil_raw<F>(&mut self, factory: &mut F,
+                                                texture: &handle::RawTexture<R>,
+                                                desc: texture::DepthStencilDesc)
+                                                -> Result<handle::RawDepthStencilView<R>,
+                                                          TargetViewError>
+        where F: Factory<R>
+    {
+        let mut create = |tex: &handle::RawTexture<R>, desc| factory.view_texture_as_depth_stencil_raw(tex, desc);
+        match self.find_texture(texture) {
+            Some(entry) => get_view(entry, desc, |t| &mut t.dsvs, create),
+            None => create(texture, desc),
+        }
+    }
+
+    /// Get a typed texture, see `Factory::create_texture`.
+    pub fn get_texture<F, S>(&mut self, factory: &mut F, kind: texture::Kind, levels: texture::Level,
+                             bind: Bind, usage: Usage, channel_hint: Option<format::ChannelType>)
+                             -> Result<handle::Texture<R, S>, texture::CreationError>
+        where F: Factory<R>, S: format::SurfaceTyped
+    {
+        let info = texture::Info {
+            kind: kind,
+            levels: levels,
+            format: S::get_surface_type(),
+            bind: bind,
+            usage: usage,
+        };
+        self.get_texture_raw(factory, info, channel_hint).map(|raw| Typed::new(raw))
+    }
+
+    /// Get a render target with its shader resource view, see `Factory::create_render_target`.
+    pub fn get_render_target<F, T>(&mut self, factory: &mut F,
+                                   width: texture::Size, height: texture::Size)
+                                   -> Result<(handle::Texture<R, T::Surface>,
+                                              handle::ShaderResourceView<R, T::View>,
+                                              handle::RenderTargetView<R, T>
+                                       ), CombinedError>
+        where F: Factory<R>, T: format::RenderFormat + format::TextureFormat
+    {
+        let kind = texture::Kind::D2(width, height, texture::AaMode::Single);
+        let levels = 1;
+        let cty = <T::Channel as format::ChannelTyped>::get_channel_type();
+        let tex: handle::Texture<R, T::Surface> = try!(self.get_texture(
+            factory, kind, levels, memory::SHADER_RESOURCE | memory::RENDER_TARGET, Usage::Data, Some(cty)));
+        let resource_desc = texture::ResourceDesc {
+            channel: cty,
+            layer: None,
+            min: 0,
+            max: levels - 1,
+            swizzle: format::Swizzle::new(),
+        };
+        let resource = try!(self.view_texture_as_shader_resource_raw(factory, tex.raw(), resource_desc));
+        let target_desc = texture::RenderDesc {
+            channel: cty,
+            level: 0,
+            layer: None,
+        };
+        let target = try!(self.view_texture_as_render_target_raw(factory, tex.raw(), target_desc));
+        Ok((tex, Typed::new(resource), Typed::new(target)))
+    }
+
+    /// Get a buffer matching the descriptor, creating it if needed.
+    pub fn get_buffer_raw<F>(&mut self, factory: &mut F, info: buffer::Info)
+                             -> Result<handle::RawBuffer<R>, buffer::CreationError>
+        where F: Factory<R>
+    {
+        let frame = self.frame;
+        if let Some(entry) = self.buffers.iter_mut().find(|e| e.key == info && e.is_free()) {
+            entry.last_used = frame;
+            return Ok(entry.handle.clone())
+        }
+        let buffer = try!(factory.create_buffer_raw(info));
+        self.buffers.push(Entry::new(info, buffer.clone(), frame));
+        Ok(buffer)
+    }
+
+    /// Get a typed buffer of `num` elements, see `Factory::create_buffer`.
+    pub fn get_buffer<F, T>(&mut self, factory: &mut F, num: usize, role: buffer::Role,
+                            usage: Usage, bind: Bind)
+                            -> Result<handle::Buffer<R, T>, buffer::CreationError>
+        where F: Factory<R>
+    {
+        let stride = mem::size_of::<T>();
+        let info = buffer::Info {
+            role: role,
+            usage: usage,
+            bind: bind,
+            size: num * stride,
+            stride: stride,
+        };
+        self.get_buffer_raw(factory, info).map(|raw| Typed::new(raw))
+    }
+
+    /// Advance to the next frame, freeing the resources that
+    /// were not used for too long.
+    pub fn end_frame(&mut self) {
+        let frame = self.frame;
+        let max = self.max_unused_frames;
+        for entry in self.textures.iter_mut().filter(|e| !e.is_free()) {
+            entry.last_used = frame;
+        }
+        for entry in self.buffers.iter_mut().filter(|e| !e.is_free()) {
+            entry.last_used = frame;
+        }
+        self.textures.retain(|e| frame - e.last_used <= max);
+        self.buffers.retain(|e| frame - e.last_used <= max);
+        self.frame += 1;
+    }
+
+    /// Free all the resources that are not used at the moment.
+    pub fn clear(&mut self) {
+        self.textures.retain(|e| !e.is_free());
+        self.buffers.retain(|e| !e.is_free());
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright 2016 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! GPU profiling with timestamp queries.
+//!
+//! A `Profiler` is attached to an `Encoder`, which then surrounds every
+//! `profile_scope` with a pair of timestamps. The results are collected
+//! later, usually a few frames after the submission, without stalling:
+//!
+//! ```ignore
+//! encoder.set_profiler(Some(Profiler::new(&mut factory, 64).unwrap()));
+//! {
+//!     let mut scope = encoder.profile_scope("shadow pass");
+//!     scope.draw(&slice, &pso, &data);
+//! }
+//! encoder.flush(&mut device);
+//! let profiler = encoder.profiler_mut().unwrap();
+//! profiler.end_frame();
+//! profiler.collect(&mut factory);
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+use core::{command, handle, query, Factory, Resources};
+use encoder::Encoder;
+
+/// A finished scope, with its GPU timings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    /// Name of the scope
+    pub name: String,
+    /// Frame the scope was recorded in
+    pub frame: u64,
+    /// Nesting depth of the scope
+    pub depth: usize,
+    /// GPU time at the scope start, in nanoseconds
+    pub start: u64,
+    /// GPU time at the scope end, in nanoseconds
+    pub end: u64,
+}
+
+impl Event {
+    /// Duration of the scope, in nanoseconds.
+    pub fn duration(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+struct OpenScope<R: Resources> {
+    name: String,
+    queries: Option<(handle::Query<R>, handle::Query<R>)>,
+}
+
+struct PendingScope<R: Resources> {
+    name: String,
+    frame: u64,
+    depth: usize,
+    begin: handle::Query<R>,
+    end: handle::Query<R>,
+}
+
+/// Records named scopes with timestamp queries and gathers their timings.
+pub struct Profiler<R: Resources> {
+    free: Vec<handle::Query<R>>,
+    open: Vec<OpenScope<R>>,
+    pending: VecDeque<PendingScope<R>>,
+    events: Vec<Event>,
+    frame: u64,
+}
+
+impl<R: Resources> Profiler<R> {
+    /// Create a new profiler, allocating `num_queries` timestamp queries.
+    /// Each scope uses two of them until its result is collected.
+    pub fn new<F: Factory<R>>(factory: &mut F, num_queries: usize)
+               -> Result<Profiler<R>, query::CreationError> {
+        let mut free = Vec::with_capacity(num_queries);
+        for _ in 0 .. num_queries {
+            free.push(try!(factory.create_query(query::QueryType::Timestamp)));
+        }
+        Ok(Profiler {
+            free: free,
+            open: Vec::new(),
+            pending: VecDeque::new(),
+            events: Vec::new(),
+            frame: 0,
+        })
+    }
+
+    /// Start a new scope, returning the query to write the timestamp into.
+    #[doc(hidden)]
+    pub fn begin_scope(&mut self, name: &str) -> Option<handle::Query<R>> {
+        let queries = if self.free.len() >= 2 {
+            let end = self.free.pop().unwrap();
+            let begin = self.free.pop().unwrap();
+            Some((begin, end))
+        } else {
+            warn!("Not enough timestamp queries to profile scope '{}'", name);
+            None
+        };
+        let begin = queries.as_ref().map(|&(ref begin, _)| begin.clone());
+        self.open.push(OpenScope {
+            name: name.to_string(),
+            queries: queries,
+        });
+        begin
+    }
+
+    /// End the innermost scope, returning the query to write the timestamp into.
+    #[doc(hidden)]
+    pub fn end_scope(&mut self) -> Option<handle::Query<R>> {
+        let scope = match self.open.pop() {
+            Some(scope) => scope,
+            None => {
+                error!("Ending a profiling scope that was never started");
+                return None
+            },
+        };
+        let (begin, end) = match scope.queries {
+            Some(queries) => queries,
+            None => return None,
+        };
+        self.pending.push_back(PendingScope {
+            name: scope.name,
+            frame: self.frame,
+            depth: self.open.len(),
+            begin: begin,
+            end: end.clone(),
+        });
+        Some(end)
+    }
+
+    /// Mark the end of a frame. The following scopes are recorded with the next frame index.
+    pub fn end_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Get the current frame index.
+    pub fn get_frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Gather the results of the scopes that are done on the GPU, without blocking.
+    /// The scopes are collected in order, stopping at the first one that is not ready.
+    pub fn collect<F: Factory<R>>(&mut self, factory: &mut F) {
+        loop {
+            let (start, end) = match self.pending.front() {
+                Some(scope) => match (factory.get_query_result(&scope.begin),
+                                      factory.get_query_result(&scope.end)) {
+                    (Some(start), Some(end)) => (start, end),
+                    _ => break,
+                },
+                None => break,
+            };
+            let scope = self.pending.pop_front().unwrap();
+            self.events.push(Event {
+                name: scope.name,
+                frame: scope.frame,
+                depth: scope.depth,
+                start: start,
+                end: end,
+            });
+            self.free.push(scope.begin);
+            self.free.push(scope.end);
+        }
+    }
+
+    /// Get the collected events.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Remove the collected events.
+    pub fn clear_events(&mut self) {
+        self.events.clear();
+    }
+
+    /// Write the collected events in the Chrome `trace_event` JSON format,
+    /// which can be loaded into `chrome://tracing`.
+    pub fn write_chrome_trace<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let origin = self.events.iter().map(|e| e.start).min().unwrap_or(0);
+        try!(write!(out, "{{\"traceEvents\":["));
+        for (i, event) in self.events.iter().enumerate() {
+            if i != 0 {
+                try!(write!(out, ","));
+            }
+            try!(write!(out, "\n{{\"name\":\""));
+            try!(write_json_escaped(out, &event.name));
+            try!(write!(out,
+                "\",\"cat\":\"gpu\",\"ph\":\"X\",\"pid\":0,\"tid\":0,\"ts\":{:.3},\"dur\":{:.3},\"args\":{{\"frame\":{}}}}}",
+                (event.start - origin) as f64 / 1000.0,
+                event.duration() as f64 / 1000.0,
+                event.frame));
+        }
+        write!(out, "\n],\"displayTimeUnit\":\"ns\"}}\n")
+    }
+}
+
+fn write_json_escaped<W: Write>(out: &mut W, s: &str) -> io::Result<()> {
+    for c in s.chars() {
+        match c {
+            '"' => try!(write!(out, "\\\"")),
+            '\\' => try!(write!(out, "\\\\")),
+            '\n' => try!(write!(out, "\\n")),
+            '\r' => try!(write!(out, "\\r")),
+            '\t' => try!(write!(out, "\\t")),
+            c if (c as u32) < 0x20 => try!(write!(out, "\\u{:04x}", c as u32)),
+            c => try!(write!(out, "{}", c)),
+        }
+    }
+    Ok(())
+}
+
+/// A profiling scope of an `Encoder`, ended when dropped.
+/// It dereferences to the encoder, so that the commands can be encoded
+/// while the scope is alive.
+pub struct Scope<'a, R: Resources + 'a, C: command::Buffer<R> + 'a> {
+    encoder: &'a mut Encoder<R, C>,
+}
+
+impl<'a, R: Resources, C: command::Buffer<R>> Scope<'a, R, C> {
+    #[doc(hidden)]
+    pub fn new(encoder: &'a mut Encoder<R, C>) -> Self {
+        Scope {
+            encoder: encoder,
+        }
+    }
+}
+
+impl<'a, R: Resources, C: command::Buffer<R>> Deref for Scope<'a, R, C> {
+    type Target = Encoder<R, C>;
+    fn deref(&self) -> &Encoder<R, C> {
+        self.encoder
+    }
+}
+
+impl<'a, R: Resources, C: command::Buffer<R>> DerefMut for Scope<'a, R, C> {
+    fn deref_mut(&mut self) -> &mut Encoder<R, C> {
+        self.encoder
+    }
+}
+
+impl<'a, R: Resources, C: command::Buffer<R>> Drop for Scope<'a, R, C> {
+    fn drop(&mut self) {
+        self.encoder.end_profile_scope();
+    }
+}
@@ -57,6 +57,12 @@ pub struct ConstantBuffer<T: Structure<shade::ConstFormat>>(RawConstantBuffer, P
 /// - init: `&str` = name of the buffer
 /// - data: `RawBuffer`
 pub struct RawConstantBuffer(Option<(Usage, ConstantBufferSlot)>);
+/// Constant buffer range component. Binds a part of a larger buffer,
+/// like the ones allocated by a `DynamicRing`.
+///
+/// - init: `&str` = name of the buffer
+/// - data: `(RawBuffer, offset, size)`, in bytes
+pub struct ConstantBufferRange<T: Structure<shade::ConstFormat>>(ConstantBuffer<T>);
 /// Global (uniform) constant component. Describes a free-standing value passed into
 /// the shader, which is not enclosed into any constant buffer. Deprecated in DX10 and higher.
 ///
@@ -206,6 +212,38 @@ DataBind<R> for ConstantBuffer<T> {
     }
 }
 
+impl<'a, T: Structure<shade::ConstFormat>>
+DataLink<'a> for ConstantBufferRange<T> {
+    type Init = &'a str;
+    fn new() -> Self {
+        ConstantBufferRange(DataLink::new())
+    }
+    fn is_active(&self) -> bool {
+        self.0.is_active()
+    }
+    fn link_constant_buffer<'b>(&mut self, cb: &'b shade::ConstantBufferVar, init: &Self::Init)
+                            -> Option<Result<pso::ConstantBufferDesc, ElementError<&'b str>>> {
+        self.0.link_constant_buffer(cb, init)
+    }
+}
+
+impl<R: Resources, T: Structure<shade::ConstFormat>>
+DataBind<R> for ConstantBufferRange<T> {
+    type Data = (handle::RawBuffer<R>, usize, usize);
+    fn bind_to(&self,
+               out: &mut RawDataSet<R>,
+               data: &Self::Data,
+               man: &mut handle::Manager<R>,
+               access: &mut AccessInfo<R>) {
+        if let Some((usage, slot)) = ((self.0).0).0 {
+            let (ref raw, offset, size) = *data;
+            let buf = man.ref_buffer(raw).clone();
+            out.constant_buffers.push(pso::ConstantBufferParam(buf, usage, slot, Some((offset, size))));
+            access.buffer_read(raw)
+        }
+    }
+}
+
 impl<'a> DataLink<'a> for RawConstantBuffer {
     type Init = &'a str;
     fn new() -> Self {
@@ -234,7 +272,7 @@ impl<R: Resources> DataBind<R> for RawConstantBuffer {
                access: &mut AccessInfo<R>) {
         if let Some((usage, slot)) = self.0 {
             let buf = man.ref_buffer(data).clone();
-            out.constant_buffers.push(pso::ConstantBufferParam(buf, usage, slot));
+            out.constant_buffers.push(pso::ConstantBufferParam(buf, usage, slot, None));
             access.buffer_read(data)
         }
     }
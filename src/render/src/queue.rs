@@ -0,0 +1,164 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sorted submission of bundles.
+//!
+//! A `RenderQueue` collects the bundles of a frame together with their
+//! `SortKey`, and encodes them in the order that minimizes the state changes.
+//! Passes are encoded in ascending order. Within an opaque pass, the bundles
+//! are grouped by pipeline state, then by material, then sorted front to back.
+//! Within a transparent pass, the bundles are sorted back to front, and the
+//! bundles at the same depth keep the order they were pushed in.
+//!
+//! The pipeline data type of the bundles is erased when they are pushed,
+//! so a single queue can hold the bundles of all the pipelines of a frame.
+
+use std::cmp::Ordering;
+use core::Resources;
+use core::command::Buffer as CommandBuffer;
+use core::handle::RawPipelineState;
+use encoder::Encoder;
+use pso::PipelineData;
+use pso::bundle::Bundle;
+
+/// The key by which the bundles of a `RenderQueue` are sorted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SortKey {
+    /// Pass the bundle belongs to. Passes are encoded in ascending order.
+    pub pass: u8,
+    /// User-defined material identifier. Bundles sharing a material are
+    /// expected to share textures and other resources.
+    pub material: u32,
+    /// Distance to the viewer.
+    pub depth: f32,
+}
+
+/// A bundle of any pipeline data type.
+trait Encode<R: Resources, C: CommandBuffer<R>> {
+    fn encode(&self, encoder: &mut Encoder<R, C>);
+}
+
+impl<R: Resources, C: CommandBuffer<R>, D: PipelineData<R>> Encode<R, C> for Bundle<R, D> {
+    fn encode(&self, encoder: &mut Encoder<R, C>) {
+        Bundle::encode(self, encoder)
+    }
+}
+
+struct Item<'a, R: 'a + Resources, C: 'a + CommandBuffer<R>> {
+    key: SortKey,
+    pso_index: usize,
+    bundle: &'a Encode<R, C>,
+}
+
+/// A queue of bundles to be sorted and encoded together
+/// into the command buffers of type `C`.
+pub struct RenderQueue<'a, R: 'a + Resources, C: 'a + CommandBuffer<R>> {
+    items: Vec<Item<'a, R, C>>,
+    psos: Vec<&'a RawPipelineState<R>>,
+    transparent_passes: Vec<u8>,
+}
+
+fn compare_depth(a: f32, b: f32) -> Ordering {
+    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+}
+
+impl<'a, R: Resources, C: CommandBuffer<R>> RenderQueue<'a, R, C> {
+    /// Create a new empty queue, with all the passes being opaque.
+    pub fn new() -> Self {
+        RenderQueue {
+            items: Vec::new(),
+            psos: Vec::new(),
+            transparent_passes: Vec::new(),
+        }
+    }
+
+    /// Mark a pass as transparent, so that its bundles are encoded
+    /// back to front in a stable order.
+    pub fn set_transparent(&mut self, pass: u8) {
+        if !self.transparent_passes.contains(&pass) {
+            self.transparent_passes.push(pass);
+        }
+    }
+
+    /// Check if a pass is transparent.
+    pub fn is_transparent(&self, pass: u8) -> bool {
+        self.transparent_passes.contains(&pass)
+    }
+
+    /// Add a bundle to the queue.
+    pub fn push<D: PipelineData<R>>(&mut self, bundle: &'a Bundle<R, D>, key: SortKey) {
+        let handle = bundle.pso.get_handle();
+        let pso_index = match self.psos.iter().position(|pso| *pso == handle) {
+            Some(index) => index,
+            None => {
+                self.psos.push(handle);
+                self.psos.len() - 1
+            },
+        };
+        self.items.push(Item {
+            key: key,
+            pso_index: pso_index,
+            bundle: bundle,
+        });
+    }
+
+    /// Get the number of queued bundles.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Check if there are no queued bundles.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Remove all the queued bundles. The transparent passes are kept.
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.psos.clear();
+    }
+
+    /// Sort the queued bundles.
+    pub fn sort(&mut self) {
+        let transparent = &self.transparent_passes;
+        // `sort_by` is stable, so equal bundles keep their push order
+        self.items.sort_by(|a, b| {
+            if a.key.pass != b.key.pass {
+                a.key.pass.cmp(&b.key.pass)
+            } else if transparent.contains(&a.key.pass) {
+                compare_depth(b.key.depth, a.key.depth)
+            } else if a.pso_index != b.pso_index {
+                a.pso_index.cmp(&b.pso_index)
+            } else if a.key.material != b.key.material {
+                a.key.material.cmp(&b.key.material)
+            } else {
+                compare_depth(a.key.depth, b.key.depth)
+            }
+        });
+    }
+
+    /// Sort the queued bundles and encode them.
+    pub fn encode(&mut self, encoder: &mut Encoder<R, C>) {
+        self.sort();
+        for item in &self.items {
+            item.bundle.encode(encoder);
+        }
+    }
+}
+
+impl<'a, R: Resources, C: CommandBuffer<R>> Default for RenderQueue<'a, R, C> {
+    fn default() -> Self {
+        RenderQueue::new()
+    }
+}
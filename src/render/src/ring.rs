@@ -0,0 +1,175 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streaming of per-draw constants.
+//!
+//! A `DynamicRing` sub-allocates aligned ranges of a large dynamic buffer,
+//! to be bound with the `ConstantBufferRange` component. The constants of a
+//! frame are gathered on the CPU, and uploaded with a single command:
+//!
+//! ```{.rust,ignore}
+//! ring.begin_frame();
+//! let ranges: Vec<_> = objects.iter().map(|o| ring.push(&o.locals).unwrap()).collect();
+//! ring.upload(&mut encoder).unwrap();
+//! for (object, range) in objects.iter().zip(ranges) {
+//!     data.locals = range;
+//!     encoder.draw(&object.slice, &pso, &data);
+//! }
+//! ```
+//!
+//! The ranges are taken from the buffer in a circular manner, so the ranges
+//! of the previous frames are not overwritten until the space is needed.
+
+use std::{fmt, mem, slice};
+use std::error::Error;
+use core::{Resources, buffer, handle};
+use core::command::Buffer as CommandBuffer;
+use core::factory::Factory;
+use core::memory::{self, Pod, Typed, Usage};
+use encoder::{Encoder, UpdateError};
+
+/// An error occuring when creating a `DynamicRing`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RingError {
+    /// Constant buffer ranges, needed to bind the allocations, are not supported by the device.
+    Unsupported,
+    /// Failed to create the buffer.
+    Buffer(buffer::CreationError),
+}
+
+impl fmt::Display for RingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RingError::Buffer(ref e) => write!(f, "{}: {}", self.description(), e),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for RingError {
+    fn description(&self) -> &str {
+        match *self {
+            RingError::Unsupported => "Constant buffer ranges are not supported by the device",
+            RingError::Buffer(_) => "Failed to create the ring buffer",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            RingError::Buffer(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<buffer::CreationError> for RingError {
+    fn from(e: buffer::CreationError) -> Self {
+        RingError::Buffer(e)
+    }
+}
+
+/// A ring buffer allocator for dynamic constants.
+pub struct DynamicRing<R: Resources> {
+    buffer: handle::Buffer<R, u8>,
+    staging: Vec<u8>,
+    alignment: usize,
+    /// Start of the current frame in the buffer
+    start: usize,
+    /// End of the allocated part of the current frame
+    end: usize,
+    /// The allocations of the current frame wrapped around the buffer end
+    wrapped: bool,
+}
+
+impl<R: Resources> DynamicRing<R> {
+    /// Create a new ring of `size` bytes.
+    /// Fails if the device can not bind constant buffer ranges.
+    pub fn new<F: Factory<R>>(factory: &mut F, size: usize)
+               -> Result<DynamicRing<R>, RingError> {
+        let caps = *factory.get_capabilities();
+        if !caps.constant_buffer_range_supported {
+            return Err(RingError::Unsupported)
+        }
+        let alignment = ::std::cmp::max(1, caps.constant_buffer_alignment);
+        let buffer = try!(factory.create_buffer::<u8>(size, buffer::Role::Constant,
+                                                      Usage::Dynamic, memory::Bind::empty()));
+        Ok(DynamicRing {
+            buffer: buffer,
+            staging: vec![0; size],
+            alignment: alignment,
+            start: 0,
+            end: 0,
+            wrapped: false,
+        })
+    }
+
+    /// Get the underlying buffer.
+    pub fn get_buffer(&self) -> &handle::RawBuffer<R> {
+        self.buffer.raw()
+    }
+
+    /// Get the alignment of the allocated ranges.
+    pub fn get_alignment(&self) -> usize {
+        self.alignment
+    }
+
+    /// Start allocating the ranges of a new frame.
+    /// The ranges of the previous frames may be re-used from now on.
+    pub fn begin_frame(&mut self) {
+        self.start = self.end;
+        self.wrapped = false;
+    }
+
+    /// Allocate a range for the raw `data`, returning the buffer with the offset
+    /// and the size of the range, or `None` if the ring is full.
+    pub fn push_raw(&mut self, data: &[u8]) -> Option<(handle::RawBuffer<R>, usize, usize)> {
+        let size = data.len();
+        let capacity = self.staging.len();
+        let mut offset = (self.end + self.alignment - 1) / self.alignment * self.alignment;
+        if !self.wrapped && offset + size > capacity {
+            // continue at the beginning of the buffer
+            self.wrapped = true;
+            offset = 0;
+        }
+        let limit = if self.wrapped { self.start } else { capacity };
+        if offset + size > limit {
+            return None
+        }
+        self.staging[offset .. offset + size].copy_from_slice(data);
+        self.end = offset + size;
+        Some((self.buffer.raw().clone(), offset, size))
+    }
+
+    /// Allocate a range for a constant structure.
+    pub fn push<T: Pod>(&mut self, data: &T) -> Option<(handle::RawBuffer<R>, usize, usize)> {
+        let bytes = unsafe {
+            slice::from_raw_parts(data as *const T as *const u8, mem::size_of::<T>())
+        };
+        self.push_raw(bytes)
+    }
+
+    /// Upload the ranges allocated in the current frame. This has to be encoded
+    /// before the draw calls using them.
+    pub fn upload<C>(&self, encoder: &mut Encoder<R, C>) -> Result<(), UpdateError<usize>>
+        where C: CommandBuffer<R>
+    {
+        if self.wrapped {
+            let capacity = self.staging.len();
+            try!(encoder.update_buffer(&self.buffer, &self.staging[self.start .. capacity], self.start));
+            encoder.update_buffer(&self.buffer, &self.staging[.. self.end], 0)
+        } else {
+            encoder.update_buffer(&self.buffer, &self.staging[self.start .. self.end], self.start)
+        }
+    }
+}
@@ -60,6 +60,8 @@ pub enum ProgramError {
     Domain(core::CreateShaderError),
     /// Unable to compile the pixel shader
     Pixel(core::CreateShaderError),
+    /// Unable to compile the compute shader
+    Compute(core::CreateShaderError),
     /// Unable to link
     Link(core::CreateProgramError),
 }
@@ -71,6 +73,7 @@ impl fmt::Display for ProgramError {
             ProgramError::Hull(ref e) => write!(f, "{}: {}", self.description(), e),
             ProgramError::Domain(ref e) => write!(f, "{}: {}", self.description(), e),
             ProgramError::Pixel(ref e) => write!(f, "{}: {}", self.description(), e),
+            ProgramError::Compute(ref e) => write!(f, "{}: {}", self.description(), e),
             ProgramError::Link(ref e) => write!(f, "{}: {}", self.description(), e),
         }
     }
@@ -83,6 +86,7 @@ impl Error for ProgramError {
             ProgramError::Hull(_) => "Unable to compile the hull shader",
             ProgramError::Domain(_) => "Unable to compile the domain shader",
             ProgramError::Pixel(_) => "Unable to compile the pixel shader",
+            ProgramError::Compute(_) => "Unable to compile the compute shader",
             ProgramError::Link(_) => "Unable to link",
         }
     }
@@ -93,6 +97,7 @@ impl Error for ProgramError {
             ProgramError::Hull(ref e) => Some(e),
             ProgramError::Domain(ref e) => Some(e),
             ProgramError::Pixel(ref e) => Some(e),
+            ProgramError::Compute(ref e) => Some(e),
             ProgramError::Link(ref e) => Some(e),
         }
     }
@@ -0,0 +1,648 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loading of DDS and KTX texture containers.
+//!
+//! The containers are parsed into an `Image`, holding the texture description
+//! and the data of all the mip levels, array layers and cube faces, in the
+//! order expected by `Factory::create_texture_raw`. The data is borrowed from
+//! the container whenever its layout allows it.
+//!
+//! ```{.rust,ignore}
+//! let bytes = include_bytes!("skybox.dds");
+//! let (texture, format) = gfx::texture_loader::load(&mut factory, bytes).unwrap();
+//! ```
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use core::{Resources, handle, texture};
+use core::factory::Factory;
+use core::format::{Format, SurfaceType};
+use core::memory::{self, Bind, Usage};
+
+/// An error occuring when loading a texture container.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadError {
+    /// The data does not start with a DDS or KTX identifier.
+    UnknownContainer,
+    /// The data ends before the contents described by the header.
+    Truncated,
+    /// The header is not valid.
+    InvalidHeader(&'static str),
+    /// The pixel format has no equivalent `Format`.
+    UnsupportedFormat,
+    /// The texture kind or dimensions are not supported.
+    UnsupportedKind,
+    /// Failed to create the texture.
+    Creation(texture::CreationError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::InvalidHeader(field) => write!(f, "{}: {}", self.description(), field),
+            LoadError::Creation(ref e) => write!(f, "{}: {}", self.description(), e),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn description(&self) -> &str {
+        match *self {
+            LoadError::UnknownContainer => "The data is not a DDS or KTX container",
+            LoadError::Truncated => "The container data is truncated",
+            LoadError::InvalidHeader(_) => "The container header is invalid",
+            LoadError::UnsupportedFormat => "The pixel format is not supported",
+            LoadError::UnsupportedKind => "The texture kind is not supported",
+            LoadError::Creation(_) => "Failed to create the texture",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            LoadError::Creation(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<texture::CreationError> for LoadError {
+    fn from(e: texture::CreationError) -> Self {
+        LoadError::Creation(e)
+    }
+}
+
+/// A texture parsed from a container.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image<'a> {
+    /// Kind of the texture.
+    pub kind: texture::Kind,
+    /// Number of mip levels.
+    pub levels: texture::Level,
+    /// Format of the texels.
+    pub format: Format,
+    /// Data of every mip level of every slice: Slice0.Mip0, Slice0.Mip1, ..., Slice1.Mip0, ...
+    /// The faces of a cube are consecutive slices.
+    pub data: Vec<Cow<'a, [u8]>>,
+}
+
+impl<'a> Image<'a> {
+    /// Get the texture description.
+    pub fn get_info(&self, bind: Bind, usage: Usage) -> texture::Info {
+        texture::Info {
+            kind: self.kind,
+            levels: self.levels,
+            format: self.format.0,
+            bind: bind,
+            usage: usage,
+        }
+    }
+
+    /// Create a texture with the contents of the image.
+    pub fn create_texture<R, F>(&self, factory: &mut F, bind: Bind, usage: Usage)
+                                -> Result<handle::RawTexture<R>, texture::CreationError>
+        where R: Resources, F: Factory<R>
+    {
+        let data: Vec<&[u8]> = self.data.iter().map(|d| &d[..]).collect();
+        factory.create_texture_raw(self.get_info(bind, usage), Some(self.format.1), Some(&data))
+    }
+}
+
+/// Parse a DDS or KTX container, depending on its identifier.
+pub fn parse(data: &[u8]) -> Result<Image, LoadError> {
+    if data.starts_with(DDS_MAGIC) {
+        parse_dds(data)
+    } else if data.starts_with(KTX_MAGIC) {
+        parse_ktx(data)
+    } else {
+        Err(LoadError::UnknownContainer)
+    }
+}
+
+/// Parse a container and create a shader resource texture with its contents.
+pub fn load<R, F>(factory: &mut F, data: &[u8])
+                  -> Result<(handle::RawTexture<R>, Format), LoadError>
+    where R: Resources, F: Factory<R>
+{
+    let image = try!(parse(data));
+    let texture = try!(image.create_texture(factory, memory::SHADER_RESOURCE, Usage::Data));
+    Ok((texture, image.format))
+}
+
+fn get_slice(data: &[u8], offset: usize, size: usize) -> Result<&[u8], LoadError> {
+    if offset + size > data.len() {
+        Err(LoadError::Truncated)
+    } else {
+        Ok(&data[offset .. offset + size])
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize, big_endian: bool) -> Result<u32, LoadError> {
+    let b = try!(get_slice(data, offset, 4));
+    Ok(if big_endian {
+        (b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32
+    } else {
+        (b[3] as u32) << 24 | (b[2] as u32) << 16 | (b[1] as u32) << 8 | b[0] as u32
+    })
+}
+
+fn to_size(value: u32) -> Result<texture::Size, LoadError> {
+    if value > texture::Size::max_value() as u32 {
+        Err(LoadError::UnsupportedKind)
+    } else {
+        Ok(value as texture::Size)
+    }
+}
+
+fn to_layer(value: u32) -> Result<texture::Layer, LoadError> {
+    if value > texture::Layer::max_value() as u32 {
+        Err(LoadError::UnsupportedKind)
+    } else {
+        Ok(value as texture::Layer)
+    }
+}
+
+fn to_levels(value: u32) -> Result<texture::Level, LoadError> {
+    match value {
+        // no mip levels are stored, the full chain is to be generated
+        0 => Ok(1),
+        n if n <= texture::Level::max_value() as u32 => Ok(n as texture::Level),
+        _ => Err(LoadError::InvalidHeader("mip level count")),
+    }
+}
+
+/// Get the number of slices of the texture data, counting the cube faces.
+fn get_num_data_slices(kind: texture::Kind) -> usize {
+    let faces = if kind.is_cube() { 6 } else { 1 };
+    kind.get_num_slices().unwrap_or(1) as usize * faces
+}
+
+/// Get the size in bytes of a row of texels, and the number of rows
+/// in a single slice of a mip level.
+fn get_level_layout(kind: texture::Kind, format: Format, level: texture::Level) -> (usize, usize) {
+    let (w, h, d, _) = kind.get_level_dimensions(level);
+    let mut image = texture::ImageInfoCommon {
+        xoffset: 0,
+        yoffset: 0,
+        zoffset: 0,
+        width: w,
+        height: 1,
+        depth: 1,
+        format: format,
+        mipmap: level,
+    };
+    let row = image.get_byte_count();
+    image.height = h;
+    image.depth = if kind.get_num_slices().is_some() || kind.is_cube() { 1 } else { d };
+    (row, image.get_byte_count() / row)
+}
+
+// DDS
+
+const DDS_MAGIC: &'static [u8] = b"DDS ";
+const DDS_HEADER_SIZE: u32 = 124;
+const DDS_PIXEL_FORMAT_SIZE: u32 = 32;
+
+const DDSD_DEPTH: u32 = 0x80_0000;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x2_0000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xFC00;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+
+const D3D10_RESOURCE_DIMENSION_TEXTURE1D: u32 = 2;
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+const D3D10_RESOURCE_DIMENSION_TEXTURE3D: u32 = 4;
+const D3D10_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+fn fourcc(code: &[u8; 4]) -> u32 {
+    (code[3] as u32) << 24 | (code[2] as u32) << 16 | (code[1] as u32) << 8 | code[0] as u32
+}
+
+fn map_dxgi_format(dxgi: u32) -> Option<Format> {
+    use core::format::ChannelType::*;
+    use core::format::SurfaceType::*;
+    Some(match dxgi {
+        2 => Format(R32_G32_B32_A32, Float),
+        3 => Format(R32_G32_B32_A32, Uint),
+        4 => Format(R32_G32_B32_A32, Int),
+        6 => Format(R32_G32_B32, Float),
+        7 => Format(R32_G32_B32, Uint),
+        8 => Format(R32_G32_B32, Int),
+        10 => Format(R16_G16_B16_A16, Float),
+        11 => Format(R16_G16_B16_A16, Unorm),
+        12 => Format(R16_G16_B16_A16, Uint),
+        13 => Format(R16_G16_B16_A16, Inorm),
+        14 => Format(R16_G16_B16_A16, Int),
+        16 => Format(R32_G32, Float),
+        17 => Format(R32_G32, Uint),
+        18 => Format(R32_G32, Int),
+        24 => Format(R10_G10_B10_A2, Unorm),
+        25 => Format(R10_G10_B10_A2, Uint),
+        26 => Format(R11_G11_B10, Float),
+        28 => Format(R8_G8_B8_A8, Unorm),
+        29 => Format(R8_G8_B8_A8, Srgb),
+        30 => Format(R8_G8_B8_A8, Uint),
+        31 => Format(R8_G8_B8_A8, Inorm),
+        32 => Format(R8_G8_B8_A8, Int),
+        34 => Format(R16_G16, Float),
+        35 => Format(R16_G16, Unorm),
+        36 => Format(R16_G16, Uint),
+        37 => Format(R16_G16, Inorm),
+        38 => Format(R16_G16, Int),
+        40 => Format(D32, Float),
+        41 => Format(R32, Float),
+        42 => Format(R32, Uint),
+        43 => Format(R32, Int),
+        45 => Format(D24_S8, Unorm),
+        49 => Format(R8_G8, Unorm),
+        50 => Format(R8_G8, Uint),
+        51 => Format(R8_G8, Inorm),
+        52 => Format(R8_G8, Int),
+        54 => Format(R16, Float),
+        55 => Format(D16, Unorm),
+        56 => Format(R16, Unorm),
+        57 => Format(R16, Uint),
+        58 => Format(R16, Inorm),
+        59 => Format(R16, Int),
+        61 => Format(R8, Unorm),
+        62 => Format(R8, Uint),
+        63 => Format(R8, Inorm),
+        64 => Format(R8, Int),
+        71 => Format(BC1_R8_G8_B8_A8, Unorm),
+        72 => Format(BC1_R8_G8_B8_A8, Srgb),
+        74 => Format(BC2_R8_G8_B8_A8, Unorm),
+        75 => Format(BC2_R8_G8_B8_A8, Srgb),
+        77 => Format(BC3_R8_G8_B8_A8, Unorm),
+        78 => Format(BC3_R8_G8_B8_A8, Srgb),
+        80 => Format(BC4_R8, Unorm),
+        81 => Format(BC4_R8, Inorm),
+        83 => Format(BC5_R8_G8, Unorm),
+        84 => Format(BC5_R8_G8, Inorm),
+        85 => Format(R5_G6_B5, Unorm),
+        87 => Format(B8_G8_R8_A8, Unorm),
+        95 => Format(BC6H_R16_G16_B16_UF, Float),
+        96 => Format(BC6H_R16_G16_B16_SF, Float),
+        98 => Format(BC7_R8_G8_B8_A8, Unorm),
+        99 => Format(BC7_R8_G8_B8_A8, Srgb),
+        _ => return None,
+    })
+}
+
+/// Map a legacy DDS pixel format, described by its flags, FourCC code,
+/// bit count and channel masks.
+fn map_dds_pixel_format(flags: u32, code: u32, bits: u32, masks: [u32; 4]) -> Option<Format> {
+    use core::format::ChannelType::*;
+    use core::format::SurfaceType::*;
+    if flags & DDPF_FOURCC != 0 {
+        // D3DFMT values stored in place of a FourCC code
+        return Some(match code {
+            0x3154_5844 => Format(BC1_R8_G8_B8_A8, Unorm), // DXT1
+            0x3354_5844 => Format(BC2_R8_G8_B8_A8, Unorm), // DXT3
+            0x3554_5844 => Format(BC3_R8_G8_B8_A8, Unorm), // DXT5
+            0x3149_5441 | 0x5534_4342 => Format(BC4_R8, Unorm), // ATI1, BC4U
+            0x5334_4342 => Format(BC4_R8, Inorm), // BC4S
+            0x3249_5441 | 0x5535_4342 => Format(BC5_R8_G8, Unorm), // ATI2, BC5U
+            0x5335_4342 => Format(BC5_R8_G8, Inorm), // BC5S
+            36 => Format(R16_G16_B16_A16, Unorm),
+            111 => Format(R16, Float),
+            112 => Format(R16_G16, Float),
+            113 => Format(R16_G16_B16_A16, Float),
+            114 => Format(R32, Float),
+            115 => Format(R32_G32, Float),
+            116 => Format(R32_G32_B32_A32, Float),
+            _ => return None,
+        })
+    }
+    let alpha = if flags & DDPF_ALPHAPIXELS != 0 { masks[3] } else { 0 };
+    Some(match (bits, masks[0], masks[1], masks[2], alpha) {
+        (32, 0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000) if flags & DDPF_RGB != 0 =>
+            Format(R8_G8_B8_A8, Unorm),
+        (32, 0xFF_0000, 0xFF00, 0xFF, 0xFF00_0000) if flags & DDPF_RGB != 0 =>
+            Format(B8_G8_R8_A8, Unorm),
+        (32, 0xFFFF, 0xFFFF_0000, 0, 0) if flags & DDPF_RGB != 0 =>
+            Format(R16_G16, Unorm),
+        (32, 0x3FF, 0xF_FC00, 0x3FF0_0000, 0xC000_0000) if flags & DDPF_RGB != 0 =>
+            Format(R10_G10_B10_A2, Unorm),
+        (16, 0xF800, 0x7E0, 0x1F, 0) if flags & DDPF_RGB != 0 =>
+            Format(R5_G6_B5, Unorm),
+        (8, 0xFF, 0, 0, 0) if flags & DDPF_LUMINANCE != 0 =>
+            Format(R8, Unorm),
+        (16, 0xFFFF, 0, 0, 0) if flags & DDPF_LUMINANCE != 0 =>
+            Format(R16, Unorm),
+        _ => return None,
+    })
+}
+
+/// Parse a DDS container, with or without the DX10 header extension.
+pub fn parse_dds(data: &[u8]) -> Result<Image, LoadError> {
+    if !data.starts_with(DDS_MAGIC) {
+        return Err(LoadError::UnknownContainer)
+    }
+    let header = |field: usize| read_u32(data, DDS_MAGIC.len() + field * 4, false);
+    if try!(header(0)) != DDS_HEADER_SIZE {
+        return Err(LoadError::InvalidHeader("header size"))
+    }
+    if try!(header(18)) != DDS_PIXEL_FORMAT_SIZE {
+        return Err(LoadError::InvalidHeader("pixel format size"))
+    }
+    let flags = try!(header(1));
+    let height = try!(header(2));
+    let width = try!(header(3));
+    let depth = try!(header(5));
+    let num_levels = try!(header(6));
+    let pf_flags = try!(header(19));
+    let pf_code = try!(header(20));
+    let caps2 = try!(header(27));
+    let mut offset = DDS_MAGIC.len() + DDS_HEADER_SIZE as usize;
+
+    let w = try!(to_size(width));
+    let h = try!(to_size(height));
+    let (kind, format) = if pf_flags & DDPF_FOURCC != 0 && pf_code == fourcc(b"DX10") {
+        let format = try!(map_dxgi_format(try!(read_u32(data, offset, false)))
+                              .ok_or(LoadError::UnsupportedFormat));
+        let dimension = try!(read_u32(data, offset + 4, false));
+        let misc = try!(read_u32(data, offset + 8, false));
+        let array = try!(read_u32(data, offset + 12, false));
+        offset += 20;
+        let kind = match dimension {
+            D3D10_RESOURCE_DIMENSION_TEXTURE1D if array <= 1 => texture::Kind::D1(w),
+            D3D10_RESOURCE_DIMENSION_TEXTURE1D => texture::Kind::D1Array(w, try!(to_layer(array))),
+            D3D10_RESOURCE_DIMENSION_TEXTURE2D if misc & D3D10_RESOURCE_MISC_TEXTURECUBE != 0 => {
+                if w != h {
+                    return Err(LoadError::InvalidHeader("cube dimensions"))
+                }
+                if array <= 1 {
+                    texture::Kind::Cube(w)
+                } else {
+                    texture::Kind::CubeArray(w, try!(to_layer(array)))
+                }
+            },
+            D3D10_RESOURCE_DIMENSION_TEXTURE2D if array <= 1 =>
+                texture::Kind::D2(w, h, texture::AaMode::Single),
+            D3D10_RESOURCE_DIMENSION_TEXTURE2D =>
+                texture::Kind::D2Array(w, h, try!(to_layer(array)), texture::AaMode::Single),
+            D3D10_RESOURCE_DIMENSION_TEXTURE3D if array <= 1 =>
+                texture::Kind::D3(w, h, try!(to_size(depth))),
+            D3D10_RESOURCE_DIMENSION_TEXTURE3D => return Err(LoadError::UnsupportedKind),
+            _ => return Err(LoadError::InvalidHeader("resource dimension")),
+        };
+        (kind, format)
+    } else {
+        let masks = [try!(header(22)), try!(header(23)), try!(header(24)), try!(header(25))];
+        let format = try!(map_dds_pixel_format(pf_flags, pf_code, try!(header(21)), masks)
+                              .ok_or(LoadError::UnsupportedFormat));
+        let kind = if caps2 & DDSCAPS2_CUBEMAP != 0 {
+            if caps2 & DDSCAPS2_CUBEMAP_ALL_FACES != DDSCAPS2_CUBEMAP_ALL_FACES {
+                // partial cube maps can not be represented
+                return Err(LoadError::UnsupportedKind)
+            }
+            if w != h {
+                return Err(LoadError::InvalidHeader("cube dimensions"))
+            }
+            texture::Kind::Cube(w)
+        } else if caps2 & DDSCAPS2_VOLUME != 0 && flags & DDSD_DEPTH != 0 {
+            texture::Kind::D3(w, h, try!(to_size(depth)))
+        } else {
+            texture::Kind::D2(w, h, texture::AaMode::Single)
+        };
+        (kind, format)
+    };
+    let levels = try!(to_levels(num_levels));
+
+    // the images are stored slice by slice, each with all its mip levels
+    let mut images = Vec::new();
+    for _ in 0 .. get_num_data_slices(kind) {
+        for level in 0 .. levels {
+            let (row, rows) = get_level_layout(kind, format, level);
+            let size = row * rows;
+            images.push(Cow::Borrowed(try!(get_slice(data, offset, size))));
+            offset += size;
+        }
+    }
+
+    Ok(Image {
+        kind: kind,
+        levels: levels,
+        format: format,
+        data: images,
+    })
+}
+
+// KTX
+
+const KTX_MAGIC: &'static [u8] = &[0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+const KTX_ENDIANNESS: u32 = 0x0403_0201;
+const KTX_HEADER_SIZE: usize = 64;
+
+const GL_BGRA: u32 = 0x80E1;
+
+fn map_gl_format(internal: u32, gl_format: u32) -> Option<Format> {
+    use core::format::ChannelType::*;
+    use core::format::SurfaceType::*;
+    Some(match internal {
+        0x8229 => Format(R8, Unorm),
+        0x8F94 => Format(R8, Inorm),
+        0x8232 => Format(R8, Uint),
+        0x8231 => Format(R8, Int),
+        0x822B => Format(R8_G8, Unorm),
+        0x8F95 => Format(R8_G8, Inorm),
+        0x8238 => Format(R8_G8, Uint),
+        0x8237 => Format(R8_G8, Int),
+        0x8058 if gl_format == GL_BGRA => Format(B8_G8_R8_A8, Unorm),
+        0x8058 => Format(R8_G8_B8_A8, Unorm),
+        0x93A1 => Format(B8_G8_R8_A8, Unorm),
+        0x8C43 => Format(R8_G8_B8_A8, Srgb),
+        0x8F97 => Format(R8_G8_B8_A8, Inorm),
+        0x8D7C => Format(R8_G8_B8_A8, Uint),
+        0x8D8E => Format(R8_G8_B8_A8, Int),
+        0x8059 => Format(R10_G10_B10_A2, Unorm),
+        0x906F => Format(R10_G10_B10_A2, Uint),
+        0x8C3A => Format(R11_G11_B10, Float),
+        0x822A => Format(R16, Unorm),
+        0x8F98 => Format(R16, Inorm),
+        0x822D => Format(R16, Float),
+        0x8234 => Format(R16, Uint),
+        0x8233 => Format(R16, Int),
+        0x822C => Format(R16_G16, Unorm),
+        0x8F99 => Format(R16_G16, Inorm),
+        0x822F => Format(R16_G16, Float),
+        0x823A => Format(R16_G16, Uint),
+        0x8239 => Format(R16_G16, Int),
+        0x8054 => Format(R16_G16_B16, Unorm),
+        0x8F9A => Format(R16_G16_B16, Inorm),
+        0x881B => Format(R16_G16_B16, Float),
+        0x8D77 => Format(R16_G16_B16, Uint),
+        0x8D89 => Format(R16_G16_B16, Int),
+        0x805B => Format(R16_G16_B16_A16, Unorm),
+        0x8F9B => Format(R16_G16_B16_A16, Inorm),
+        0x881A => Format(R16_G16_B16_A16, Float),
+        0x8D76 => Format(R16_G16_B16_A16, Uint),
+        0x8D88 => Format(R16_G16_B16_A16, Int),
+        0x822E => Format(R32, Float),
+        0x8236 => Format(R32, Uint),
+        0x8235 => Format(R32, Int),
+        0x8230 => Format(R32_G32, Float),
+        0x823C => Format(R32_G32, Uint),
+        0x823B => Format(R32_G32, Int),
+        0x8815 => Format(R32_G32_B32, Float),
+        0x8D71 => Format(R32_G32_B32, Uint),
+        0x8D83 => Format(R32_G32_B32, Int),
+        0x8814 => Format(R32_G32_B32_A32, Float),
+        0x8D70 => Format(R32_G32_B32_A32, Uint),
+        0x8D82 => Format(R32_G32_B32_A32, Int),
+        0x8D62 => Format(R5_G6_B5, Unorm),
+        0x8056 => Format(R4_G4_B4_A4, Unorm),
+        0x8057 => Format(R5_G5_B5_A1, Unorm),
+        0x81A5 => Format(D16, Unorm),
+        0x81A6 => Format(D24, Unorm),
+        0x88F0 => Format(D24_S8, Unorm),
+        0x8CAC => Format(D32, Float),
+        0x83F1 => Format(BC1_R8_G8_B8_A8, Unorm),
+        0x8C4D => Format(BC1_R8_G8_B8_A8, Srgb),
+        0x83F2 => Format(BC2_R8_G8_B8_A8, Unorm),
+        0x8C4E => Format(BC2_R8_G8_B8_A8, Srgb),
+        0x83F3 => Format(BC3_R8_G8_B8_A8, Unorm),
+        0x8C4F => Format(BC3_R8_G8_B8_A8, Srgb),
+        0x8DBB => Format(BC4_R8, Unorm),
+        0x8DBC => Format(BC4_R8, Inorm),
+        0x8DBD => Format(BC5_R8_G8, Unorm),
+        0x8DBE => Format(BC5_R8_G8, Inorm),
+        0x8E8F => Format(BC6H_R16_G16_B16_UF, Float),
+        0x8E8E => Format(BC6H_R16_G16_B16_SF, Float),
+        0x8E8C => Format(BC7_R8_G8_B8_A8, Unorm),
+        0x8E8D => Format(BC7_R8_G8_B8_A8, Srgb),
+        0x9270 => Format(EAC_R11, Unorm),
+        0x9271 => Format(EAC_R11, Inorm),
+        0x9272 => Format(EAC_R11_G11, Unorm),
+        0x9273 => Format(EAC_R11_G11, Inorm),
+        0x9274 => Format(ETC2_R8_G8_B8, Unorm),
+        0x9275 => Format(ETC2_R8_G8_B8, Srgb),
+        0x9276 => Format(ETC2_R8_G8_B8_A1, Unorm),
+        0x9277 => Format(ETC2_R8_G8_B8_A1, Srgb),
+        0x9278 => Format(ETC2_R8_G8_B8_A8, Unorm),
+        0x9279 => Format(ETC2_R8_G8_B8_A8, Srgb),
+        0x93B0 ... 0x93BD => Format(ASTC_SURFACES[(internal - 0x93B0) as usize], Unorm),
+        0x93D0 ... 0x93DD => Format(ASTC_SURFACES[(internal - 0x93D0) as usize], Srgb),
+        _ => return None,
+    })
+}
+
+/// ASTC surfaces in the order of their GL enumerants.
+const ASTC_SURFACES: [SurfaceType; 14] = [
+    SurfaceType::ASTC_4x4, SurfaceType::ASTC_5x4, SurfaceType::ASTC_5x5,
+    SurfaceType::ASTC_6x5, SurfaceType::ASTC_6x6, SurfaceType::ASTC_8x5,
+    SurfaceType::ASTC_8x6, SurfaceType::ASTC_8x8, SurfaceType::ASTC_10x5,
+    SurfaceType::ASTC_10x6, SurfaceType::ASTC_10x8, SurfaceType::ASTC_10x10,
+    SurfaceType::ASTC_12x10, SurfaceType::ASTC_12x12,
+];
+
+/// Copy the rows of an image without their padding,
+/// swapping the bytes of each `swap_size` elements.
+fn repack(src: &[u8], row: usize, stride: usize, rows: usize, swap_size: usize) -> Vec<u8> {
+    let mut dst = Vec::with_capacity(row * rows);
+    for r in 0 .. rows {
+        dst.extend_from_slice(&src[r * stride .. r * stride + row]);
+    }
+    if swap_size > 1 {
+        for chunk in dst.chunks_mut(swap_size) {
+            chunk.reverse();
+        }
+    }
+    dst
+}
+
+/// Parse a KTX container, in either endianness.
+pub fn parse_ktx(data: &[u8]) -> Result<Image, LoadError> {
+    if !data.starts_with(KTX_MAGIC) {
+        return Err(LoadError::UnknownContainer)
+    }
+    let big_endian = match try!(read_u32(data, KTX_MAGIC.len(), false)) {
+        KTX_ENDIANNESS => false,
+        0x0102_0304 => true,
+        _ => return Err(LoadError::InvalidHeader("endianness")),
+    };
+    let header = |field: usize| read_u32(data, KTX_MAGIC.len() + field * 4, big_endian);
+    let type_size = try!(header(2)) as usize;
+    let gl_format = try!(header(3));
+    let internal_format = try!(header(4));
+    let width = try!(header(6));
+    let height = try!(header(7));
+    let depth = try!(header(8));
+    let num_elements = try!(header(9));
+    let num_faces = try!(header(10));
+    let num_levels = try!(header(11));
+    let kv_size = try!(header(12)) as usize;
+
+    let format = try!(map_gl_format(internal_format, gl_format).ok_or(LoadError::UnsupportedFormat));
+    let w = try!(to_size(width));
+    let h = try!(to_size(height));
+    let kind = match (height, depth, num_elements, num_faces) {
+        (_, _, _, 6) if width != height => return Err(LoadError::InvalidHeader("cube dimensions")),
+        (_, 0, 0, 6) => texture::Kind::Cube(w),
+        (_, 0, n, 6) => texture::Kind::CubeArray(w, try!(to_layer(n))),
+        (_, _, _, 1) => match (height, depth, num_elements) {
+            (0, 0, 0) => texture::Kind::D1(w),
+            (0, 0, n) => texture::Kind::D1Array(w, try!(to_layer(n))),
+            (_, 0, 0) => texture::Kind::D2(w, h, texture::AaMode::Single),
+            (_, 0, n) => texture::Kind::D2Array(w, h, try!(to_layer(n)), texture::AaMode::Single),
+            (_, d, 0) => texture::Kind::D3(w, h, try!(to_size(d))),
+            _ => return Err(LoadError::UnsupportedKind),
+        },
+        _ => return Err(LoadError::InvalidHeader("number of faces")),
+    };
+    let levels = try!(to_levels(num_levels));
+    let num_slices = get_num_data_slices(kind);
+    let swap_size = if big_endian { type_size } else { 1 };
+
+    // the images are stored level by level, each with all the slices,
+    // while the factory expects them slice by slice
+    let mut images = vec![Cow::Borrowed(&[][..]); num_slices * levels as usize];
+    let mut offset = KTX_HEADER_SIZE + kv_size;
+    for level in 0 .. levels {
+        let (row, rows) = get_level_layout(kind, format, level);
+        // rows, faces and levels are aligned to 4 bytes
+        let stride = (row + 3) & !3;
+        let size = stride * rows;
+        let image_size = try!(read_u32(data, offset, big_endian)) as usize;
+        let expected = match kind {
+            texture::Kind::Cube(_) => size,
+            _ => size * num_slices,
+        };
+        if image_size != expected {
+            return Err(LoadError::InvalidHeader("image size"))
+        }
+        offset += 4;
+        for slice in 0 .. num_slices {
+            let src = try!(get_slice(data, offset, size));
+            images[slice * levels as usize + level as usize] = if stride == row && swap_size <= 1 {
+                Cow::Borrowed(src)
+            } else {
+                Cow::Owned(repack(src, row, stride, rows, swap_size))
+            };
+            offset += (size + 3) & !3;
+        }
+    }
+
+    Ok(Image {
+        kind: kind,
+        levels: levels,
+        format: format,
+        data: images,
+    })
+}
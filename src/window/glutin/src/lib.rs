@@ -134,6 +134,21 @@ pub fn init_existing_raw(window: &glutin::Window,
     (device, factory, color_view, ds_view)
 }
 
+/// Initialize with a headless context builder, for rendering without a window,
+/// e.g. with OSMesa on top of a software driver.
+/// There is no main framebuffer, so the targets have to be created by the factory.
+pub fn init_headless(builder: glutin::HeadlessRendererBuilder) ->
+                     Result<(glutin::HeadlessContext, device_gl::Device, device_gl::Factory),
+                            glutin::CreationError>
+{
+    let context = try!(builder.build());
+    try!(unsafe { context.make_current() }.map_err(|e|
+        glutin::CreationError::OsError(format!("Unable to make the context current: {:?}", e))));
+    let (device, factory) = device_gl::create(|s|
+        context.get_proc_address(s) as *const std::os::raw::c_void);
+    Ok((context, device, factory))
+}
+
 /// Update the internal dimensions of the main framebuffer targets. Generic version over the format.
 pub fn update_views<Cf, Df>(window: &glutin::Window, color_view: &mut handle::RenderTargetView<R, Cf>,
                     ds_view: &mut handle::DepthStencilView<R, Df>)
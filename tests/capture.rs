@@ -0,0 +1,89 @@
+extern crate gfx;
+extern crate gfx_app;
+extern crate gfx_device_gl;
+extern crate image;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use gfx_app::{Application, Capture};
+
+/// Create an empty directory to capture into.
+fn capture_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("gfx-capture-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn take_paths() {
+    let dir = capture_dir("take-paths");
+    let mut capture = Capture::new(&dir);
+    assert!(capture.take_paths().is_empty());
+
+    capture.screenshot_to(dir.join("shot.png"));
+    capture.start_recording_to(dir.join("rec"));
+    assert_eq!(capture.take_paths(), vec![dir.join("shot.png"), dir.join("rec").join("00000.png")]);
+    // the screenshot is only taken once, while the recording goes on
+    assert_eq!(capture.take_paths(), vec![dir.join("rec").join("00001.png")]);
+    assert_eq!(capture.take_paths(), vec![dir.join("rec").join("00002.png")]);
+
+    capture.stop_recording();
+    assert!(!capture.is_recording());
+    assert!(capture.take_paths().is_empty());
+    // a new recording starts from the first frame
+    capture.start_recording_to(dir.join("rec"));
+    assert_eq!(capture.take_paths(), vec![dir.join("rec").join("00000.png")]);
+}
+
+#[test]
+fn free_paths() {
+    let dir = capture_dir("free-paths");
+    fs::File::create(dir.join("screenshot-0.png")).unwrap();
+    fs::create_dir(dir.join("recording-0")).unwrap();
+    fs::create_dir(dir.join("recording-1")).unwrap();
+
+    // the captures of previous runs are kept
+    let mut capture = Capture::new(&dir);
+    capture.screenshot();
+    capture.start_recording();
+    assert_eq!(capture.take_paths(), vec![dir.join("screenshot-1.png"),
+                                          dir.join("recording-2").join("00000.png")]);
+}
+
+/// Clears the target and takes a screenshot of its only frame.
+struct Screenshot<R: gfx::Resources> {
+    color: gfx::handle::RenderTargetView<R, gfx_app::ColorFormat>,
+}
+
+impl<R: gfx::Resources> Application<R> for Screenshot<R> {
+    fn new<F: gfx::Factory<R>>(_: &mut F, _: gfx_app::shade::Backend,
+                               targets: gfx_app::WindowTargets<R>) -> Self {
+        Screenshot {
+            color: targets.color,
+        }
+    }
+
+    fn render<C: gfx::CommandBuffer<R>>(&mut self, encoder: &mut gfx::Encoder<R, C>) {
+        encoder.clear(&self.color, [0.0, 1.0, 1.0, 1.0]);
+    }
+
+    fn capture(&mut self, capture: &mut Capture) {
+        capture.screenshot_to(env::temp_dir().join("gfx-capture-frame").join("shot.png"));
+    }
+}
+
+/// Needs a headless OpenGL implementation and the `png` feature,
+/// like the tests of the examples.
+#[test]
+#[ignore]
+fn capture_frame() {
+    let dir = capture_dir("frame");
+    let frame = Screenshot::<gfx_device_gl::Resources>::launch_headless(4, 2, 1);
+    let image = image::open(dir.join("shot.png")).unwrap().to_rgba();
+    assert_eq!(image.dimensions(), (4, 2));
+    assert_eq!(image.into_raw(), frame.data);
+    assert_eq!(&frame.data[.. 4], &[0, 0xFF, 0xFF, 0xFF]);
+}
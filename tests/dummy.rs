@@ -0,0 +1,493 @@
+#[macro_use]
+extern crate gfx;
+extern crate gfx_core;
+
+use gfx::{Device, Factory};
+use gfx::{DrawIndexedIndirectArgs, DrawIndirectArgs};
+use gfx::format::{B8_G8_R8_A8, BC1_R8_G8_B8_A8, ChannelType, D24_S8, DepthStencil, Formatted,
+                  R8_G8_B8_A8, Rgba8, Unorm};
+use gfx::shade::core::{self as shade, AttributeVar, BaseType, ContainerType, OutputVar, ProgramInfo,
+                       UnorderedVar};
+use gfx::texture::{AaMode, CreationError, ImageInfoCommon, Kind};
+use gfx::traits::FactoryExt;
+use gfx_core::command::AccessInfo;
+use gfx_core::dummy::{DummyCommandBuffer, DummyDevice, DummyFactory, RecordedCommand,
+                      RecordingCommandBuffer};
+
+gfx_defines!{
+    vertex Vertex {
+        pos: [f32; 2] = "a_Pos",
+    }
+
+    pipeline pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        out: gfx::RenderTarget<Rgba8> = "Target0",
+    }
+
+    pipeline compute {
+        values: gfx::UnorderedAccess<[f32; 4]> = "u_Values",
+    }
+}
+
+fn program_info(attribute: &str) -> ProgramInfo {
+    ProgramInfo {
+        vertex_attributes: vec![AttributeVar {
+            name: attribute.to_string(),
+            slot: 0,
+            base_type: BaseType::F32,
+            container: ContainerType::Vector(2),
+        }],
+        globals: Vec::new(),
+        constant_buffers: Vec::new(),
+        textures: Vec::new(),
+        unordereds: Vec::new(),
+        samplers: Vec::new(),
+        outputs: vec![OutputVar {
+            name: "Target0".to_string(),
+            slot: 0,
+            base_type: BaseType::F32,
+            container: ContainerType::Vector(4),
+        }],
+        output_depth: false,
+        knows_outputs: true,
+    }
+}
+
+#[test]
+fn test_pipeline_link() {
+    let mut factory = DummyFactory::new();
+    factory.set_program_info(program_info("a_Pos"));
+    assert!(factory.create_pipeline_simple(&[], &[], pipe::new()).is_ok());
+
+    factory.set_program_info(program_info("a_Unknown"));
+    match factory.create_pipeline_simple(&[], &[], pipe::new()) {
+        Err(gfx::PipelineStateError::DescriptorInit(gfx::pso::InitError::VertexImport(ref name, None)))
+            if name == "a_Unknown" => (),
+        other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_vertex_buffer() {
+    let mut factory = DummyFactory::new();
+    let vertices = [Vertex { pos: [0.0, 0.0] }; 3];
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices, &[0u16, 1, 2][..]);
+    assert_eq!(vbuf.len(), 3);
+    assert_eq!(slice.end, 3);
+}
+
+#[test]
+fn test_texture_data() {
+    let mut factory = DummyFactory::new();
+    let kind = Kind::D2(2, 2, AaMode::Single);
+    let full: &[[u8; 4]] = &[[0; 4]; 4];
+    let partial: &[[u8; 4]] = &[[0; 4]; 3];
+    assert!(factory.create_texture_immutable::<Rgba8>(kind, &[full]).is_ok());
+    match factory.create_texture_immutable::<Rgba8>(kind, &[partial]) {
+        Err(gfx::CombinedError::Texture(CreationError::Data(12))) => (),
+        other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+    }
+    let multisampled = Kind::D2(2, 2, AaMode::Multi(4));
+    match factory.create_texture::<R8_G8_B8_A8>(multisampled, 2, gfx::SHADER_RESOURCE,
+                                                gfx::memory::Usage::Data, None) {
+        Err(CreationError::Samples(AaMode::Multi(4))) => (),
+        other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_compressed_texture() {
+    let mut factory = DummyFactory::new();
+    let kind = Kind::D2(6, 6, AaMode::Single);
+    // 2x2 blocks for the top level, a single one for the next
+    let (top, next): (&[[u8; 8]], &[[u8; 8]]) = (&[[0; 8]; 4], &[[0; 8]; 1]);
+    assert!(factory.create_texture_immutable::<(BC1_R8_G8_B8_A8, Unorm)>(kind, &[top, next]).is_ok());
+    match factory.create_texture::<BC1_R8_G8_B8_A8>(kind, 1, gfx::RENDER_TARGET,
+                                                    gfx::memory::Usage::Data, Some(ChannelType::Unorm)) {
+        Err(CreationError::Format(_, Some(ChannelType::Unorm))) => (),
+        other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+    }
+
+    let texture = factory.create_texture::<BC1_R8_G8_B8_A8>(kind, 1, gfx::SHADER_RESOURCE,
+        gfx::memory::Usage::Dynamic, Some(ChannelType::Unorm)).unwrap();
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    let region = |x, w| ImageInfoCommon {
+        xoffset: x, yoffset: 0, zoffset: 0,
+        width: w, height: 4, depth: 0,
+        format: (),
+        mipmap: 0,
+    };
+    // the last block column is clipped by the edge of the level
+    assert!(encoder.update_texture::<_, (BC1_R8_G8_B8_A8, Unorm)>(
+        &texture, None, region(4, 2), &[[0; 8]]).is_ok());
+    assert_eq!(encoder.update_texture::<_, (BC1_R8_G8_B8_A8, Unorm)>(
+        &texture, None, region(2, 4), &[[0; 8]]), Err(gfx::UpdateError::UnalignedRegion));
+}
+
+#[test]
+fn test_mapping() {
+    let mut factory = DummyFactory::new();
+    let upload = factory.create_upload_buffer::<u32>(4).unwrap();
+    factory.write_mapping(&upload).unwrap().copy_from_slice(&[1, 2, 3, 4]);
+    // the memory is kept between the mappings
+    assert_eq!(&factory.write_mapping(&upload).unwrap()[..], &[1, 2, 3, 4]);
+
+    let download = factory.create_download_buffer::<[u8; 3]>(2).unwrap();
+    assert_eq!(&factory.read_mapping(&download).unwrap()[..], &[[0; 3]; 2]);
+}
+
+#[test]
+fn test_fenced_submit() {
+    let mut device = DummyDevice::new();
+    let fence = device.fenced_submit(&mut DummyCommandBuffer, &AccessInfo::new(), None).unwrap();
+    device.wait_fence(&fence);
+    assert!(device.is_fence_signaled(&fence));
+}
+
+#[test]
+fn test_readback_mapped() {
+    let mut device = DummyDevice::new();
+    let mut factory = DummyFactory::new();
+    let buffer = factory.create_buffer::<u32>(4, gfx::buffer::Role::Vertex,
+        gfx::memory::Usage::Data, gfx::TRANSFER_SRC).unwrap();
+    let mut encoder: gfx::Encoder<_, _> = DummyCommandBuffer.into();
+    let numbers = encoder.read_buffer(&mut factory, &buffer).unwrap();
+    encoder.flush(&mut device);
+    {
+        let _reader = factory.read_mapping(numbers.get_buffer()).unwrap();
+        assert_eq!(numbers.wait(&mut device, &mut factory),
+                   Err(gfx::ReadbackError::Map(gfx::mapping::Error::AccessOverlap)));
+    }
+    // nothing is executed, so the staging buffer keeps its initial contents
+    assert_eq!(numbers.wait(&mut device, &mut factory), Ok(Some(vec![0; 4])));
+}
+
+#[test]
+fn test_recording() {
+    let mut factory = DummyFactory::new();
+    factory.set_program_info(program_info("a_Pos"));
+    let pso = factory.create_pipeline_simple(&[], &[], pipe::new()).unwrap();
+    let vertices = [Vertex { pos: [0.0, 0.0] }; 3];
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices, ());
+    let (_, _, rtv) = factory.create_render_target::<Rgba8>(4, 4).unwrap();
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: rtv,
+    };
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    for _ in 0 .. 3 {
+        encoder.draw(&slice, &pso, &data);
+    }
+    assert_eq!(encoder.command_buffer().count_draws(), 3);
+
+    // the pipeline state is only bound for the first draw
+    assert_eq!(encoder.command_buffer().count_pso_binds(pso.get_handle()), 1);
+    assert!(encoder.get_state_stats().skipped > 0);
+
+    encoder.reset();
+    assert!(encoder.command_buffer().commands.is_empty());
+}
+
+fn count_vertex_binds(cb: &RecordingCommandBuffer) -> usize {
+    cb.commands.iter().filter(|com| match **com {
+        RecordedCommand::BindVertexBuffers(..) => true,
+        _ => false,
+    }).count()
+}
+
+#[test]
+fn test_redundant_binds() {
+    let mut factory = DummyFactory::new();
+    factory.set_program_info(program_info("a_Pos"));
+    let pso = factory.create_pipeline_simple(&[], &[], pipe::new()).unwrap();
+    let vertices = [Vertex { pos: [0.0, 0.0] }; 3];
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices, ());
+    let (_, _, rtv) = factory.create_render_target::<Rgba8>(4, 4).unwrap();
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: rtv,
+    };
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    encoder.draw(&slice, &pso, &data);
+    let num_commands = encoder.command_buffer().commands.len();
+    encoder.draw(&slice, &pso, &data);
+    // only the draw call itself is added
+    assert_eq!(encoder.command_buffer().commands.len(), num_commands + 1);
+    assert_eq!(encoder.command_buffer().count_pso_binds(pso.get_handle()), 1);
+    assert_eq!(count_vertex_binds(encoder.command_buffer()), 1);
+}
+
+#[test]
+fn test_changed_binds() {
+    let mut factory = DummyFactory::new();
+    factory.set_program_info(program_info("a_Pos"));
+    let pso = factory.create_pipeline_simple(&[], &[], pipe::new()).unwrap();
+    let other_pso = factory.create_pipeline_simple(&[], &[], pipe::new()).unwrap();
+    let vertices = [Vertex { pos: [0.0, 0.0] }; 3];
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices, ());
+    let (_, _, rtv) = factory.create_render_target::<Rgba8>(4, 4).unwrap();
+    let mut data = pipe::Data {
+        vbuf: vbuf,
+        out: rtv,
+    };
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    encoder.draw(&slice, &pso, &data);
+    // a new vertex buffer is bound without touching the pipeline state
+    data.vbuf = factory.create_vertex_buffer(&vertices);
+    encoder.draw(&slice, &pso, &data);
+    assert_eq!(encoder.command_buffer().count_pso_binds(pso.get_handle()), 1);
+    assert_eq!(count_vertex_binds(encoder.command_buffer()), 2);
+    // a new pipeline state binds everything again
+    encoder.draw(&slice, &other_pso, &data);
+    assert_eq!(encoder.command_buffer().count_pso_binds(other_pso.get_handle()), 1);
+    assert_eq!(count_vertex_binds(encoder.command_buffer()), 3);
+    encoder.draw(&slice, &pso, &data);
+    assert_eq!(encoder.command_buffer().count_pso_binds(pso.get_handle()), 2);
+    assert_eq!(count_vertex_binds(encoder.command_buffer()), 4);
+}
+
+#[test]
+fn test_update_keeps_binds() {
+    let mut factory = DummyFactory::new();
+    factory.set_program_info(program_info("a_Pos"));
+    let pso = factory.create_pipeline_simple(&[], &[], pipe::new()).unwrap();
+    let vertices = [Vertex { pos: [0.0, 0.0] }; 3];
+    let vbuf = factory.create_buffer::<Vertex>(3, gfx::buffer::Role::Vertex,
+        gfx::memory::Usage::Dynamic, gfx::Bind::empty()).unwrap();
+    let slice = gfx::Slice::new_match_vertex_buffer(&vbuf);
+    let constants = factory.create_constant_buffer::<[f32; 4]>(1);
+    let (_, _, rtv) = factory.create_render_target::<Rgba8>(4, 4).unwrap();
+    let data = pipe::Data {
+        vbuf: vbuf.clone(),
+        out: rtv,
+    };
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    encoder.draw(&slice, &pso, &data);
+    let num_commands = encoder.command_buffer().commands.len();
+    encoder.update_buffer(&vbuf, &vertices, 0).unwrap();
+    encoder.update_constant_buffer(&constants, &[1.0; 4]);
+    encoder.draw(&slice, &pso, &data);
+    // the two updates and the draw call
+    assert_eq!(encoder.command_buffer().commands.len(), num_commands + 3);
+    assert_eq!(encoder.command_buffer().count_pso_binds(pso.get_handle()), 1);
+    assert_eq!(count_vertex_binds(encoder.command_buffer()), 1);
+}
+
+#[test]
+fn test_draw_indirect() {
+    let mut factory = DummyFactory::new();
+    factory.set_program_info(program_info("a_Pos"));
+    let pso = factory.create_pipeline_simple(&[], &[], pipe::new()).unwrap();
+    let vertices = [Vertex { pos: [0.0, 0.0] }; 3];
+    let vbuf = factory.create_vertex_buffer(&vertices);
+    let (_, _, rtv) = factory.create_render_target::<Rgba8>(4, 4).unwrap();
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: rtv,
+    };
+    let args = factory.create_buffer_immutable(&[DrawIndirectArgs::default(); 2],
+                                               gfx::buffer::Role::Indirect, gfx::Bind::empty()).unwrap();
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    encoder.set_capabilities(Some(*factory.get_capabilities()));
+    assert!(encoder.draw_indirect(&args, 1, 1, &pso, &data).is_ok());
+    assert_eq!(encoder.draw_indirect(&args, 1, 2, &pso, &data),
+               Err(gfx::DrawIndirectError::OutOfBounds { size: 2, draw_end: 3 }));
+    assert_eq!(encoder.draw_indirect(&args, usize::max_value(), 2, &pso, &data),
+               Err(gfx::DrawIndirectError::OutOfBounds { size: 2, draw_end: usize::max_value() }));
+    let indexed_args = factory.create_buffer_immutable(&[DrawIndexedIndirectArgs::default()],
+                                                       gfx::buffer::Role::Indirect, gfx::Bind::empty()).unwrap();
+    assert_eq!(encoder.draw_indexed_indirect(&gfx::IndexBuffer::Auto, &indexed_args, 0, 1, &pso, &data),
+               Err(gfx::DrawIndirectError::NoIndexBuffer));
+    assert_eq!(encoder.command_buffer().count_draws(), 1);
+
+    let mut caps = *factory.get_capabilities();
+    caps.draw_indirect_supported = false;
+    encoder.set_capabilities(Some(caps));
+    assert_eq!(encoder.draw_indirect(&args, 0, 1, &pso, &data),
+               Err(gfx::DrawIndirectError::Unsupported));
+    assert_eq!(encoder.command_buffer().count_draws(), 1);
+}
+
+#[test]
+fn test_unsupported_copies() {
+    let mut factory = DummyFactory::new();
+    let kind = Kind::D2(4, 4, AaMode::Single);
+    let bind = gfx::TRANSFER_SRC | gfx::TRANSFER_DST;
+    let src = factory.create_texture::<R8_G8_B8_A8>(kind, 1, bind, gfx::memory::Usage::Data,
+                                                    Some(ChannelType::Unorm)).unwrap();
+    let dst = factory.create_texture::<R8_G8_B8_A8>(kind, 1, bind, gfx::memory::Usage::Data,
+                                                    Some(ChannelType::Unorm)).unwrap();
+    let image = src.get_info().to_image_info(0);
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    let mut caps = *factory.get_capabilities();
+    encoder.set_capabilities(Some(caps));
+    assert!(encoder.copy_texture::<Rgba8>(&src, None, image, &dst, None, image).is_ok());
+    assert!(encoder.blit::<Rgba8, Rgba8>(&src, None, image, &dst, None, image,
+                                         gfx::Mirror::empty(), gfx::BlitFilter::Linear).is_ok());
+
+    caps.copy_texture_supported = false;
+    caps.blit_texture_supported = false;
+    encoder.set_capabilities(Some(caps));
+    assert_eq!(encoder.copy_texture::<Rgba8>(&src, None, image, &dst, None, image),
+               Err(gfx::CopyError::Unsupported));
+    assert_eq!(encoder.blit::<Rgba8, Rgba8>(&src, None, image, &dst, None, image,
+                                            gfx::Mirror::empty(), gfx::BlitFilter::Linear),
+               Err(gfx::CopyError::Unsupported));
+    assert_eq!(encoder.command_buffer().commands.len(), 2);
+}
+
+#[test]
+fn test_copy_texture_errors() {
+    let mut factory = DummyFactory::new();
+    let kind = Kind::D2(4, 4, AaMode::Single);
+    let bind = gfx::TRANSFER_SRC | gfx::TRANSFER_DST;
+    let src = factory.create_texture::<R8_G8_B8_A8>(kind, 1, bind, gfx::memory::Usage::Data,
+                                                    Some(ChannelType::Unorm)).unwrap();
+    let dst = factory.create_texture::<R8_G8_B8_A8>(kind, 1, bind, gfx::memory::Usage::Data,
+                                                    Some(ChannelType::Unorm)).unwrap();
+    let depth = factory.create_texture::<D24_S8>(kind, 1, bind, gfx::memory::Usage::Data,
+                                                 Some(ChannelType::Unorm)).unwrap();
+    let image = src.get_info().to_image_info(0);
+    let half = ImageInfoCommon { width: 2, height: 2, ..image };
+    let shifted = ImageInfoCommon { xoffset: 2, ..image };
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    encoder.set_capabilities(Some(*factory.get_capabilities()));
+    assert_eq!(encoder.copy_texture::<Rgba8>(&src, None, half, &dst, None, image),
+               Err(gfx::CopyError::SizeMismatch { src: [2, 2, 0], dst: [4, 4, 0] }));
+    assert_eq!(encoder.copy_texture::<Rgba8>(&src, None, shifted, &dst, None, image),
+               Err(gfx::CopyError::OutOfSrcBounds { size: [4, 4, 0], copy_end: [6, 4, 0] }));
+    assert_eq!(encoder.copy_texture::<Rgba8>(&src, None, image, &dst, None, shifted),
+               Err(gfx::CopyError::OutOfDstBounds { size: [4, 4, 0], copy_end: [6, 4, 0] }));
+    assert_eq!(encoder.copy_texture_raw(src.raw(), None, image.convert(Rgba8::get_format()),
+                                        depth.raw(), None, image.convert(DepthStencil::get_format())),
+               Err(gfx::CopyError::IncompatibleFormats));
+    assert_eq!(encoder.command_buffer().commands.len(), 0);
+}
+
+#[test]
+fn test_blit_errors() {
+    let mut factory = DummyFactory::new();
+    let kind = Kind::D2(4, 4, AaMode::Single);
+    let bind = gfx::TRANSFER_SRC | gfx::TRANSFER_DST;
+    let src = factory.create_texture::<R8_G8_B8_A8>(kind, 1, bind, gfx::memory::Usage::Data,
+                                                    Some(ChannelType::Unorm)).unwrap();
+    let dst = factory.create_texture::<R8_G8_B8_A8>(kind, 1, bind, gfx::memory::Usage::Data,
+                                                    Some(ChannelType::Unorm)).unwrap();
+    let src_depth = factory.create_texture::<D24_S8>(kind, 1, bind, gfx::memory::Usage::Data,
+                                                     Some(ChannelType::Unorm)).unwrap();
+    let dst_depth = factory.create_texture::<D24_S8>(kind, 1, bind, gfx::memory::Usage::Data,
+                                                     Some(ChannelType::Unorm)).unwrap();
+    let image = src.get_info().to_image_info(0);
+    let half = ImageInfoCommon { width: 2, height: 2, ..image };
+    let shifted = ImageInfoCommon { yoffset: 3, ..image };
+    let (mirror, linear) = (gfx::Mirror::empty(), gfx::BlitFilter::Linear);
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    encoder.set_capabilities(Some(*factory.get_capabilities()));
+    // blits scale the source, so the sizes may differ
+    assert_eq!(encoder.blit::<Rgba8, Rgba8>(&src, None, half, &dst, None, image, mirror, linear),
+               Ok(()));
+    assert_eq!(encoder.blit::<Rgba8, Rgba8>(&src, None, shifted, &dst, None, image, mirror, linear),
+               Err(gfx::CopyError::OutOfSrcBounds { size: [4, 4, 0], copy_end: [4, 7, 0] }));
+    assert_eq!(encoder.blit::<Rgba8, Rgba8>(&src, None, image, &dst, None, shifted, mirror, linear),
+               Err(gfx::CopyError::OutOfDstBounds { size: [4, 4, 0], copy_end: [4, 7, 0] }));
+    assert_eq!(encoder.blit::<DepthStencil, Rgba8>(&src_depth, None, image, &dst, None, image,
+                                                   mirror, gfx::BlitFilter::Nearest),
+               Err(gfx::CopyError::IncompatibleFormats));
+    assert_eq!(encoder.blit::<DepthStencil, DepthStencil>(&src_depth, None, image, &dst_depth, None, image,
+                                                          mirror, linear),
+               Err(gfx::CopyError::InvalidFilter));
+    assert_eq!(encoder.blit::<DepthStencil, DepthStencil>(&src_depth, None, image, &dst_depth, None, image,
+                                                          mirror, gfx::BlitFilter::Nearest),
+               Ok(()));
+    assert_eq!(encoder.command_buffer().commands.len(), 2);
+}
+
+#[test]
+fn test_unsupported_resolve() {
+    let mut factory = DummyFactory::new();
+    let bind = gfx::RENDER_TARGET | gfx::TRANSFER_SRC | gfx::TRANSFER_DST;
+    let src = factory.create_texture::<R8_G8_B8_A8>(Kind::D2(4, 4, AaMode::Multi(4)), 1, bind,
+                                                    gfx::memory::Usage::Data, Some(ChannelType::Unorm)).unwrap();
+    let dst = factory.create_texture::<R8_G8_B8_A8>(Kind::D2(4, 4, AaMode::Single), 1, bind,
+                                                    gfx::memory::Usage::Data, Some(ChannelType::Unorm)).unwrap();
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    let mut caps = *factory.get_capabilities();
+    encoder.set_capabilities(Some(caps));
+    assert_eq!(encoder.resolve::<Rgba8, _, _>(&src, &dst), Ok(()));
+    caps.resolve_texture_supported = false;
+    encoder.set_capabilities(Some(caps));
+    assert_eq!(encoder.resolve::<Rgba8, _, _>(&src, &dst), Err(gfx::CopyError::Unsupported));
+    assert_eq!(encoder.command_buffer().commands.len(), 1);
+}
+
+#[test]
+fn test_resolve_errors() {
+    let mut factory = DummyFactory::new();
+    let bind = gfx::RENDER_TARGET | gfx::TRANSFER_SRC | gfx::TRANSFER_DST;
+    let (multi, single) = (Kind::D2(4, 4, AaMode::Multi(4)), Kind::D2(4, 4, AaMode::Single));
+    let src = factory.create_texture::<R8_G8_B8_A8>(multi, 1, bind, gfx::memory::Usage::Data,
+                                                    Some(ChannelType::Unorm)).unwrap();
+    let dst = factory.create_texture::<R8_G8_B8_A8>(single, 1, bind, gfx::memory::Usage::Data,
+                                                    Some(ChannelType::Unorm)).unwrap();
+    let small = factory.create_texture::<R8_G8_B8_A8>(Kind::D2(2, 2, AaMode::Single), 1, bind,
+                                                      gfx::memory::Usage::Data, Some(ChannelType::Unorm)).unwrap();
+    let bgra = factory.create_texture::<B8_G8_R8_A8>(single, 1, bind, gfx::memory::Usage::Data,
+                                                     Some(ChannelType::Unorm)).unwrap();
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    encoder.set_capabilities(Some(*factory.get_capabilities()));
+    assert_eq!(encoder.resolve::<Rgba8, _, _>(&dst, &dst), Err(gfx::CopyError::SrcNotMultisampled));
+    assert_eq!(encoder.resolve::<Rgba8, _, _>(&src, &src), Err(gfx::CopyError::DstMultisampled));
+    assert_eq!(encoder.resolve::<Rgba8, _, _>(&src, &small),
+               Err(gfx::CopyError::SizeMismatch { src: [4, 4, 0], dst: [2, 2, 0] }));
+    assert_eq!(encoder.resolve_raw(src.raw(), bgra.raw(), Rgba8::get_format()),
+               Err(gfx::CopyError::IncompatibleFormats));
+    assert_eq!(encoder.command_buffer().commands.len(), 0);
+}
+
+#[test]
+fn test_dispatch() {
+    let mut factory = DummyFactory::new();
+    let mut info = program_info("a_Pos");
+    info.vertex_attributes.clear();
+    info.outputs.clear();
+    info.unordereds.push(UnorderedVar {
+        name: "u_Values".to_string(),
+        slot: 0,
+        usage: shade::COMPUTE,
+    });
+    factory.set_program_info(info);
+    let pso = factory.create_pipeline_compute(&[], compute::new()).unwrap();
+    let values = factory.create_buffer::<[f32; 4]>(64, gfx::buffer::Role::Vertex,
+        gfx::memory::Usage::Data, gfx::UNORDERED_ACCESS).unwrap();
+    let data = compute::Data {
+        values: factory.view_buffer_as_unordered_access(&values).unwrap(),
+    };
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    // without capabilities, the dispatch is assumed to be supported
+    assert_eq!(encoder.dispatch(&pso, &data, [4, 2, 1]), Ok(()));
+    let mut caps = *factory.get_capabilities();
+    encoder.set_capabilities(Some(caps));
+    assert_eq!(encoder.dispatch(&pso, &data, [1, 1, 1]), Ok(()));
+    let dispatches: Vec<_> = encoder.command_buffer().commands.iter().filter_map(|com| match *com {
+        RecordedCommand::Dispatch(groups) => Some(groups),
+        _ => None,
+    }).collect();
+    assert_eq!(dispatches, vec![[4, 2, 1], [1, 1, 1]]);
+
+    caps.compute_supported = false;
+    encoder.set_capabilities(Some(caps));
+    let num_commands = encoder.command_buffer().commands.len();
+    assert_eq!(encoder.dispatch(&pso, &data, [1, 1, 1]), Err(gfx::DispatchError::Unsupported));
+    assert_eq!(encoder.command_buffer().commands.len(), num_commands);
+}
@@ -0,0 +1,160 @@
+//! Image comparison of the examples against the reference renders
+//! in `tests/reference`, see the README there.
+//!
+//! The examples are run headlessly, so these tests need an OpenGL
+//! implementation that works without a display (OSMesa on Linux),
+//! and are ignored by default. The examples have to be built with the
+//! `png` feature first:
+//!
+//! ```sh
+//! cargo build --examples --features png
+//! cargo test --test examples -- --ignored
+//! ```
+
+extern crate gfx;
+extern crate gfx_app;
+extern crate gfx_device_gl;
+extern crate image;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 256;
+
+fn get_build_dir() -> PathBuf {
+    // the test executable is in `target/<profile>/deps`
+    let mut path = env::current_exe().unwrap();
+    path.pop();
+    if path.ends_with("deps") {
+        path.pop();
+    }
+    path
+}
+
+/// The references are in `tests/reference`, unless `GFX_REFERENCE_DIR` is set.
+fn get_reference_dir() -> PathBuf {
+    match env::var_os("GFX_REFERENCE_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("reference"),
+    }
+}
+
+fn render(name: &str, num_frames: usize) -> image::RgbaImage {
+    let build_dir = get_build_dir();
+    let output_dir = build_dir.join("example-renders");
+    fs::create_dir_all(&output_dir).unwrap();
+    let output = output_dir.join(format!("{}.png", name));
+    let examples_dir = match env::var_os("GFX_EXAMPLES_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => build_dir.join("examples"),
+    };
+    let example = examples_dir.join(format!("{}{}", name, env::consts::EXE_SUFFIX));
+    let result = Command::new(&example)
+        .env("GFX_APP_HEADLESS_FRAMES", num_frames.to_string())
+        .env("GFX_APP_HEADLESS_SIZE", format!("{}x{}", WIDTH, HEIGHT))
+        .env("GFX_APP_HEADLESS_OUTPUT", &output)
+        .output()
+        .expect("Unable to run the example, was it built?");
+    if !result.status.success() {
+        panic!("Example {} failed: {}", name, String::from_utf8_lossy(&result.stderr));
+    }
+    image::open(&output).unwrap().to_rgba()
+}
+
+/// Render an example and compare it with its reference, allowing each channel
+/// to differ by `tolerance`. On failure, an image with the differing pixels
+/// in red is saved next to the render.
+///
+/// With `GFX_BLESS` set, the render becomes the new reference instead.
+fn check(name: &str, num_frames: usize, tolerance: u8) {
+    let actual = render(name, num_frames);
+    let reference_path = get_reference_dir().join(format!("{}.png", name));
+    if env::var("GFX_BLESS").is_ok() {
+        fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        actual.save(&reference_path).unwrap();
+        return
+    }
+    let reference = match image::open(&reference_path) {
+        Ok(image) => image.to_rgba(),
+        Err(e) => panic!("Unable to open the reference {:?}: {}. Run with GFX_BLESS=1 to create it",
+                         reference_path, e),
+    };
+    assert_eq!(actual.dimensions(), reference.dimensions());
+
+    let mut diff = image::RgbaImage::new(WIDTH, HEIGHT);
+    let mut num_failed = 0;
+    for (x, y, pixel) in actual.enumerate_pixels() {
+        let expected = reference.get_pixel(x, y);
+        let distance = pixel.data.iter().zip(expected.data.iter())
+                            .map(|(&a, &b)| if a > b { a - b } else { b - a })
+                            .max().unwrap();
+        let color = if distance > tolerance {
+            num_failed += 1;
+            [0xFF, 0, 0, 0xFF]
+        } else {
+            // keep a faded version of the image around the differences
+            let gray = pixel.data[..3].iter().map(|&c| c as u32).sum::<u32>() / 12;
+            [gray as u8, gray as u8, gray as u8, 0xFF]
+        };
+        diff.put_pixel(x, y, image::Rgba { data: color });
+    }
+    if num_failed != 0 {
+        let diff_path = get_build_dir().join("example-renders").join(format!("{}-diff.png", name));
+        diff.save(&diff_path).unwrap();
+        panic!("{} pixels of {} differ from the reference by more than {}, see {:?}",
+               num_failed, name, tolerance, diff_path);
+    }
+}
+
+/// Only clears the off-screen target, to check that it's read back.
+struct Clear<R: gfx::Resources> {
+    color: gfx::handle::RenderTargetView<R, gfx_app::ColorFormat>,
+}
+
+impl<R: gfx::Resources> gfx_app::Application<R> for Clear<R> {
+    fn new<F: gfx::Factory<R>>(_: &mut F, _: gfx_app::shade::Backend,
+                               targets: gfx_app::WindowTargets<R>) -> Self {
+        Clear {
+            color: targets.color,
+        }
+    }
+
+    fn render<C: gfx::CommandBuffer<R>>(&mut self, encoder: &mut gfx::Encoder<R, C>) {
+        encoder.clear(&self.color, [1.0, 0.0, 1.0, 1.0]);
+    }
+}
+
+#[test]
+#[ignore]
+fn clear_color() {
+    use gfx_app::Application;
+
+    let frame = Clear::<gfx_device_gl::Resources>::launch_headless(4, 2, 1);
+    assert_eq!((frame.width, frame.height), (4, 2));
+    assert_eq!(frame.data, [0xFF, 0, 0xFF, 0xFF].iter().cycle().cloned().take(4 * 2 * 4)
+                                                .collect::<Vec<u8>>());
+}
+
+macro_rules! examples {
+    { $( $name:ident: $num_frames:expr, $tolerance:expr; )* } => {
+        $(
+            #[test]
+            #[ignore]
+            fn $name() {
+                check(stringify!($name), $num_frames, $tolerance)
+            }
+        )*
+    }
+}
+
+// only the examples which don't depend on the time or randomness
+examples! {
+    blend: 1, 2;
+    cube: 1, 2;
+    mipmap: 1, 2;
+    shadow: 4, 4;
+    ubo_tilemap: 1, 2;
+}
@@ -0,0 +1,134 @@
+extern crate gfx;
+extern crate gfx_core;
+
+use std::cell::RefCell;
+use gfx::CombinedError;
+use gfx::format::{Rgba8, SurfaceType};
+use gfx::ResourcePool;
+use gfx::graph::{Graph, GraphError};
+use gfx::memory::{Bind, Usage};
+use gfx::texture::{AaMode, CreationError, Info, Kind};
+use gfx_core::dummy::{DummyFactory, DummyResources, RecordingCommandBuffer};
+
+fn info() -> Info {
+    Info {
+        kind: Kind::D2(4, 4, AaMode::Single),
+        levels: 1,
+        format: SurfaceType::R8_G8_B8_A8,
+        bind: Bind::empty(),
+        usage: Usage::Data,
+    }
+}
+
+/// Execute a graph rendering into a texture, then reading from it.
+fn execute(pool: &mut ResourcePool<DummyResources>, info: Info) -> Result<(), GraphError> {
+    let mut factory = DummyFactory::new();
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    let mut graph: Graph<DummyResources, RecordingCommandBuffer> = Graph::new();
+    let texture = graph.create_texture::<Rgba8>("texture", info);
+    graph.add_pass("write", |_, _| {}).write(texture);
+    graph.add_pass("read", |_, _| {}).read(texture);
+    graph.execute(pool, &mut factory, &mut encoder)
+}
+
+#[test]
+fn test_schedule() {
+    let mut factory = DummyFactory::new();
+    let mut pool = ResourcePool::new(1);
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    let executed = RefCell::new(Vec::new());
+    let log = &executed;
+
+    let mut graph: Graph<DummyResources, RecordingCommandBuffer> = Graph::new();
+    let t1 = graph.create_texture::<Rgba8>("t1", info());
+    let t2 = graph.create_texture::<Rgba8>("t2", info());
+    let t3 = graph.create_texture::<Rgba8>("t3", info());
+    let unused = graph.create_texture::<Rgba8>("unused", info());
+    graph.add_pass("a", move |_, targets| {
+        targets.get_render_target(t1);
+        log.borrow_mut().push("a");
+    }).write(t1);
+    graph.add_pass("b", move |_, targets| {
+        targets.get_shader_resource(t1);
+        targets.get_render_target(t2);
+        log.borrow_mut().push("b");
+    }).read(t1).write(t2);
+    graph.add_pass("unused", move |_, _| {
+        log.borrow_mut().push("unused");
+    }).read(t1).write(unused);
+    // declared before the pass producing its input
+    graph.add_pass("d", move |_, targets| {
+        targets.get_shader_resource(t3);
+        log.borrow_mut().push("d");
+    }).read(t3);
+    graph.add_pass("c", move |_, _| {
+        log.borrow_mut().push("c");
+    }).read(t2).write(t3);
+    graph.execute(&mut pool, &mut factory, &mut encoder).unwrap();
+
+    assert_eq!(*executed.borrow(), vec!["a", "b", "c", "d"]);
+    // `t3` re-uses the storage of `t1`
+    assert_eq!(pool.num_textures(), 2);
+}
+
+#[test]
+fn test_unwritten() {
+    let mut factory = DummyFactory::new();
+    let mut pool = ResourcePool::new(1);
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+
+    let mut graph: Graph<DummyResources, RecordingCommandBuffer> = Graph::new();
+    let texture = graph.create_texture::<Rgba8>("texture", info());
+    graph.add_pass("read", |_, _| {}).read(texture);
+    match graph.execute(&mut pool, &mut factory, &mut encoder) {
+        Err(GraphError::Unwritten(ref name)) if name == "texture" => (),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_cycle() {
+    let mut factory = DummyFactory::new();
+    let mut pool = ResourcePool::new(1);
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+
+    let mut graph: Graph<DummyResources, RecordingCommandBuffer> = Graph::new();
+    let t1 = graph.create_texture::<Rgba8>("t1", info());
+    let t2 = graph.create_texture::<Rgba8>("t2", info());
+    graph.add_pass("a", |_, _| {}).read(t2).write(t1);
+    graph.add_pass("b", |_, _| {}).read(t1).write(t2);
+    graph.add_pass("present", |_, _| {}).read(t1);
+    match graph.execute(&mut pool, &mut factory, &mut encoder) {
+        Err(GraphError::Cycle(ref name)) if name == "a" => (),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_eviction() {
+    let mut pool = ResourcePool::new(1);
+    execute(&mut pool, info()).unwrap();
+    pool.end_frame();
+    execute(&mut pool, info()).unwrap();
+    pool.end_frame();
+    assert_eq!(pool.num_textures(), 1);
+
+    // after a resize, the textures of the old size are freed
+    let resized = Info { kind: Kind::D2(8, 8, AaMode::Single), .. info() };
+    execute(&mut pool, resized).unwrap();
+    pool.end_frame();
+    assert_eq!(pool.num_textures(), 2);
+    execute(&mut pool, resized).unwrap();
+    pool.end_frame();
+    assert_eq!(pool.num_textures(), 1);
+}
+
+#[test]
+fn test_no_levels() {
+    let mut pool = ResourcePool::new(1);
+    let info = Info { levels: 0, .. info() };
+    match execute(&mut pool, info) {
+        Err(GraphError::Resource(CombinedError::Texture(CreationError::Size(0)))) => (),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
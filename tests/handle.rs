@@ -1,7 +1,7 @@
 extern crate gfx_core as core;
 
 use std::mem;
-use core::dummy::DummyResources;
+use core::dummy::{DummyId, DummyResources};
 use core::buffer;
 use core::memory::{Bind, Usage};
 use core::handle::{Buffer, Manager, Producer};
@@ -9,7 +9,7 @@ use core::handle::{Buffer, Manager, Producer};
 fn mock_buffer<T>(len: usize) -> Buffer<DummyResources, T> {
     use core::memory::Typed;
     let mut handler = Manager::new();
-    let raw = handler.make_buffer((), buffer::Info {
+    let raw = handler.make_buffer(DummyId(0), buffer::Info {
         role: buffer::Role::Vertex,
         usage: Usage::Data,
         size: mem::size_of::<T>() * len,
@@ -34,7 +34,7 @@ fn test_buffer_zero_len() {
 #[test]
 fn test_cleanup() {
     let mut man: Manager<DummyResources> = Manager::new();
-    let _ = man.make_shader(());
+    let _ = man.make_shader(DummyId(0));
     let mut count = 0u8;
     man.clean_with(&mut count,
         |_,_| (),
@@ -48,6 +48,7 @@ fn test_cleanup() {
         |_,_| (),
         |_,_| (),
         |_,_| (),
+        |_,_| (),
         );
     assert_eq!(count, 1);
 }
@@ -0,0 +1,61 @@
+extern crate gfx_core;
+
+use gfx_core::Factory;
+use gfx_core::buffer::Role;
+use gfx_core::dummy::{DummyFactory, DummyFence, DummyResources};
+use gfx_core::handle::{Manager, Producer};
+use gfx_core::memory::{Bind, Usage};
+use gfx_core::mapping::{Error, Status, get_region};
+
+#[test]
+fn test_regions() {
+    assert_eq!(get_region(12, 0, 3), Ok((0, 4)));
+    assert_eq!(get_region(12, 2, 3), Ok((8, 12)));
+}
+
+#[test]
+fn test_region_remainder() {
+    // the last element is not part of any region
+    assert_eq!(get_region(10, 0, 3), Ok((0, 3)));
+    assert_eq!(get_region(10, 2, 3), Ok((6, 9)));
+    // the regions are empty if there are less elements than regions
+    assert_eq!(get_region(2, 2, 3), Ok((0, 0)));
+}
+
+#[test]
+fn test_invalid_regions() {
+    assert_eq!(get_region(10, 0, 0), Err(Error::InvalidRegion(0, 0)));
+    assert_eq!(get_region(10, 3, 3), Err(Error::InvalidRegion(3, 3)));
+    assert_eq!(get_region(10, 7, 3), Err(Error::InvalidRegion(7, 3)));
+}
+
+#[test]
+fn test_region_mapping() {
+    let mut factory = DummyFactory::new();
+    let buffer = factory.create_buffer::<u32>(10, Role::Staging, Usage::Upload, Bind::empty()).unwrap();
+    factory.write_mapping_region(&buffer, 1, 3).unwrap().copy_from_slice(&[1, 2, 3]);
+    assert_eq!(&factory.write_mapping(&buffer).unwrap()[.. 7], &[0, 0, 0, 1, 2, 3, 0]);
+    assert_eq!(factory.write_mapping_region(&buffer, 3, 3).err(), Some(Error::InvalidRegion(3, 3)));
+    assert_eq!(factory.write_mapping_region(&buffer, 0, 0).err(), Some(Error::InvalidRegion(0, 0)));
+    // the mapping is released on failure
+    assert!(factory.write_mapping(&buffer).is_ok());
+}
+
+#[test]
+fn test_region_fences() {
+    let mut manager = Manager::<DummyResources>::new();
+    let mut status = Status::clean();
+    let mut waits = 0;
+
+    status.cpu_write_region_access(0, 2, |_| waits += 1);
+    status.gpu_access(manager.make_fence(DummyFence));
+    // the other region is not used by the GPU
+    status.cpu_write_region_access(1, 2, |_| waits += 1);
+    assert_eq!(waits, 0);
+    status.gpu_access(manager.make_fence(DummyFence));
+    status.cpu_write_region_access(0, 2, |_| waits += 1);
+    assert_eq!(waits, 1);
+    // accessing the whole buffer waits for the remaining region
+    status.cpu_access(|_| waits += 1);
+    assert_eq!(waits, 2);
+}
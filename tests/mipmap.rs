@@ -0,0 +1,50 @@
+extern crate gfx;
+extern crate gfx_core;
+
+use gfx::format::{ChannelType, Format, Srgba8, SurfaceType};
+use gfx::mipmap;
+use gfx::texture::{AaMode, Kind, Mipmap, MipmapFilter};
+use gfx::Factory;
+use gfx_core::dummy::DummyFactory;
+
+const SRGBA8: Format = Format(SurfaceType::R8_G8_B8_A8, ChannelType::Srgb);
+
+#[test]
+fn srgb_average() {
+    let kind = Kind::D2(2, 1, AaMode::Single);
+    let top = [0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+    let chain = mipmap::generate(kind, SRGBA8, MipmapFilter::Box, &[&top]).unwrap();
+    assert_eq!(chain.len(), 2);
+    // half of the linear intensity, encoded back into sRGB
+    assert_eq!(chain[1], vec![0xBC, 0xBC, 0xBC, 0xFF]);
+}
+
+#[test]
+fn cube_chain() {
+    let kind = Kind::Cube(4);
+    let faces: Vec<Vec<u8>> = (0 .. 6).map(|face| vec![face * 40; 4 * 4 * 4]).collect();
+    let top: Vec<&[u8]> = faces.iter().map(|face| &face[..]).collect();
+    for &filter in [MipmapFilter::Box, MipmapFilter::Kaiser].iter() {
+        let chain = mipmap::generate(kind, SRGBA8, filter, &top).unwrap();
+        // slice by slice, with 3 levels each
+        assert_eq!(chain.len(), 18);
+        assert_eq!(chain[5], vec![40; 4]);
+        assert_eq!(chain[17].len(), 4);
+        // uniform faces stay uniform
+        for (i, level) in chain.iter().enumerate() {
+            assert!(level.iter().all(|&b| b == (i / 3) as u8 * 40), "level {}: {:?}", i, level);
+        }
+    }
+}
+
+#[test]
+fn generated_texture() {
+    let mut factory = DummyFactory::new();
+    let kind = Kind::D2(5, 3, AaMode::Single);
+    let top = [[0xFF; 4]; 15];
+    let (texture, _) = factory.create_texture_immutable_with_mipmaps::<Srgba8>(
+        kind, Mipmap::Generated(MipmapFilter::Box), &[&top]).unwrap();
+    assert_eq!(texture.get_info().levels, 3);
+    assert!(mipmap::generate(Kind::D2(5, 3, AaMode::Multi(4)), SRGBA8,
+                             MipmapFilter::Box, &[&[0; 60][..]]).is_err());
+}
@@ -0,0 +1,81 @@
+extern crate gfx;
+extern crate gfx_core;
+
+use gfx::ResourcePool;
+use gfx::format::Rgba8;
+use gfx::handle::{RenderTargetView, ShaderResourceView};
+use gfx_core::dummy::{DummyFactory, DummyId, DummyResources};
+use gfx_core::handle::Manager;
+
+fn view_ids(srv: &ShaderResourceView<DummyResources, [f32; 4]>,
+            rtv: &RenderTargetView<DummyResources, Rgba8>) -> (DummyId, DummyId) {
+    let mut handles = Manager::new();
+    (*handles.ref_srv(srv.raw()), *handles.ref_rtv(rtv.raw()))
+}
+
+#[test]
+fn test_texture_reuse() {
+    let mut factory = DummyFactory::new();
+    let mut pool = ResourcePool::new(2);
+
+    let (texture, _, _) = pool.get_render_target::<_, Rgba8>(&mut factory, 4, 4).unwrap();
+    // still in use, so a new one is created
+    let (other, _, _) = pool.get_render_target::<_, Rgba8>(&mut factory, 4, 4).unwrap();
+    assert!(texture != other);
+    assert_eq!(pool.num_textures(), 2);
+
+    drop(texture);
+    let (again, _, _) = pool.get_render_target::<_, Rgba8>(&mut factory, 4, 4).unwrap();
+    assert_eq!(pool.num_textures(), 2);
+    // a different size doesn't match
+    pool.get_render_target::<_, Rgba8>(&mut factory, 8, 8).unwrap();
+    assert_eq!(pool.num_textures(), 3);
+    drop(again);
+    drop(other);
+
+    pool.end_frame();
+    assert_eq!(pool.num_textures(), 3);
+    pool.end_frame();
+    assert_eq!(pool.num_textures(), 3);
+    pool.end_frame();
+    assert_eq!(pool.num_textures(), 0);
+}
+
+#[test]
+fn test_buffer_reuse() {
+    let mut factory = DummyFactory::new();
+    let mut pool = ResourcePool::new(1);
+    let buffer = pool.get_buffer::<_, [f32; 4]>(&mut factory, 16, gfx::buffer::Role::Constant,
+                                              gfx::memory::Usage::Dynamic, gfx::Bind::empty()).unwrap();
+    assert_eq!(buffer.len(), 16);
+    drop(buffer);
+    pool.get_buffer::<_, [f32; 4]>(&mut factory, 16, gfx::buffer::Role::Constant,
+                                   gfx::memory::Usage::Dynamic, gfx::Bind::empty()).unwrap();
+    assert_eq!(pool.num_buffers(), 1);
+}
+
+#[test]
+fn test_view_cache() {
+    let mut factory = DummyFactory::new();
+    let mut pool = ResourcePool::new(1);
+    let ids = {
+        let (_, srv, rtv) = pool.get_render_target::<_, Rgba8>(&mut factory, 4, 4).unwrap();
+        view_ids(&srv, &rtv)
+    };
+    // the texture is free despite the views cached by the pool
+    let (_, srv, rtv) = pool.get_render_target::<_, Rgba8>(&mut factory, 4, 4).unwrap();
+    assert_eq!(pool.num_textures(), 1);
+    assert_eq!(view_ids(&srv, &rtv), ids);
+}
+
+#[test]
+fn test_no_unused_frames() {
+    let mut factory = DummyFactory::new();
+    let mut pool = ResourcePool::new(0);
+    let (texture, _, _) = pool.get_render_target::<_, Rgba8>(&mut factory, 4, 4).unwrap();
+    pool.end_frame();
+    assert_eq!(pool.num_textures(), 1);
+    drop(texture);
+    pool.end_frame();
+    assert_eq!(pool.num_textures(), 0);
+}
@@ -0,0 +1,112 @@
+extern crate gfx;
+extern crate gfx_core;
+
+use gfx::profiler::{Event, Profiler};
+use gfx_core::dummy::{DummyFactory, DummyId, DummyResources, RecordedCommand, RecordingCommandBuffer};
+
+type Encoder = gfx::Encoder<DummyResources, RecordingCommandBuffer>;
+
+fn new_encoder(factory: &mut DummyFactory) -> Encoder {
+    let mut encoder: Encoder = RecordingCommandBuffer::new().into();
+    encoder.set_profiler(Some(Profiler::new(factory, 8).unwrap()));
+    encoder
+}
+
+/// Get the timestamp queries written by the encoded commands, in order.
+fn timestamps(encoder: &Encoder) -> Vec<DummyId> {
+    encoder.command_buffer().commands.iter().filter_map(|com| match *com {
+        RecordedCommand::WriteTimestamp(id) => Some(id),
+        _ => None,
+    }).collect()
+}
+
+/// Write the given times into the queries, as the GPU would.
+fn resolve(factory: &mut DummyFactory, queries: &[DummyId], times: &[u64]) {
+    assert_eq!(queries.len(), times.len());
+    for (&query, &time) in queries.iter().zip(times.iter()) {
+        factory.set_query_result(query, time);
+    }
+}
+
+fn event(name: &str, frame: u64, depth: usize, start: u64, end: u64) -> Event {
+    Event {
+        name: name.to_string(),
+        frame: frame,
+        depth: depth,
+        start: start,
+        end: end,
+    }
+}
+
+#[test]
+fn nested_scopes() {
+    let mut factory = DummyFactory::new();
+    let mut encoder = new_encoder(&mut factory);
+    {
+        let mut outer = encoder.profile_scope("outer");
+        outer.profile_scope("inner");
+    }
+    let queries = timestamps(&encoder);
+    assert_eq!(queries.len(), 4);
+    resolve(&mut factory, &queries, &[1000, 2000, 3000, 4000]);
+
+    let profiler = encoder.profiler_mut().unwrap();
+    profiler.collect(&mut factory);
+    // the inner scope is done first
+    assert_eq!(profiler.events(), &[
+        event("inner", 0, 1, 2000, 3000),
+        event("outer", 0, 0, 1000, 4000),
+    ][..]);
+    assert_eq!(profiler.events()[1].duration(), 3000);
+}
+
+#[test]
+fn results_across_frames() {
+    let mut factory = DummyFactory::new();
+    let mut encoder = new_encoder(&mut factory);
+
+    encoder.profile_scope("first");
+    let first = timestamps(&encoder);
+    encoder.reset();
+    encoder.profiler_mut().unwrap().end_frame();
+    encoder.profile_scope("second");
+    let second = timestamps(&encoder);
+    encoder.reset();
+
+    // the scopes are collected in order, so the second one waits for the first
+    resolve(&mut factory, &second, &[300, 400]);
+    encoder.profiler_mut().unwrap().collect(&mut factory);
+    assert!(encoder.profiler().unwrap().events().is_empty());
+
+    resolve(&mut factory, &first, &[100, 200]);
+    encoder.profiler_mut().unwrap().collect(&mut factory);
+    assert_eq!(encoder.profiler().unwrap().events(), &[
+        event("first", 0, 0, 100, 200),
+        event("second", 1, 0, 300, 400),
+    ][..]);
+    assert_eq!(encoder.profiler().unwrap().get_frame(), 1);
+}
+
+#[test]
+fn chrome_trace() {
+    let mut factory = DummyFactory::new();
+    let mut encoder = new_encoder(&mut factory);
+    {
+        let mut scope = encoder.profile_scope("shadow \"sun\"");
+        scope.profile_scope("cascade\t0");
+    }
+    let queries = timestamps(&encoder);
+    resolve(&mut factory, &queries, &[10000, 11500, 12000, 20000]);
+    let profiler = encoder.profiler_mut().unwrap();
+    profiler.collect(&mut factory);
+
+    let mut output = Vec::new();
+    profiler.write_chrome_trace(&mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), concat!(
+        "{\"traceEvents\":[\n",
+        "{\"name\":\"cascade\\t0\",\"cat\":\"gpu\",\"ph\":\"X\",\"pid\":0,\"tid\":0,",
+        "\"ts\":1.500,\"dur\":0.500,\"args\":{\"frame\":0}},\n",
+        "{\"name\":\"shadow \\\"sun\\\"\",\"cat\":\"gpu\",\"ph\":\"X\",\"pid\":0,\"tid\":0,",
+        "\"ts\":0.000,\"dur\":10.000,\"args\":{\"frame\":0}}\n",
+        "],\"displayTimeUnit\":\"ns\"}\n"));
+}
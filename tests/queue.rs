@@ -0,0 +1,110 @@
+#[macro_use]
+extern crate gfx;
+extern crate gfx_core;
+
+use gfx::{Bundle, RenderQueue, SortKey};
+use gfx::format::Rgba8;
+use gfx::shade::core::{AttributeVar, BaseType, ContainerType, OutputVar, ProgramInfo};
+use gfx::traits::FactoryExt;
+use gfx_core::dummy::{DummyFactory, RecordedCommand, RecordingCommandBuffer};
+
+gfx_defines!{
+    vertex Vertex {
+        pos: [f32; 2] = "a_Pos",
+    }
+
+    pipeline pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        out: gfx::RenderTarget<Rgba8> = "Target0",
+    }
+
+    pipeline blend_pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        out: gfx::BlendTarget<Rgba8> =
+            ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
+    }
+}
+
+fn program_info() -> ProgramInfo {
+    ProgramInfo {
+        vertex_attributes: vec![AttributeVar {
+            name: "a_Pos".to_string(),
+            slot: 0,
+            base_type: BaseType::F32,
+            container: ContainerType::Vector(2),
+        }],
+        globals: Vec::new(),
+        constant_buffers: Vec::new(),
+        textures: Vec::new(),
+        unordereds: Vec::new(),
+        samplers: Vec::new(),
+        outputs: vec![OutputVar {
+            name: "Target0".to_string(),
+            slot: 0,
+            base_type: BaseType::F32,
+            container: ContainerType::Vector(4),
+        }],
+        output_depth: false,
+        knows_outputs: true,
+    }
+}
+
+fn key(pass: u8, material: u32, depth: f32) -> SortKey {
+    SortKey {
+        pass: pass,
+        material: material,
+        depth: depth,
+    }
+}
+
+#[test]
+fn test_sorting() {
+    let mut factory = DummyFactory::new();
+    factory.set_program_info(program_info());
+    let vertices = [Vertex { pos: [0.0, 0.0] }; 3];
+    let (vbuf, _) = factory.create_vertex_buffer_with_slice(&vertices, ());
+    let (_, _, rtv) = factory.create_render_target::<Rgba8>(4, 4).unwrap();
+
+    // the slice start identifies each bundle in the recorded draws
+    let bundles: Vec<_> = (0 .. 6).map(|i| {
+        let pso = factory.create_pipeline_simple(&[], &[], pipe::new()).unwrap();
+        let (_, mut slice) = factory.create_vertex_buffer_with_slice(&vertices, ());
+        slice.start = i;
+        Bundle::new(slice, pso, pipe::Data {
+            vbuf: vbuf.clone(),
+            out: rtv.clone(),
+        })
+    }).collect();
+    // a bundle of another pipeline type can share the queue
+    let blended = {
+        let pso = factory.create_pipeline_simple(&[], &[], blend_pipe::new()).unwrap();
+        let (_, mut slice) = factory.create_vertex_buffer_with_slice(&vertices, ());
+        slice.start = 6;
+        Bundle::new(slice, pso, blend_pipe::Data {
+            vbuf: vbuf.clone(),
+            out: rtv.clone(),
+        })
+    };
+
+    let mut queue = RenderQueue::new();
+    queue.set_transparent(1);
+    // transparent bundles, with 3 and 4 at the same depth
+    queue.push(&bundles[3], key(1, 0, 1.0));
+    queue.push(&bundles[4], key(1, 0, 1.0));
+    queue.push(&bundles[5], key(1, 0, 2.0));
+    queue.push(&blended, key(1, 0, 3.0));
+    // opaque bundles, two of them sharing a pipeline state
+    queue.push(&bundles[0], key(0, 0, 1.0));
+    queue.push(&bundles[1], key(0, 0, 1.0));
+    queue.push(&bundles[0], key(0, 1, 0.5));
+    queue.push(&bundles[2], key(0, 0, 1.0));
+    assert_eq!(queue.len(), 8);
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    queue.encode(&mut encoder);
+    let order: Vec<_> = encoder.command_buffer().commands.iter().filter_map(|com| match *com {
+        RecordedCommand::Draw(start, _, _) => Some(start),
+        _ => None,
+    }).collect();
+    assert_eq!(order, vec![0, 0, 1, 2, 6, 5, 3, 4]);
+}
@@ -0,0 +1,104 @@
+#[macro_use]
+extern crate gfx;
+extern crate gfx_core;
+
+use gfx::{DynamicRing, Factory, RingError};
+use gfx::format::Rgba8;
+use gfx::shade::core::{self as shade, ConstantBufferVar};
+use gfx::traits::FactoryExt;
+use gfx_core::dummy::{DummyFactory, RecordedCommand, RecordingCommandBuffer};
+use support::program_info;
+
+mod support;
+
+gfx_defines!{
+    vertex Vertex {
+        pos: [f32; 2] = "a_Pos",
+    }
+
+    constant Locals {
+        color: [f32; 4] = "u_Color",
+    }
+
+    pipeline pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        locals: gfx::ConstantBufferRange<Locals> = "Locals",
+        out: gfx::RenderTarget<Rgba8> = "Target0",
+    }
+}
+
+#[test]
+fn test_allocation() {
+    let mut factory = DummyFactory::new();
+    let mut ring = DynamicRing::new(&mut factory, 1024).unwrap();
+    assert_eq!(ring.get_alignment(), 256);
+    let locals = Locals { color: [1.0; 4] };
+
+    ring.begin_frame();
+    let offsets: Vec<_> = (0 .. 3).map(|_| ring.push(&locals).unwrap().1).collect();
+    assert_eq!(offsets, vec![0, 256, 512]);
+    ring.begin_frame();
+    // the second allocation wraps around the end of the buffer
+    assert_eq!(ring.push(&locals).unwrap().1, 768);
+    assert_eq!(ring.push(&locals).unwrap().1, 0);
+    assert_eq!(ring.push(&locals).unwrap().1, 256);
+    assert_eq!(ring.push(&locals).unwrap().1, 512);
+    // the rest of the buffer is used by the current frame
+    assert!(ring.push(&locals).is_none());
+}
+
+#[test]
+fn test_unsupported() {
+    let mut factory = DummyFactory::new();
+    let mut caps = *factory.get_capabilities();
+    caps.constant_buffer_range_supported = false;
+    factory.set_capabilities(caps);
+    assert_eq!(DynamicRing::new(&mut factory, 1024).err(), Some(RingError::Unsupported));
+}
+
+#[test]
+fn test_binding() {
+    let mut factory = DummyFactory::new();
+    let mut info = program_info("a_Pos");
+    info.constant_buffers.push(ConstantBufferVar {
+        name: "Locals".to_string(),
+        slot: 0,
+        size: 16,
+        usage: shade::VERTEX,
+        elements: Vec::new(),
+    });
+    factory.set_program_info(info);
+    let pso = factory.create_pipeline_simple(&[], &[], pipe::new()).unwrap();
+    let vertices = [Vertex { pos: [0.0, 0.0] }; 3];
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices, ());
+    let (_, _, rtv) = factory.create_render_target::<Rgba8>(4, 4).unwrap();
+    let mut ring = DynamicRing::new(&mut factory, 1024).unwrap();
+
+    let mut encoder: gfx::Encoder<_, _> = RecordingCommandBuffer::new().into();
+    ring.begin_frame();
+    let ranges: Vec<_> = (0 .. 2).map(|i| {
+        ring.push(&Locals { color: [i as f32; 4] }).unwrap()
+    }).collect();
+    ring.upload(&mut encoder).unwrap();
+    let mut data = pipe::Data {
+        vbuf: vbuf,
+        locals: ranges[0].clone(),
+        out: rtv,
+    };
+    for range in ranges {
+        data.locals = range;
+        encoder.draw(&slice, &pso, &data);
+    }
+
+    let commands = &encoder.command_buffer().commands;
+    let num_updates = commands.iter().filter(|com| match **com {
+        RecordedCommand::UpdateBuffer(..) => true,
+        _ => false,
+    }).count();
+    assert_eq!(num_updates, 1);
+    let bound: Vec<_> = commands.iter().filter_map(|com| match *com {
+        RecordedCommand::BindConstantBuffers(ref cbs) => cbs[0].3,
+        _ => None,
+    }).collect();
+    assert_eq!(bound, vec![(0, 16), (256, 16)]);
+}
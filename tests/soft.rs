@@ -0,0 +1,610 @@
+#[macro_use]
+extern crate gfx;
+extern crate gfx_device_soft;
+
+use gfx::Factory;
+use gfx::format::{ChannelType, Depth, DepthStencil, Rgba8, R8_G8_B8_A8};
+use gfx::handle::{RenderTargetView, Texture};
+use gfx::shade::core::{BaseType, ContainerType, IsArray, IsComparison, IsMultiSample,
+                       IsRect, SamplerType, TextureType};
+use gfx::state::{Comparison, Stencil, StencilOp, StencilSide};
+use gfx::texture::{AaMode, FilterMethod, Kind, SamplerInfo, WrapMode};
+use gfx::traits::FactoryExt;
+use gfx_device_soft::shade::{Context, Interface, PixelInput, PixelOutput,
+                             VertexInput, VertexOutput};
+
+gfx_defines!{
+    vertex Vertex {
+        pos: [f32; 2] = "a_Pos",
+        color: [f32; 3] = "a_Color",
+    }
+
+    pipeline pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        out: gfx::RenderTarget<Rgba8> = "Target0",
+    }
+
+    vertex ColorVertex {
+        pos: [f32; 3] = "a_Pos",
+        color: [f32; 4] = "a_Color",
+    }
+
+    vertex TexVertex {
+        pos: [f32; 2] = "a_Pos",
+        uv: [f32; 2] = "a_Uv",
+    }
+
+    pipeline blend_pipe {
+        vbuf: gfx::VertexBuffer<ColorVertex> = (),
+        out: gfx::BlendTarget<Rgba8> =
+            ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
+    }
+
+    pipeline depth_pipe {
+        vbuf: gfx::VertexBuffer<ColorVertex> = (),
+        out: gfx::RenderTarget<Rgba8> = "Target0",
+        depth: gfx::DepthTarget<Depth> = gfx::preset::depth::LESS_EQUAL_WRITE,
+    }
+
+    pipeline stencil_pipe {
+        vbuf: gfx::VertexBuffer<ColorVertex> = (),
+        out: gfx::RenderTarget<Rgba8> = "Target0",
+        depth_stencil: gfx::DepthStencilTarget<DepthStencil> = (gfx::preset::depth::PASS_TEST,
+            Stencil::new(Comparison::Always, 0xFF, (StencilOp::Keep, StencilOp::Keep, StencilOp::Keep))),
+    }
+
+    pipeline scissor_pipe {
+        vbuf: gfx::VertexBuffer<ColorVertex> = (),
+        out: gfx::RenderTarget<Rgba8> = "Target0",
+        scissor: gfx::Scissor = (),
+    }
+
+    pipeline texture_pipe {
+        vbuf: gfx::VertexBuffer<TexVertex> = (),
+        color: gfx::TextureSampler<[f32; 4]> = "t_Color",
+        out: gfx::RenderTarget<Rgba8> = "Target0",
+    }
+}
+
+fn vertex(_: &Context, input: &VertexInput, output: &mut VertexOutput) {
+    let pos = input.attributes[0];
+    output.position = [pos[0], pos[1], 0.0, 1.0];
+    output.varyings[0] = input.attributes[1];
+}
+
+fn pixel(_: &Context, input: &PixelInput, output: &mut PixelOutput) {
+    let color = input.varyings[0];
+    output.colors[0] = [color[0], color[1], color[2], 1.0];
+}
+
+fn color_vertex(_: &Context, input: &VertexInput, output: &mut VertexOutput) {
+    let pos = input.attributes[0];
+    output.position = [pos[0], pos[1], pos[2], 1.0];
+    output.varyings[0] = input.attributes[1];
+}
+
+fn color_pixel(_: &Context, input: &PixelInput, output: &mut PixelOutput) {
+    output.colors[0] = input.varyings[0];
+}
+
+fn texture_pixel(ctx: &Context, input: &PixelInput, output: &mut PixelOutput) {
+    let uv = input.varyings[0];
+    output.colors[0] = ctx.sample(0, 0, [uv[0], uv[1], 0.0]);
+}
+
+type Resources = gfx_device_soft::Resources;
+
+const SIZE: u16 = 4;
+
+const RED: [u8; 4] = [0xFF, 0, 0, 0xFF];
+const GREEN: [u8; 4] = [0, 0xFF, 0, 0xFF];
+const BLUE: [u8; 4] = [0, 0, 0xFF, 0xFF];
+const WHITE: [u8; 4] = [0xFF; 4];
+const YELLOW: [u8; 4] = [0xFF, 0xFF, 0, 0xFF];
+
+fn create_pipeline(factory: &mut gfx_device_soft::Factory)
+                   -> gfx::PipelineState<gfx_device_soft::Resources, pipe::Meta> {
+    let vs = factory.create_vertex_shader(Interface::new()
+        .attribute("a_Pos", BaseType::F32, ContainerType::Vector(2))
+        .attribute("a_Color", BaseType::F32, ContainerType::Vector(3)),
+        vertex);
+    let ps = factory.create_pixel_shader(Interface::new()
+        .output("Target0", BaseType::F32, ContainerType::Vector(4)),
+        pixel);
+    let program = factory.create_program(&gfx::ShaderSet::Simple(vs, ps)).unwrap();
+    factory.create_pipeline_from_program(&program,
+        gfx::Primitive::TriangleList, gfx::state::Rasterizer::new_fill(), pipe::new()
+        ).unwrap()
+}
+
+fn create_color_program(factory: &mut gfx_device_soft::Factory) -> gfx::handle::Program<Resources> {
+    let vs = factory.create_vertex_shader(Interface::new()
+        .attribute("a_Pos", BaseType::F32, ContainerType::Vector(3))
+        .attribute("a_Color", BaseType::F32, ContainerType::Vector(4)),
+        color_vertex);
+    let ps = factory.create_pixel_shader(Interface::new()
+        .output("Target0", BaseType::F32, ContainerType::Vector(4)),
+        color_pixel);
+    factory.create_program(&gfx::ShaderSet::Simple(vs, ps)).unwrap()
+}
+
+fn create_target(factory: &mut gfx_device_soft::Factory)
+                 -> (Texture<Resources, R8_G8_B8_A8>, RenderTargetView<Resources, Rgba8>) {
+    let texture = factory.create_texture::<R8_G8_B8_A8>(
+        Kind::D2(SIZE, SIZE, AaMode::Single), 1,
+        gfx::RENDER_TARGET | gfx::TRANSFER_SRC, gfx::memory::Usage::Data,
+        Some(ChannelType::Unorm)).unwrap();
+    let rtv = factory.view_texture_as_render_target::<Rgba8>(&texture, 0, None).unwrap();
+    (texture, rtv)
+}
+
+/// Submit the encoder and read the target back, bottom row first.
+fn read_target(device: &mut gfx_device_soft::Device, factory: &mut gfx_device_soft::Factory,
+               encoder: &mut gfx::Encoder<Resources, gfx_device_soft::CommandBuffer>,
+               texture: &Texture<Resources, R8_G8_B8_A8>) -> Vec<[u8; 4]> {
+    let texels = encoder.read_texture::<_, _, Rgba8>(factory, texture, None,
+                                                     texture.get_info().to_image_info(0)).unwrap();
+    encoder.flush(device);
+    texels.wait(device, factory).unwrap().unwrap()
+}
+
+/// A quad covering `[x0, x1] x [y0, y1]` in NDC, drawn with 6 indices.
+fn quad(x0: f32, y0: f32, x1: f32, y1: f32, z: f32, color: [f32; 4]) -> [ColorVertex; 4] {
+    [
+        ColorVertex { pos: [x0, y0, z], color: color },
+        ColorVertex { pos: [x1, y0, z], color: color },
+        ColorVertex { pos: [x0, y1, z], color: color },
+        ColorVertex { pos: [x1, y1, z], color: color },
+    ]
+}
+
+const QUAD_INDICES: &'static [u16] = &[0, 1, 2, 2, 1, 3];
+
+#[test]
+fn draw_triangle() {
+    let (mut device, mut factory) = gfx_device_soft::create();
+    let pso = create_pipeline(&mut factory);
+
+    let texture = factory.create_texture::<R8_G8_B8_A8>(
+        Kind::D2(SIZE, SIZE, AaMode::Single), 1,
+        gfx::RENDER_TARGET | gfx::TRANSFER_SRC, gfx::memory::Usage::Data,
+        Some(ChannelType::Unorm)).unwrap();
+    let rtv = factory.view_texture_as_render_target::<Rgba8>(&texture, 0, None).unwrap();
+    let download = factory.create_download_buffer::<[u8; 4]>(SIZE as usize * SIZE as usize).unwrap();
+
+    // covers the lower left half of the target
+    let vertices = [
+        Vertex { pos: [-1.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [ 1.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [-1.0,  1.0], color: [1.0, 0.0, 0.0] },
+    ];
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices, ());
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: rtv.clone(),
+    };
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.clear(&rtv, [0.0, 0.0, 1.0, 1.0]);
+    encoder.draw(&slice, &pso, &data);
+    let info = texture.get_info().to_raw_image_info(ChannelType::Unorm, 0);
+    encoder.copy_texture_to_buffer_raw(texture.raw(), None, info, download.raw(), 0).unwrap();
+    encoder.flush(&mut device);
+
+    let reader = factory.read_mapping(&download).unwrap();
+    // the first row in memory is the bottom one
+    assert_eq!(reader[0], [0xFF, 0, 0, 0xFF]);
+    assert_eq!(reader[1], [0xFF, 0, 0, 0xFF]);
+    assert_eq!(reader[SIZE as usize * SIZE as usize - 1], [0, 0, 0xFF, 0xFF]);
+}
+
+#[test]
+fn read_back() {
+    let (mut device, mut factory) = gfx_device_soft::create();
+
+    let texture = factory.create_texture::<R8_G8_B8_A8>(
+        Kind::D2(SIZE, SIZE, AaMode::Single), 1,
+        gfx::RENDER_TARGET | gfx::TRANSFER_SRC, gfx::memory::Usage::Data,
+        Some(ChannelType::Unorm)).unwrap();
+    let rtv = factory.view_texture_as_render_target::<Rgba8>(&texture, 0, None).unwrap();
+    let buffer = factory.create_buffer::<u32>(4, gfx::buffer::Role::Vertex,
+        gfx::memory::Usage::Dynamic, gfx::TRANSFER_SRC).unwrap();
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.clear(&rtv, [0.0, 1.0, 0.0, 1.0]);
+    encoder.update_buffer(&buffer, &[1, 2, 3, 4], 0).unwrap();
+    let texels = encoder.read_texture::<_, _, Rgba8>(&mut factory, &texture, None,
+                                                     texture.get_info().to_image_info(0)).unwrap();
+    let numbers = encoder.read_buffer(&mut factory, &buffer).unwrap();
+    assert!(!texels.is_submitted());
+    assert_eq!(texels.try_get(&mut device, &mut factory), Ok(None));
+    assert_eq!(numbers.wait(&mut device, &mut factory), Ok(None));
+
+    encoder.flush(&mut device);
+    let data = texels.try_get(&mut device, &mut factory).unwrap().unwrap();
+    assert_eq!(data.len(), SIZE as usize * SIZE as usize);
+    assert!(data.iter().all(|t| *t == [0, 0xFF, 0, 0xFF]));
+    assert_eq!(numbers.wait(&mut device, &mut factory), Ok(Some(vec![1, 2, 3, 4])));
+}
+
+#[test]
+fn reuse_query() {
+    let (mut device, mut factory) = gfx_device_soft::create();
+    let pso = create_pipeline(&mut factory);
+    let (_, _, rtv) = factory.create_render_target::<Rgba8>(SIZE, SIZE).unwrap();
+    let vertices = [
+        Vertex { pos: [-1.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [ 1.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [-1.0,  1.0], color: [1.0, 0.0, 0.0] },
+    ];
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices, ());
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: rtv,
+    };
+    let query = factory.create_query(gfx::QueryType::Occlusion).unwrap();
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.begin_query(&query);
+    encoder.draw(&slice, &pso, &data);
+    encoder.end_query(&query);
+    encoder.flush(&mut device);
+    let samples = factory.get_query_result(&query).unwrap();
+    assert!(samples > 0);
+
+    // nothing is drawn the second time around
+    encoder.begin_query(&query);
+    encoder.end_query(&query);
+    // the previous result is stale until the new commands are submitted
+    assert_eq!(factory.get_query_result(&query), None);
+    encoder.flush(&mut device);
+    assert_eq!(factory.get_query_result(&query), Some(0));
+}
+
+#[test]
+fn index_buffer() {
+    let (mut device, mut factory) = gfx_device_soft::create();
+    let pso = create_pipeline(&mut factory);
+    let (texture, rtv) = create_target(&mut factory);
+
+    // only the upper right half is referenced by the indices
+    let vertices = [
+        Vertex { pos: [-1.0, -1.0], color: [0.0, 1.0, 0.0] },
+        Vertex { pos: [ 1.0, -1.0], color: [0.0, 1.0, 0.0] },
+        Vertex { pos: [-1.0,  1.0], color: [0.0, 1.0, 0.0] },
+        Vertex { pos: [ 1.0,  1.0], color: [0.0, 1.0, 0.0] },
+    ];
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices, &[1u32, 3, 2][..]);
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: rtv.clone(),
+    };
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.clear(&rtv, [0.0, 0.0, 1.0, 1.0]);
+    encoder.draw(&slice, &pso, &data);
+
+    let texels = read_target(&mut device, &mut factory, &mut encoder, &texture);
+    for y in 0 .. SIZE as usize {
+        for x in 0 .. SIZE as usize {
+            let texel = texels[y * SIZE as usize + x];
+            // the pixels on the diagonal depend on the fill rule
+            if x + y < 3 {
+                assert_eq!(texel, BLUE, "pixel ({}, {})", x, y);
+            } else if x + y > 3 {
+                assert_eq!(texel, GREEN, "pixel ({}, {})", x, y);
+            }
+        }
+    }
+}
+
+#[test]
+fn blend() {
+    let (mut device, mut factory) = gfx_device_soft::create();
+    let program = create_color_program(&mut factory);
+    let pso = factory.create_pipeline_from_program(&program,
+        gfx::Primitive::TriangleList, gfx::state::Rasterizer::new_fill(), blend_pipe::new()
+        ).unwrap();
+    let (texture, rtv) = create_target(&mut factory);
+
+    let vertices = quad(-1.0, -1.0, 1.0, 1.0, 0.0, [1.0, 0.0, 0.0, 0.25]);
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices, QUAD_INDICES);
+    let data = blend_pipe::Data {
+        vbuf: vbuf,
+        out: rtv.clone(),
+    };
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.clear(&rtv, [0.0, 0.0, 1.0, 1.0]);
+    encoder.draw(&slice, &pso, &data);
+
+    // `src * src.a + dst * (1 - src.a)`, rounded to the nearest value
+    let texels = read_target(&mut device, &mut factory, &mut encoder, &texture);
+    assert_eq!(texels.len(), SIZE as usize * SIZE as usize);
+    for texel in texels {
+        assert_eq!(&texel[.. 3], &[64, 0, 191]);
+    }
+}
+
+#[test]
+fn depth_test() {
+    let (mut device, mut factory) = gfx_device_soft::create();
+    let program = create_color_program(&mut factory);
+    let pso = factory.create_pipeline_from_program(&program,
+        gfx::Primitive::TriangleList, gfx::state::Rasterizer::new_fill(), depth_pipe::new()
+        ).unwrap();
+    let (texture, rtv) = create_target(&mut factory);
+    let dsv = factory.create_depth_stencil_view_only::<Depth>(SIZE, SIZE).unwrap();
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.clear(&rtv, [0.0, 0.0, 0.0, 1.0]);
+    encoder.clear_depth(&dsv, 1.0);
+
+    let quads = [
+        // written, since the depth is cleared to the far plane
+        quad(-1.0, -1.0, 1.0, 1.0, 0.5, [1.0, 0.0, 0.0, 1.0]),
+        // behind the first quad
+        quad(-1.0, -1.0, 1.0, 1.0, 0.8, [1.0, 1.0, 1.0, 1.0]),
+        // in front of the first quad, over the left half
+        quad(-1.0, -1.0, 0.0, 1.0, 0.0, [0.0, 0.0, 1.0, 1.0]),
+        // between the previous two, over the bottom half
+        quad(-1.0, -1.0, 1.0, 0.0, 0.25, [0.0, 1.0, 0.0, 1.0]),
+    ];
+    for vertices in quads.iter() {
+        let (vbuf, slice) = factory.create_vertex_buffer_with_slice(vertices, QUAD_INDICES);
+        let data = depth_pipe::Data {
+            vbuf: vbuf,
+            out: rtv.clone(),
+            depth: dsv.clone(),
+        };
+        encoder.draw(&slice, &pso, &data);
+    }
+
+    let texels = read_target(&mut device, &mut factory, &mut encoder, &texture);
+    for y in 0 .. SIZE as usize {
+        for x in 0 .. SIZE as usize {
+            let expected = match (x < 2, y < 2) {
+                (true, _) => BLUE,
+                (false, true) => GREEN,
+                (false, false) => RED,
+            };
+            assert_eq!(texels[y * SIZE as usize + x], expected, "pixel ({}, {})", x, y);
+        }
+    }
+}
+
+/// Draw the quads over a black target, with the depth cleared to 1 and
+/// the stencil to 0. Each quad comes with its depth and stencil states,
+/// and the stencil reference value.
+fn draw_stencil(draws: &[([ColorVertex; 4], gfx::state::Depth, Stencil, u8)]) -> Vec<[u8; 4]> {
+    let (mut device, mut factory) = gfx_device_soft::create();
+    let program = create_color_program(&mut factory);
+    let (texture, rtv) = create_target(&mut factory);
+    let dsv = factory.create_depth_stencil_view_only::<DepthStencil>(SIZE, SIZE).unwrap();
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.clear(&rtv, [0.0, 0.0, 0.0, 1.0]);
+    encoder.clear_depth(&dsv, 1.0);
+    encoder.clear_stencil(&dsv, 0);
+
+    for &(ref vertices, depth, stencil, reference) in draws {
+        let pso = factory.create_pipeline_from_program(&program,
+            gfx::Primitive::TriangleList, gfx::state::Rasterizer::new_fill(),
+            stencil_pipe::Init {
+                vbuf: (),
+                out: "Target0",
+                depth_stencil: (depth, stencil),
+            }).unwrap();
+        let (vbuf, slice) = factory.create_vertex_buffer_with_slice(vertices, QUAD_INDICES);
+        let data = stencil_pipe::Data {
+            vbuf: vbuf,
+            out: rtv.clone(),
+            depth_stencil: (dsv.clone(), (reference, reference)),
+        };
+        encoder.draw(&slice, &pso, &data);
+    }
+    read_target(&mut device, &mut factory, &mut encoder, &texture)
+}
+
+/// Check the color of each quarter of the target, given as
+/// `[bottom left, bottom right, top left, top right]`.
+fn check_quarters(texels: &[[u8; 4]], quarters: [[u8; 4]; 4]) {
+    for y in 0 .. SIZE as usize {
+        for x in 0 .. SIZE as usize {
+            let expected = quarters[(y / 2) * 2 + x / 2];
+            assert_eq!(texels[y * SIZE as usize + x], expected, "pixel ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn stencil_reference() {
+    let always = gfx::preset::depth::PASS_TEST;
+    let keep = (StencilOp::Keep, StencilOp::Keep, StencilOp::Keep);
+    let texels = draw_stencil(&[
+        // writes 3 over the left half
+        (quad(-1.0, -1.0, 0.0, 1.0, 0.0, [1.0, 0.0, 0.0, 1.0]), always,
+         Stencil::new(Comparison::Always, 0xFF, (StencilOp::Keep, StencilOp::Keep, StencilOp::Replace)), 3),
+        // only passes where 3 has been written
+        (quad(-1.0, -1.0, 1.0, 1.0, 0.0, [0.0, 1.0, 0.0, 1.0]), always,
+         Stencil::new(Comparison::Equal, 0xFF, keep), 3),
+        // only passes where the stencil is still cleared
+        (quad(-1.0, -1.0, 1.0, 1.0, 0.0, [0.0, 0.0, 1.0, 1.0]), always,
+         Stencil::new(Comparison::Equal, 0xFF, keep), 0),
+        // the reference is greater than everything stored
+        (quad(-1.0, -1.0, 1.0, 1.0, 0.0, [1.0, 1.0, 1.0, 1.0]), always,
+         Stencil::new(Comparison::Less, 0xFF, keep), 4),
+    ]);
+    check_quarters(&texels, [GREEN, BLUE, GREEN, BLUE]);
+}
+
+#[test]
+fn stencil_mask() {
+    let always = gfx::preset::depth::PASS_TEST;
+    let keep = (StencilOp::Keep, StencilOp::Keep, StencilOp::Keep);
+    let write_low = StencilSide {
+        fun: Comparison::Always,
+        mask_read: 0xFF,
+        mask_write: 0x0F,
+        op_fail: StencilOp::Keep,
+        op_depth_fail: StencilOp::Keep,
+        op_pass: StencilOp::Replace,
+    };
+    let texels = draw_stencil(&[
+        // only the low bits of the reference are written
+        (quad(-1.0, -1.0, 1.0, 1.0, 0.0, [1.0, 0.0, 0.0, 1.0]), always,
+         Stencil { front: write_low, back: write_low }, 0xFF),
+        // the masked reference and stored values are equal
+        (quad(-1.0, -1.0, 0.0, 1.0, 0.0, [0.0, 1.0, 0.0, 1.0]), always,
+         Stencil::new(Comparison::Equal, 0x0F, keep), 0xFF),
+        // the high bits of the stored value are still cleared
+        (quad(-1.0, 0.0, 1.0, 1.0, 0.0, [1.0, 1.0, 1.0, 1.0]), always,
+         Stencil::new(Comparison::Equal, 0xFF, keep), 0xFF),
+    ]);
+    check_quarters(&texels, [GREEN, RED, GREEN, RED]);
+}
+
+#[test]
+fn stencil_operations() {
+    let always = gfx::preset::depth::PASS_TEST;
+    let keep = (StencilOp::Keep, StencilOp::Keep, StencilOp::Keep);
+    let texels = draw_stencil(&[
+        // the top half is in front, and keeps the stencil
+        (quad(-1.0, 0.0, 1.0, 1.0, 0.0, [1.0, 0.0, 0.0, 1.0]), gfx::preset::depth::LESS_EQUAL_WRITE,
+         Stencil::new(Comparison::Always, 0xFF, keep), 0),
+        // passes on the bottom half, where 2 is written, and fails
+        // the depth test on the top half, which is incremented to 1
+        (quad(-1.0, -1.0, 1.0, 1.0, 0.5, [0.0, 1.0, 0.0, 1.0]), gfx::preset::depth::LESS_EQUAL_TEST,
+         Stencil::new(Comparison::Always, 0xFF,
+                      (StencilOp::Keep, StencilOp::IncrementClamp, StencilOp::Replace)), 2),
+        // fails the stencil test on the top left quarter, which is inverted
+        (quad(-1.0, -1.0, 0.0, 1.0, 0.0, [1.0, 1.0, 1.0, 1.0]), always,
+         Stencil::new(Comparison::Equal, 0xFF,
+                      (StencilOp::Invert, StencilOp::Keep, StencilOp::Keep)), 2),
+        // tell the stored values apart
+        (quad(-1.0, -1.0, 1.0, 1.0, 0.0, [1.0, 1.0, 0.0, 1.0]), always,
+         Stencil::new(Comparison::Equal, 0xFF, keep), !1),
+        (quad(-1.0, -1.0, 1.0, 1.0, 0.0, [0.0, 0.0, 1.0, 1.0]), always,
+         Stencil::new(Comparison::Equal, 0xFF, keep), 1),
+        (quad(0.0, -1.0, 1.0, 1.0, 0.0, [1.0, 1.0, 1.0, 1.0]), always,
+         Stencil::new(Comparison::Equal, 0xFF, keep), 2),
+    ]);
+    check_quarters(&texels, [WHITE, WHITE, YELLOW, BLUE]);
+}
+
+#[test]
+fn scissor() {
+    let (mut device, mut factory) = gfx_device_soft::create();
+    let program = create_color_program(&mut factory);
+    let pso = factory.create_pipeline_from_program(&program,
+        gfx::Primitive::TriangleList, gfx::state::Rasterizer::new_fill(), scissor_pipe::new()
+        ).unwrap();
+    let (texture, rtv) = create_target(&mut factory);
+
+    let vertices = quad(-1.0, -1.0, 1.0, 1.0, 0.0, [1.0, 0.0, 0.0, 1.0]);
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices, QUAD_INDICES);
+    let data = scissor_pipe::Data {
+        vbuf: vbuf,
+        out: rtv.clone(),
+        scissor: gfx::Rect { x: 1, y: 0, w: 2, h: 3 },
+    };
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.clear(&rtv, [0.0, 0.0, 1.0, 1.0]);
+    encoder.draw(&slice, &pso, &data);
+
+    let texels = read_target(&mut device, &mut factory, &mut encoder, &texture);
+    for y in 0 .. SIZE as usize {
+        for x in 0 .. SIZE as usize {
+            let inside = x >= 1 && x < 3 && y < 3;
+            let expected = if inside { RED } else { BLUE };
+            assert_eq!(texels[y * SIZE as usize + x], expected, "pixel ({}, {})", x, y);
+        }
+    }
+}
+
+/// Draw the texture over the whole target, with the texture coordinates
+/// going from 0 to `scale` on both axes.
+fn draw_texture(texels: &[[u8; 4]], sampler_info: SamplerInfo, scale: f32) -> Vec<[u8; 4]> {
+    let (mut device, mut factory) = gfx_device_soft::create();
+    let vs = factory.create_vertex_shader(Interface::new()
+        .attribute("a_Pos", BaseType::F32, ContainerType::Vector(2))
+        .attribute("a_Uv", BaseType::F32, ContainerType::Vector(2)),
+        vertex);
+    let ps = factory.create_pixel_shader(Interface::new()
+        .texture("t_Color", TextureType::D2(IsArray::NoArray, IsMultiSample::NoMultiSample))
+        .sampler("t_Color", SamplerType(IsComparison::NoCompare, IsRect::NoRect))
+        .output("Target0", BaseType::F32, ContainerType::Vector(4)),
+        texture_pixel);
+    let program = factory.create_program(&gfx::ShaderSet::Simple(vs, ps)).unwrap();
+    let pso = factory.create_pipeline_from_program(&program,
+        gfx::Primitive::TriangleList, gfx::state::Rasterizer::new_fill(), texture_pipe::new()
+        ).unwrap();
+    let (texture, rtv) = create_target(&mut factory);
+
+    let (_, srv) = factory.create_texture_immutable::<Rgba8>(
+        Kind::D2(2, 2, AaMode::Single), &[texels]).unwrap();
+    let sampler = factory.create_sampler(sampler_info);
+    let vertices = [
+        TexVertex { pos: [-1.0, -1.0], uv: [0.0, 0.0] },
+        TexVertex { pos: [ 1.0, -1.0], uv: [scale, 0.0] },
+        TexVertex { pos: [-1.0,  1.0], uv: [0.0, scale] },
+        TexVertex { pos: [ 1.0,  1.0], uv: [scale, scale] },
+    ];
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices, QUAD_INDICES);
+    let data = texture_pipe::Data {
+        vbuf: vbuf,
+        color: (srv, sampler),
+        out: rtv,
+    };
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.draw(&slice, &pso, &data);
+    read_target(&mut device, &mut factory, &mut encoder, &texture)
+}
+
+#[test]
+fn sampler_nearest() {
+    // the first row of the texture is at v = 0
+    let texels = [RED, GREEN, BLUE, WHITE];
+    let info = SamplerInfo::new(FilterMethod::Scale, WrapMode::Clamp);
+    let output = draw_texture(&texels, info, 1.0);
+    for y in 0 .. SIZE as usize {
+        for x in 0 .. SIZE as usize {
+            let expected = texels[(y / 2) * 2 + x / 2];
+            assert_eq!(output[y * SIZE as usize + x], expected, "pixel ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn sampler_bilinear() {
+    let black = [0, 0, 0, 0xFF];
+    let texels = [black, WHITE, black, WHITE];
+    let info = SamplerInfo::new(FilterMethod::Bilinear, WrapMode::Clamp);
+    let output = draw_texture(&texels, info, 1.0);
+    // the outer pixels are clamped to the edge texels
+    let row = [0, 64, 191, 0xFF];
+    for y in 0 .. SIZE as usize {
+        for x in 0 .. SIZE as usize {
+            let v = row[x];
+            assert_eq!(output[y * SIZE as usize + x], [v, v, v, 0xFF], "pixel ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn sampler_wrap() {
+    let texels = [RED, GREEN, BLUE, WHITE];
+    // each texel covers a single pixel, and the coordinates reach 2
+    let cases = [
+        (WrapMode::Tile, [0, 1, 0, 1]),
+        (WrapMode::Mirror, [0, 1, 1, 0]),
+        (WrapMode::Clamp, [0, 1, 1, 1]),
+    ];
+    for &(mode, indices) in cases.iter() {
+        let info = SamplerInfo::new(FilterMethod::Scale, mode);
+        let output = draw_texture(&texels, info, 2.0);
+        for y in 0 .. SIZE as usize {
+            for x in 0 .. SIZE as usize {
+                let expected = texels[indices[y] * 2 + indices[x]];
+                assert_eq!(output[y * SIZE as usize + x], expected,
+                           "pixel ({}, {}) with {:?}", x, y, mode);
+            }
+        }
+    }
+}
@@ -0,0 +1,6 @@
+#!/bin/sh
+
+echo Assembling...
+spirv-as --target-env spv1.0 -o reflection.spv reflection.spvasm
+echo Validating...
+spirv-val reflection.spv
@@ -0,0 +1,173 @@
+//! Reflection of the SPIR-V shaders by the Vulkan backend,
+//! run with `cargo test --features vulkan --test spirv_reflection`
+
+#![cfg(feature = "vulkan")]
+
+extern crate gfx_core;
+extern crate gfx_device_vulkan;
+
+use gfx_core::shade::*;
+use gfx_device_vulkan::mirror::{self, ParseError};
+
+fn reflect(info: &mut ProgramInfo, stage: Stage, code: &[u8]) -> Result<(), String> {
+    let reflection = mirror::reflect_spirv_module(code).unwrap();
+    mirror::populate_info(info, stage, &reflection)
+}
+
+fn new_info() -> ProgramInfo {
+    ProgramInfo {
+        vertex_attributes: Vec::new(),
+        globals: Vec::new(),
+        constant_buffers: Vec::new(),
+        textures: Vec::new(),
+        unordereds: Vec::new(),
+        samplers: Vec::new(),
+        outputs: Vec::new(),
+        output_depth: false,
+        knows_outputs: false,
+    }
+}
+
+fn constant(name: &str, location: Location, count: usize, base_type: BaseType,
+            container: ContainerType) -> ConstVar {
+    ConstVar {
+        name: name.to_string(),
+        location: location,
+        count: count,
+        base_type: base_type,
+        container: container,
+    }
+}
+
+#[test]
+fn cube() {
+    let mut info = new_info();
+    reflect(&mut info, Stage::Vertex, include_bytes!("../examples/cube/data/vert.spv")).unwrap();
+    reflect(&mut info, Stage::Pixel, include_bytes!("../examples/cube/data/frag.spv")).unwrap();
+
+    assert_eq!(info.vertex_attributes, vec![
+        AttributeVar {
+            name: "a_TexCoord".to_string(),
+            slot: 1,
+            base_type: BaseType::F32,
+            container: ContainerType::Vector(2),
+        },
+        AttributeVar {
+            name: "a_Pos".to_string(),
+            slot: 0,
+            base_type: BaseType::F32,
+            container: ContainerType::Vector(4),
+        },
+    ]);
+    assert_eq!(info.constant_buffers, vec![ConstantBufferVar {
+        name: "Locals".to_string(),
+        slot: 0,
+        size: 64,
+        usage: VERTEX,
+        elements: vec![constant("u_Transform", 0, 1, BaseType::F32,
+                                ContainerType::Matrix(MatrixFormat::ColumnMajor, 4, 4))],
+    }]);
+    assert_eq!(info.textures, vec![TextureVar {
+        name: "t_Color".to_string(),
+        slot: 1,
+        base_type: BaseType::F32,
+        ty: TextureType::D2(IsArray::NoArray, IsMultiSample::NoMultiSample),
+        usage: PIXEL,
+    }]);
+    assert_eq!(info.samplers, vec![SamplerVar {
+        name: "t_Color".to_string(),
+        slot: 2,
+        ty: SamplerType(IsComparison::NoCompare, IsRect::NoRect),
+        usage: PIXEL,
+    }]);
+    assert_eq!(info.outputs, vec![OutputVar {
+        name: "Target0".to_string(),
+        slot: 0,
+        base_type: BaseType::F32,
+        container: ContainerType::Vector(4),
+    }]);
+    assert!(info.knows_outputs && !info.output_depth);
+}
+
+#[test]
+fn resources() {
+    // see `spirv/reflection.spvasm`
+    let mut info = new_info();
+    reflect(&mut info, Stage::Pixel, include_bytes!("spirv/reflection.spv")).unwrap();
+
+    assert_eq!(info.constant_buffers.len(), 1);
+    let params = &info.constant_buffers[0];
+    assert_eq!((&params.name[..], params.slot, params.size), ("Params", 0, 116));
+    assert_eq!(params.elements, vec![
+        constant("colors", 0, 2, BaseType::F32, ContainerType::Vector(4)),
+        constant("lights[0].pos", 32, 1, BaseType::F32, ContainerType::Vector(3)),
+        constant("lights[0].radius", 44, 1, BaseType::F32, ContainerType::Single),
+        constant("lights[1].pos", 48, 1, BaseType::F32, ContainerType::Vector(3)),
+        constant("lights[1].radius", 60, 1, BaseType::F32, ContainerType::Single),
+        constant("normal", 64, 1, BaseType::F32, ContainerType::Matrix(MatrixFormat::RowMajor, 3, 3)),
+        constant("count", 112, 1, BaseType::I32, ContainerType::Single),
+    ]);
+
+    let textures: Vec<_> = info.textures.iter().map(|t| (&t.name[..], t.slot, t.base_type, t.ty)).collect();
+    assert_eq!(textures, vec![
+        ("s_Shadow", 1, BaseType::F32, TextureType::D2(IsArray::NoArray, IsMultiSample::NoMultiSample)),
+        ("t_Array", 2, BaseType::U32, TextureType::D2(IsArray::Array, IsMultiSample::NoMultiSample)),
+    ]);
+    assert_eq!(info.samplers, vec![SamplerVar {
+        name: "s_Shadow".to_string(),
+        slot: 1,
+        ty: SamplerType(IsComparison::Compare, IsRect::NoRect),
+        usage: PIXEL,
+    }]);
+    // the binding of the image is given by a decoration group
+    let unordereds: Vec<_> = info.unordereds.iter().map(|u| (&u.name[..], u.slot)).collect();
+    assert_eq!(unordereds, vec![("u_Image", 3), ("Data", 4)]);
+
+    let outputs: Vec<_> = info.outputs.iter().map(|o| (&o.name[..], o.slot, o.base_type, o.container)).collect();
+    assert_eq!(outputs, vec![
+        ("Target0", 0, BaseType::F32, ContainerType::Vector(4)),
+        ("Target1", 1, BaseType::I32, ContainerType::Vector(2)),
+    ]);
+    assert!(info.output_depth);
+}
+
+/// Assemble a module from the instructions following the header.
+fn assemble(instructions: &[&[u32]]) -> Vec<u8> {
+    let mut words = vec![0x07230203, 0x00010000, 0, 16, 0];
+    for instruction in instructions {
+        words.push((instruction.len() as u32) << 16 | instruction[0]);
+        words.extend_from_slice(&instruction[1..]);
+    }
+    words.iter().flat_map(|&w| (0 .. 4).map(move |i| (w >> (8 * i)) as u8)).collect()
+}
+
+#[test]
+fn malformed() {
+    // %float = OpTypeFloat 32
+    let float = [22, 1, 32];
+    assert!(mirror::reflect_spirv_module(&assemble(&[&float, &[23, 2, 1, 4]])).is_ok());
+    // the component count of the vector is missing
+    assert_eq!(mirror::reflect_spirv_module(&assemble(&[&float, &[23, 2, 1]])),
+               Err(ParseError::Instruction(23)));
+    // so is the column count of the matrix
+    assert_eq!(mirror::reflect_spirv_module(&assemble(&[&float, &[23, 2, 1, 4], &[24, 3, 2]])),
+               Err(ParseError::Instruction(24)));
+    // the variable type is not a pointer
+    assert_eq!(mirror::reflect_spirv_module(&assemble(&[&float, &[59, 1, 4, 7]])),
+               Err(ParseError::Instruction(59)));
+}
+
+#[test]
+fn errors() {
+    let code = include_bytes!("../examples/cube/data/vert.spv");
+    assert_eq!(mirror::reflect_spirv_module(&code[.. 6]), Err(ParseError::Size(6)));
+    assert_eq!(mirror::reflect_spirv_module(&[0; 20]), Err(ParseError::MagicNumber(0)));
+    assert_eq!(mirror::reflect_spirv_module(&code[.. 16]), Err(ParseError::Truncated));
+
+    // the attributes of the terrain shader have no locations
+    let mut info = new_info();
+    let result = reflect(&mut info, Stage::Vertex, include_bytes!("../examples/terrain/data/vert.spv"));
+    assert!(result.unwrap_err().contains("location"));
+    // there is no vertex entry point in a pixel shader
+    assert!(reflect(&mut info, Stage::Vertex, include_bytes!("../examples/cube/data/frag.spv")).is_err());
+}
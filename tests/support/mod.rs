@@ -0,0 +1,29 @@
+//! Fixtures shared by the tests running on the dummy backend.
+
+use gfx::shade::core::{AttributeVar, BaseType, ContainerType, OutputVar, ProgramInfo};
+
+/// Get the information of a program reading a single 2D position
+/// attribute, and writing a color into `Target0`.
+pub fn program_info(attribute: &str) -> ProgramInfo {
+    ProgramInfo {
+        vertex_attributes: vec![AttributeVar {
+            name: attribute.to_string(),
+            slot: 0,
+            base_type: BaseType::F32,
+            container: ContainerType::Vector(2),
+        }],
+        globals: Vec::new(),
+        constant_buffers: Vec::new(),
+        textures: Vec::new(),
+        unordereds: Vec::new(),
+        samplers: Vec::new(),
+        outputs: vec![OutputVar {
+            name: "Target0".to_string(),
+            slot: 0,
+            base_type: BaseType::F32,
+            container: ContainerType::Vector(4),
+        }],
+        output_depth: false,
+        knows_outputs: true,
+    }
+}
@@ -0,0 +1,139 @@
+extern crate gfx;
+extern crate gfx_core;
+
+use std::borrow::Cow;
+use gfx::format::{ChannelType, Format, SurfaceType};
+use gfx::texture::{AaMode, Kind};
+use gfx::texture_loader::{self, LoadError};
+use gfx_core::dummy::DummyFactory;
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    for i in 0 .. 4 {
+        out.push((value >> (i * 8)) as u8);
+    }
+}
+
+/// Build a DDS header, with the DX10 extension if `dx10` is given.
+fn dds(width: u32, height: u32, levels: u32, pixel_format: [u32; 8], caps2: u32,
+       dx10: Option<[u32; 4]>) -> Vec<u8> {
+    let mut out = b"DDS ".to_vec();
+    let mut header = [0u32; 31];
+    header[0] = 124;
+    header[2] = height;
+    header[3] = width;
+    header[6] = levels;
+    header[18 .. 26].copy_from_slice(&pixel_format);
+    header[27] = caps2;
+    for value in header.iter() {
+        push_u32(&mut out, *value);
+    }
+    if let Some(ext) = dx10 {
+        for value in ext.iter() {
+            push_u32(&mut out, *value);
+        }
+        push_u32(&mut out, 0);
+    }
+    out
+}
+
+const DDPF_DX10: [u32; 8] = [32, 0x4, 0x3031_5844, 0, 0, 0, 0, 0];
+
+/// Build a KTX header for an uncompressed format.
+fn ktx(internal_format: u32, type_size: u32, size: [u32; 3], elements: u32,
+       faces: u32, levels: u32) -> Vec<u8> {
+    let mut out = vec![0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+    for value in [0x0403_0201, 0x1401, type_size, 0, internal_format, 0,
+                  size[0], size[1], size[2], elements, faces, levels, 0].iter() {
+        push_u32(&mut out, *value);
+    }
+    out
+}
+
+#[test]
+fn dds_mip_levels() {
+    let rgba8 = [32, 0x41, 0, 32, 0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000];
+    let mut data = dds(4, 4, 3, rgba8, 0, None);
+    for &(level, size) in [(0u8, 64), (1, 16), (2, 4)].iter() {
+        data.extend(std::iter::repeat(level).take(size));
+    }
+    let image = texture_loader::parse(&data).unwrap();
+    assert_eq!(image.kind, Kind::D2(4, 4, AaMode::Single));
+    assert_eq!(image.levels, 3);
+    assert_eq!(image.format, Format(SurfaceType::R8_G8_B8_A8, ChannelType::Unorm));
+    assert_eq!(image.data.len(), 3);
+    assert_eq!(image.data[1], Cow::Borrowed(&[1u8; 16][..]));
+    assert_eq!(image.data[2], Cow::Borrowed(&[2u8; 4][..]));
+
+    data.pop();
+    assert_eq!(texture_loader::parse(&data), Err(LoadError::Truncated));
+}
+
+#[test]
+fn dds_cube_array() {
+    // two R32_FLOAT cubes of 2x2
+    let mut data = dds(2, 2, 1, DDPF_DX10, 0, Some([41, 3, 0x4, 2]));
+    for slice in 0 .. 12 {
+        data.extend(std::iter::repeat(slice).take(16));
+    }
+    let image = texture_loader::parse_dds(&data).unwrap();
+    assert_eq!(image.kind, Kind::CubeArray(2, 2));
+    assert_eq!(image.format, Format(SurfaceType::R32, ChannelType::Float));
+    assert_eq!(image.data.len(), 12);
+    assert_eq!(image.data[7], Cow::Borrowed(&[7u8; 16][..]));
+}
+
+#[test]
+fn dds_compressed() {
+    // DXT1 of 8x6, taking 2x2 blocks of 8 bytes
+    let dxt1 = [32, 0x4, 0x3154_5844, 0, 0, 0, 0, 0];
+    let mut data = dds(8, 6, 1, dxt1, 0, None);
+    data.extend_from_slice(&[0x55; 32]);
+    let image = texture_loader::parse(&data).unwrap();
+    assert_eq!(image.format, Format(SurfaceType::BC1_R8_G8_B8_A8, ChannelType::Unorm));
+    assert_eq!(image.data[0].len(), 32);
+}
+
+#[test]
+fn ktx_row_padding() {
+    // R8 of 3x2, with rows padded to 4 bytes
+    let mut data = ktx(0x8229, 1, [3, 2, 0], 0, 1, 2);
+    push_u32(&mut data, 8);
+    data.extend_from_slice(&[1, 2, 3, 0, 4, 5, 6, 0]);
+    push_u32(&mut data, 4);
+    data.extend_from_slice(&[7, 0, 0, 0]);
+    let image = texture_loader::parse(&data).unwrap();
+    assert_eq!(image.kind, Kind::D2(3, 2, AaMode::Single));
+    assert_eq!(image.format, Format(SurfaceType::R8, ChannelType::Unorm));
+    assert_eq!(&image.data[0][..], &[1, 2, 3, 4, 5, 6]);
+    assert_eq!(&image.data[1][..], &[7]);
+}
+
+#[test]
+fn ktx_cube_order() {
+    // RGBA8 cube of 2x2 with 2 levels, stored level by level
+    let mut data = ktx(0x8058, 1, [2, 2, 0], 0, 6, 2);
+    for &(level, size) in [(0u8, 16), (1, 4)].iter() {
+        push_u32(&mut data, size as u32);
+        for face in 0 .. 6 {
+            data.extend(std::iter::repeat(face * 2 + level).take(size));
+        }
+    }
+    let image = texture_loader::parse_ktx(&data).unwrap();
+    assert_eq!(image.kind, Kind::Cube(2));
+    assert_eq!(image.levels, 2);
+    // the data is reordered face by face
+    for (i, chunk) in image.data.iter().enumerate() {
+        assert!(chunk.iter().all(|b| *b as usize == i));
+    }
+}
+
+#[test]
+fn load_texture() {
+    let mut factory = DummyFactory::new();
+    let mut data = dds(2, 2, 1, DDPF_DX10, 0, Some([29, 3, 0, 1]));
+    data.extend_from_slice(&[0xFF; 16]);
+    let (texture, format) = texture_loader::load(&mut factory, &data).unwrap();
+    assert_eq!(format, Format(SurfaceType::R8_G8_B8_A8, ChannelType::Srgb));
+    assert_eq!(texture.get_info().kind, Kind::D2(2, 2, AaMode::Single));
+    assert_eq!(texture_loader::parse(b"PNG"), Err(LoadError::UnknownContainer));
+}
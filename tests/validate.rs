@@ -0,0 +1,208 @@
+//! Validation layer checks, run with `cargo test --features validate --test validate`
+
+#![cfg(feature = "validate")]
+
+#[macro_use]
+extern crate gfx;
+extern crate gfx_core;
+extern crate gfx_device_soft;
+
+use gfx::Factory;
+use gfx::format::{ChannelType, Rgba8, R8_G8_B8_A8};
+use gfx::shade::core::{BaseType, ContainerType};
+use gfx::traits::FactoryExt;
+use gfx_core::Device;
+use gfx_core::command::{AccessInfo, Buffer};
+use gfx_core::dummy::{DummyCommandBuffer, DummyDevice, DummyFactory, DummyId, DummyResources};
+use gfx_core::handle::Manager;
+use gfx_core::pso::{PixelTargetSet, ResourceViewParam};
+use gfx_core::shade::PIXEL;
+use gfx_core::texture::{AaMode, Kind};
+use gfx_core::validate::{ValidatingCommandBuffer, ValidatingDevice};
+use gfx_device_soft::shade::{Context, Interface, PixelInput, PixelOutput,
+                             VertexInput, VertexOutput};
+
+gfx_defines!{
+    vertex Vertex {
+        pos: [f32; 2] = "a_Pos",
+    }
+
+    pipeline pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        out: gfx::RenderTarget<Rgba8> = "Target0",
+    }
+}
+
+fn vertex(_: &Context, input: &VertexInput, output: &mut VertexOutput) {
+    let pos = input.attributes[0];
+    output.position = [pos[0], pos[1], 0.0, 1.0];
+}
+
+fn pixel(_: &Context, _: &PixelInput, output: &mut PixelOutput) {
+    output.colors[0] = [1.0; 4];
+}
+
+fn draw_triangle(num_indices: gfx::VertexCount) -> usize {
+    let (device, mut factory) = gfx_device_soft::create();
+    let mut device = ValidatingDevice::new(device, false);
+
+    let vs = factory.create_vertex_shader(Interface::new()
+        .attribute("a_Pos", BaseType::F32, ContainerType::Vector(2)),
+        vertex);
+    let ps = factory.create_pixel_shader(Interface::new()
+        .output("Target0", BaseType::F32, ContainerType::Vector(4)),
+        pixel);
+    let program = factory.create_program(&gfx::ShaderSet::Simple(vs, ps)).unwrap();
+    let pso = factory.create_pipeline_from_program(&program,
+        gfx::Primitive::TriangleList, gfx::state::Rasterizer::new_fill(), pipe::new()
+        ).unwrap();
+    let (_, _, rtv) = factory.create_render_target::<Rgba8>(4, 4).unwrap();
+
+    let vertices = [
+        Vertex { pos: [-1.0, -1.0] },
+        Vertex { pos: [ 1.0, -1.0] },
+        Vertex { pos: [-1.0,  1.0] },
+    ];
+    let (vbuf, mut slice) = factory.create_vertex_buffer_with_slice(&vertices, &[0u16, 1, 2][..]);
+    slice.end = num_indices;
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: rtv,
+    };
+
+    let mut encoder: gfx::Encoder<_, _> =
+        ValidatingCommandBuffer::new(factory.create_command_buffer()).into();
+    encoder.draw(&slice, &pso, &data);
+    encoder.flush(&mut device);
+    device.get_num_errors()
+}
+
+type CommandBuffer = ValidatingCommandBuffer<DummyResources, DummyCommandBuffer>;
+
+/// Validate the raw commands against a device with no optional features,
+/// returning the number of errors found.
+fn validate<F>(handles: &Manager<DummyResources>, fun: F) -> usize
+    where F: FnOnce(&mut CommandBuffer)
+{
+    let mut device = ValidatingDevice::new(DummyDevice::new(), false);
+    let mut cb = ValidatingCommandBuffer::new(DummyCommandBuffer);
+    fun(&mut cb);
+    device.pin_submitted_resources(handles);
+    device.submit(&mut cb, &AccessInfo::new()).unwrap();
+    device.get_num_errors()
+}
+
+fn targets(rtv: DummyId) -> PixelTargetSet<DummyResources> {
+    let mut targets = PixelTargetSet::new();
+    targets.add_color(0, &rtv, (4, 4, 1, AaMode::Single));
+    targets
+}
+
+#[test]
+fn test_valid_draw() {
+    assert_eq!(draw_triangle(3), 0);
+}
+
+#[test]
+fn test_index_out_of_bounds() {
+    assert_eq!(draw_triangle(6), 1);
+}
+
+#[test]
+fn test_target_aliasing() {
+    let mut factory = DummyFactory::new();
+    let (_, srv, rtv) = factory.create_render_target::<Rgba8>(4, 4).unwrap();
+    let (_, other_srv, _) = factory.create_render_target::<Rgba8>(4, 4).unwrap();
+    let mut handles = Manager::new();
+    let srv = *handles.ref_srv(srv.raw());
+    let other_srv = *handles.ref_srv(other_srv.raw());
+    let rtv = *handles.ref_rtv(rtv.raw());
+
+    let errors = validate(&handles, |cb| {
+        cb.bind_pipeline_state(DummyId(0));
+        cb.bind_pixel_targets(targets(rtv));
+        cb.bind_resource_views(&[ResourceViewParam(other_srv, PIXEL, 0)]);
+        cb.call_draw(0, 3, None);
+        // the texture being rendered into is also read from
+        cb.bind_resource_views(&[ResourceViewParam(srv, PIXEL, 1)]);
+        cb.call_draw(0, 3, None);
+    });
+    assert_eq!(errors, 1);
+}
+
+#[test]
+fn test_instancing_unsupported() {
+    let handles = Manager::new();
+    let errors = validate(&handles, |cb| {
+        cb.bind_pipeline_state(DummyId(0));
+        cb.bind_pixel_targets(targets(DummyId(1)));
+        cb.call_draw(0, 3, None);
+        cb.call_draw(0, 3, Some((2, 0)));
+        cb.call_draw_indexed(0, 3, 0, Some((2, 0)));
+    });
+    // the indexed draw also misses an index buffer
+    assert_eq!(errors, 3);
+}
+
+#[test]
+fn test_copy_bounds() {
+    let mut factory = DummyFactory::new();
+    let buffer = factory.create_buffer::<u32>(4, gfx::buffer::Role::Vertex,
+        gfx::memory::Usage::Data, gfx::TRANSFER_SRC | gfx::TRANSFER_DST).unwrap();
+    let texture = factory.create_texture::<R8_G8_B8_A8>(
+        Kind::D2(4, 4, AaMode::Single), 1, gfx::TRANSFER_SRC, gfx::memory::Usage::Data,
+        Some(ChannelType::Unorm)).unwrap();
+    let mut handles = Manager::new();
+    let buf = *handles.ref_buffer(buffer.raw());
+    let tex = *handles.ref_texture(texture.raw());
+    let kind = texture.get_info().kind;
+    let whole = texture.get_info().to_raw_image_info(ChannelType::Unorm, 0);
+    let mut corner = whole;
+    corner.width = 2;
+    corner.height = 2;
+    corner.xoffset = 2;
+    corner.yoffset = 2;
+    let mut shifted = corner;
+    shifted.xoffset = 3;
+
+    let valid = validate(&handles, |cb| {
+        cb.copy_buffer(buf, buf, 0, 8, 8);
+        cb.copy_texture_to_texture(tex, kind, None, corner, tex, kind, None, whole);
+        cb.copy_texture_to_buffer(tex, kind, None, corner, buf, 0);
+    });
+    assert_eq!(valid, 0);
+
+    let errors = validate(&handles, |cb| {
+        // 12 .. 20 is past the 16 bytes of the buffer
+        cb.copy_buffer(buf, buf, 0, 12, 8);
+        cb.copy_texture_to_texture(tex, kind, None, shifted, tex, kind, None, whole);
+        // the whole texture takes 64 bytes
+        cb.copy_texture_to_buffer(tex, kind, None, whole, buf, 0);
+    });
+    assert_eq!(errors, 3);
+}
+
+#[test]
+fn test_overflowing_bounds() {
+    let mut factory = DummyFactory::new();
+    let buffer = factory.create_buffer::<u32>(4, gfx::buffer::Role::Vertex,
+        gfx::memory::Usage::Data, gfx::TRANSFER_SRC | gfx::TRANSFER_DST).unwrap();
+    let texture = factory.create_texture::<R8_G8_B8_A8>(
+        Kind::D2(4, 4, AaMode::Single), 1, gfx::TRANSFER_SRC, gfx::memory::Usage::Data,
+        Some(ChannelType::Unorm)).unwrap();
+    let mut handles = Manager::new();
+    let buf = *handles.ref_buffer(buffer.raw());
+    let tex = *handles.ref_texture(texture.raw());
+    let kind = texture.get_info().kind;
+    let whole = texture.get_info().to_raw_image_info(ChannelType::Unorm, 0);
+    let mut far = whole;
+    far.xoffset = u16::max_value();
+
+    let errors = validate(&handles, |cb| {
+        // both ranges end past `usize::MAX`
+        cb.copy_buffer(buf, buf, usize::max_value(), usize::max_value() - 4, 8);
+        cb.copy_texture_to_buffer(tex, kind, None, far, buf, 0);
+    });
+    // the texture copy is also larger than the buffer
+    assert_eq!(errors, 4);
+}